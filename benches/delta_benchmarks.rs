@@ -1,5 +1,5 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
-use zaz::__bench::{DirtyRegion, find_line_diff};
+use zaz::__bench::{Buffer, DirtyRegion, find_line_diff};
 use zaz::{Attr, Cell, Color};
 
 fn bench_find_line_diff(c: &mut Criterion) {
@@ -69,7 +69,14 @@ fn bench_find_line_diff(c: &mut Criterion) {
                 .map(|i| Cell::new((b'A' + (i % 26) as u8) as char))
                 .collect();
             let line2: Vec<Cell> = (0..size)
-                .map(|i| Cell::with_style((b'A' + (i % 26) as u8) as char, Attr::BOLD, None, None))
+                .map(|i| {
+                    Cell::with_style(
+                        (b'A' + (i % 26) as u8) as char,
+                        Attr::BOLD,
+                        Color::Reset,
+                        Color::Reset,
+                    )
+                })
                 .collect();
             b.iter(|| black_box(find_line_diff(&line1, &line2)));
         });
@@ -135,6 +142,67 @@ fn bench_buffer_swap(c: &mut Criterion) {
                 });
             },
         );
+
+        group.bench_with_input(
+            BenchmarkId::new("copy_flat", format!("{}x{}", rows, cols)),
+            screen_size,
+            |b, &(rows, cols)| {
+                let current = Buffer::new(rows, cols, Cell::blank());
+                let mut pending = Buffer::new(rows, cols, Cell::blank());
+
+                b.iter(|| {
+                    pending.copy_from(&current);
+                    black_box(&pending);
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Diff every row of two screens that differ in a few cells per row,
+/// stored as nested rows and as one flat buffer
+fn bench_screen_diff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("screen_diff");
+
+    for screen_size in [(24, 80), (60, 200), (100, 300)].iter() {
+        let (rows, cols) = *screen_size;
+        let cell = |y: usize, x: usize| Cell::new((b'A' + ((x + y) % 26) as u8) as char);
+        let label = format!("{}x{}", rows, cols);
+
+        group.bench_function(BenchmarkId::new("nested", &label), |b| {
+            let current: Vec<Vec<Cell>> = (0..rows)
+                .map(|y| (0..cols).map(|x| cell(y, x)).collect())
+                .collect();
+            let mut pending = current.clone();
+            for (y, row) in pending.iter_mut().enumerate() {
+                row[(y * 7) % cols] = Cell::new('X');
+            }
+            b.iter(|| {
+                for y in 0..rows {
+                    black_box(find_line_diff(&current[y], &pending[y]));
+                }
+            });
+        });
+
+        group.bench_function(BenchmarkId::new("flat", &label), |b| {
+            let mut current = Buffer::new(rows, cols, Cell::blank());
+            for (y, row) in current.rows_mut().enumerate() {
+                for (x, c) in row.iter_mut().enumerate() {
+                    *c = cell(y, x);
+                }
+            }
+            let mut pending = current.clone();
+            for y in 0..rows {
+                pending[y][(y * 7) % cols] = Cell::new('X');
+            }
+            b.iter(|| {
+                for y in 0..rows {
+                    black_box(find_line_diff(&current[y], &pending[y]));
+                }
+            });
+        });
     }
 
     group.finish();
@@ -145,5 +213,6 @@ criterion_group!(
     bench_find_line_diff,
     bench_dirty_region_operations,
     bench_buffer_swap,
+    bench_screen_diff,
 );
 criterion_main!(benches);
@@ -1,7 +1,7 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use zaz::__bench::hash_line;
+use zaz::__bench::{hash_line, rehash_span};
 use zaz::{Attr, Cell, Color};
 
 // Current hash implementation (multiplication-based)
@@ -173,11 +173,37 @@ fn bench_hash_screen_lines(c: &mut Criterion) {
     group.finish();
 }
 
+/// Rehashing a wide line after a one-word edit: whole line vs the span
+fn bench_rehash_span(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rehash_after_edit");
+
+    for width in [80, 200, 400] {
+        let old: Vec<Cell> = (0..width)
+            .map(|i| Cell::new((b'a' + (i % 26) as u8) as char))
+            .collect();
+        let mut new = old.clone();
+        for cell in &mut new[10..15] {
+            *cell = Cell::new('X');
+        }
+        let hash = hash_line(&old);
+
+        group.bench_with_input(BenchmarkId::new("full", width), &new, |b, new| {
+            b.iter(|| black_box(hash_line(new)));
+        });
+        group.bench_with_input(BenchmarkId::new("span", width), &new, |b, new| {
+            b.iter(|| black_box(rehash_span(hash, &old[10..15], &new[10..15], 10)));
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_hash_functions,
     bench_hash_collision_rate,
     bench_hash_consistency,
     bench_hash_screen_lines,
+    bench_rehash_span,
 );
 criterion_main!(benches);
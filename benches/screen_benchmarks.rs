@@ -225,11 +225,11 @@ fn bench_line_rendering(c: &mut Criterion) {
 
         group.bench_with_input(BenchmarkId::new("full_line", width), &cells, |b, cells| {
             let mut buffer = String::with_capacity(2000);
-            let mut last_style = (Attr::NORMAL, None, None);
+            let mut last_style = (Attr::NORMAL, Color::Reset, Color::Reset);
 
             b.iter(|| {
                 buffer.clear();
-                last_style = (Attr::NORMAL, None, None);
+                last_style = (Attr::NORMAL, Color::Reset, Color::Reset);
 
                 for cell in cells {
                     let current_style = (cell.attr, cell.fg(), cell.bg());
@@ -263,8 +263,8 @@ fn bench_full_screen_simulation(c: &mut Criterion) {
                             } else {
                                 Attr::NORMAL
                             },
-                            None,
-                            None,
+                            Color::Reset,
+                            Color::Reset,
                         )
                     })
                     .collect()
@@ -13,9 +13,35 @@
 extern "C" {
 #endif
 
+/* Error codes
+ *
+ * Functions that fail (returning -1, or NULL for pointers) record an error
+ * code and message for the calling thread; read them with
+ * zaz_last_error_code and zaz_last_error_message. Successful calls leave
+ * the last error unchanged.
+ */
+typedef enum {
+    ZazError_Ok = 0,                 /* No error recorded on this thread */
+    ZazError_InvalidArgument = 1,    /* NULL pointer, invalid UTF-8 or out-of-range value */
+    ZazError_Io = 2,                 /* I/O error talking to the terminal */
+    ZazError_NotATty = 3,            /* The terminal device isn't a TTY */
+    ZazError_NotInitialized = 4,     /* Terminal not initialized, or already closed */
+    ZazError_AlreadyInitialized = 5, /* A screen is already open on this terminal */
+    ZazError_InvalidColorPair = 6,   /* Color pair 0 or one that was never defined */
+    ZazError_InvalidPanel = 7,       /* Unknown or removed panel id */
+    ZazError_InvalidCoordinates = 8, /* Position outside the screen or window */
+    ZazError_InvalidDimensions = 9,  /* Zero or too large window size */
+    ZazError_NotSupported = 10,      /* Not supported on this platform */
+    ZazError_Shutdown = 11,          /* Input was shut down */
+    ZazError_Other = 12,             /* Anything else; see the message */
+} ZazError;
+
 /* Opaque screen handle */
 typedef struct ZazScreen ZazScreen;
 
+/* Opaque window handle */
+typedef struct ZazWindow ZazWindow;
+
 /* Key codes - C representation of Rust enum */
 typedef enum {
     ZazKey_Char = 0,
@@ -45,25 +71,117 @@ typedef enum {
     ZazKey_F11,
     ZazKey_F12,
     ZazKey_Unknown,
+    ZazKey_Insert,
+    ZazKey_Ctrl,        /* Control + char_value */
+    ZazKey_Alt,         /* Alt + char_value */
+    ZazKey_Paste,
+    ZazKey_Mouse,
+    ZazKey_ScrollUp,
+    ZazKey_ScrollDown,
+    ZazKey_ScrollLeft,
+    ZazKey_ScrollRight,
 } ZazKeyTag;
 
 /* Tagged union for key events */
 typedef struct {
     ZazKeyTag tag;
     union {
-        uint32_t char_value; /* For ZazKey_Char */
+        uint32_t char_value; /* For ZazKey_Char, ZazKey_Ctrl and ZazKey_Alt */
     } value;
 } ZazKey;
 
-/* Attribute constants */
-#define ZAZ_ATTR_BOLD           1
-#define ZAZ_ATTR_DIM            2
-#define ZAZ_ATTR_ITALIC         4
-#define ZAZ_ATTR_UNDERLINE      8
-#define ZAZ_ATTR_BLINK          16
-#define ZAZ_ATTR_REVERSE        32
-#define ZAZ_ATTR_HIDDEN         64
-#define ZAZ_ATTR_STRIKETHROUGH  128
+/* Kind of key event (only told apart with the kitty keyboard protocol) */
+typedef enum {
+    ZazKeyEvent_Press = 0,
+    ZazKeyEvent_Repeat,
+    ZazKeyEvent_Release,
+} ZazKeyEventType;
+
+/* A key together with what the terminal reported about it */
+typedef struct {
+    ZazKey key;
+    uint8_t modifiers;          /* ZAZ_MOD_* bits */
+    ZazKeyEventType event_type;
+    uint32_t code;              /* Kitty key code, 0 without the protocol */
+    uint32_t shifted_key;       /* Kitty shifted key code, or 0 */
+    uint32_t base_key;          /* Kitty base layout key code, or 0 */
+} ZazKeyEvent;
+
+/* Kind of event passed to a zaz_run callback */
+typedef enum {
+    ZazEvent_Key = 0,   /* A key was read; see key */
+    ZazEvent_Resize,    /* The terminal changed size; see rows and cols */
+    ZazEvent_Tick,      /* A frame is due */
+} ZazEventKind;
+
+/* Event passed to a zaz_run callback */
+typedef struct {
+    ZazEventKind kind;
+    ZazKeyEvent key;     /* For ZazEvent_Key */
+    uint16_t rows;       /* Terminal size */
+    uint16_t cols;
+    uint64_t elapsed_ms; /* Milliseconds since zaz_run started */
+} ZazEvent;
+
+/* Callback for zaz_run: return 0 to keep running, anything else to stop */
+typedef int32_t (*ZazEventCallback)(ZazScreen* screen, const ZazEvent* event, void* user_data);
+
+/* Modifier bits */
+#define ZAZ_MOD_SHIFT           1
+#define ZAZ_MOD_ALT             2
+#define ZAZ_MOD_CTRL            4
+#define ZAZ_MOD_SUPER           8
+
+/* Colors for zaz_init_pair and zaz_wset_colors: ZAZ_COLOR_DEFAULT, a
+ * palette index 0-255, or ZAZ_RGB(r, g, b) */
+#define ZAZ_COLOR_DEFAULT       (-1)
+#define ZAZ_COLOR_RGB           0x01000000
+#define ZAZ_RGB(r, g, b) \
+    (ZAZ_COLOR_RGB | ((int32_t)(r) << 16) | ((int32_t)(g) << 8) | (int32_t)(b))
+
+/* Image data formats for zaz_display_kitty_image */
+typedef enum {
+    ZazImage_Png = 0,
+    ZazImage_Rgb,
+    ZazImage_Rgba,
+} ZazImageFormat;
+
+/* Attribute flags, OR'd together; the values never change.
+ * Bits outside ZAZ_ATTR_MASK fail with ZazError_InvalidArgument. */
+typedef enum {
+    ZAZ_ATTR_BOLD             = 1,
+    ZAZ_ATTR_DIM              = 2,
+    ZAZ_ATTR_ITALIC           = 4,
+    ZAZ_ATTR_UNDERLINE        = 8,
+    ZAZ_ATTR_BLINK            = 16,
+    ZAZ_ATTR_REVERSE          = 32,
+    ZAZ_ATTR_HIDDEN           = 64,
+    ZAZ_ATTR_STRIKETHROUGH    = 128,
+    ZAZ_ATTR_DOUBLE_UNDERLINE = 256,
+    ZAZ_ATTR_OVERLINE         = 512,
+    ZAZ_ATTR_SUPERSCRIPT      = 1024,
+    ZAZ_ATTR_SUBSCRIPT        = 2048,
+} ZazAttr;
+
+#define ZAZ_ATTR_MASK 0x0FFF
+
+/* Errors */
+
+/**
+ * Code of the last error on the calling thread
+ */
+ZazError zaz_last_error_code(void);
+
+/**
+ * Message for the last error on the calling thread, or NULL if none
+ * Owned by the library; valid until the next failing call on this thread
+ */
+const char* zaz_last_error_message(void);
+
+/**
+ * Forget the last error on the calling thread
+ */
+void zaz_clear_error(void);
 
 /* Screen management */
 
@@ -122,6 +240,26 @@ int32_t zaz_getch(ZazScreen* screen, ZazKey* key_out);
  */
 int32_t zaz_getch_timeout(ZazScreen* screen, uint64_t timeout_ms, ZazKey* key_out);
 
+/**
+ * Get a key with its modifiers and kitty protocol data
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_getch_event(ZazScreen* screen, ZazKeyEvent* event_out);
+
+/**
+ * Get a key event with timeout
+ * Returns 1 if a key was read (event_out is set), 0 if timeout, -1 on error
+ */
+int32_t zaz_getch_event_timeout(ZazScreen* screen, uint64_t timeout_ms, ZazKeyEvent* event_out);
+
+/**
+ * Run an event loop: read keys, watch for resizes, tick fps times a second
+ * (0 for no ticks) and refresh after every batch of callbacks
+ * The callback may draw on the screen but must not end it
+ * Returns 0 once the callback asks to stop, -1 on error
+ */
+int32_t zaz_run(ZazScreen* screen, ZazEventCallback callback, void* user_data, uint32_t fps);
+
 /**
  * Set foreground color (RGB)
  * Returns 0 on success, -1 on error
@@ -154,6 +292,164 @@ int32_t zaz_attroff(ZazScreen* screen, uint32_t attr);
  */
 uint32_t zaz_get_size(ZazScreen* screen);
 
+/**
+ * Change the screen size, for hosts that learn it out of band; the next
+ * refresh repaints everything (zaz_run does this on its own)
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_resize(ZazScreen* screen, uint16_t rows, uint16_t cols);
+
+/**
+ * Define color pair `pair` (1 or more) as fg on bg (see ZAZ_COLOR_*)
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_init_pair(ZazScreen* screen, uint16_t pair, int32_t fg, int32_t bg);
+
+/**
+ * Draw with the colors of a pair defined by zaz_init_pair
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_color_pair(ZazScreen* screen, uint16_t pair);
+
+/**
+ * Draw a box around the edge of the screen
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_draw_box(ZazScreen* screen);
+
+/* Windows */
+
+/**
+ * Create a window of height x width at (y, x)
+ * Returns NULL on error. Free with zaz_delwin, or hand to zaz_new_panel.
+ */
+ZazWindow* zaz_newwin(ZazScreen* screen, uint16_t height, uint16_t width, uint16_t y, uint16_t x);
+
+/**
+ * Free a window created by zaz_newwin
+ */
+void zaz_delwin(ZazWindow* window);
+
+/**
+ * Print string at the window's cursor position
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wprint(ZazWindow* window, const char* text);
+
+/**
+ * Print string at position (y, x) within the window
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wmvprint(ZazWindow* window, uint16_t y, uint16_t x, const char* text);
+
+/**
+ * Draw a box around the edge of the window
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wdraw_box(ZazWindow* window);
+
+/**
+ * Clear the window
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wclear(ZazWindow* window);
+
+/**
+ * Turn attributes on / off in the window (ZAZ_ATTR_* constants)
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wattron(ZazWindow* window, uint32_t attr);
+int32_t zaz_wattroff(ZazWindow* window, uint32_t attr);
+
+/**
+ * Set the window's foreground and background colors (see ZAZ_COLOR_*)
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wset_colors(ZazWindow* window, int32_t fg, int32_t bg);
+
+/**
+ * Queue the window for the next zaz_doupdate
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_wnoutrefresh(ZazWindow* window);
+
+/**
+ * Draw every queued window and panel on the screen and refresh
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_doupdate(ZazScreen* screen);
+
+/* Panels */
+
+/**
+ * Put a window on top of the screen's panel stack
+ * Takes ownership of the window: reach it through zaz_panel_window afterwards
+ * Returns the panel id, or -1 on error
+ */
+int64_t zaz_new_panel(ZazScreen* screen, ZazWindow* window);
+
+/**
+ * Borrow the window of a panel, valid until the panel is deleted
+ * Returns NULL if there is no such panel. Don't pass it to zaz_delwin.
+ */
+ZazWindow* zaz_panel_window(ZazScreen* screen, int64_t panel);
+
+/**
+ * Remove a panel and free its window
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_del_panel(ZazScreen* screen, int64_t panel);
+
+/**
+ * Show, hide, raise or lower a panel
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_panel_show(ZazScreen* screen, int64_t panel);
+int32_t zaz_panel_hide(ZazScreen* screen, int64_t panel);
+int32_t zaz_panel_top(ZazScreen* screen, int64_t panel);
+int32_t zaz_panel_bottom(ZazScreen* screen, int64_t panel);
+
+/**
+ * Queue every visible panel in stacking order; follow with zaz_doupdate
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_update_panels(ZazScreen* screen);
+
+/* Images */
+
+/**
+ * Show an image with the kitty graphics protocol at cell (y, x), scaled to
+ * cols x rows cells (0 = natural size)
+ * width_px and height_px are required for RGB and RGBA data
+ * The image is written by the next refresh
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_display_kitty_image(
+    ZazScreen* screen,
+    const uint8_t* data,
+    size_t data_len,
+    ZazImageFormat format,
+    uint32_t width_px,
+    uint32_t height_px,
+    uint16_t y,
+    uint16_t x,
+    uint16_t cols,
+    uint16_t rows
+);
+
+/**
+ * Show RGB data (3 bytes per pixel) as a sixel image at the cursor position
+ * The image is written by the next refresh
+ * Returns 0 on success, -1 on error
+ */
+int32_t zaz_display_sixel(
+    ZazScreen* screen,
+    const uint8_t* data,
+    size_t data_len,
+    uint32_t width,
+    uint32_t height
+);
+
 /**
  * Render mosaic (Unicode block art) from RGB image data
  *
@@ -128,8 +128,30 @@ pub const Screen = opaque {
             .width = @intCast(size & 0xFFFF),
         };
     }
+
+    /// Change the screen size, for hosts that learn it out of band
+    pub fn resize(self: *Screen, height: u16, width: u16) !void {
+        const result = c.zaz_resize(@ptrCast(self), height, width);
+        if (result != 0) {
+            return error.ResizeFailed;
+        }
+    }
 };
 
+/// Code of the last error reported on the calling thread
+pub fn lastErrorCode() c.ZazError {
+    return c.zaz_last_error_code();
+}
+
+/// Message for the last error on the calling thread, if any
+pub fn lastErrorMessage() ?[:0]const u8 {
+    const message = c.zaz_last_error_message();
+    if (message == null) {
+        return null;
+    }
+    return std.mem.span(message);
+}
+
 /// Key events
 pub const Key = union(enum) {
     char: u32,
@@ -159,6 +181,15 @@ pub const Key = union(enum) {
     f11,
     f12,
     unknown,
+    insert,
+    ctrl: u32,
+    alt: u32,
+    paste,
+    mouse,
+    scroll_up,
+    scroll_down,
+    scroll_left,
+    scroll_right,
 
     fn fromC(c_key: c.ZazKey) Key {
         return switch (c_key.tag) {
@@ -189,6 +220,15 @@ pub const Key = union(enum) {
             c.ZazKey_F11 => .f11,
             c.ZazKey_F12 => .f12,
             c.ZazKey_Unknown => .unknown,
+            c.ZazKey_Insert => .insert,
+            c.ZazKey_Ctrl => .{ .ctrl = c_key.value.char_value },
+            c.ZazKey_Alt => .{ .alt = c_key.value.char_value },
+            c.ZazKey_Paste => .paste,
+            c.ZazKey_Mouse => .mouse,
+            c.ZazKey_ScrollUp => .scroll_up,
+            c.ZazKey_ScrollDown => .scroll_down,
+            c.ZazKey_ScrollLeft => .scroll_left,
+            c.ZazKey_ScrollRight => .scroll_right,
             else => .unknown,
         };
     }
@@ -202,5 +242,10 @@ pub const Attr = enum(u32) {
     underline = c.ZAZ_ATTR_UNDERLINE,
     blink = c.ZAZ_ATTR_BLINK,
     reverse = c.ZAZ_ATTR_REVERSE,
+    hidden = c.ZAZ_ATTR_HIDDEN,
     strikethrough = c.ZAZ_ATTR_STRIKETHROUGH,
+    double_underline = c.ZAZ_ATTR_DOUBLE_UNDERLINE,
+    overline = c.ZAZ_ATTR_OVERLINE,
+    superscript = c.ZAZ_ATTR_SUPERSCRIPT,
+    subscript = c.ZAZ_ATTR_SUBSCRIPT,
 };
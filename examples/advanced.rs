@@ -1,4 +1,4 @@
-use zaz::{ACS_BULLET, ACS_DIAMOND, Color, Panel, Screen};
+use zaz::{ACS_BULLET, ACS_DIAMOND, Color, Screen};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut scr = Screen::init()?;
@@ -22,14 +22,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     win2.mvprint(2, 2, "Panel 2")?;
     win2.mvprint(3, 2, &format!("{} ACS Bullet", ACS_BULLET.as_char()))?;
 
-    // Create panels
-    let mut panel1 = Panel::new(win1)?;
-    let mut panel2 = Panel::new(win2)?;
+    // Create panels (the second one starts on top)
+    let panel1 = scr.new_panel(win1)?;
+    let panel2 = scr.new_panel(win2)?;
 
-    // Use wnoutrefresh and doupdate for efficient rendering
-    panel1.wnoutrefresh()?;
-    panel2.wnoutrefresh()?;
-    Screen::doupdate()?;
+    // Raise the first panel above the second one
+    scr.panels_mut().move_above(panel1, panel2)?;
+
+    // Queue panels in z-order and flush them in one update
+    scr.update_panels()?;
+    scr.doupdate()?;
 
     scr.mvprint(18, 2, "Press any key within 3 seconds...")?;
     scr.refresh()?;
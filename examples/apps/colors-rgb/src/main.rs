@@ -12,8 +12,6 @@
 
 use std::time::{Duration, Instant};
 
-use palette::convert::FromColorUnclamped;
-use palette::{Okhsv, Srgb};
 use zaz::{Color, Screen};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -55,7 +53,8 @@ struct ColorsWidget {
 
 impl App {
     fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let screen = Screen::init()?;
+        let mut screen = Screen::init()?;
+        screen.set_target_fps(60);
         Ok(Self {
             screen,
             fps_widget: FpsWidget::new(),
@@ -69,6 +68,7 @@ impl App {
         while running {
             self.render()?;
             running = self.handle_events()?;
+            self.screen.tick()?;
         }
 
         self.screen.endwin()?;
@@ -109,8 +109,8 @@ impl App {
     }
 
     fn handle_events(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
-        // Check for input with timeout to target ~60 FPS (16ms per frame)
-        if let Some(_key) = self.screen.getch_timeout(16)? {
+        // Poll for input; tick() paces the loop to the target frame rate
+        if let Some(_key) = self.screen.getch_timeout(0)? {
             return Ok(false); // Any key press quits
         }
 
@@ -175,11 +175,7 @@ impl ColorsWidget {
             for x in 0..width {
                 let hue = x as f32 * 360.0 / width as f32;
                 let value = (height - y) as f32 / height as f32;
-                let saturation = Okhsv::max_saturation();
-                let color = Okhsv::new(hue, saturation, value);
-                let color = Srgb::<f32>::from_color_unclamped(color);
-                let color: Srgb<u8> = color.into_format();
-                row.push(Color::Rgb(color.red, color.green, color.blue));
+                row.push(Color::from_hsv(hue, 1.0, value));
             }
             self.colors.push(row);
         }
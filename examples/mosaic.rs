@@ -2,7 +2,7 @@
 ///
 /// This example shows how to use Zaz's mosaic module to render images
 /// as Unicode block art with ANSI colors in the terminal.
-use zaz::{MosaicConfig, SymbolSet, render_mosaic};
+use zaz::{Dither, MosaicConfig, MosaicMode, Resample, SymbolSet, render_mosaic};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load the yellow.png image from resources
@@ -45,5 +45,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let art4 = render_mosaic(data, width as usize, height as usize, &config4);
     println!("{}", art4);
 
+    // Demo 5: True color half blocks (no thresholding)
+    println!("\n5. True Color Half Blocks:");
+    let config5 = MosaicConfig::with_width(60).mode(MosaicMode::Color);
+    let art5 = render_mosaic(data, width as usize, height as usize, &config5);
+    println!("{}", art5);
+
+    // Demo 6: True color with least-squares quadrant fitting
+    println!("\n6. True Color with All Blocks:");
+    let config6 = MosaicConfig::with_width(60)
+        .symbols(SymbolSet::All)
+        .mode(MosaicMode::Color);
+    let art6 = render_mosaic(data, width as usize, height as usize, &config6);
+    println!("{}", art6);
+
+    // Demo 7: Lanczos resampling with Floyd–Steinberg dithering
+    println!("\n7. Lanczos + Floyd-Steinberg Dithering:");
+    let config7 = MosaicConfig::with_width(60)
+        .symbols(SymbolSet::Quarter)
+        .resample(Resample::Lanczos3)
+        .dither(Dither::FloydSteinberg);
+    let art7 = render_mosaic(data, width as usize, height as usize, &config7);
+    println!("{}", art7);
+
     Ok(())
 }
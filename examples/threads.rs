@@ -0,0 +1,41 @@
+use std::thread;
+use std::time::Duration;
+use zaz::{Attr, Color, Renderer, Screen, Style};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut scr = Screen::init()?;
+    let mut renderer = Renderer::new();
+
+    scr.mvprint(
+        0,
+        2,
+        "Workers draw through a DrawHandle; press any key to quit",
+    )?;
+    scr.refresh()?;
+
+    // Each worker reports its progress on its own line
+    for worker in 0..4u16 {
+        let handle = renderer.handle();
+        thread::spawn(move || {
+            let delay = Duration::from_millis(40 + worker as u64 * 25);
+            for percent in 0..=100 {
+                let bar: String = "#".repeat(percent / 4);
+                let line = format!("worker {}: [{:<25}] {:3}%", worker, bar, percent);
+                if handle.print(2 + worker, 2, line).is_err() {
+                    return; // The renderer is gone
+                }
+                thread::sleep(delay);
+            }
+            let done = Style::new().fg(Color::Green).attr(Attr::BOLD);
+            handle.print_styled(2 + worker, 40, "done", done).ok();
+        });
+    }
+
+    // The main thread owns the screen: it reads keys and renders
+    while scr.getch_timeout(0)?.is_none() {
+        renderer.wait(&mut scr, Duration::from_millis(16))?;
+    }
+
+    scr.endwin()?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Accessibility announcements
+//!
+//! Screen readers can't make sense of a full-screen UI from its escape
+//! codes. Apps register an announcer callback instead and send it the
+//! messages worth speaking; the screen can also mirror the text of every
+//! region it redraws, so speech output follows the display without
+//! scraping the terminal.
+
+use crate::layout::Rect;
+
+/// How urgently an announcement should be spoken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    /// Spoken after the current speech finishes
+    #[default]
+    Polite,
+    /// Interrupts the current speech
+    Assertive,
+}
+
+/// Text for the announcer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Announcement<'a> {
+    pub text: &'a str,
+    pub priority: Priority,
+    /// Screen cells the text was drawn in, for mirrored output; `None` for
+    /// `Screen::announce`
+    pub region: Option<Rect>,
+}
+
+/// Callback receiving announcements
+pub(crate) type Announcer = Box<dyn FnMut(&Announcement) + Send>;
+
+/// Announcer registered on a screen and whether redrawn text is mirrored
+#[derive(Default)]
+pub(crate) struct Accessibility {
+    pub(crate) announcer: Option<Announcer>,
+    pub(crate) mirror: bool,
+}
+
+impl Accessibility {
+    /// Whether redrawn regions should be collected for the announcer
+    pub(crate) fn mirrors(&self) -> bool {
+        self.mirror && self.announcer.is_some()
+    }
+
+    /// Send `text` to the announcer, if there is one; blank text is skipped
+    pub(crate) fn announce(&mut self, text: &str, priority: Priority, region: Option<Rect>) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        if let Some(announcer) = self.announcer.as_mut() {
+            announcer(&Announcement {
+                text,
+                priority,
+                region,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_announce() {
+        let heard = Arc::new(Mutex::new(Vec::new()));
+        let sink = heard.clone();
+        let mut a11y = Accessibility::default();
+        a11y.announce("nobody listening", Priority::Polite, None);
+
+        a11y.announcer = Some(Box::new(move |a: &Announcement| {
+            sink.lock().unwrap().push((a.text.to_string(), a.priority));
+        }));
+        a11y.announce("  saved  ", Priority::Assertive, None);
+        a11y.announce("   ", Priority::Polite, None);
+        assert_eq!(
+            *heard.lock().unwrap(),
+            vec![("saved".to_string(), Priority::Assertive)]
+        );
+        assert!(!a11y.mirrors());
+        a11y.mirror = true;
+        assert!(a11y.mirrors());
+    }
+}
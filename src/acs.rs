@@ -1,3 +1,5 @@
+use crate::caps::Capabilities;
+
 /// Alternative Character Set (ACS) for box drawing and special characters
 ///
 /// These are special characters used for drawing boxes, borders, and other
@@ -12,6 +14,56 @@ impl AcsChar {
     pub fn as_char(&self) -> char {
         self.0
     }
+
+    /// ASCII stand-in for the character
+    pub fn ascii(&self) -> char {
+        ascii_fallback(self.0)
+    }
+
+    /// The character to draw on a terminal with `caps`: itself, or its
+    /// ASCII stand-in without Unicode
+    pub fn resolved(&self, caps: &Capabilities) -> char {
+        if caps.unicode { self.0 } else { self.ascii() }
+    }
+}
+
+/// Border characters as drawn: unchanged with Unicode, else their ASCII
+/// stand-ins
+pub(crate) fn line_chars<const N: usize>(unicode: bool, chars: [char; N]) -> [char; N] {
+    if unicode {
+        chars
+    } else {
+        chars.map(ascii_fallback)
+    }
+}
+
+/// ASCII stand-in for a line-drawing or ACS character, as curses uses on
+/// terminals without them; other characters are returned unchanged
+pub(crate) fn ascii_fallback(ch: char) -> char {
+    match ch {
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' | '╭' | '╮' | '╯' | '╰' | '┏' | '┓'
+        | '┗' | '┛' | '╔' | '╗' | '╚' | '╝' | '╠' | '╣' | '╦' | '╩' | '╬' => {
+            '+'
+        }
+        '─' | '━' | '═' | '⎺' | '⎻' | '⎼' => '-',
+        '⎽' => '_',
+        '│' | '┃' | '║' => '|',
+        '◆' => '+',
+        '░' => ':',
+        '▒' | '▓' | '█' | '±' => '#',
+        '°' => '\'',
+        '•' => 'o',
+        '←' => '<',
+        '→' => '>',
+        '↓' => 'v',
+        '↑' => '^',
+        '≤' => '<',
+        '≥' => '>',
+        'π' => '*',
+        '≠' => '!',
+        '£' => 'f',
+        other => other,
+    }
 }
 
 /// Upper left corner
@@ -114,6 +166,17 @@ pub const ACS_STERLING: AcsChar = AcsChar('£');
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ascii_fallback() {
+        let ascii = Capabilities::default().unicode(false);
+        assert_eq!(ACS_ULCORNER.resolved(&ascii), '+');
+        assert_eq!(ACS_HLINE.resolved(&ascii), '-');
+        assert_eq!(ACS_VLINE.resolved(&ascii), '|');
+        assert_eq!(ACS_DARROW.resolved(&ascii), 'v');
+        assert_eq!(ACS_HLINE.resolved(&Capabilities::default()), '─');
+        assert_eq!(ascii_fallback('x'), 'x');
+    }
+
     #[test]
     fn test_acs_corners() {
         assert_eq!(ACS_ULCORNER.as_char(), '┌');
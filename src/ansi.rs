@@ -0,0 +1,268 @@
+//! Styled text from ANSI escape sequences
+//!
+//! Output of other tools (`git diff --color`, `ls --color`, ...) carries its
+//! colors as SGR sequences. Passed through raw, they would change the
+//! terminal's style behind the renderer's back; `ansi_runs` turns them into
+//! styles for cells instead, and drops every other escape sequence.
+
+use crate::attr::Attr;
+use crate::color::Color;
+use crate::style::Style;
+
+const BASIC: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+const BRIGHT: [Color; 8] = [
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// SGR parameter for each attribute it turns on
+const ATTR_ON: &[(u16, Attr)] = &[
+    (1, Attr::BOLD),
+    (2, Attr::DIM),
+    (3, Attr::ITALIC),
+    (4, Attr::UNDERLINE),
+    (5, Attr::BLINK),
+    (6, Attr::BLINK),
+    (7, Attr::REVERSE),
+    (8, Attr::HIDDEN),
+    (9, Attr::STRIKETHROUGH),
+    (21, Attr::DOUBLE_UNDERLINE),
+    (53, Attr::OVERLINE),
+    (73, Attr::SUPERSCRIPT),
+    (74, Attr::SUBSCRIPT),
+];
+
+/// SGR parameter for each set of attributes it turns off
+const ATTR_OFF: &[(u16, Attr)] = &[
+    (22, Attr(Attr::BOLD.0 | Attr::DIM.0)),
+    (23, Attr::ITALIC),
+    (24, Attr(Attr::UNDERLINE.0 | Attr::DOUBLE_UNDERLINE.0)),
+    (25, Attr::BLINK),
+    (27, Attr::REVERSE),
+    (28, Attr::HIDDEN),
+    (29, Attr::STRIKETHROUGH),
+    (55, Attr::OVERLINE),
+    (75, Attr(Attr::SUPERSCRIPT.0 | Attr::SUBSCRIPT.0)),
+];
+
+/// Split `text` into runs of plain text and the style each is drawn in
+///
+/// Styles start from `base`, and SGR 0 goes back to it rather than to the
+/// terminal default. Sequences cut off at the end of `text` are dropped.
+pub(crate) fn ansi_runs(text: &str, base: Style) -> Vec<(Style, &str)> {
+    let bytes = text.as_bytes();
+    let mut runs = Vec::new();
+    let mut style = base;
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != 0x1b {
+            i += 1;
+            continue;
+        }
+        if start < i {
+            runs.push((style, &text[start..i]));
+        }
+        let len = sequence_len(&bytes[i..]);
+        if len > 2 && bytes[i + 1] == b'[' && bytes[i + len - 1] == b'm' {
+            apply_sgr(&text[i + 2..i + len - 1], base, &mut style);
+        }
+        i += len;
+        start = i;
+    }
+    if start < bytes.len() {
+        runs.push((style, &text[start..]));
+    }
+    runs
+}
+
+/// Length of the escape sequence at the start of `seq`; all of `seq` if it
+/// is cut off
+fn sequence_len(seq: &[u8]) -> usize {
+    match seq.get(1) {
+        Some(b'[') => seq[2..]
+            .iter()
+            .position(|b| (0x40..=0x7e).contains(b))
+            .map_or(seq.len(), |end| end + 3),
+        // String sequences (OSC, DCS, SOS, PM, APC) end with BEL or ST
+        Some(b']' | b'P' | b'X' | b'^' | b'_') => seq[2..]
+            .iter()
+            .enumerate()
+            .find_map(|(i, &b)| match b {
+                0x07 => Some(i + 3),
+                0x1b if seq.get(i + 3) == Some(&b'\\') => Some(i + 4),
+                _ => None,
+            })
+            .unwrap_or(seq.len()),
+        // Intermediate bytes, then a final byte, e.g. `ESC ( B`
+        Some(b) if b.is_ascii() => seq[1..]
+            .iter()
+            .position(|b| !(0x20..=0x2f).contains(b))
+            .map_or(seq.len(), |end| end + 1 + seq[1 + end].is_ascii() as usize),
+        // ESC before a multi-byte character: drop only the ESC
+        _ => 1,
+    }
+}
+
+/// Apply SGR parameters (the text between `CSI` and `m`) to `style`
+fn apply_sgr(params: &str, base: Style, style: &mut Style) {
+    // Private sequences such as `CSI > 4 ; 2 m` (modifyOtherKeys)
+    if params.starts_with(['<', '=', '>', '?']) {
+        return;
+    }
+
+    let mut groups = params.split(';');
+    while let Some(group) = groups.next() {
+        // ITU T.416 form with `:` separators, e.g. `38:2::255:0:0` or `4:3`
+        if group.contains(':') {
+            let mut subparams = group.split(':');
+            let code = subparams.next().and_then(|code| code.parse::<u16>().ok());
+            match code {
+                Some(38) => style.fg = colon_color(subparams).unwrap_or(style.fg),
+                Some(48) => style.bg = colon_color(subparams).unwrap_or(style.bg),
+                Some(4) => {
+                    let underline = Attr(Attr::UNDERLINE.0 | Attr::DOUBLE_UNDERLINE.0);
+                    style.attr = style.attr & !underline;
+                    match subparams.next() {
+                        Some("0") => {}
+                        Some("2") => style.attr = style.attr | Attr::DOUBLE_UNDERLINE,
+                        _ => style.attr = style.attr | Attr::UNDERLINE,
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let code = if group.is_empty() {
+            0
+        } else {
+            match group.parse::<u16>() {
+                Ok(code) => code,
+                Err(_) => continue,
+            }
+        };
+        match code {
+            0 => *style = base,
+            30..=37 => style.fg = BASIC[code as usize - 30],
+            40..=47 => style.bg = BASIC[code as usize - 40],
+            90..=97 => style.fg = BRIGHT[code as usize - 90],
+            100..=107 => style.bg = BRIGHT[code as usize - 100],
+            39 => style.fg = Color::Reset,
+            49 => style.bg = Color::Reset,
+            38 => style.fg = semicolon_color(&mut groups).unwrap_or(style.fg),
+            48 => style.bg = semicolon_color(&mut groups).unwrap_or(style.bg),
+            // Underline color isn't kept; skip its arguments
+            58 => {
+                semicolon_color(&mut groups);
+            }
+            _ => {
+                if let Some(&(_, attr)) = ATTR_ON.iter().find(|(c, _)| *c == code) {
+                    style.attr = style.attr | attr;
+                } else if let Some(&(_, attr)) = ATTR_OFF.iter().find(|(c, _)| *c == code) {
+                    style.attr = style.attr & !attr;
+                }
+            }
+        }
+    }
+}
+
+/// Extended color from the parameters after 38/48: `5;n` or `2;r;g;b`
+fn semicolon_color<'a>(params: &mut impl Iterator<Item = &'a str>) -> Option<Color> {
+    let mut next = || params.next().and_then(|p| p.parse::<u8>().ok());
+    match next()? {
+        5 => Some(Color::Ansi256(next()?)),
+        2 => Some(Color::Rgb(next()?, next()?, next()?)),
+        _ => None,
+    }
+}
+
+/// Extended color from the subparameters after 38/48: `5:n`, `2:r:g:b`, or
+/// `2:colorspace:r:g:b`
+fn colon_color<'a>(subparams: impl Iterator<Item = &'a str>) -> Option<Color> {
+    let values: Vec<&str> = subparams.collect();
+    let number = |i: usize| values.get(i).and_then(|v| v.parse::<u8>().ok());
+    match *values.first()? {
+        "5" => Some(Color::Ansi256(number(1)?)),
+        "2" => {
+            let skip = if values.len() >= 5 { 2 } else { 1 };
+            Some(Color::Rgb(
+                number(skip)?,
+                number(skip + 1)?,
+                number(skip + 2)?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles(text: &str) -> Vec<(Style, &str)> {
+        ansi_runs(text, Style::new())
+    }
+
+    #[test]
+    fn test_basic_sgr() {
+        let runs = styles("plain \x1b[1;31mred\x1b[0m done");
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0], (Style::new(), "plain "));
+        assert_eq!(runs[1].0, Style::new().attr(Attr::BOLD).fg(Color::Red));
+        assert_eq!(runs[1].1, "red");
+        assert_eq!(runs[2], (Style::new(), " done"));
+    }
+
+    #[test]
+    fn test_extended_colors() {
+        let runs = styles("\x1b[38;5;196;48;2;1;2;3ma\x1b[38:2::4:5:6mb\x1b[39;49mc");
+        assert_eq!(runs[0].0.fg, Color::Ansi256(196));
+        assert_eq!(runs[0].0.bg, Color::Rgb(1, 2, 3));
+        assert_eq!(runs[1].0.fg, Color::Rgb(4, 5, 6));
+        assert_eq!(runs[2].0, Style::new());
+    }
+
+    #[test]
+    fn test_attributes_off() {
+        let runs = styles("\x1b[1;2;4:2;9ma\x1b[22;24mb");
+        assert_eq!(
+            runs[0].0.attr,
+            Attr::BOLD | Attr::DIM | Attr::DOUBLE_UNDERLINE | Attr::STRIKETHROUGH
+        );
+        assert_eq!(runs[1].0.attr, Attr::STRIKETHROUGH);
+    }
+
+    #[test]
+    fn test_other_sequences_dropped() {
+        let runs = styles("a\x1b[2Jb\x1b]0;title\x07c\x1b]8;;url\x1b\\d\x1b(Be\x1b(é\x1b[3");
+        let text: String = runs.iter().map(|(_, run)| *run).collect();
+        assert_eq!(text, "abcdeé");
+        assert!(runs.iter().all(|(style, _)| *style == Style::new()));
+    }
+
+    #[test]
+    fn test_reset_returns_to_base() {
+        let base = Style::new().fg(Color::Cyan);
+        let runs = ansi_runs("\x1b[31mx\x1b[my", base);
+        assert_eq!(runs[0].0.fg, Color::Red);
+        assert_eq!(runs[1].0, base);
+    }
+}
@@ -14,6 +14,12 @@ impl Attr {
     pub const REVERSE: Attr = Attr(1 << 5);
     pub const HIDDEN: Attr = Attr(1 << 6);
     pub const STRIKETHROUGH: Attr = Attr(1 << 7);
+    pub const DOUBLE_UNDERLINE: Attr = Attr(1 << 8);
+    pub const OVERLINE: Attr = Attr(1 << 9);
+    pub const SUPERSCRIPT: Attr = Attr(1 << 10);
+    pub const SUBSCRIPT: Attr = Attr(1 << 11);
+    /// Every attribute above
+    pub const ALL: Attr = Attr((1 << 12) - 1);
 
     pub const fn new() -> Self {
         Self::NORMAL
@@ -27,39 +33,12 @@ impl Attr {
         (self.0 & other.0) == other.0
     }
 
-    pub const fn is_empty(&self) -> bool {
-        self.0 == 0
+    pub const fn intersects(&self, other: Attr) -> bool {
+        (self.0 & other.0) != 0
     }
 
-    pub(crate) fn to_ansi_codes(&self) -> Vec<&'static str> {
-        let mut codes = Vec::new();
-
-        if self.contains(Attr::BOLD) {
-            codes.push("1");
-        }
-        if self.contains(Attr::DIM) {
-            codes.push("2");
-        }
-        if self.contains(Attr::ITALIC) {
-            codes.push("3");
-        }
-        if self.contains(Attr::UNDERLINE) {
-            codes.push("4");
-        }
-        if self.contains(Attr::BLINK) {
-            codes.push("5");
-        }
-        if self.contains(Attr::REVERSE) {
-            codes.push("7");
-        }
-        if self.contains(Attr::HIDDEN) {
-            codes.push("8");
-        }
-        if self.contains(Attr::STRIKETHROUGH) {
-            codes.push("9");
-        }
-
-        codes
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
     }
 }
 
@@ -93,10 +72,69 @@ impl Default for Attr {
     }
 }
 
+/// What to draw instead of attributes the terminal doesn't support
+///
+/// Unsupported attributes are dropped at emission time, or replaced by a
+/// substitute the terminal does support. The default draws ITALIC and
+/// DOUBLE_UNDERLINE as UNDERLINE and drops the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrPolicy {
+    substitutes: Vec<(Attr, Attr)>,
+}
+
+impl Default for AttrPolicy {
+    fn default() -> Self {
+        Self::drop_all()
+            .substitute(Attr::ITALIC, Attr::UNDERLINE)
+            .substitute(Attr::DOUBLE_UNDERLINE, Attr::UNDERLINE)
+    }
+}
+
+impl AttrPolicy {
+    /// Drop every unsupported attribute
+    pub fn drop_all() -> Self {
+        Self {
+            substitutes: Vec::new(),
+        }
+    }
+
+    /// Draw `attr` as `with` where `attr` is unsupported
+    pub fn substitute(mut self, attr: Attr, with: Attr) -> Self {
+        self.substitutes.retain(|&(from, _)| from != attr);
+        self.substitutes.push((attr, with));
+        self
+    }
+
+    /// Drop `attr` where it is unsupported, removing any substitute
+    pub fn drop(mut self, attr: Attr) -> Self {
+        self.substitutes.retain(|&(from, _)| from != attr);
+        self
+    }
+
+    /// The attributes to emit for `attr` on a terminal supporting `supported`
+    pub fn apply(&self, attr: Attr, supported: Attr) -> Attr {
+        let missing = attr & !supported;
+        if missing.is_empty() {
+            return attr;
+        }
+        let mut result = attr & supported;
+        for &(from, with) in &self.substitutes {
+            if missing.intersects(from) {
+                result = result | (with & supported);
+            }
+        }
+        result
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn codes(attr: Attr) -> Vec<&'static str> {
+        crate::sgr::attr_codes(attr).collect()
+    }
+
     #[test]
     fn test_attr_creation() {
         let attr = Attr::new();
@@ -121,10 +159,18 @@ mod tests {
         assert!(!attr.contains(Attr::UNDERLINE));
     }
 
+    #[test]
+    fn test_attr_intersects() {
+        let attr = Attr::BOLD | Attr::ITALIC;
+        assert!(attr.intersects(Attr::BOLD | Attr::UNDERLINE));
+        assert!(!attr.intersects(Attr::UNDERLINE | Attr::REVERSE));
+        assert!(!Attr::NORMAL.intersects(Attr::BOLD));
+    }
+
     #[test]
     fn test_attr_ansi_codes() {
         let attr = Attr::BOLD | Attr::UNDERLINE;
-        let codes = attr.to_ansi_codes();
+        let codes = codes(attr);
         assert!(codes.contains(&"1"));
         assert!(codes.contains(&"4"));
         assert_eq!(codes.len(), 2);
@@ -134,7 +180,7 @@ mod tests {
     fn test_attr_normal() {
         let attr = Attr::NORMAL;
         assert!(attr.is_empty());
-        assert_eq!(attr.to_ansi_codes().len(), 0);
+        assert_eq!(codes(attr).len(), 0);
     }
 
     #[test]
@@ -147,14 +193,41 @@ mod tests {
             | Attr::REVERSE
             | Attr::HIDDEN
             | Attr::STRIKETHROUGH;
-        let codes = attr.to_ansi_codes();
+        let codes = codes(attr);
         assert_eq!(codes.len(), 8);
     }
 
+    #[test]
+    fn test_attr_extended_codes() {
+        let attr = Attr::DOUBLE_UNDERLINE | Attr::OVERLINE | Attr::SUPERSCRIPT;
+        assert_eq!(codes(attr), vec!["21", "53", "73"]);
+        assert_eq!(codes(Attr::SUBSCRIPT), vec!["74"]);
+    }
+
     #[test]
     fn test_attr_equality() {
         assert_eq!(Attr::BOLD, Attr::BOLD);
         assert_ne!(Attr::BOLD, Attr::ITALIC);
         assert_eq!(Attr::BOLD | Attr::ITALIC, Attr::ITALIC | Attr::BOLD);
     }
+
+    #[test]
+    fn test_policy() {
+        let supported = Attr::BOLD | Attr::UNDERLINE | Attr::REVERSE;
+        let policy = AttrPolicy::default();
+        assert_eq!(
+            policy.apply(Attr::ITALIC | Attr::BOLD, supported),
+            Attr::BOLD | Attr::UNDERLINE
+        );
+        assert_eq!(policy.apply(Attr::BLINK, supported), Attr::NORMAL);
+        assert_eq!(policy.apply(Attr::ITALIC, Attr::ALL), Attr::ITALIC);
+
+        let policy = policy
+            .drop(Attr::ITALIC)
+            .substitute(Attr::BLINK, Attr::BOLD);
+        assert_eq!(policy.apply(Attr::ITALIC, supported), Attr::NORMAL);
+        assert_eq!(policy.apply(Attr::BLINK, supported), Attr::BOLD);
+        // Substitutes the terminal lacks are dropped too
+        assert_eq!(policy.apply(Attr::BLINK, Attr::NORMAL), Attr::NORMAL);
+    }
 }
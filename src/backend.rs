@@ -1,18 +1,127 @@
-use crate::error::{Error, Result};
+use crate::error::{Error, Result, ResultExt};
+#[cfg(unix)]
+use crate::escape::{EscapeParser, Progress};
 use crate::input::Key;
-use std::io::{self, Read, Write};
-use std::sync::{Mutex, OnceLock};
+use crate::keymap::KeyMap;
+use crate::paste::{PASTE_START, PasteOptions};
+#[cfg(unix)]
+use crate::platform_io::InputReader;
+use crate::query::Reply;
+#[cfg(unix)]
+use crate::query::{DA1_QUERY, split_replies};
+use crate::wakeup::Wakeup;
+#[cfg(not(unix))]
+use std::io::Write;
+use std::io::{self, Read};
+use std::sync::Mutex;
+
+/// How long to wait after ESC before taking it as the Escape key
+pub(crate) const ESC_SEQUENCE_WAIT_MS: u64 = 10;
+/// Once a sequence has started, how many times longer to wait for each of
+/// its remaining bytes (they may arrive in separate reads)
+#[cfg(unix)]
+const SEQUENCE_WAIT_FACTOR: u64 = 10;
+/// How long to wait for more of a paste before taking what arrived as all
+/// of it (its end marker was lost)
+#[cfg(unix)]
+const PASTE_WAIT_MS: u64 = 500;
+/// Longest escape sequence kept; longer ones are consumed and ignored
+#[cfg(unix)]
+const MAX_ESCAPE_SEQUENCE_LEN: usize = 32;
+
+/// Screen state used while reading a key
+pub(crate) struct ReadOptions<'a> {
+    pub(crate) keymap: &'a KeyMap,
+    pub(crate) paste: &'a mut PasteOptions,
+    pub(crate) wakeup: &'a Wakeup,
+    /// How long to wait after ESC before taking it as the Escape key
+    pub(crate) esc_wait_ms: u64,
+}
 
-static BACKEND: OnceLock<Mutex<Backend>> = OnceLock::new();
-static UPDATE_BUFFER: OnceLock<Mutex<String>> = OnceLock::new();
+/// Terminal modes kept on top of raw mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct TermOptions {
+    /// Map CR to NL on input and NL to CR NL on output
+    pub(crate) translate_newlines: bool,
+    /// Ctrl+C, Ctrl+Z and Ctrl+\ raise signals instead of arriving as keys
+    pub(crate) signals: bool,
+    /// Ctrl+S and Ctrl+Q pause and resume output
+    pub(crate) flow_control: bool,
+    /// Draw on the normal screen, for terminals without an alternate one
+    pub(crate) main_screen: bool,
+}
+
+impl TermOptions {
+    /// Sequence taking over the display: enter the alternate screen (if
+    /// used), hide the cursor and clear
+    fn enter_sequence(&self) -> &'static [u8] {
+        if self.main_screen {
+            b"\x1b[?25l\x1b[2J"
+        } else {
+            b"\x1b[?1049h\x1b[?25l\x1b[2J"
+        }
+    }
+
+    /// Sequence handing the display back: show the cursor and leave the
+    /// alternate screen, or clear the normal one
+    fn leave_sequence(&self) -> &'static [u8] {
+        if self.main_screen {
+            b"\x1b[?25h\x1b[2J\x1b[H"
+        } else {
+            b"\x1b[?25h\x1b[?1049l"
+        }
+    }
+
+    /// Turn the chosen modes back on in raw-mode settings
+    #[cfg(unix)]
+    fn apply(&self, termios: &mut libc::termios) {
+        if self.translate_newlines {
+            termios.c_iflag |= libc::ICRNL;
+            termios.c_oflag |= libc::OPOST | libc::ONLCR;
+        }
+        if self.signals {
+            termios.c_lflag |= libc::ISIG;
+        }
+        if self.flow_control {
+            termios.c_iflag |= libc::IXON;
+        }
+    }
+}
 
+/// One terminal's settings and input, owned by the `Screen` drawing on it
+///
+/// Opening a backend puts its terminal in raw mode on the alternate screen;
+/// closing it (or dropping it) puts the terminal back. Any number of
+/// backends can be open at once, as long as each has its own terminal.
 pub(crate) struct Backend {
+    #[cfg(unix)]
+    input: InputReader,
+    #[cfg(unix)]
+    output_fd: RawFd,
     original_termios: Option<Termios>,
-    initialized: bool,
+    // Whether raw mode is on, so the settings it replaced aren't saved twice
+    raw: bool,
+    options: TermOptions,
+    // Terminal registered in ACTIVE_DEVICES, None once closed
+    device: Option<DeviceId>,
+    // Whether this backend installed the signal handlers
+    signals_installed: bool,
+    // Owners of the fds, if the backend was given them; dropped after the
+    // terminal is restored
+    devices: Vec<Box<dyn Send>>,
+    // Output isn't a terminal: no raw mode and no control sequences
+    plain: bool,
+    // Undoes the keyboard modes the screen turned on; written whenever the
+    // terminal is given back, including when a panic drops the screen
+    keyboard_restore: String,
+    // A query gave up before its DA1 reply, so replies may still arrive
+    // among the keys
+    #[cfg(unix)]
+    replies_pending: bool,
 }
 
 #[cfg(unix)]
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsRawFd, RawFd};
 
 #[cfg(unix)]
 #[derive(Clone)]
@@ -24,61 +133,230 @@ struct Termios {
 #[derive(Clone)]
 struct Termios;
 
+/// Identity of the file behind an fd (device and inode), so two fds on the
+/// same terminal are recognized
+type DeviceId = (u64, u64);
+
+/// Terminals with an open backend
+static ACTIVE_DEVICES: Mutex<Vec<DeviceId>> = Mutex::new(Vec::new());
+
+#[cfg(unix)]
+fn device_id(fd: RawFd) -> Result<DeviceId> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        return Err(Error::Io(io::Error::last_os_error()));
+    }
+    Ok((stat.st_dev as u64, stat.st_ino as u64))
+}
+
 impl Backend {
-    fn new() -> Self {
-        Self {
+    /// Open the terminal read from `input_fd` and drawn on `output_fd`
+    ///
+    /// Fails with `Error::AlreadyInitialized` if another backend has the
+    /// same terminal open.
+    #[cfg(unix)]
+    pub(crate) fn open(input_fd: RawFd, output_fd: RawFd, options: TermOptions) -> Result<Self> {
+        Self::open_with(input_fd, output_fd, options, false)
+    }
+
+    /// Open a backend for output that isn't a terminal
+    ///
+    /// The terminal settings are left alone and nothing but the text written
+    /// by the screen goes to `output_fd`.
+    #[cfg(unix)]
+    pub(crate) fn open_plain(input_fd: RawFd, output_fd: RawFd) -> Result<Self> {
+        Self::open_with(input_fd, output_fd, TermOptions::default(), true)
+    }
+
+    #[cfg(unix)]
+    fn open_with(
+        input_fd: RawFd,
+        output_fd: RawFd,
+        options: TermOptions,
+        plain: bool,
+    ) -> Result<Self> {
+        let device = device_id(input_fd).context("initializing terminal")?;
+        {
+            let mut active = ACTIVE_DEVICES.lock().unwrap();
+            if active.contains(&device) {
+                return Err(Error::AlreadyInitialized);
+            }
+            active.push(device);
+        }
+
+        let mut backend = Self {
+            input: InputReader::new(input_fd),
+            output_fd,
             original_termios: None,
-            initialized: false,
+            raw: false,
+            options,
+            device: Some(device),
+            signals_installed: false,
+            devices: Vec::new(),
+            plain,
+            keyboard_restore: String::new(),
+            replies_pending: false,
+        };
+        if plain {
+            return Ok(backend);
         }
+        backend.enable_raw_mode().context("initializing terminal")?;
+
+        if options.signals {
+            let original = backend.original_termios.as_ref().map(|t| &t.termios);
+            backend.signals_installed =
+                crate::signals::install(input_fd, output_fd, original, !options.main_screen);
+        }
+
+        backend
+            .write(options.enter_sequence())
+            .context("initializing terminal")?;
+        Ok(backend)
     }
 
-    pub(crate) fn init() -> Result<()> {
-        let backend = BACKEND.get_or_init(|| Mutex::new(Backend::new()));
-        let mut guard = backend.lock().unwrap();
+    /// Open the terminal behind `input` and `output`, keeping them open
+    /// until the backend is dropped
+    #[cfg(unix)]
+    pub(crate) fn open_devices<I, O>(input: I, output: O, options: TermOptions) -> Result<Self>
+    where
+        I: AsRawFd + Send + 'static,
+        O: AsRawFd + Send + 'static,
+    {
+        let mut backend = Self::open(input.as_raw_fd(), output.as_raw_fd(), options)?;
+        backend.devices.push(Box::new(input));
+        backend.devices.push(Box::new(output));
+        Ok(backend)
+    }
 
-        if guard.initialized {
-            return Err(Error::AlreadyInitialized);
-        }
+    /// Open the controlling terminal (/dev/tty) for both input and output,
+    /// whatever stdin and stdout are connected to
+    #[cfg(unix)]
+    pub(crate) fn open_tty(options: TermOptions) -> Result<Self> {
+        let tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .context("opening /dev/tty")?;
+        let output = tty.try_clone().context("opening /dev/tty")?;
+        Self::open_devices(tty, output, options)
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn open(options: TermOptions) -> Result<Self> {
+        let mut backend = Self {
+            original_termios: None,
+            raw: false,
+            options,
+            device: None,
+            signals_installed: false,
+            devices: Vec::new(),
+            plain: false,
+            keyboard_restore: String::new(),
+        };
+        backend.enable_raw_mode()?;
+        Ok(backend)
+    }
 
-        guard.enable_raw_mode()?;
-        guard.initialized = true;
+    /// The fd input is read from
+    #[cfg(unix)]
+    pub(crate) fn input_fd(&self) -> RawFd {
+        self.input.fd()
+    }
 
-        // Enter alternate screen
-        print!("\x1b[?1049h");
-        // Hide cursor
-        print!("\x1b[?25l");
-        // Clear screen
-        print!("\x1b[2J");
-        io::stdout().flush()?;
+    /// The fd output is written to
+    #[cfg(unix)]
+    pub(crate) fn output_fd(&self) -> RawFd {
+        self.output_fd
+    }
 
-        Ok(())
+    /// Check if the output isn't a terminal (see `open_plain`)
+    pub(crate) fn is_plain(&self) -> bool {
+        self.plain
     }
 
-    pub(crate) fn cleanup() -> Result<()> {
-        let backend = BACKEND.get().ok_or(Error::NotInitialized)?;
-        let mut guard = backend.lock().unwrap();
+    /// Put the terminal back as it was before `open`
+    ///
+    /// Does nothing if already closed.
+    pub(crate) fn close(&mut self) -> Result<()> {
+        let Some(device) = self.device.take() else {
+            return Ok(());
+        };
+        ACTIVE_DEVICES.lock().unwrap().retain(|&d| d != device);
+        if self.plain {
+            return Ok(());
+        }
 
-        if !guard.initialized {
+        let written = self
+            .write(self.keyboard_restore.as_bytes())
+            .and_then(|()| self.write(self.options.leave_sequence()));
+
+        if self.signals_installed {
+            crate::signals::uninstall();
+            self.signals_installed = false;
+        }
+        self.disable_raw_mode()?;
+        written
+    }
+
+    /// Give the terminal back to the shell for a while: show the cursor,
+    /// leave the alternate screen and restore the original settings
+    pub(crate) fn suspend(&mut self) -> Result<()> {
+        if self.plain {
             return Ok(());
         }
+        self.write(self.keyboard_restore.as_bytes())?;
+        self.write(self.options.leave_sequence())?;
+        self.disable_raw_mode()
+    }
 
-        // Show cursor
-        print!("\x1b[?25h");
-        // Exit alternate screen
-        print!("\x1b[?1049l");
-        io::stdout().flush()?;
+    /// Set the sequence undoing the screen's keyboard modes, written by
+    /// `close` and `suspend`
+    pub(crate) fn set_keyboard_restore(&mut self, sequence: String) {
+        #[cfg(unix)]
+        if self.signals_installed {
+            crate::signals::set_keyboard_restore(sequence.as_bytes());
+        }
+        self.keyboard_restore = sequence;
+    }
 
-        guard.disable_raw_mode()?;
-        guard.initialized = false;
+    /// Take the terminal back after `suspend`
+    ///
+    /// The settings are saved again, since the shell may have changed them
+    /// meanwhile, unless `raw_restored`: then the stop signal handler has
+    /// already put raw mode back, and the current settings aren't the
+    /// shell's. The alternate screen is cleared; the caller repaints it.
+    pub(crate) fn resume(&mut self, raw_restored: bool) -> Result<()> {
+        if self.plain {
+            return Ok(());
+        }
+        if raw_restored {
+            self.raw = true;
+        }
+        self.enable_raw_mode()?;
+        self.write(self.options.enter_sequence())
+    }
 
+    /// Write `bytes` to the terminal right away
+    fn write(&self, bytes: &[u8]) -> Result<()> {
+        #[cfg(unix)]
+        crate::platform_io::write_all_fd(self.output_fd, bytes)?;
+        #[cfg(not(unix))]
+        {
+            let mut stdout = io::stdout();
+            stdout.write_all(bytes)?;
+            stdout.flush()?;
+        }
         Ok(())
     }
 
     #[cfg(unix)]
     fn enable_raw_mode(&mut self) -> Result<()> {
-        let fd = io::stdin().as_raw_fd();
+        if self.raw {
+            return Ok(());
+        }
+        let fd = self.input.fd();
 
-        // Check if stdin is a TTY
+        // Check if input is a TTY
         if unsafe { libc::isatty(fd) } == 0 {
             // Not a TTY - skip raw mode setup
             return Ok(());
@@ -97,10 +375,12 @@ impl Backend {
         // Set raw mode
         unsafe {
             libc::cfmakeraw(&mut termios);
+            self.options.apply(&mut termios);
             if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
                 return Err(Error::Io(io::Error::last_os_error()));
             }
         }
+        self.raw = true;
 
         Ok(())
     }
@@ -108,13 +388,13 @@ impl Backend {
     #[cfg(unix)]
     fn disable_raw_mode(&mut self) -> Result<()> {
         if let Some(original) = &self.original_termios {
-            let fd = io::stdin().as_raw_fd();
             unsafe {
-                if libc::tcsetattr(fd, libc::TCSANOW, &original.termios) != 0 {
+                if libc::tcsetattr(self.input.fd(), libc::TCSANOW, &original.termios) != 0 {
                     return Err(Error::Io(io::Error::last_os_error()));
                 }
             }
         }
+        self.raw = false;
         Ok(())
     }
 
@@ -129,131 +409,322 @@ impl Backend {
         Ok(())
     }
 
-    pub(crate) fn read_key_timeout(timeout_ms: Option<u64>) -> Result<Option<Key>> {
+    pub(crate) fn read_key_timeout(
+        &mut self,
+        timeout_ms: Option<u64>,
+        options: &mut ReadOptions,
+    ) -> Result<Option<Key>> {
+        #[cfg(unix)]
+        {
+            Self::read_key_from(
+                &mut self.input,
+                timeout_ms,
+                options,
+                &mut self.replies_pending,
+            )
+            .context("reading input")
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = (timeout_ms, options);
+            Err(Error::NotSupported)
+        }
+    }
+
+    /// Send `request` followed by a DA1 query and collect the replies
+    ///
+    /// Waits until the DA1 reply arrives or `timeout_ms` passes. Other input
+    /// received meanwhile is kept for the next read.
+    pub(crate) fn query(
+        &mut self,
+        request: &str,
+        timeout_ms: u64,
+        wakeup: &Wakeup,
+    ) -> Result<Vec<Reply>> {
         #[cfg(unix)]
         {
-            use std::io::ErrorKind;
-
-            let mut buf = [0u8; 8];
-            let mut stdin = io::stdin();
-            let fd = stdin.as_raw_fd();
-
-            if let Some(timeout) = timeout_ms {
-                // Use select to wait for input with timeout
-                unsafe {
-                    let mut readfds: libc::fd_set = std::mem::zeroed();
-                    libc::FD_ZERO(&mut readfds);
-                    libc::FD_SET(fd, &mut readfds);
-
-                    let mut tv = libc::timeval {
-                        tv_sec: (timeout / 1000) as libc::time_t,
-                        tv_usec: ((timeout % 1000) * 1000) as libc::suseconds_t,
-                    };
-
-                    let result = libc::select(
-                        fd + 1,
-                        &mut readfds,
-                        std::ptr::null_mut(),
-                        std::ptr::null_mut(),
-                        &mut tv,
-                    );
-
-                    if result == 0 {
-                        return Ok(None); // Timeout
-                    } else if result < 0 {
-                        return Err(Error::Io(io::Error::last_os_error()));
+            let mut out = String::with_capacity(request.len() + DA1_QUERY.len());
+            out.push_str(request);
+            out.push_str(DA1_QUERY);
+            self.write(out.as_bytes()).context("querying terminal")?;
+
+            let replies = Self::collect_replies(&mut self.input, timeout_ms, wakeup)
+                .context("querying terminal")?;
+            // DA1 is answered last, so every earlier reply has arrived
+            self.replies_pending = !replies.iter().any(Reply::is_primary_attributes);
+            Ok(replies)
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = (request, timeout_ms, wakeup);
+            Err(Error::NotSupported)
+        }
+    }
+
+    /// Read replies from `reader` until the DA1 reply or the timeout
+    #[cfg(unix)]
+    fn collect_replies(
+        reader: &mut InputReader,
+        timeout_ms: u64,
+        wakeup: &Wakeup,
+    ) -> Result<Vec<Reply>> {
+        use std::io::BufRead;
+        use std::time::{Duration, Instant};
+
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        let mut data = Vec::new();
+
+        loop {
+            let (replies, _) = split_replies(&data);
+            if replies.iter().any(Reply::is_primary_attributes) {
+                break;
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !Self::wait_for_input(reader, Some(remaining.as_millis() as u64), wakeup)? {
+                break; // Timeout
+            }
+
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                break; // EOF
+            }
+            let len = chunk.len();
+            data.extend_from_slice(chunk);
+            reader.consume(len);
+        }
+
+        let (replies, other) = split_replies(&data);
+        reader.unread(&other);
+        Ok(replies)
+    }
+
+    /// Read one key from `reader`, waiting up to `timeout_ms` (forever if None)
+    ///
+    /// While `replies_pending`, late query replies are consumed as
+    /// `Key::Unknown`; it is cleared once the DA1 reply is read.
+    #[cfg(unix)]
+    fn read_key_from(
+        reader: &mut InputReader,
+        timeout_ms: Option<u64>,
+        options: &mut ReadOptions,
+        replies_pending: &mut bool,
+    ) -> Result<Option<Key>> {
+        use std::io::ErrorKind;
+
+        if options.wakeup.is_triggered() {
+            return Err(Error::Shutdown);
+        }
+
+        if !Self::wait_for_input(reader, timeout_ms, options.wakeup)? {
+            return Ok(None); // Timeout
+        }
+
+        // Read available input
+        let mut byte = [0u8; 1];
+        match reader.read(&mut byte) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                let key = Self::parse_key_from_byte(byte[0], reader, options, replies_pending)?;
+                Ok(Some(key))
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Wait until `reader` has input, either already buffered or readable
+    /// from its fd
+    ///
+    /// Returns false on timeout, and `Error::Shutdown` if the wakeup fires first.
+    #[cfg(unix)]
+    fn wait_for_input(
+        reader: &InputReader,
+        timeout_ms: Option<u64>,
+        wakeup: &Wakeup,
+    ) -> Result<bool> {
+        use libc::{POLLIN, poll, pollfd};
+
+        // poll() can't see bytes already read from the kernel
+        if reader.buffered() > 0 {
+            return Ok(true);
+        }
+
+        let mut fds = [
+            pollfd {
+                fd: reader.fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: wakeup.fd().unwrap_or(-1), // Negative fds are ignored
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+        let timeout = timeout_ms.map_or(-1, |t| t.min(i32::MAX as u64) as i32);
+
+        loop {
+            let result = unsafe { poll(fds.as_mut_ptr(), 2, timeout) };
+
+            if result < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    // Let the screen repaint after a stop and continue
+                    if timeout_ms.is_none() && !crate::signals::resume_pending() {
+                        continue;
                     }
+                    return Ok(false); // EINTR - treat as timeout
                 }
+                return Err(Error::Io(err));
+            }
+
+            if fds[1].revents & POLLIN != 0 || wakeup.is_triggered() {
+                return Err(Error::Shutdown);
+            }
+
+            return Ok(result > 0);
+        }
+    }
+
+    /// Read the rest of an escape sequence after its ESC
+    ///
+    /// A lone ESC is returned if nothing follows within `esc_wait_ms`.
+    /// Once a sequence has started, its bytes may straggle in over several
+    /// reads; if it is never finished, the bytes after the first one are
+    /// put back to be read as keys.
+    #[cfg(unix)]
+    fn read_escape_sequence(
+        reader: &mut InputReader,
+        options: &ReadOptions,
+        replies_pending: &mut bool,
+    ) -> Result<Vec<u8>> {
+        let mut seq = vec![27];
+        let mut parser = if *replies_pending {
+            EscapeParser::expecting_replies()
+        } else {
+            EscapeParser::new()
+        };
+        let mut byte = [0u8; 1];
+
+        loop {
+            let wait = if parser.in_sequence() {
+                options.esc_wait_ms * SEQUENCE_WAIT_FACTOR
+            } else {
+                options.esc_wait_ms
+            };
+            if !Self::wait_for_input(reader, Some(wait), options.wakeup)? {
+                break;
+            }
+            if reader.read(&mut byte)? == 0 {
+                break;
             }
 
-            // Read available input
-            match stdin.read(&mut buf[..1]) {
-                Ok(0) => return Ok(None),
-                Ok(_) => {
-                    let key = Self::parse_key_from_byte(buf[0], &mut stdin, &mut buf)?;
-                    return Ok(Some(key));
+            match parser.advance(byte[0]) {
+                Progress::Incomplete => {}
+                Progress::Complete => {
+                    seq.push(byte[0]);
+                    if *replies_pending
+                        && split_replies(&seq)
+                            .0
+                            .iter()
+                            .any(Reply::is_primary_attributes)
+                    {
+                        *replies_pending = false;
+                    }
+                    // Too long to be a key: consumed, but never matches
+                    seq.truncate(MAX_ESCAPE_SEQUENCE_LEN);
+                    return Ok(seq);
+                }
+                Progress::Invalid => {
+                    // The byte starts the next key
+                    reader.unread(&byte);
+                    return Ok(seq);
                 }
-                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
-                Err(e) => return Err(e.into()),
             }
+            seq.push(byte[0]);
         }
 
-        #[cfg(not(unix))]
-        {
-            Err(Error::NotSupported)
+        // Timed out mid-sequence: the bytes were keys typed after ESC (a
+        // single one is kept, as Alt+key)
+        if seq.len() > 2 && seq.len() <= MAX_ESCAPE_SEQUENCE_LEN {
+            reader.unread(&seq[1..]);
+            seq.truncate(1);
         }
+        Ok(seq)
     }
 
-    fn parse_key_from_byte(byte: u8, stdin: &mut io::Stdin, buf: &mut [u8; 8]) -> Result<Key> {
+    #[cfg(unix)]
+    fn parse_key_from_byte(
+        byte: u8,
+        reader: &mut InputReader,
+        options: &mut ReadOptions,
+        replies_pending: &mut bool,
+    ) -> Result<Key> {
+        // Terminal-specific single bytes (e.g. 0x08 for Backspace)
+        if byte != 27
+            && let Some(key) = options.keymap.lookup(&[byte])
+        {
+            return Ok(key.clone());
+        }
+
         // Handle special ASCII characters
         match byte {
-            b'\r' | b'\n' => return Ok(Key::Enter),
-            b'\t' => return Ok(Key::Tab),
-            127 => return Ok(Key::Backspace),
+            b'\r' | b'\n' => Ok(Key::Enter),
+            b'\t' => Ok(Key::Tab),
+            127 => Ok(Key::Backspace),
             27 => {
-                // Escape sequence - try to read more
-                let mut seq = vec![27];
-
-                // Use non-blocking read for escape sequences
-                #[cfg(unix)]
-                {
-                    use std::io::ErrorKind;
-                    use std::time::Duration;
-
-                    // Set a short timeout to detect lone ESC
-                    std::thread::sleep(Duration::from_millis(1));
-
-                    loop {
-                        match stdin.read(&mut buf[..1]) {
-                            Ok(0) => break,
-                            Ok(_) => {
-                                seq.push(buf[0]);
-                                if seq.len() >= 6 {
-                                    break;
-                                }
-                            }
-                            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
-                            Err(e) => return Err(e.into()),
-                        }
-                    }
+                let seq = Self::read_escape_sequence(reader, options, replies_pending)?;
+
+                // Bracketed paste: read the body in bulk instead of key by key
+                if seq == PASTE_START {
+                    let text = crate::paste::read_paste(reader, options.paste, |reader| {
+                        Self::wait_for_input(reader, Some(PASTE_WAIT_MS), options.wakeup)
+                    })?;
+                    return Ok(Key::Paste(text));
                 }
 
+                if let Some(key) = options.keymap.lookup(&seq) {
+                    return Ok(key.clone());
+                }
                 if let Some(key) = Key::from_escape_sequence(&seq) {
                     return Ok(key);
                 }
-                return Ok(Key::Escape);
+                match seq[..] {
+                    [_] => Ok(Key::Escape),
+                    // ESC + key is Alt+key; after ESC ESC, the key may itself
+                    // be a sequence (Alt+Up) or nothing (Alt+Escape)
+                    [_, byte] => {
+                        Ok(
+                            Self::parse_key_from_byte(byte, reader, options, replies_pending)?
+                                .with_alt(),
+                        )
+                    }
+                    _ => Ok(Key::Unknown),
+                }
             }
             1..=26 => {
                 // Control characters
                 let ch = (byte - 1 + b'a') as char;
-                return Ok(Key::Ctrl(ch));
+                Ok(Key::Ctrl(ch))
             }
             32..=126 => {
                 // Printable ASCII
-                return Ok(Key::Char(byte as char));
+                Ok(Key::Char(byte as char))
             }
-            _ => return Ok(Key::Unknown),
+            _ => Ok(Key::Unknown),
         }
     }
 
-    pub(crate) fn read_key() -> Result<Key> {
-        let mut buf = [0u8; 8];
-        let mut stdin = io::stdin();
-
-        let n = stdin.read(&mut buf[..1])?;
-        if n == 0 {
-            return Ok(Key::Unknown);
-        }
-
-        Self::parse_key_from_byte(buf[0], &mut stdin, &mut buf)
-    }
-
-    pub(crate) fn get_terminal_size() -> Result<(u16, u16)> {
+    /// Size (rows, cols) of the terminal
+    pub(crate) fn terminal_size(&self) -> Result<(u16, u16)> {
         #[cfg(unix)]
         {
-            let fd = io::stdout().as_raw_fd();
+            let fd = self.output_fd;
 
-            // Check if stdout is a TTY
+            // Check if output is a TTY
             if unsafe { libc::isatty(fd) } == 0 {
                 // Not a TTY - return a default size or error
                 // For now, return a reasonable default size (24x80 is classic terminal size)
@@ -276,27 +747,11 @@ impl Backend {
             Err(Error::NotSupported)
         }
     }
+}
 
-    /// Add content to the update buffer (for wnoutrefresh)
-    pub(crate) fn add_to_update_buffer(content: &str) -> Result<()> {
-        let buffer = UPDATE_BUFFER.get_or_init(|| Mutex::new(String::new()));
-        let mut guard = buffer.lock().unwrap();
-        guard.push_str(content);
-        Ok(())
-    }
-
-    /// Flush the update buffer to screen (doupdate)
-    pub(crate) fn doupdate() -> Result<()> {
-        let buffer = UPDATE_BUFFER.get_or_init(|| Mutex::new(String::new()));
-        let mut guard = buffer.lock().unwrap();
-
-        if !guard.is_empty() {
-            io::stdout().write_all(guard.as_bytes())?;
-            io::stdout().flush()?;
-            guard.clear();
-        }
-
-        Ok(())
+impl Drop for Backend {
+    fn drop(&mut self) {
+        let _ = self.close();
     }
 }
 
@@ -304,20 +759,424 @@ impl Backend {
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    fn pipe() -> (RawFd, RawFd) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        (fds[0], fds[1])
+    }
+
     #[test]
-    fn test_backend_creation() {
-        let backend = Backend::new();
-        assert!(!backend.initialized);
+    #[cfg(unix)]
+    fn test_open_close_reopen() {
+        let (input, _input_writer) = pipe();
+        let (output_reader, output) = pipe();
+
+        let mut backend = Backend::open(input, output, TermOptions::default()).unwrap();
+        // Not a TTY: no settings to restore
         assert!(backend.original_termios.is_none());
+        assert!(matches!(
+            Backend::open(input, output, TermOptions::default()),
+            Err(Error::AlreadyInitialized)
+        ));
+        // A different terminal can be open at the same time
+        let (other_input, _other_writer) = pipe();
+        let other = Backend::open(other_input, output, TermOptions::default()).unwrap();
+
+        backend.close().unwrap();
+        backend.close().unwrap();
+        drop(other);
+        drop(Backend::open(input, output, TermOptions::default()).unwrap());
+
+        let mut written = [0u8; 128];
+        let n = unsafe {
+            libc::read(
+                output_reader,
+                written.as_mut_ptr() as *mut libc::c_void,
+                written.len(),
+            )
+        };
+        let written = String::from_utf8_lossy(&written[..n as usize]);
+        assert!(written.starts_with("\x1b[?1049h\x1b[?25l\x1b[2J"));
+        assert!(written.contains("\x1b[?25h\x1b[?1049l"));
     }
 
     #[test]
     #[cfg(unix)]
-    fn test_terminal_size() {
-        // This will work in a real terminal
-        if let Ok((rows, cols)) = Backend::get_terminal_size() {
-            assert!(rows > 0);
-            assert!(cols > 0);
+    fn test_main_screen() {
+        let (input, _input_writer) = pipe();
+        let (output_reader, output) = pipe();
+        let options = TermOptions {
+            main_screen: true,
+            ..TermOptions::default()
+        };
+        Backend::open(input, output, options)
+            .unwrap()
+            .close()
+            .unwrap();
+
+        let mut written = [0u8; 128];
+        let n = unsafe {
+            libc::read(
+                output_reader,
+                written.as_mut_ptr() as *mut libc::c_void,
+                written.len(),
+            )
+        };
+        let written = String::from_utf8_lossy(&written[..n as usize]);
+        assert_eq!(written, "\x1b[?25l\x1b[2J\x1b[?25h\x1b[2J\x1b[H");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_resume_after_stop_handler() {
+        let (mut master, mut slave) = (0, 0);
+        let opened = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(opened, 0);
+        let lflag = || unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            libc::tcgetattr(slave, &mut termios);
+            termios.c_lflag
+        };
+        assert_ne!(lflag() & libc::ICANON, 0);
+
+        let mut backend = Backend::open(slave, slave, TermOptions::default()).unwrap();
+        assert_eq!(lflag() & libc::ICANON, 0);
+        backend.suspend().unwrap();
+        assert_ne!(lflag() & libc::ICANON, 0);
+
+        // The SIGTSTP handler puts raw mode back when the process continues
+        unsafe {
+            let mut raw: libc::termios = std::mem::zeroed();
+            libc::tcgetattr(slave, &mut raw);
+            libc::cfmakeraw(&mut raw);
+            libc::tcsetattr(slave, libc::TCSANOW, &raw);
+        }
+        backend.resume(true).unwrap();
+        backend.close().unwrap();
+        assert_ne!(lflag() & libc::ICANON, 0);
+
+        for fd in [master, slave] {
+            unsafe { libc::close(fd) };
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_keyboard_restore() {
+        let (input, _input_writer) = pipe();
+        let (output_reader, output) = pipe();
+        let mut backend = Backend::open(input, output, TermOptions::default()).unwrap();
+        backend.set_keyboard_restore("\x1b[<2u".to_string());
+        // Dropping, as a panic unwinding past the screen does
+        drop(backend);
+
+        let mut written = [0u8; 128];
+        let n = unsafe {
+            libc::read(
+                output_reader,
+                written.as_mut_ptr() as *mut libc::c_void,
+                written.len(),
+            )
+        };
+        let written = String::from_utf8_lossy(&written[..n as usize]);
+        assert!(written.ends_with("\x1b[<2u\x1b[?25h\x1b[?1049l"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_term_options() {
+        let raw = || {
+            let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+            termios.c_iflag = libc::ICRNL | libc::IXON;
+            termios.c_lflag = libc::ISIG;
+            unsafe { libc::cfmakeraw(&mut termios) };
+            termios
+        };
+
+        let mut termios = raw();
+        TermOptions::default().apply(&mut termios);
+        assert_eq!(termios.c_iflag & (libc::ICRNL | libc::IXON), 0);
+        assert_eq!(termios.c_lflag & libc::ISIG, 0);
+
+        let mut termios = raw();
+        let options = TermOptions {
+            translate_newlines: true,
+            signals: true,
+            flow_control: true,
+            main_screen: false,
+        };
+        options.apply(&mut termios);
+        assert_ne!(termios.c_iflag & libc::ICRNL, 0);
+        assert_ne!(termios.c_iflag & libc::IXON, 0);
+        assert_ne!(termios.c_oflag & libc::ONLCR, 0);
+        assert_ne!(termios.c_lflag & libc::ISIG, 0);
+    }
+
+    #[cfg(unix)]
+    fn read(
+        reader: &mut InputReader,
+        timeout_ms: Option<u64>,
+        wakeup: &Wakeup,
+    ) -> Result<Option<Key>> {
+        let mut options = ReadOptions {
+            keymap: &KeyMap::default(),
+            paste: &mut PasteOptions::default(),
+            wakeup,
+            esc_wait_ms: ESC_SEQUENCE_WAIT_MS,
+        };
+        Backend::read_key_from(reader, timeout_ms, &mut options, &mut false)
+    }
+
+    #[cfg(unix)]
+    fn pipe_reader(input: &[u8]) -> (InputReader, RawFd) {
+        let (read_fd, write_fd) = pipe();
+        unsafe { libc::write(write_fd, input.as_ptr() as *const libc::c_void, input.len()) };
+        (InputReader::new(read_fd), write_fd)
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_keys_from_fd() {
+        let (mut reader, write_fd) = pipe_reader(b"a\x1b[A\x7f");
+        let wakeup = Wakeup::new();
+
+        let mut next = || read(&mut reader, Some(0), &wakeup).unwrap();
+        assert_eq!(next(), Some(Key::Char('a')));
+        assert_eq!(next(), Some(Key::Up));
+        assert_eq!(next(), Some(Key::Backspace));
+        assert_eq!(next(), None); // Timeout
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
         }
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_collect_replies_keeps_keys() {
+        let (mut reader, write_fd) = pipe_reader(b"x\x1b]11;rgb:0/0/0\x07\x1b[?62cy");
+        let wakeup = Wakeup::new();
+
+        let replies = Backend::collect_replies(&mut reader, 1000, &wakeup).unwrap();
+        assert_eq!(replies.len(), 2);
+        assert!(replies[1].is_primary_attributes());
+
+        // Keys around the replies are still read as input
+        assert_eq!(
+            read(&mut reader, Some(0), &wakeup).unwrap(),
+            Some(Key::Char('x'))
+        );
+        assert_eq!(
+            read(&mut reader, Some(0), &wakeup).unwrap(),
+            Some(Key::Char('y'))
+        );
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_collect_replies_timeout() {
+        let (mut reader, write_fd) = pipe_reader(b"\x1b]10;rgb:f/f/f\x07");
+        let wakeup = Wakeup::new();
+
+        // No DA1 reply: return what arrived once the timeout passes
+        let replies = Backend::collect_replies(&mut reader, 20, &wakeup).unwrap();
+        assert_eq!(replies.len(), 1);
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_sequence_split_across_reads() {
+        let (mut reader, write_fd) = pipe_reader(b"\x1b[1;");
+        let wakeup = Wakeup::new();
+
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            unsafe { libc::write(write_fd, b"5Cz".as_ptr() as *const libc::c_void, 3) };
+            write_fd
+        });
+
+        let key = read(&mut reader, None, &wakeup).unwrap().unwrap();
+        assert_eq!(key.modified(), (Key::Right, crate::kitty::Modifiers::CTRL));
+        assert_eq!(
+            read(&mut reader, Some(0), &wakeup).unwrap(),
+            Some(Key::Char('z'))
+        );
+
+        let write_fd = thread.join().unwrap();
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_escape_sequences_keep_following_keys() {
+        // A late OSC reply is skipped whole, an unfinished CSI is cut short
+        // by the next ESC, and once DA1 arrives ESC ] is Alt+]
+        let (mut reader, write_fd) = pipe_reader(b"\x1b]0;a~b\x07q\x1b[1\x1b[Bx\x1b[?62c\x1b]k");
+        let wakeup = Wakeup::new();
+        let mut options = ReadOptions {
+            keymap: &KeyMap::default(),
+            paste: &mut PasteOptions::default(),
+            wakeup: &wakeup,
+            esc_wait_ms: ESC_SEQUENCE_WAIT_MS,
+        };
+        let mut replies_pending = true;
+
+        let mut next = || {
+            Backend::read_key_from(&mut reader, Some(0), &mut options, &mut replies_pending)
+                .unwrap()
+        };
+        assert_eq!(next(), Some(Key::Unknown));
+        assert_eq!(next(), Some(Key::Char('q')));
+        assert_eq!(next(), Some(Key::Unknown));
+        assert_eq!(next(), Some(Key::Down));
+        assert_eq!(next(), Some(Key::Char('x')));
+        assert_eq!(next(), Some(Key::Unknown));
+        assert_eq!(next(), Some(Key::Alt(']')));
+        assert_eq!(next(), Some(Key::Char('k')));
+        assert!(!replies_pending);
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_lone_escape_does_not_block() {
+        let (mut reader, write_fd) = pipe_reader(b"\x1b");
+        let wakeup = Wakeup::new();
+
+        let key = read(&mut reader, None, &wakeup).unwrap();
+        assert_eq!(key, Some(Key::Escape));
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_alt_keys() {
+        use crate::kitty::Modifiers;
+
+        let (mut reader, write_fd) =
+            pipe_reader(b"\x1bx\x1bX\x1b\x01\x1b\x7f\x1b\x1b[A\x1b.a\x1bPab\x1b-x\x1b\x1b");
+        let wakeup = Wakeup::new();
+
+        let mut next = || read(&mut reader, Some(0), &wakeup).unwrap().unwrap();
+        assert_eq!(next(), Key::Alt('x'));
+        // Alt+Shift+x
+        assert_eq!(next(), Key::Alt('X'));
+        assert_eq!(
+            next().modified(),
+            (Key::Char('a'), Modifiers::CTRL | Modifiers::ALT)
+        );
+        assert_eq!(next().modified(), (Key::Backspace, Modifiers::ALT));
+        // ESC before a whole sequence
+        assert_eq!(next().modified(), (Key::Up, Modifiers::ALT));
+        // Alt+punctuation and Alt+Shift+P don't swallow the next keys
+        assert_eq!(next(), Key::Alt('.'));
+        assert_eq!(next(), Key::Char('a'));
+        assert_eq!(next(), Key::Alt('P'));
+        assert_eq!(next(), Key::Char('a'));
+        assert_eq!(next(), Key::Char('b'));
+        assert_eq!(next(), Key::Alt('-'));
+        assert_eq!(next(), Key::Char('x'));
+        // ESC ESC then nothing: Alt+Escape, not two Escapes
+        assert_eq!(next().modified(), (Key::Escape, Modifiers::ALT));
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_shutdown_wakes_blocked_read() {
+        use std::sync::Arc;
+
+        let (mut reader, write_fd) = pipe_reader(b"");
+        let wakeup = Arc::new(Wakeup::new());
+
+        let trigger = wakeup.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            trigger.trigger();
+        });
+
+        // Blocks with no timeout until the other thread shuts down
+        let result = read(&mut reader, None, &wakeup);
+        assert!(matches!(result, Err(Error::Shutdown)));
+        thread.join().unwrap();
+
+        // Later reads fail immediately, even with input pending
+        unsafe { libc::write(write_fd, b"x".as_ptr() as *const libc::c_void, 1) };
+        let result = read(&mut reader, None, &wakeup);
+        assert!(matches!(result, Err(Error::Shutdown)));
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_shutdown_during_unterminated_paste() {
+        use std::sync::Arc;
+
+        let (mut reader, write_fd) = pipe_reader(b"\x1b[200~pasted, but no end marker");
+        let wakeup = Arc::new(Wakeup::new());
+
+        let trigger = wakeup.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            trigger.trigger();
+        });
+
+        let result = read(&mut reader, None, &wakeup);
+        assert!(matches!(result, Err(Error::Shutdown)));
+        thread.join().unwrap();
+
+        unsafe {
+            libc::close(write_fd);
+            libc::close(reader.fd());
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_terminal_size() {
+        // Not a TTY: the classic 24x80
+        let (input, _input_writer) = pipe();
+        let (_output_reader, output) = pipe();
+        let backend = Backend::open(input, output, TermOptions::default()).unwrap();
+        assert_eq!(backend.terminal_size().unwrap(), (24, 80));
+    }
 }
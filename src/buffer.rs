@@ -0,0 +1,158 @@
+//! Flat cell grid
+//!
+//! All rows live in one contiguous `Vec<Cell>`, so a grid is a single
+//! allocation and the diff loop walks memory in order. Indexing by row gives
+//! a slice, so `buffer[y][x]` reads like the nested `Vec<Vec<Cell>>` it
+//! replaces.
+
+use crate::cell::Cell;
+use std::ops::{Index, IndexMut};
+
+/// A `rows` x `cols` grid of cells stored row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Buffer {
+    cells: Vec<Cell>,
+    rows: usize,
+    cols: usize,
+}
+
+impl Buffer {
+    /// Grid of `rows` x `cols` copies of `fill`
+    pub fn new(rows: usize, cols: usize, fill: Cell) -> Self {
+        Self {
+            cells: vec![fill; rows * cols],
+            rows,
+            cols,
+        }
+    }
+
+    /// Number of rows
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    /// Check if the grid has no rows
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Number of cells per row
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.cols
+    }
+
+    /// All cells, row by row
+    #[inline]
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Iterate over the rows
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        // chunks_exact panics on 0; a zero-width grid has no cells anyway
+        self.cells.chunks_exact(self.cols.max(1))
+    }
+
+    /// Iterate mutably over the rows
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Cell]> {
+        self.cells.chunks_exact_mut(self.cols.max(1))
+    }
+
+    /// Set every cell to `cell`
+    pub fn fill(&mut self, cell: &Cell) {
+        self.cells.fill(cell.clone());
+    }
+
+    /// Move rows up by `lines` (down if negative), filling the rows left
+    /// behind with `fill`
+    pub fn shift_rows(&mut self, lines: isize, fill: &Cell) {
+        let n = lines.unsigned_abs().min(self.rows) * self.cols;
+        if lines > 0 {
+            self.cells.rotate_left(n);
+            let len = self.cells.len();
+            self.cells[len - n..].fill(fill.clone());
+        } else {
+            self.cells.rotate_right(n);
+            self.cells[..n].fill(fill.clone());
+        }
+    }
+
+    /// Copy the contents of a grid of the same size
+    pub fn copy_from(&mut self, other: &Buffer) {
+        debug_assert_eq!((self.rows, self.cols), (other.rows, other.cols));
+        self.cells.clone_from_slice(&other.cells);
+    }
+}
+
+impl Index<usize> for Buffer {
+    type Output = [Cell];
+
+    #[inline]
+    fn index(&self, y: usize) -> &[Cell] {
+        let start = y * self.cols;
+        &self.cells[start..start + self.cols]
+    }
+}
+
+impl IndexMut<usize> for Buffer {
+    #[inline]
+    fn index_mut(&mut self, y: usize) -> &mut [Cell] {
+        let start = y * self.cols;
+        &mut self.cells[start..start + self.cols]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_indexing() {
+        let mut buf = Buffer::new(3, 4, Cell::blank());
+        buf[1][2] = Cell::new('x');
+        buf[2].fill(Cell::new('-'));
+
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf.width(), 4);
+        assert_eq!(buf.cells()[6].ch, 'x');
+        assert_eq!(buf.rows().count(), 3);
+        let text: Vec<String> = buf
+            .rows()
+            .map(|row| row.iter().map(Cell::ch).collect())
+            .collect();
+        assert_eq!(text, vec!["    ", "  x ", "----"]);
+    }
+
+    #[test]
+    fn test_fill_and_copy() {
+        let mut a = Buffer::new(2, 2, Cell::blank());
+        let mut b = Buffer::new(2, 2, Cell::blank());
+        a.fill(&Cell::new('a'));
+        b.copy_from(&a);
+        assert_eq!(a, b);
+        for row in b.rows_mut() {
+            row[0] = Cell::new('b');
+        }
+        assert_eq!(b[1][0].ch, 'b');
+        assert_eq!(b[1][1].ch, 'a');
+    }
+
+    #[test]
+    fn test_shift_rows() {
+        let mut buf = Buffer::new(3, 1, Cell::blank());
+        for (y, ch) in ['a', 'b', 'c'].into_iter().enumerate() {
+            buf[y][0] = Cell::new(ch);
+        }
+        let text = |buf: &Buffer| -> String { buf.cells().iter().map(Cell::ch).collect() };
+
+        buf.shift_rows(1, &Cell::new('-'));
+        assert_eq!(text(&buf), "bc-");
+        buf.shift_rows(-2, &Cell::new('+'));
+        assert_eq!(text(&buf), "++b");
+        buf.shift_rows(5, &Cell::blank());
+        assert_eq!(text(&buf), "   ");
+    }
+}
@@ -0,0 +1,215 @@
+//! Options for starting a `Screen`
+
+use crate::backend::{Backend, ESC_SEQUENCE_WAIT_MS, TermOptions};
+use crate::caps::Capabilities;
+use crate::error::Result;
+use crate::keymap::KeyMap;
+use crate::screen::Screen;
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+
+/// What `ScreenBuilder::build` does when stdout isn't a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonTty {
+    /// Fail with `Error::NotATty` (the default)
+    #[default]
+    Error,
+    /// Run without control sequences: each refresh writes the rows that
+    /// changed as plain lines of text, and input is read as typed
+    Plain,
+}
+
+/// Configures how `Screen` sets up the terminal
+///
+/// `Screen::init()` is `ScreenBuilder::new().build()`. By default the
+/// terminal is in raw mode: Ctrl+C, Ctrl+Z, Ctrl+S and Ctrl+Q arrive as
+/// keys, and Enter as `\r`.
+///
+/// ```no_run
+/// use zaz::Screen;
+///
+/// // Let Ctrl+C interrupt the program, wait longer for escape sequences
+/// let scr = Screen::builder().signals(true).esc_delay(50).build()?;
+/// # Ok::<(), zaz::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScreenBuilder {
+    esc_delay_ms: u64,
+    options: TermOptions,
+    controlling_tty: bool,
+    non_tty: NonTty,
+    capabilities: Option<Capabilities>,
+}
+
+impl Default for ScreenBuilder {
+    fn default() -> Self {
+        Self {
+            esc_delay_ms: ESC_SEQUENCE_WAIT_MS,
+            options: TermOptions::default(),
+            controlling_tty: false,
+            non_tty: NonTty::default(),
+            capabilities: None,
+        }
+    }
+}
+
+impl ScreenBuilder {
+    /// Builder with the default (raw mode) settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long to wait after ESC before reporting the Escape key (default
+    /// 10ms); see `Screen::set_escape_timeout`
+    pub fn esc_delay(mut self, ms: u64) -> Self {
+        self.esc_delay_ms = ms;
+        self
+    }
+
+    /// Translate CR to NL on input and NL to CR NL on output, like curses
+    /// `nl()` (off by default)
+    pub fn translate_newlines(mut self, enabled: bool) -> Self {
+        self.options.translate_newlines = enabled;
+        self
+    }
+
+    /// Let Ctrl+C, Ctrl+Z and Ctrl+\ raise SIGINT, SIGTSTP and SIGQUIT
+    /// instead of arriving as keys (off by default)
+    ///
+    /// If SIGINT, SIGQUIT or SIGTERM ends the process, the terminal is
+    /// restored first. To keep Ctrl+C as a key but still exit cleanly, see
+    /// `Screen::on_interrupt`.
+    pub fn signals(mut self, enabled: bool) -> Self {
+        self.options.signals = enabled;
+        self
+    }
+
+    /// Let Ctrl+S and Ctrl+Q pause and resume output (XON/XOFF) instead of
+    /// arriving as keys (off by default)
+    pub fn flow_control(mut self, enabled: bool) -> Self {
+        self.options.flow_control = enabled;
+        self
+    }
+
+    /// Draw on and read from the controlling terminal (`/dev/tty`) instead
+    /// of stdout and stdin (off by default)
+    ///
+    /// The screen then still works when stdout is piped, as in
+    /// `mytui | tee log`, leaving stdout to carry the program's output.
+    pub fn controlling_tty(mut self, enabled: bool) -> Self {
+        self.controlling_tty = enabled;
+        self
+    }
+
+    /// What to do when stdout isn't a terminal, as when piped to a file
+    /// (default `NonTty::Error`)
+    ///
+    /// Has no effect with `controlling_tty`, which draws on the terminal
+    /// whatever stdout is.
+    pub fn non_tty(mut self, mode: NonTty) -> Self {
+        self.non_tty = mode;
+        self
+    }
+
+    /// Use these capabilities instead of looking up `$TERM`
+    ///
+    /// Read before the terminal is set up, so a terminal without an
+    /// alternate screen is never switched to one.
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Set up the terminal and create the screen
+    pub fn build(mut self) -> Result<Screen> {
+        self.apply_capabilities();
+        #[cfg(unix)]
+        let backend = if self.controlling_tty {
+            Backend::open_tty(self.options)?
+        } else if unsafe { libc::isatty(libc::STDOUT_FILENO) } == 0 {
+            match self.non_tty {
+                NonTty::Error => return Err(crate::error::Error::NotATty),
+                NonTty::Plain => Backend::open_plain(libc::STDIN_FILENO, libc::STDOUT_FILENO)?,
+            }
+        } else {
+            Backend::open(libc::STDIN_FILENO, libc::STDOUT_FILENO, self.options)?
+        };
+        #[cfg(not(unix))]
+        let backend = Backend::open(self.options)?;
+        Ok(self.finish(backend))
+    }
+
+    /// Create the screen on the terminal read from `input` and drawn on
+    /// `output`, like curses `newterm`; see `Screen::on_device`
+    #[cfg(unix)]
+    pub fn build_on<I, O>(mut self, input: I, output: O) -> Result<Screen>
+    where
+        I: Read + AsRawFd + Send + 'static,
+        O: Write + AsRawFd + Send + 'static,
+    {
+        self.apply_capabilities();
+        let backend = Backend::open_devices(input, output, self.options)?;
+        Ok(self.finish(backend))
+    }
+
+    /// Settle the capabilities and the terminal modes that depend on them
+    fn apply_capabilities(&mut self) {
+        let capabilities = self.capabilities.unwrap_or_else(Capabilities::from_env);
+        self.options.main_screen = !capabilities.alt_screen;
+    }
+
+    fn finish(self, backend: Backend) -> Screen {
+        let (rows, cols) = backend.terminal_size().unwrap_or((24, 80));
+        let mut screen = Screen::with_size(rows, cols);
+        screen.attach(backend);
+        screen.set_keymap(KeyMap::from_env());
+        match self.capabilities {
+            Some(capabilities) => screen.set_capabilities(capabilities),
+            None => screen.use_capabilities(Capabilities::from_env()),
+        }
+        screen.set_escape_timeout(self.esc_delay_ms);
+        screen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let builder = ScreenBuilder::new()
+            .esc_delay(40)
+            .signals(true)
+            .flow_control(true)
+            .controlling_tty(true)
+            .non_tty(NonTty::Plain);
+        assert_eq!(builder.esc_delay_ms, 40);
+        assert!(builder.controlling_tty);
+        assert_eq!(builder.non_tty, NonTty::Plain);
+        assert_eq!(
+            builder.options,
+            TermOptions {
+                translate_newlines: false,
+                signals: true,
+                flow_control: true,
+                main_screen: false,
+            }
+        );
+        assert_eq!(ScreenBuilder::new().options, TermOptions::default());
+    }
+
+    #[test]
+    fn test_capabilities_pick_screen() {
+        let mut builder = ScreenBuilder::new().capabilities(Capabilities::from_term("linux"));
+        builder.apply_capabilities();
+        assert!(builder.options.main_screen);
+
+        let mut builder =
+            ScreenBuilder::new().capabilities(Capabilities::from_term("xterm-256color"));
+        builder.apply_capabilities();
+        assert!(!builder.options.main_screen);
+    }
+}
@@ -0,0 +1,540 @@
+//! Terminal capability detection
+//!
+//! Not every terminal understands every sequence the renderer can emit.
+//! `Capabilities` records which optional features are safe to use, looked up
+//! by `$TERM` in a small table (a stand-in for terminfo) with manual
+//! overrides. Terminals missing from the table are assumed to be
+//! xterm-compatible apart from the rarer features.
+//!
+//! Inside tmux or GNU screen, sequences the multiplexer doesn't understand
+//! (graphics, kitty keyboard) only reach the outer terminal wrapped in a DCS
+//! passthrough; see `Passthrough`.
+
+use crate::attr::Attr;
+use crate::query::TerminalIdentity;
+use std::borrow::Cow;
+
+/// Largest payload GNU screen passes through in one DCS
+const SCREEN_DCS_LIMIT: usize = 760;
+
+/// How to get sequences through a terminal multiplexer to the terminal
+/// outside it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Passthrough {
+    /// Not inside a multiplexer: send sequences as they are
+    #[default]
+    None,
+    /// tmux: `ESC P tmux; <sequence with ESC doubled> ESC \`
+    ///
+    /// tmux also needs `set -g allow-passthrough on` (3.3 and later).
+    Tmux,
+    /// GNU screen: `ESC P <sequence> ESC \`, split into chunks screen
+    /// accepts
+    Screen,
+}
+
+/// How the terminal raises desktop notifications (`Screen::notify`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notify {
+    /// No notification support: `notify` sends nothing
+    #[default]
+    None,
+    /// OSC 9 (iTerm2, kitty): a single message, no separate title
+    Osc9,
+    /// OSC 777 (urxvt, foot, wezterm, ghostty): `notify;title;body`
+    Osc777,
+}
+
+impl Passthrough {
+    /// Wrap `sequence` so it reaches the outer terminal
+    pub fn wrap<'a>(&self, sequence: &'a str) -> Cow<'a, str> {
+        match self {
+            Passthrough::None => Cow::Borrowed(sequence),
+            Passthrough::Tmux => {
+                let mut out = String::with_capacity(sequence.len() + 16);
+                out.push_str("\x1bPtmux;");
+                out.push_str(&sequence.replace('\x1b', "\x1b\x1b"));
+                out.push_str("\x1b\\");
+                Cow::Owned(out)
+            }
+            Passthrough::Screen => {
+                let mut out = String::with_capacity(sequence.len() + 16);
+                let mut rest = sequence;
+                while !rest.is_empty() {
+                    let mut end = rest.len().min(SCREEN_DCS_LIMIT);
+                    while !rest.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    out.push_str("\x1bP");
+                    out.push_str(&rest[..end]);
+                    out.push_str("\x1b\\");
+                    rest = &rest[end..];
+                }
+                Cow::Owned(out)
+            }
+        }
+    }
+}
+
+/// Optional terminal features used by the renderer
+///
+/// The default has the widely supported features (ECH, the alternate
+/// screen, scroll regions, line insertion) and none of the rarer ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// REP (`CSI Ps b`): repeat the preceding character
+    pub rep: bool,
+    /// XTPUSHTITLE/XTPOPTITLE (`CSI 22 t`/`CSI 23 t`): save and restore the title
+    pub title_stack: bool,
+    /// ECH (`CSI Ps X`): erase characters; blank runs are written as spaces
+    /// without it
+    pub ech: bool,
+    /// Alternate screen (`CSI ? 1049 h`); the normal screen is drawn on and
+    /// cleared on exit without it
+    pub alt_screen: bool,
+    /// DECSTBM (`CSI Pt ; Pb r`): scroll regions
+    pub scroll_region: bool,
+    /// IL/DL (`CSI Ps L`/`CSI Ps M`): insert and delete lines; scrolled
+    /// content is redrawn without it
+    pub insert_line: bool,
+    /// ICH/DCH (`CSI Ps @`/`CSI Ps P`): insert and delete characters;
+    /// horizontally shifted content is redrawn without it
+    pub insert_char: bool,
+    /// Multiplexer between the program and the terminal, whose passthrough
+    /// wraps image and kitty keyboard sequences
+    pub passthrough: Passthrough,
+    /// Unicode output (a UTF-8 locale); line drawing falls back to ASCII
+    /// without it
+    pub unicode: bool,
+    /// Attributes the terminal draws; others go through the screen's
+    /// `AttrPolicy`
+    pub attrs: Attr,
+    /// Desktop notification sequence
+    pub notify: Notify,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            rep: false,
+            title_stack: false,
+            ech: true,
+            alt_screen: true,
+            scroll_region: true,
+            insert_line: true,
+            insert_char: true,
+            passthrough: Passthrough::None,
+            unicode: true,
+            attrs: Attr::ALL,
+            notify: Notify::None,
+        }
+    }
+}
+
+/// Attributes of the DEC VT series
+const VT_ATTRS: Attr = Attr(Attr::BOLD.0 | Attr::UNDERLINE.0 | Attr::BLINK.0 | Attr::REVERSE.0);
+
+/// Modern terminal emulators with every feature
+const FULL: Capabilities = Capabilities {
+    rep: true,
+    title_stack: true,
+    ech: true,
+    alt_screen: true,
+    scroll_region: true,
+    insert_line: true,
+    insert_char: true,
+    passthrough: Passthrough::None,
+    unicode: true,
+    attrs: Attr::ALL,
+    notify: Notify::None,
+};
+
+/// Capabilities by `$TERM` prefix (the part before the first `-`)
+const TERMINALS: &[(&str, Capabilities)] = &[
+    ("xterm", FULL),
+    (
+        "kitty",
+        Capabilities {
+            notify: Notify::Osc9,
+            ..FULL
+        },
+    ),
+    ("alacritty", FULL),
+    (
+        "foot",
+        Capabilities {
+            notify: Notify::Osc777,
+            ..FULL
+        },
+    ),
+    (
+        "wezterm",
+        Capabilities {
+            notify: Notify::Osc777,
+            ..FULL
+        },
+    ),
+    ("contour", FULL),
+    (
+        "ghostty",
+        Capabilities {
+            notify: Notify::Osc777,
+            ..FULL
+        },
+    ),
+    (
+        "tmux",
+        Capabilities {
+            title_stack: false,
+            passthrough: Passthrough::Tmux,
+            ..FULL
+        },
+    ),
+    (
+        "screen",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            passthrough: Passthrough::Screen,
+            ..FULL
+        },
+    ),
+    (
+        "rxvt",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            notify: Notify::Osc777,
+            ..FULL
+        },
+    ),
+    // The Linux console has no alternate screen
+    (
+        "linux",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            alt_screen: false,
+            attrs: Attr(VT_ATTRS.0 | Attr::DIM.0),
+            ..FULL
+        },
+    ),
+    (
+        "vt220",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            alt_screen: false,
+            attrs: VT_ATTRS,
+            ..FULL
+        },
+    ),
+    // ECH arrived with the VT220, IL/DL and ICH/DCH with the VT102
+    (
+        "vt102",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            ech: false,
+            alt_screen: false,
+            attrs: VT_ATTRS,
+            ..FULL
+        },
+    ),
+    (
+        "vt100",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            ech: false,
+            alt_screen: false,
+            scroll_region: true,
+            insert_line: false,
+            insert_char: false,
+            passthrough: Passthrough::None,
+            unicode: true,
+            attrs: VT_ATTRS,
+            notify: Notify::None,
+        },
+    ),
+    (
+        "dumb",
+        Capabilities {
+            rep: false,
+            title_stack: false,
+            ech: false,
+            alt_screen: false,
+            scroll_region: false,
+            insert_line: false,
+            insert_char: false,
+            passthrough: Passthrough::None,
+            unicode: false,
+            attrs: Attr::NORMAL,
+            notify: Notify::None,
+        },
+    ),
+];
+
+impl Capabilities {
+    /// Detect capabilities from `$TERM` and `$TERM_PROGRAM`
+    pub fn from_env() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let mut caps = Self::from_term(&term);
+
+        // Terminal.app claims xterm-256color but lacks REP; others behind
+        // an xterm $TERM name themselves here
+        match std::env::var("TERM_PROGRAM").as_deref() {
+            Ok("Apple_Terminal") => caps.rep = false,
+            Ok("iTerm.app") => caps.notify = Notify::Osc9,
+            Ok("WezTerm" | "ghostty") => caps.notify = Notify::Osc777,
+            _ => {}
+        }
+        if std::env::var_os("KITTY_WINDOW_ID").is_some() {
+            caps.notify = Notify::Osc9;
+        }
+
+        if let Some(unicode) = locale_is_utf8() {
+            caps.unicode &= unicode;
+        }
+
+        // The multiplexer's own variables are more reliable than $TERM,
+        // which tmux may set to screen-*
+        if std::env::var_os("TMUX").is_some() {
+            caps.passthrough = Passthrough::Tmux;
+        } else if std::env::var_os("STY").is_some() {
+            caps.passthrough = Passthrough::Screen;
+        }
+
+        caps
+    }
+
+    /// Look up capabilities for a terminal name (the value of `$TERM`)
+    pub fn from_term(term: &str) -> Self {
+        Self::lookup(term.split('-').next().unwrap_or("")).unwrap_or_default()
+    }
+
+    /// Refine the capabilities with what the terminal reported about itself
+    ///
+    /// A terminal that names itself in XTVERSION is looked up in the table,
+    /// which beats a `$TERM` claiming xterm. The multiplexer passthrough and
+    /// the locale's charset still come from the environment.
+    pub fn with_identity(self, identity: &TerminalIdentity) -> Self {
+        let Some(caps) = identity
+            .name
+            .as_deref()
+            .and_then(|name| Self::lookup(&name.to_ascii_lowercase()))
+        else {
+            return self;
+        };
+        Self {
+            passthrough: self.passthrough,
+            unicode: self.unicode,
+            ..caps
+        }
+    }
+
+    fn lookup(name: &str) -> Option<Self> {
+        TERMINALS
+            .iter()
+            .find(|(entry, _)| *entry == name)
+            .map(|&(_, caps)| caps)
+    }
+
+    /// Override REP support
+    pub fn rep(mut self, enabled: bool) -> Self {
+        self.rep = enabled;
+        self
+    }
+
+    /// Override title stack support
+    pub fn title_stack(mut self, enabled: bool) -> Self {
+        self.title_stack = enabled;
+        self
+    }
+
+    /// Override ECH support
+    pub fn ech(mut self, enabled: bool) -> Self {
+        self.ech = enabled;
+        self
+    }
+
+    /// Override alternate screen support
+    pub fn alt_screen(mut self, enabled: bool) -> Self {
+        self.alt_screen = enabled;
+        self
+    }
+
+    /// Override scroll region support
+    pub fn scroll_region(mut self, enabled: bool) -> Self {
+        self.scroll_region = enabled;
+        self
+    }
+
+    /// Override line insertion and deletion support
+    pub fn insert_line(mut self, enabled: bool) -> Self {
+        self.insert_line = enabled;
+        self
+    }
+
+    /// Override character insertion and deletion support
+    pub fn insert_char(mut self, enabled: bool) -> Self {
+        self.insert_char = enabled;
+        self
+    }
+
+    /// Override Unicode output (ASCII line drawing when off)
+    pub fn unicode(mut self, enabled: bool) -> Self {
+        self.unicode = enabled;
+        self
+    }
+
+    /// Override the attributes the terminal draws
+    pub fn attrs(mut self, attrs: Attr) -> Self {
+        self.attrs = attrs;
+        self
+    }
+
+    /// Override the desktop notification sequence
+    pub fn notify(mut self, notify: Notify) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    /// Override the multiplexer passthrough
+    pub fn passthrough(mut self, passthrough: Passthrough) -> Self {
+        self.passthrough = passthrough;
+        self
+    }
+}
+
+/// Check the locale's character set, as chosen by the first of `LC_ALL`,
+/// `LC_CTYPE` and `LANG` that is set (None if none is)
+fn locale_is_utf8() -> Option<bool> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(|locale| charset_is_utf8(&locale))
+}
+
+/// Check if a locale name such as `en_US.UTF-8` or `C` uses UTF-8
+fn charset_is_utf8(locale: &str) -> bool {
+    let charset = locale
+        .split_once('.')
+        .map_or("", |(_, rest)| rest.split('@').next().unwrap_or(""));
+    charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rep_detection() {
+        assert!(Capabilities::from_term("xterm").rep);
+        assert!(Capabilities::from_term("xterm-256color").rep);
+        assert!(Capabilities::from_term("xterm-kitty").rep);
+        assert!(Capabilities::from_term("alacritty").rep);
+        assert!(Capabilities::from_term("tmux-256color").rep);
+
+        assert!(!Capabilities::from_term("screen-256color").rep);
+        assert!(!Capabilities::from_term("linux").rep);
+        assert!(!Capabilities::from_term("vt100").rep);
+        assert!(!Capabilities::from_term("dumb").rep);
+        assert!(!Capabilities::from_term("").rep);
+    }
+
+    #[test]
+    fn test_title_stack_detection() {
+        assert!(Capabilities::from_term("xterm-256color").title_stack);
+        assert!(Capabilities::from_term("xterm-kitty").title_stack);
+        assert!(!Capabilities::from_term("tmux-256color").title_stack);
+        assert!(!Capabilities::from_term("linux").title_stack);
+    }
+
+    #[test]
+    fn test_table() {
+        let linux = Capabilities::from_term("linux");
+        assert!(!linux.alt_screen);
+        assert!(linux.ech && linux.scroll_region && linux.insert_line);
+
+        let vt100 = Capabilities::from_term("vt100");
+        assert!(!vt100.ech && !vt100.insert_line && !vt100.insert_char);
+        assert!(Capabilities::from_term("vt102").insert_char);
+        assert!(vt100.scroll_region);
+        assert!(!vt100.attrs.intersects(Attr::ITALIC | Attr::DIM));
+        assert!(vt100.attrs.contains(Attr::BOLD | Attr::REVERSE));
+
+        let dumb = Capabilities::from_term("dumb");
+        assert!(!dumb.ech && !dumb.alt_screen && !dumb.scroll_region);
+
+        // Unknown terminals get the common features only
+        assert_eq!(
+            Capabilities::from_term("some-new-term"),
+            Capabilities::default()
+        );
+        assert!(Capabilities::from_term("screen-256color").alt_screen);
+
+        assert_eq!(Capabilities::from_term("foot").notify, Notify::Osc777);
+        assert_eq!(Capabilities::from_term("kitty").notify, Notify::Osc9);
+        assert_eq!(Capabilities::from_term("xterm").notify, Notify::None);
+    }
+
+    #[test]
+    fn test_with_identity() {
+        let caps = Capabilities::from_term("xterm-256color").passthrough(Passthrough::Tmux);
+        let identity = TerminalIdentity {
+            name: Some("foot".to_string()),
+            ..TerminalIdentity::default()
+        };
+        let refined = caps.with_identity(&identity);
+        assert_eq!(refined.notify, Notify::Osc777);
+        assert_eq!(refined.passthrough, Passthrough::Tmux);
+
+        // Unknown or missing names change nothing
+        assert_eq!(caps.with_identity(&TerminalIdentity::default()), caps);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        assert_eq!(
+            Capabilities::from_term("tmux-256color").passthrough,
+            Passthrough::Tmux
+        );
+        assert_eq!(
+            Capabilities::from_term("xterm").passthrough,
+            Passthrough::None
+        );
+
+        let seq = "\x1b_Ga=d\x1b\\";
+        assert_eq!(Passthrough::None.wrap(seq), seq);
+        assert_eq!(
+            Passthrough::Tmux.wrap(seq),
+            "\x1bPtmux;\x1b\x1b_Ga=d\x1b\x1b\\\x1b\\"
+        );
+        assert_eq!(Passthrough::Screen.wrap(seq), "\x1bP\x1b_Ga=d\x1b\\\x1b\\");
+
+        // Long sequences are split for screen
+        let long = "x".repeat(SCREEN_DCS_LIMIT * 2 + 1);
+        assert_eq!(Passthrough::Screen.wrap(&long).matches("\x1bP").count(), 3);
+    }
+
+    #[test]
+    fn test_charset() {
+        assert!(charset_is_utf8("en_US.UTF-8"));
+        assert!(charset_is_utf8("de_DE.utf8@euro"));
+        assert!(charset_is_utf8("C.UTF-8"));
+        assert!(!charset_is_utf8("C"));
+        assert!(!charset_is_utf8("POSIX"));
+        assert!(!charset_is_utf8("en_US.ISO-8859-1"));
+        assert!(!Capabilities::from_term("dumb").unicode);
+        assert!(Capabilities::from_term("xterm").unicode);
+    }
+
+    #[test]
+    fn test_override() {
+        let caps = Capabilities::from_term("linux").rep(true);
+        assert!(caps.rep);
+        assert!(!Capabilities::default().rep);
+    }
+}
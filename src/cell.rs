@@ -4,20 +4,31 @@
 /// avoiding color quantization artifacts in gradients.
 use crate::attr::Attr;
 use crate::color::Color;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use unicode_width::UnicodeWidthChar;
+
+/// Zero width joiner, gluing emoji into one grapheme
+const ZWJ: char = '\u{200D}';
+
+/// Characters after the first of a grapheme cluster
+type Combining = SmallVec<[char; 4]>;
 
 /// A single cell in the screen buffer, containing a character and its styling
 ///
-/// Memory layout (16 bytes total):
+/// Memory layout (24 bytes total):
 /// - ch: char (4 bytes)
 /// - attr: u16 (2 bytes)
-/// - padding: 2 bytes (for alignment)
+/// - theme_style: u16 (2 bytes, in what would be padding)
 /// - fg: Color (4 bytes)
 /// - bg: Color (4 bytes)
+/// - combining: pointer (8 bytes), allocated only for clusters such as
+///   "e" + U+0301 or emoji ZWJ sequences
 ///
 /// Uses Color::Reset to represent terminal default colors (similar to ratatui's approach)
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct Cell {
-    /// The character to display
+    /// The character to display (the first of its grapheme cluster)
     pub ch: char,
     /// Text attributes (bold, underline, etc.)
     pub attr: Attr,
@@ -25,9 +36,17 @@ pub struct Cell {
     pub fg: Color,
     /// Background color (Color::Reset = terminal default)
     pub bg: Color,
+    // Theme style the cell was drawn with, numbered by its screen from 1;
+    // 0 for none (see `Screen::use_style`)
+    theme_style: u16,
+    /// Rest of the grapheme cluster: combining marks, joined emoji, ...
+    combining: Option<Box<Combining>>,
 }
 
 impl Cell {
+    /// Character of a cell covered by the wide character to its left
+    pub const CONTINUATION: char = '\0';
+
     /// Create a new cell with a character and default styling
     pub fn new(ch: char) -> Self {
         Self {
@@ -35,9 +54,22 @@ impl Cell {
             attr: Attr::NORMAL,
             fg: Color::Reset,
             bg: Color::Reset,
+            theme_style: 0,
+            combining: None,
         }
     }
 
+    /// Create a cell showing a grapheme cluster, e.g. "e\u{301}"; an empty
+    /// string gives a blank
+    pub fn from_grapheme(grapheme: &str) -> Self {
+        let mut chars = grapheme.chars();
+        let mut cell = Self::new(chars.next().unwrap_or(' '));
+        for ch in chars {
+            cell.push_combining(ch);
+        }
+        cell
+    }
+
     /// Create a blank cell (space character with no styling)
     pub fn blank() -> Self {
         Self::new(' ')
@@ -45,7 +77,14 @@ impl Cell {
 
     /// Create a cell with a character and specific styling
     pub fn with_style(ch: char, attr: Attr, fg: Color, bg: Color) -> Self {
-        Self { ch, attr, fg, bg }
+        Self {
+            ch,
+            attr,
+            fg,
+            bg,
+            theme_style: 0,
+            combining: None,
+        }
     }
 
     /// Get the character
@@ -54,6 +93,53 @@ impl Cell {
         self.ch
     }
 
+    /// Characters drawn after `ch` in the same cell
+    #[inline]
+    pub fn combining(&self) -> &[char] {
+        self.combining
+            .as_deref()
+            .map_or(&[], |chars| chars.as_slice())
+    }
+
+    /// Add a character to the cell's grapheme cluster
+    pub fn push_combining(&mut self, ch: char) {
+        self.combining.get_or_insert_default().push(ch);
+    }
+
+    /// The cell's whole grapheme cluster
+    pub fn grapheme(&self) -> String {
+        let mut text = String::new();
+        self.push_grapheme(&mut text);
+        text
+    }
+
+    /// Append the grapheme cluster to `out`
+    #[inline]
+    pub(crate) fn push_grapheme(&self, out: &mut String) {
+        out.push(self.ch);
+        out.extend(self.combining());
+    }
+
+    /// Whether `ch` belongs to this cell's grapheme cluster rather than
+    /// starting a new one
+    pub(crate) fn continued_by(&self, ch: char) -> bool {
+        let combining = self.combining();
+        !self.is_continuation() && continues_grapheme(self.ch, combining.last().copied(), ch)
+    }
+
+    /// Theme style the cell was drawn with, as numbered by its screen; 0
+    /// if none
+    #[inline]
+    pub(crate) fn theme_style(&self) -> u16 {
+        self.theme_style
+    }
+
+    /// Record the theme style the cell was drawn with
+    #[inline]
+    pub(crate) fn set_theme_style(&mut self, id: u16) {
+        self.theme_style = id;
+    }
+
     /// Get the attributes
     #[inline]
     pub fn attr(&self) -> Attr {
@@ -94,18 +180,115 @@ impl Cell {
             && self.bg == Color::Reset
     }
 
+    /// Check if this cell is the second column of a wide character
+    pub fn is_continuation(&self) -> bool {
+        self.ch == Self::CONTINUATION
+    }
+
     /// Check if this cell has the same styling as another (ignoring character)
     pub fn same_style(&self, other: &Cell) -> bool {
         self.attr == other.attr && self.fg == other.fg && self.bg == other.bg
     }
 }
 
+// Cells that look the same are equal, whichever theme style drew them
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.ch == other.ch
+            && self.attr == other.attr
+            && self.fg == other.fg
+            && self.bg == other.bg
+            && self.combining == other.combining
+    }
+}
+
+impl Eq for Cell {}
+
 impl Default for Cell {
     fn default() -> Self {
         Self::blank()
     }
 }
 
+/// Text shown by a run of cells (wide characters once, not per column)
+pub(crate) fn cells_text(cells: &[Cell]) -> String {
+    let mut text = String::with_capacity(cells.len());
+    for cell in cells.iter().filter(|cell| !cell.is_continuation()) {
+        cell.push_grapheme(&mut text);
+    }
+    text
+}
+
+/// Whether `ch` extends a grapheme cluster starting with `base`, whose
+/// later characters end with `last`
+///
+/// Covers combining marks and other zero-width characters, emoji joined
+/// with ZWJ or given a skin tone, and flag pairs; not the full UAX #29
+/// rules.
+pub(crate) fn continues_grapheme(base: char, last: Option<char>, ch: char) -> bool {
+    if ch.is_control() || base.is_control() {
+        return false;
+    }
+    ch.width() == Some(0)
+        || last.unwrap_or(base) == ZWJ
+        || ('\u{1F3FB}'..='\u{1F3FF}').contains(&ch)
+        || (last.is_none() && is_regional_indicator(base) && is_regional_indicator(ch))
+}
+
+/// Regional indicators pair up into flags
+fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch)
+}
+
+/// Grapheme clusters of `text` with their widths, each as wide as its
+/// first character
+pub(crate) fn graphemes(text: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices();
+        let (_, base) = chars.next()?;
+        let mut last = None;
+        let mut end = rest.len();
+        for (i, ch) in chars {
+            if !continues_grapheme(base, last, ch) {
+                end = i;
+                break;
+            }
+            last = Some(ch);
+        }
+        let (grapheme, tail) = rest.split_at(end);
+        rest = tail;
+        Some((grapheme, base.width().unwrap_or(0)))
+    })
+}
+
+/// Visible stand-in for a control character, like curses `unctrl`: `^X`
+/// for C0 and DEL, `~X` for C1; None for other characters
+pub(crate) fn control_placeholder(ch: char) -> Option<[char; 2]> {
+    match ch as u32 {
+        0x00..=0x1f => Some(['^', char::from(ch as u8 ^ 0x40)]),
+        0x7f => Some(['^', '?']),
+        0x80..=0x9f => Some(['~', char::from(ch as u8 - 0x40)]),
+        _ => None,
+    }
+}
+
+/// `text` with control characters replaced by visible placeholders, so
+/// printed text can't inject escape sequences
+pub(crate) fn sanitize_controls(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(|ch| ch.is_control()) {
+        return Cow::Borrowed(text);
+    }
+    let mut clean = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        match control_placeholder(ch) {
+            Some(placeholder) => clean.extend(placeholder),
+            None => clean.push(ch),
+        }
+    }
+    Cow::Owned(clean)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,12 +297,16 @@ mod tests {
     fn test_cell_size() {
         let size = std::mem::size_of::<Cell>();
 
-        // Color enum: Cell should be 16 bytes (char=4, Attr=2, padding=2, fg=4, bg=4)
-        assert_eq!(size, 16, "Cell should be exactly 16 bytes");
-        assert!(
-            size < 24,
-            "Cell should be significantly smaller than original ~32 bytes"
-        );
+        // char=4, Attr=2, theme style=2, fg=4, bg=4, combining pointer=8
+        assert_eq!(size, 24, "Cell should be exactly 24 bytes");
+        assert!(size < 32, "Cell should be smaller than original ~32 bytes");
+    }
+
+    #[test]
+    fn test_sanitize_controls() {
+        assert!(matches!(sanitize_controls("plain ü"), Cow::Borrowed(_)));
+        assert_eq!(sanitize_controls("a\x1b[2Jb"), "a^[[2Jb");
+        assert_eq!(sanitize_controls("\0\t\n\x7f\u{9b}"), "^@^I^J^?~[");
     }
 
     #[test]
@@ -193,6 +380,48 @@ mod tests {
         assert!(!cell1.same_style(&cell3));
     }
 
+    #[test]
+    fn test_cells_text() {
+        let cells = [
+            Cell::new('字'),
+            Cell::new(Cell::CONTINUATION),
+            Cell::new('a'),
+        ];
+        assert!(cells[1].is_continuation());
+        assert_eq!(cells_text(&cells), "字a");
+    }
+
+    #[test]
+    fn test_combining() {
+        let mut cell = Cell::new('e');
+        assert!(cell.continued_by('\u{301}'));
+        assert!(!cell.continued_by('f'));
+        cell.push_combining('\u{301}');
+        assert_eq!(cell.grapheme(), "e\u{301}");
+        assert_ne!(cell, Cell::new('e'));
+        assert_eq!(cell, Cell::from_grapheme("e\u{301}"));
+        assert_eq!(cells_text(&[cell, Cell::new('x')]), "e\u{301}x");
+        assert!(!Cell::new(Cell::CONTINUATION).continued_by('\u{301}'));
+    }
+
+    #[test]
+    fn test_graphemes() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let text = format!("e\u{301}{family}\u{1F44D}\u{1F3FD}\u{1F1EB}\u{1F1F7}\u{1F1E9}a");
+        let clusters: Vec<_> = graphemes(&text).collect();
+        assert_eq!(
+            clusters,
+            vec![
+                ("e\u{301}", 1),
+                (family, 2),
+                ("\u{1F44D}\u{1F3FD}", 2),
+                ("\u{1F1EB}\u{1F1F7}", 1),
+                ("\u{1F1E9}", 1),
+                ("a", 1),
+            ]
+        );
+    }
+
     #[test]
     fn test_cell_default() {
         let cell = Cell::default();
@@ -285,12 +514,12 @@ mod tests {
 
         assert_eq!(size, expected);
 
-        // Verify it's significantly smaller than original
+        // Verify it's smaller than original
         // Original was ~32 bytes, so 80 cells = 2560 bytes
-        // New should be 16 bytes, so 80 cells = 1280 bytes
+        // New should be 24 bytes, so 80 cells = 1920 bytes
         assert_eq!(
-            size, 1280,
-            "80 cells should use exactly 1280 bytes (16 bytes per cell)"
+            size, 1920,
+            "80 cells should use exactly 1920 bytes (24 bytes per cell)"
         );
     }
 }
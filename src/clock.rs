@@ -0,0 +1,180 @@
+//! Time and randomness sources that tests can control
+//!
+//! Frame pacing, input timeouts and animations read time through a `Clock`.
+//! The system clock follows wall time; a manual clock only moves when
+//! `advance` is called (or something sleeps on it), so golden tests of
+//! animated output are reproducible.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of time for the screen and widgets
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    manual: Option<Arc<Mutex<Instant>>>,
+}
+
+impl Clock {
+    /// Clock following wall time
+    pub fn system() -> Self {
+        Self { manual: None }
+    }
+
+    /// Clock frozen at the current instant until advanced
+    ///
+    /// Clones share the same time, so a test can keep a clone and drive the
+    /// clock installed on a screen.
+    pub fn manual() -> Self {
+        Self {
+            manual: Some(Arc::new(Mutex::new(Instant::now()))),
+        }
+    }
+
+    /// Check if this is a manual clock
+    pub fn is_manual(&self) -> bool {
+        self.manual.is_some()
+    }
+
+    /// Current time
+    pub fn now(&self) -> Instant {
+        match &self.manual {
+            Some(now) => *now.lock().unwrap(),
+            None => Instant::now(),
+        }
+    }
+
+    /// Time elapsed since `earlier`
+    pub fn elapsed(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+
+    /// Move a manual clock forward by `ms` milliseconds (no-op on the system clock)
+    pub fn advance(&self, ms: u64) {
+        self.advance_by(Duration::from_millis(ms));
+    }
+
+    /// Move a manual clock forward (no-op on the system clock)
+    pub fn advance_by(&self, duration: Duration) {
+        if let Some(now) = &self.manual {
+            *now.lock().unwrap() += duration;
+        }
+    }
+
+    /// Sleep on the system clock, or advance a manual clock instantly
+    pub fn sleep(&self, duration: Duration) {
+        if self.is_manual() {
+            self.advance_by(duration);
+        } else {
+            std::thread::sleep(duration);
+        }
+    }
+}
+
+/// Small deterministic pseudo-random generator (xorshift64*)
+///
+/// Not suitable for cryptography; meant for animations and effects that
+/// must be reproducible under a fixed seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator from a seed
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Self {
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+        }
+    }
+
+    /// Create a generator seeded from the system time
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    /// Next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Random number in `0..bound` (0 if `bound` is 0)
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0;
+        }
+        // Multiply-shift keeps the bias negligible without rejection loops
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Random float in `0.0..1.0`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = Clock::manual();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        clock.advance(250);
+        assert_eq!(clock.elapsed(start), Duration::from_millis(250));
+
+        // Clones share time
+        let other = clock.clone();
+        other.sleep(Duration::from_secs(1));
+        assert_eq!(clock.elapsed(start), Duration::from_millis(1250));
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let clock = Clock::system();
+        assert!(!clock.is_manual());
+
+        let start = clock.now();
+        clock.advance(10_000); // No effect
+        assert!(clock.elapsed(start) < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_rng_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let xs: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let ys: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(xs, ys);
+
+        let mut c = Rng::new(43);
+        assert_ne!(c.next_u64(), xs[0]);
+    }
+
+    #[test]
+    fn test_rng_ranges() {
+        let mut rng = Rng::new(0);
+        for _ in 0..1000 {
+            assert!(rng.below(6) < 6);
+            let f = rng.next_f32();
+            assert!((0.0..1.0).contains(&f));
+        }
+        assert_eq!(rng.below(0), 0);
+    }
+}
@@ -117,6 +117,141 @@ impl Color {
     }
 }
 
+/// xterm's default colors for the 16 named colors
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+impl Color {
+    /// Approximate RGB value (xterm defaults for palette colors)
+    ///
+    /// None for `Color::Reset`, whose value only the terminal knows.
+    pub fn to_rgb(&self) -> Option<(u8, u8, u8)> {
+        let index = match *self {
+            Color::Rgb(r, g, b) => return Some((r, g, b)),
+            Color::Reset => return None,
+            Color::Ansi256(index) => index,
+            named => named.hash_bytes().0 - 1,
+        };
+
+        Some(match index {
+            0..=15 => ANSI_RGB[index as usize],
+            16..=231 => {
+                // 6x6x6 color cube
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let i = index - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            _ => {
+                let grey = 8 + (index - 232) * 10;
+                (grey, grey, grey)
+            }
+        })
+    }
+
+    /// Blend from `a` to `b`; `t` is clamped to 0.0-1.0
+    ///
+    /// Palette colors are blended through their approximate RGB values. If
+    /// either color is `Color::Reset`, the nearer of the two is returned.
+    pub fn lerp(a: Color, b: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        match (a.to_rgb(), b.to_rgb()) {
+            (Some((ar, ag, ab)), Some((br, bg, bb))) => {
+                let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+                Color::Rgb(mix(ar, br), mix(ag, bg), mix(ab, bb))
+            }
+            _ if t < 0.5 => a,
+            _ => b,
+        }
+    }
+
+    /// Color from hue (degrees), saturation and value (0.0-1.0)
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Color {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+
+        let c = v * s;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        let m = v - c;
+        let channel = |f: f32| ((f + m) * 255.0).round() as u8;
+        Color::Rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// Relative luminance (0.0 black to 1.0 white), None for `Color::Reset`
+    pub fn luminance(&self) -> Option<f32> {
+        let (r, g, b) = self.to_rgb()?;
+        let linear = |c: u8| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Some(0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b))
+    }
+}
+
+/// A color ramp through evenly spaced stops
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<Color>,
+}
+
+impl Gradient {
+    /// Create a gradient through `stops` (at least one)
+    pub fn new(stops: Vec<Color>) -> Self {
+        assert!(!stops.is_empty(), "gradient needs at least one stop");
+        Self { stops }
+    }
+
+    /// Color at position `t` (0.0 first stop, 1.0 last stop)
+    pub fn at(&self, t: f32) -> Color {
+        let segments = self.stops.len() - 1;
+        if segments == 0 {
+            return self.stops[0];
+        }
+
+        let pos = t.clamp(0.0, 1.0) * segments as f32;
+        let i = (pos as usize).min(segments - 1);
+        Color::lerp(self.stops[i], self.stops[i + 1], pos - i as f32)
+    }
+
+    /// `n` colors evenly spaced from the first stop to the last
+    pub fn steps(&self, n: usize) -> Vec<Color> {
+        match n {
+            0 => Vec::new(),
+            1 => vec![self.at(0.0)],
+            _ => (0..n).map(|i| self.at(i as f32 / (n - 1) as f32)).collect(),
+        }
+    }
+}
+
 /// A color pair consisting of foreground and background colors
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ColorPair {
@@ -170,4 +305,68 @@ mod tests {
         assert_eq!(Color::Reset.to_ansi_fg(), "39");
         assert_eq!(Color::Reset.to_ansi_bg(), "49");
     }
+
+    #[test]
+    fn test_to_rgb() {
+        assert_eq!(Color::Black.to_rgb(), Some((0, 0, 0)));
+        assert_eq!(Color::BrightWhite.to_rgb(), Some((255, 255, 255)));
+        assert_eq!(Color::Ansi256(1).to_rgb(), Color::Red.to_rgb());
+        assert_eq!(Color::Ansi256(196).to_rgb(), Some((255, 0, 0)));
+        assert_eq!(Color::Ansi256(232).to_rgb(), Some((8, 8, 8)));
+        assert_eq!(Color::Ansi256(255).to_rgb(), Some((238, 238, 238)));
+        assert_eq!(Color::Reset.to_rgb(), None);
+    }
+
+    #[test]
+    fn test_lerp() {
+        let (a, b) = (Color::Rgb(0, 0, 0), Color::Rgb(200, 100, 50));
+        assert_eq!(Color::lerp(a, b, 0.0), a);
+        assert_eq!(Color::lerp(a, b, 0.5), Color::Rgb(100, 50, 25));
+        assert_eq!(Color::lerp(a, b, 2.0), b);
+        assert_eq!(
+            Color::lerp(Color::Black, Color::BrightWhite, 1.0),
+            Color::Rgb(255, 255, 255)
+        );
+
+        assert_eq!(Color::lerp(Color::Reset, b, 0.4), Color::Reset);
+        assert_eq!(Color::lerp(Color::Reset, b, 0.6), b);
+    }
+
+    #[test]
+    fn test_from_hsv() {
+        assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), Color::Rgb(255, 0, 0));
+        assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::Rgb(0, 255, 0));
+        assert_eq!(Color::from_hsv(240.0, 1.0, 0.5), Color::Rgb(0, 0, 128));
+        assert_eq!(Color::from_hsv(360.0, 1.0, 1.0), Color::Rgb(255, 0, 0));
+        assert_eq!(Color::from_hsv(42.0, 0.0, 1.0), Color::Rgb(255, 255, 255));
+    }
+
+    #[test]
+    fn test_luminance() {
+        assert_eq!(Color::Rgb(0, 0, 0).luminance(), Some(0.0));
+        assert!((Color::Rgb(255, 255, 255).luminance().unwrap() - 1.0).abs() < 1e-6);
+        assert!(Color::Yellow.luminance() > Color::Blue.luminance());
+        assert_eq!(Color::Reset.luminance(), None);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let gradient = Gradient::new(vec![
+            Color::Rgb(0, 0, 0),
+            Color::Rgb(100, 0, 0),
+            Color::Rgb(100, 100, 0),
+        ]);
+        assert_eq!(gradient.at(0.25), Color::Rgb(50, 0, 0));
+        assert_eq!(gradient.at(0.5), Color::Rgb(100, 0, 0));
+        assert_eq!(gradient.at(1.0), Color::Rgb(100, 100, 0));
+
+        let steps = gradient.steps(5);
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[4], Color::Rgb(100, 100, 0));
+        assert!(gradient.steps(0).is_empty());
+        assert_eq!(
+            Gradient::new(vec![Color::Red]).steps(3),
+            vec![Color::Red; 3]
+        );
+    }
 }
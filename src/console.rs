@@ -0,0 +1,325 @@
+//! Debug console overlay (feature `debug-console`)
+//!
+//! A small command interpreter shown in a panel at the bottom of the screen,
+//! for inspecting refresh stats, dumping regions and adjusting tuning knobs
+//! while the application runs. Route keys through `handle_key` before the
+//! application sees them:
+//!
+//! ```no_run
+//! # use zaz::{DebugConsole, Screen};
+//! # let mut scr = Screen::init()?;
+//! let mut console = DebugConsole::new();
+//! loop {
+//!     let key = scr.getch()?;
+//!     if console.handle_key(&mut scr, &key)? {
+//!         continue;
+//!     }
+//!     // Application input handling
+//! #   break;
+//! }
+//! # Ok::<(), zaz::Error>(())
+//! ```
+
+use crate::error::Result;
+use crate::input::Key;
+use crate::layout::Rect;
+use crate::panel::PanelId;
+use crate::screen::Screen;
+
+/// Output lines kept in the console history
+const MAX_HISTORY: usize = 200;
+
+/// Rows used by the console panel (including its border)
+const CONSOLE_HEIGHT: u16 = 10;
+
+const HELP: &[&str] = &[
+    "stats               last refresh statistics",
+    "term                terminal identity and capabilities",
+    "dump Y X H W        print a screen region",
+    "damage on|off       highlight cells written by refresh",
+    "check N             check for input every N lines",
+    "fps N               target frame rate (0 = unlimited)",
+    "rep on|off          use REP for repeated cells",
+    "close               hide the console",
+];
+
+/// Toggleable debug console
+pub struct DebugConsole {
+    hotkey: Key,
+    panel: Option<PanelId>,
+    input: String,
+    history: Vec<String>,
+}
+
+impl DebugConsole {
+    /// Create a console toggled with F12
+    pub fn new() -> Self {
+        Self {
+            hotkey: Key::F(12),
+            panel: None,
+            input: String::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Use a different key to toggle the console
+    pub fn hotkey(mut self, key: Key) -> Self {
+        self.hotkey = key;
+        self
+    }
+
+    /// Check if the console is shown
+    pub fn is_open(&self) -> bool {
+        self.panel.is_some()
+    }
+
+    /// Output produced so far
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Handle a key, returning true if the console consumed it
+    ///
+    /// While open, the console consumes every key.
+    pub fn handle_key(&mut self, scr: &mut Screen, key: &Key) -> Result<bool> {
+        if *key == self.hotkey {
+            if self.is_open() {
+                self.close(scr)?;
+            } else {
+                self.open(scr)?;
+            }
+            return Ok(true);
+        }
+
+        if !self.is_open() {
+            return Ok(false);
+        }
+
+        match key {
+            Key::Escape => return self.close(scr).map(|_| true),
+            Key::Char(ch) => self.input.push(*ch),
+            Key::Backspace => {
+                self.input.pop();
+            }
+            Key::Enter => {
+                let command = std::mem::take(&mut self.input);
+                self.push(format!("> {}", command));
+                for line in self.execute(scr, &command) {
+                    self.push(line);
+                }
+                if !self.is_open() {
+                    return Ok(true);
+                }
+            }
+            _ => {}
+        }
+
+        self.draw(scr)?;
+        Ok(true)
+    }
+
+    /// Run a console command, returning its output
+    pub fn execute(&mut self, scr: &mut Screen, command: &str) -> Vec<String> {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        let number = |i: usize| args.get(i).and_then(|arg| arg.parse::<u32>().ok());
+        let switch = |i: usize| match args.get(i) {
+            Some(&"on") => Some(true),
+            Some(&"off") => Some(false),
+            _ => None,
+        };
+
+        match args.as_slice() {
+            [] => Vec::new(),
+            ["help"] => HELP.iter().map(|line| line.to_string()).collect(),
+            ["stats"] => {
+                let stats = scr.last_refresh_stats();
+                vec![
+                    format!(
+                        "bytes {}  cells {}  lines {}  scrolls {}{}",
+                        stats.bytes_written,
+                        stats.cells_changed,
+                        stats.lines_touched,
+                        stats.scroll_ops,
+                        if stats.aborted { "  (aborted)" } else { "" }
+                    ),
+                    format!(
+                        "diff {:?}  emit {:?}  total {:?}",
+                        stats.diff_time,
+                        stats.emit_time,
+                        stats.total_time()
+                    ),
+                ]
+            }
+            ["term"] => {
+                let caps = scr.capabilities();
+                let identity = match scr.terminal_identity() {
+                    Some(id) => format!(
+                        "{} {}  da1 {:?} {:?}  da2 {:?} {:?}",
+                        id.name.as_deref().unwrap_or("?"),
+                        id.version.as_deref().unwrap_or("?"),
+                        id.conformance,
+                        id.attributes,
+                        id.terminal_type,
+                        id.firmware
+                    ),
+                    None => "identity not queried".to_string(),
+                };
+                vec![
+                    identity,
+                    format!(
+                        "rep {}  ech {}  il/dl {}  ich/dch {}  notify {:?}",
+                        caps.rep, caps.ech, caps.insert_line, caps.insert_char, caps.notify
+                    ),
+                ]
+            }
+            ["dump", ..] => match (number(1), number(2), number(3), number(4)) {
+                (Some(y), Some(x), Some(h), Some(w)) => {
+                    let rect = Rect::new(y as u16, x as u16, h as u16, w as u16);
+                    scr.capture(rect).iter().map(|line| line.text()).collect()
+                }
+                _ => vec!["usage: dump Y X H W".to_string()],
+            },
+            ["damage", ..] => match switch(1) {
+                Some(on) => {
+                    scr.set_show_damage(on);
+                    vec![format!("damage {}", args[1])]
+                }
+                None => vec!["usage: damage on|off".to_string()],
+            },
+            ["check", ..] => match number(1) {
+                Some(lines) if lines > 0 => {
+                    scr.set_check_interval(lines as usize);
+                    vec![format!("check interval {}", lines)]
+                }
+                _ => vec!["usage: check N (N > 0)".to_string()],
+            },
+            ["fps", ..] => match number(1) {
+                Some(fps) => {
+                    scr.set_target_fps(fps);
+                    vec![format!("target fps {}", fps)]
+                }
+                None => vec!["usage: fps N".to_string()],
+            },
+            ["rep", ..] => match switch(1) {
+                Some(on) => {
+                    scr.set_capabilities(scr.capabilities().rep(on));
+                    vec![format!("rep {}", args[1])]
+                }
+                None => vec!["usage: rep on|off".to_string()],
+            },
+            ["close"] => match self.close(scr) {
+                Ok(()) => Vec::new(),
+                Err(err) => vec![format!("error: {}", err)],
+            },
+            [other, ..] => vec![format!("unknown command: {} (try help)", other)],
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        self.history.push(line);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    fn rect(scr: &Screen) -> Rect {
+        let (rows, cols) = scr.dimensions();
+        let height = CONSOLE_HEIGHT.min(rows);
+        Rect::new(rows - height, 0, height, cols)
+    }
+
+    fn open(&mut self, scr: &mut Screen) -> Result<()> {
+        let rect = Self::rect(scr);
+        let window = scr.newwin(rect.height, rect.width, rect.y, rect.x)?;
+        self.panel = Some(scr.new_panel(window)?);
+        self.draw(scr)
+    }
+
+    fn close(&mut self, scr: &mut Screen) -> Result<()> {
+        if let Some(id) = self.panel.take() {
+            scr.panels_mut().remove(id)?;
+            // Repaint what the console covered on the next refresh
+            let rect = Self::rect(scr);
+            for y in rect.y..rect.bottom() {
+                scr.invalidate_line(y);
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, scr: &mut Screen) -> Result<()> {
+        let Some(id) = self.panel else {
+            return Ok(());
+        };
+        let Some(panel) = scr.panels_mut().get_mut(id) else {
+            return Ok(());
+        };
+
+        let window = panel.window_mut();
+        let (height, width) = window.get_size();
+        let inner = width.saturating_sub(2) as usize;
+        let visible = height.saturating_sub(3) as usize;
+
+        window.clear()?;
+        window.draw_box()?;
+        window.mvprint(0, 2, " debug ")?;
+
+        let start = self.history.len().saturating_sub(visible);
+        for (row, line) in self.history[start..].iter().enumerate() {
+            let text: String = line.chars().take(inner).collect();
+            window.mvprint(row as u16 + 1, 1, &text)?;
+        }
+
+        // Keep the end of long input visible
+        let prompt = format!("> {}", self.input);
+        let skip = prompt.chars().count().saturating_sub(inner);
+        let prompt: String = prompt.chars().skip(skip).collect();
+        window.mvprint(height.saturating_sub(2), 1, &prompt)?;
+
+        scr.update_panels()?;
+        scr.doupdate()
+    }
+}
+
+impl Default for DebugConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut console = DebugConsole::new();
+
+        scr.mvprint(2, 4, "hello").unwrap();
+        assert_eq!(console.execute(&mut scr, "dump 2 4 1 5"), vec!["hello"]);
+        assert_eq!(console.execute(&mut scr, "stats").len(), 2);
+        assert_eq!(console.execute(&mut scr, "term")[0], "identity not queried");
+        assert_eq!(console.execute(&mut scr, "help").len(), HELP.len());
+
+        console.execute(&mut scr, "rep off");
+        assert!(!scr.capabilities().rep);
+        console.execute(&mut scr, "rep on");
+        assert!(scr.capabilities().rep);
+
+        assert!(console.execute(&mut scr, "dump 1")[0].starts_with("usage"));
+        assert!(console.execute(&mut scr, "check 0")[0].starts_with("usage"));
+        assert!(console.execute(&mut scr, "bogus")[0].starts_with("unknown"));
+        assert!(console.execute(&mut scr, "  ").is_empty());
+    }
+
+    #[test]
+    fn test_keys_ignored_while_closed() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut console = DebugConsole::new().hotkey(Key::Ctrl('d'));
+
+        assert!(!console.handle_key(&mut scr, &Key::Char('a')).unwrap());
+        assert!(!console.handle_key(&mut scr, &Key::F(12)).unwrap());
+        assert!(!console.is_open());
+    }
+}
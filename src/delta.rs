@@ -1,13 +1,36 @@
 use crate::Color;
+use crate::buffer::Buffer;
 use crate::cell::Cell;
+use smallvec::SmallVec;
+
+/// Gap (in cells) up to which two dirty spans on a line are merged:
+/// re-sending a few unchanged cells is cheaper than a cursor move
+pub const COALESCE_GAP: u16 = 8;
+
+/// Fewest lines moved by a scroll hunk (from ncurses)
+const MIN_HUNK: usize = 3;
+
+/// Most spans tracked per line; past this the closest spans are merged
+const MAX_SPANS: usize = 8;
+
+/// Screen size (in cells) from which dirty lines are diffed in parallel
+#[cfg(feature = "parallel")]
+const PARALLEL_DIFF_MIN_CELLS: usize = 20_000;
+
+/// Changed columns (first, last, inclusive) found in one line
+pub(crate) type LineChanges = SmallVec<[(usize, usize); 8]>;
 
 /// Represents a dirty region within a line
+///
+/// Changes are kept as sorted, disjoint column spans, so edits at opposite
+/// ends of a wide line don't force re-sending everything between them.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DirtyRegion {
     /// First changed column (inclusive), None if line is clean
     pub first_changed: Option<u16>,
     /// Last changed column (inclusive), None if line is clean
     pub last_changed: Option<u16>,
+    spans: SmallVec<[(u16, u16); 4]>,
 }
 
 /// Represents a scroll operation (like ncurses' scroll hunks)
@@ -27,32 +50,45 @@ impl DirtyRegion {
         Self {
             first_changed: None,
             last_changed: None,
+            spans: SmallVec::new(),
         }
     }
 
     /// Create a dirty region covering the entire line
     pub fn full(width: u16) -> Self {
-        Self {
-            first_changed: Some(0),
-            last_changed: Some(width.saturating_sub(1)),
-        }
+        let mut region = Self::clean();
+        region.mark(0, width.saturating_sub(1));
+        region
     }
 
     /// Mark a range as dirty
+    ///
+    /// Spans closer than `COALESCE_GAP` cells are merged.
     pub fn mark(&mut self, start: u16, end: u16) {
-        match (self.first_changed, self.last_changed) {
-            (None, None) => {
-                // First time marking this line
-                self.first_changed = Some(start);
-                self.last_changed = Some(end);
-            }
-            (Some(first), Some(last)) => {
-                // Expand the dirty region
-                self.first_changed = Some(first.min(start));
-                self.last_changed = Some(last.max(end));
+        let at = self.spans.partition_point(|&(first, _)| first < start);
+        self.spans.insert(at, (start, end));
+
+        let mut merged: SmallVec<[(u16, u16); 4]> = SmallVec::new();
+        for &(first, last) in &self.spans {
+            match merged.last_mut() {
+                Some(prev) if first <= prev.1.saturating_add(COALESCE_GAP + 1) => {
+                    prev.1 = prev.1.max(last);
+                }
+                _ => merged.push((first, last)),
             }
-            _ => unreachable!("Invalid dirty region state"),
         }
+
+        while merged.len() > MAX_SPANS {
+            // Merge the pair with the smallest gap
+            let i = (0..merged.len() - 1)
+                .min_by_key(|&i| merged[i + 1].0 - merged[i].1)
+                .unwrap();
+            merged[i].1 = merged[i + 1].1;
+            merged.remove(i + 1);
+        }
+
+        self.spans = merged;
+        self.sync_range();
     }
 
     /// Check if the region is dirty
@@ -67,6 +103,40 @@ impl DirtyRegion {
             _ => None,
         }
     }
+
+    /// Changed column spans (inclusive), left to right
+    pub fn spans(&self) -> &[(u16, u16)] {
+        &self.spans
+    }
+
+    /// Remove columns `start..=end` from the region, returning the part of
+    /// the region inside them
+    pub fn take_range(&mut self, start: u16, end: u16) -> DirtyRegion {
+        let mut inside = Self::clean();
+        let mut outside: SmallVec<[(u16, u16); 4]> = SmallVec::new();
+        for &(first, last) in &self.spans {
+            if last < start || first > end {
+                outside.push((first, last));
+                continue;
+            }
+            inside.spans.push((first.max(start), last.min(end)));
+            if first < start {
+                outside.push((first, start - 1));
+            }
+            if last > end {
+                outside.push((end + 1, last));
+            }
+        }
+        self.spans = outside;
+        self.sync_range();
+        inside.sync_range();
+        inside
+    }
+
+    fn sync_range(&mut self) {
+        self.first_changed = self.spans.first().map(|span| span.0);
+        self.last_changed = self.spans.last().map(|span| span.1);
+    }
 }
 
 /// Find the first and last difference in a line
@@ -110,68 +180,141 @@ pub fn find_line_diff(old_line: &[Cell], new_line: &[Cell]) -> Option<(usize, us
     Some((first_diff, last_diff))
 }
 
+/// Find the actual changes inside each dirty span of a line
+pub(crate) fn line_changes(
+    old_line: &[Cell],
+    new_line: &[Cell],
+    dirty: &DirtyRegion,
+) -> LineChanges {
+    let last_col = new_line.len().saturating_sub(1);
+    let mut changes = LineChanges::new();
+    for &(first, last) in dirty.spans() {
+        let (first, last) = (first as usize, (last as usize).min(last_col));
+        if first > last {
+            continue;
+        }
+        if let Some((first_diff, last_diff)) =
+            find_line_diff(&old_line[first..=last], &new_line[first..=last])
+        {
+            changes.push((first + first_diff, first + last_diff));
+        }
+    }
+    changes
+}
+
+/// Changes of every dirty line, top to bottom
+///
+/// With the `parallel` feature, large screens are diffed on the rayon
+/// thread pool; the result is the same either way.
+pub(crate) fn diff_dirty_lines(
+    old: &Buffer,
+    new: &Buffer,
+    dirty: &[DirtyRegion],
+) -> Vec<(usize, LineChanges)> {
+    let rows = (0..new.len()).filter(|&y| dirty[y].is_dirty());
+
+    #[cfg(feature = "parallel")]
+    if new.len() * new.width() >= PARALLEL_DIFF_MIN_CELLS {
+        use rayon::prelude::*;
+
+        let rows: Vec<usize> = rows.collect();
+        return rows
+            .into_par_iter()
+            .map(|y| (y, line_changes(&old[y], &new[y], &dirty[y])))
+            .collect();
+    }
+
+    rows.map(|y| (y, line_changes(&old[y], &new[y], &dirty[y])))
+        .collect()
+}
+
 /// Compute hash for a line (used for line matching)
 ///
-/// Optimized version using FNV-1a hash algorithm which is faster than
-/// polynomial rolling hash and provides good distribution.
+/// The hash is the wrapping sum of per-cell hashes that include the column,
+/// so a change to a few cells can be applied with `rehash_span` without
+/// rehashing the whole line.
 pub fn hash_line(cells: &[Cell]) -> u64 {
     // Empty lines hash to 0 (for compatibility with blank line detection)
-    if cells.is_empty() {
-        return 0;
-    }
+    cells
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (x, cell)| hash.wrapping_add(hash_cell(cell, x)))
+}
+
+/// Update `hash` of a line whose cells starting at column `start` changed
+/// from `old` to `new`
+pub fn rehash_span(hash: u64, old: &[Cell], new: &[Cell], start: usize) -> u64 {
+    let removed = old.iter().enumerate().fold(hash, |hash, (i, cell)| {
+        hash.wrapping_sub(hash_cell(cell, start + i))
+    });
+    new.iter().enumerate().fold(removed, |hash, (i, cell)| {
+        hash.wrapping_add(hash_cell(cell, start + i))
+    })
+}
 
+/// Hash one cell at column `x`
+///
+/// FNV-1a over the cell's bytes, seeded with the column and finished with a
+/// splitmix64 mix so the sums in `hash_line` don't cancel out.
+#[inline]
+fn hash_cell(cell: &Cell, x: usize) -> u64 {
     // FNV-1a constants
     const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
     const FNV_PRIME: u64 = 0x100000001b3;
 
-    let mut hash = FNV_OFFSET_BASIS;
+    let mut hash = (FNV_OFFSET_BASIS ^ x as u64).wrapping_mul(FNV_PRIME);
 
-    for cell in cells {
-        // Hash character (4 bytes)
-        let ch_bytes = (cell.ch as u32).to_ne_bytes();
-        for &byte in &ch_bytes {
-            hash ^= byte as u64;
-            hash = hash.wrapping_mul(FNV_PRIME);
-        }
+    // Hash character (4 bytes), then the rest of its grapheme cluster
+    let ch_bytes = (cell.ch as u32).to_ne_bytes();
+    for &byte in &ch_bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    for &ch in cell.combining() {
+        hash ^= ch as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
 
-        // Hash attributes (2 bytes)
-        let attr_bytes = cell.attr.bits().to_ne_bytes();
-        for &byte in &attr_bytes {
-            hash ^= byte as u64;
-            hash = hash.wrapping_mul(FNV_PRIME);
-        }
+    // Hash attributes (2 bytes)
+    let attr_bytes = cell.attr.bits().to_ne_bytes();
+    for &byte in &attr_bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
 
-        // Optimized: hash colors using discriminant+data approach (2-3x faster)
-        // Converts color to (type_byte, data_u32) to minimize branches
-        #[inline(always)]
-        fn hash_color(hash: &mut u64, color: Color) {
-            const FNV_PRIME: u64 = 0x100000001b3;
+    // Optimized: hash colors using discriminant+data approach (2-3x faster)
+    // Converts color to (type_byte, data_u32) to minimize branches
+    #[inline(always)]
+    fn hash_color(hash: &mut u64, color: Color) {
+        let (disc, data) = color.hash_bytes();
 
-            let (disc, data) = color.hash_bytes();
+        // Hash discriminant
+        *hash ^= disc as u64;
+        *hash = hash.wrapping_mul(FNV_PRIME);
 
-            // Hash discriminant
-            *hash ^= disc as u64;
+        // Hash data (for Ansi256 and RGB colors)
+        if data != 0 {
+            // Unroll data bytes for better performance
+            *hash ^= (data & 0xFF) as u64;
+            *hash = hash.wrapping_mul(FNV_PRIME);
+            *hash ^= ((data >> 8) & 0xFF) as u64;
+            *hash = hash.wrapping_mul(FNV_PRIME);
+            *hash ^= ((data >> 16) & 0xFF) as u64;
+            *hash = hash.wrapping_mul(FNV_PRIME);
+            *hash ^= ((data >> 24) & 0xFF) as u64;
             *hash = hash.wrapping_mul(FNV_PRIME);
-
-            // Hash data (for Ansi256 and RGB colors)
-            if data != 0 {
-                // Unroll data bytes for better performance
-                *hash ^= (data & 0xFF) as u64;
-                *hash = hash.wrapping_mul(FNV_PRIME);
-                *hash ^= ((data >> 8) & 0xFF) as u64;
-                *hash = hash.wrapping_mul(FNV_PRIME);
-                *hash ^= ((data >> 16) & 0xFF) as u64;
-                *hash = hash.wrapping_mul(FNV_PRIME);
-                *hash ^= ((data >> 24) & 0xFF) as u64;
-                *hash = hash.wrapping_mul(FNV_PRIME);
-            }
         }
-
-        hash_color(&mut hash, cell.fg());
-        hash_color(&mut hash, cell.bg());
     }
 
-    hash
+    hash_color(&mut hash, cell.fg());
+    hash_color(&mut hash, cell.bg());
+
+    // splitmix64 finalizer
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58476d1ce4e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
 }
 
 /// Detect scroll operations using hash-based line matching (Modified Heckel's Algorithm)
@@ -277,7 +420,7 @@ pub fn detect_scrolls(old_hashes: &[u64], new_hashes: &[u64]) -> Vec<ScrollOp> {
             let min_efficiency = size + (size / 8).min(2);
             let shift_abs = shift.unsigned_abs();
 
-            if size >= 3 && min_efficiency >= shift_abs {
+            if size >= MIN_HUNK && min_efficiency >= shift_abs {
                 scrolls.push(ScrollOp { start, size, shift });
             }
 
@@ -290,6 +433,39 @@ pub fn detect_scrolls(old_hashes: &[u64], new_hashes: &[u64]) -> Vec<ScrollOp> {
     scrolls
 }
 
+/// Keep the parts of scroll hunks whose lines really match
+///
+/// Hunks come from 64-bit hash matches, and a collision would move the
+/// wrong line. Each hunk is checked cell by cell; a mismatched line is left
+/// to normal diffing and splits the hunk, keeping the matching runs that
+/// are still worth a scroll.
+pub fn verify_scrolls(scrolls: &[ScrollOp], old: &Buffer, new: &Buffer) -> Vec<ScrollOp> {
+    let mut verified = Vec::new();
+    for scroll in scrolls {
+        let matches = |y: usize| {
+            y.checked_add_signed(scroll.shift)
+                .is_some_and(|from| from < old.len() && old[from] == new[y])
+        };
+        let mut y = scroll.start;
+        let end = (scroll.start + scroll.size).min(new.len());
+        while y < end {
+            let start = y;
+            while y < end && matches(y) {
+                y += 1;
+            }
+            if y - start >= MIN_HUNK {
+                verified.push(ScrollOp {
+                    start,
+                    size: y - start,
+                    shift: scroll.shift,
+                });
+            }
+            y += 1;
+        }
+    }
+    verified
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,6 +510,52 @@ mod tests {
         assert_eq!(region.range(), Some((5, 25)));
     }
 
+    #[test]
+    fn test_dirty_region_separate_spans() {
+        let mut region = DirtyRegion::clean();
+        region.mark(300, 301);
+        region.mark(2, 3);
+        assert_eq!(region.spans(), &[(2, 3), (300, 301)]);
+        assert_eq!(region.range(), Some((2, 301)));
+
+        // Close edits coalesce, and a covering edit swallows the rest
+        region.mark(10, 10);
+        assert_eq!(region.spans(), &[(2, 10), (300, 301)]);
+        region.mark(0, 400);
+        assert_eq!(region.spans(), &[(0, 400)]);
+    }
+
+    #[test]
+    fn test_dirty_region_take_range() {
+        let mut region = DirtyRegion::clean();
+        region.mark(0, 50);
+        region.mark(100, 110);
+
+        let inside = region.take_range(20, 30);
+        assert_eq!(inside.spans(), &[(20, 30)]);
+        assert_eq!(region.spans(), &[(0, 19), (31, 50), (100, 110)]);
+
+        let inside = region.take_range(40, 105);
+        assert_eq!(inside.spans(), &[(40, 50), (100, 105)]);
+        assert_eq!(region.range(), Some((0, 110)));
+
+        assert!(!region.take_range(60, 70).is_dirty());
+        region.take_range(0, 200);
+        assert!(!region.is_dirty());
+    }
+
+    #[test]
+    fn test_dirty_region_span_limit() {
+        let mut region = DirtyRegion::clean();
+        for i in 0..12 {
+            region.mark(i * 20, i * 20);
+        }
+        region.mark(100 + 12, 100 + 12);
+        assert_eq!(region.spans().len(), MAX_SPANS);
+        assert_eq!(region.range(), Some((0, 220)));
+        assert!(region.spans().windows(2).all(|w| w[0].1 < w[1].0));
+    }
+
     #[test]
     fn test_find_line_diff_identical() {
         let line1 = vec![Cell::new('A'), Cell::new('B'), Cell::new('C')];
@@ -446,6 +668,53 @@ mod tests {
         assert_eq!(hash_line(&line1), hash_line(&line2));
     }
 
+    #[test]
+    fn test_hash_line_order_matters() {
+        let line1 = vec![Cell::new('A'), Cell::new('B')];
+        let line2 = vec![Cell::new('B'), Cell::new('A')];
+        assert_ne!(hash_line(&line1), hash_line(&line2));
+    }
+
+    #[test]
+    fn test_rehash_span_matches_full_hash() {
+        let old: Vec<Cell> = "hello world".chars().map(Cell::new).collect();
+        let mut new = old.clone();
+        new[6] = Cell::with_style('W', Attr::BOLD, Color::Red, Color::Reset);
+        new[7] = Cell::new('O');
+
+        let hash = rehash_span(hash_line(&old), &old[6..8], &new[6..8], 6);
+        assert_eq!(hash, hash_line(&new));
+    }
+
+    #[test]
+    fn test_diff_dirty_lines() {
+        // Large enough to take the parallel path when it's enabled
+        let old = Buffer::new(120, 200, Cell::blank());
+        let mut new = old.clone();
+        let mut dirty = vec![DirtyRegion::clean(); 120];
+        for y in (0..120).step_by(3) {
+            new[y][y] = Cell::new('x');
+            new[y][199] = Cell::new('y');
+            dirty[y].mark(0, 50);
+            dirty[y].mark(y as u16, y as u16);
+            dirty[y].mark(190, 199);
+        }
+        // Dirty but unchanged
+        dirty[1].mark(0, 199);
+
+        let diffs = diff_dirty_lines(&old, &new, &dirty);
+        assert_eq!(diffs.len(), 41);
+        assert_eq!(
+            diffs[0],
+            (0, LineChanges::from_slice(&[(0, 0), (199, 199)]))
+        );
+        assert_eq!(diffs[1], (1, LineChanges::new()));
+        assert_eq!(
+            diffs[40],
+            (117, LineChanges::from_slice(&[(117, 117), (199, 199)]))
+        );
+    }
+
     #[test]
     fn test_detect_scrolls_empty() {
         let old: Vec<u64> = vec![];
@@ -597,4 +866,49 @@ mod tests {
         assert_eq!(scrolls[1].size, 8);
         assert_eq!(scrolls[1].shift, -9);
     }
+
+    #[test]
+    fn test_verify_scrolls() {
+        let line = |text: &str| -> Vec<Cell> { text.chars().map(Cell::new).collect() };
+        let mut old = Buffer::new(10, 4, Cell::blank());
+        let mut new = Buffer::new(10, 4, Cell::blank());
+        for y in 0..10 {
+            old[y].clone_from_slice(&line(&format!("L{:03}", y)));
+        }
+        for y in 0..9 {
+            new[y].clone_from_slice(&line(&format!("L{:03}", y + 1)));
+        }
+        let hunk = ScrollOp {
+            start: 0,
+            size: 9,
+            shift: 1,
+        };
+        assert_eq!(
+            verify_scrolls(std::slice::from_ref(&hunk), &old, &new),
+            vec![hunk.clone()]
+        );
+
+        // A colliding line in the middle splits the hunk around it
+        new[4].clone_from_slice(&line("XXXX"));
+        assert_eq!(
+            verify_scrolls(std::slice::from_ref(&hunk), &old, &new),
+            vec![
+                ScrollOp {
+                    start: 0,
+                    size: 4,
+                    shift: 1
+                },
+                ScrollOp {
+                    start: 5,
+                    size: 4,
+                    shift: 1
+                },
+            ]
+        );
+
+        // Runs too short to scroll are dropped
+        new[1].clone_from_slice(&line("XXXX"));
+        new[7].clone_from_slice(&line("XXXX"));
+        assert!(verify_scrolls(&[hunk], &old, &new).is_empty());
+    }
 }
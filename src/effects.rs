@@ -0,0 +1,112 @@
+//! Cell effects for giving dialogs depth: drop shadows and dimmed or
+//! desaturated backdrops
+
+use crate::attr::Attr;
+use crate::cell::Cell;
+use crate::color::Color;
+
+/// Turn a cell into drop-shadow: its character stays, drawn dark grey on black
+pub(crate) fn shadow_cell(cell: &mut Cell) {
+    cell.attr = Attr::NORMAL;
+    cell.fg = Color::BrightBlack;
+    cell.bg = Color::Black;
+}
+
+/// Darken a cell, keeping `factor` (0.0-1.0) of its brightness
+///
+/// Only RGB colors can be scaled; cells with palette colors get `Attr::DIM`.
+pub(crate) fn dim_cell(cell: &mut Cell, factor: f32) {
+    let factor = factor.clamp(0.0, 1.0);
+    let scale = |c: u8| (c as f32 * factor).round() as u8;
+    map_colors(cell, |r, g, b| (scale(r), scale(g), scale(b)));
+}
+
+/// Move a cell's colors `amount` (0.0-1.0) of the way towards grey
+///
+/// Only RGB colors can be desaturated; cells with palette colors get
+/// `Attr::DIM`.
+pub(crate) fn desaturate_cell(cell: &mut Cell, amount: f32) {
+    let amount = amount.clamp(0.0, 1.0);
+    map_colors(cell, |r, g, b| {
+        let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+        let mix = |c: u8| (c as f32 + (luma - c as f32) * amount).round() as u8;
+        (mix(r), mix(g), mix(b))
+    });
+}
+
+fn map_colors(cell: &mut Cell, f: impl Fn(u8, u8, u8) -> (u8, u8, u8)) {
+    let mut scaled = true;
+    for color in [&mut cell.fg, &mut cell.bg] {
+        match *color {
+            Color::Rgb(r, g, b) => {
+                let (r, g, b) = f(r, g, b);
+                *color = Color::Rgb(r, g, b);
+            }
+            _ => scaled = false,
+        }
+    }
+
+    if !scaled {
+        cell.attr = cell.attr | Attr::DIM;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dim_rgb() {
+        let mut cell = Cell::with_style(
+            'x',
+            Attr::BOLD,
+            Color::Rgb(200, 100, 50),
+            Color::Rgb(10, 20, 30),
+        );
+        dim_cell(&mut cell, 0.5);
+        assert_eq!(cell.fg, Color::Rgb(100, 50, 25));
+        assert_eq!(cell.bg, Color::Rgb(5, 10, 15));
+        assert_eq!(cell.attr, Attr::BOLD);
+    }
+
+    #[test]
+    fn test_dim_palette_colors_use_attr() {
+        let mut cell = Cell::with_style('x', Attr::NORMAL, Color::Red, Color::Rgb(100, 100, 100));
+        dim_cell(&mut cell, 0.5);
+        assert_eq!(cell.fg, Color::Red);
+        assert_eq!(cell.bg, Color::Rgb(50, 50, 50));
+        assert!(cell.attr.contains(Attr::DIM));
+    }
+
+    #[test]
+    fn test_desaturate() {
+        let mut cell = Cell::with_style(
+            ' ',
+            Attr::NORMAL,
+            Color::Rgb(255, 0, 0),
+            Color::Rgb(80, 80, 80),
+        );
+        desaturate_cell(&mut cell, 1.0);
+        assert_eq!(cell.fg, Color::Rgb(54, 54, 54));
+        assert_eq!(cell.bg, Color::Rgb(80, 80, 80));
+
+        let mut cell = Cell::with_style(
+            ' ',
+            Attr::NORMAL,
+            Color::Rgb(255, 0, 0),
+            Color::Rgb(0, 0, 0),
+        );
+        desaturate_cell(&mut cell, 0.0);
+        assert_eq!(cell.fg, Color::Rgb(255, 0, 0));
+    }
+
+    #[test]
+    fn test_shadow() {
+        let mut cell = Cell::with_style('a', Attr::REVERSE, Color::Green, Color::Blue);
+        shadow_cell(&mut cell);
+        assert_eq!(
+            cell,
+            Cell::with_style('a', Attr::NORMAL, Color::BrightBlack, Color::Black)
+        );
+    }
+}
@@ -14,13 +14,91 @@ pub enum Error {
     /// Terminal is already initialized
     AlreadyInitialized,
     /// Invalid color pair ID
-    InvalidColorPair(u8),
+    InvalidColorPair(u16),
+    /// Invalid or removed panel ID
+    InvalidPanel(usize),
     /// Invalid coordinates
     InvalidCoordinates { y: u16, x: u16 },
     /// Invalid window dimensions
     InvalidDimensions { height: u16, width: u16 },
     /// Operation not supported on this platform
     NotSupported,
+    /// Input was shut down (see `Screen::shutdown`)
+    Shutdown,
+    /// No style with this name in the current theme
+    UnknownStyle(String),
+    /// Soft label number outside the layout
+    InvalidSoftLabel(usize),
+    /// Shortcut string that can't be parsed
+    InvalidShortcut(String),
+    /// Screen dump that can't be read (see `Screen::restore_from`)
+    InvalidDump(String),
+    /// The `Renderer` a `DrawHandle` sends to was dropped
+    RendererClosed,
+    /// Output is not a terminal (see `ScreenBuilder::non_tty`)
+    NotATty,
+    /// An I/O error with what the library was doing when it happened
+    Context {
+        /// What was being done, such as "reading input"
+        context: &'static str,
+        /// The underlying error
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// What the library was doing when the error happened, if recorded
+    pub fn context(&self) -> Option<&'static str> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, without any context wrapped around it
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Kind of the underlying I/O error, if this is one
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self.root() {
+            Error::Io(e) => Some(e.kind()),
+            _ => None,
+        }
+    }
+
+    /// Check if a signal interrupted the operation; retrying is safe
+    pub fn is_interrupted(&self) -> bool {
+        self.io_kind() == Some(io::ErrorKind::Interrupted)
+    }
+
+    /// Check if the terminal couldn't take or give data without blocking
+    pub fn is_would_block(&self) -> bool {
+        self.io_kind() == Some(io::ErrorKind::WouldBlock)
+    }
+}
+
+/// Attach context to I/O errors on their way out of the library
+pub(crate) trait ResultExt<T> {
+    /// Wrap an `Error::Io` in `Error::Context`; other errors already say
+    /// what went wrong and pass through unchanged
+    fn context(self, context: &'static str) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: &'static str) -> Result<T> {
+        self.map_err(|err| match err.into() {
+            err @ Error::Io(_) => Error::Context {
+                context,
+                source: Box::new(err),
+            },
+            err => err,
+        })
+    }
 }
 
 impl fmt::Display for Error {
@@ -30,6 +108,7 @@ impl fmt::Display for Error {
             Error::NotInitialized => write!(f, "Terminal not initialized"),
             Error::AlreadyInitialized => write!(f, "Terminal already initialized"),
             Error::InvalidColorPair(id) => write!(f, "Invalid color pair ID: {}", id),
+            Error::InvalidPanel(id) => write!(f, "Invalid panel ID: {}", id),
             Error::InvalidCoordinates { y, x } => {
                 write!(f, "Invalid coordinates: ({}, {})", y, x)
             }
@@ -37,6 +116,14 @@ impl fmt::Display for Error {
                 write!(f, "Invalid dimensions: {}x{}", height, width)
             }
             Error::NotSupported => write!(f, "Operation not supported"),
+            Error::Shutdown => write!(f, "Screen input shut down"),
+            Error::UnknownStyle(name) => write!(f, "Unknown style: {}", name),
+            Error::InvalidSoftLabel(n) => write!(f, "Invalid soft label: {}", n),
+            Error::InvalidShortcut(s) => write!(f, "Invalid shortcut: {:?}", s),
+            Error::InvalidDump(reason) => write!(f, "Invalid screen dump: {}", reason),
+            Error::RendererClosed => write!(f, "Renderer closed"),
+            Error::NotATty => write!(f, "Output is not a terminal"),
+            Error::Context { context, source } => write!(f, "{}: {}", context, source),
         }
     }
 }
@@ -45,6 +132,7 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::Io(e) => Some(e),
+            Error::Context { source, .. } => Some(source.as_ref()),
             _ => None,
         }
     }
@@ -78,6 +166,30 @@ mod tests {
         assert_eq!(err.to_string(), "Invalid coordinates: (10, 20)");
     }
 
+    #[test]
+    fn test_context() {
+        use std::error::Error as _;
+
+        let result: std::result::Result<(), io::Error> =
+            Err(io::Error::new(io::ErrorKind::Interrupted, "signal"));
+        let err = result.context("reading input").unwrap_err();
+        assert_eq!(err.context(), Some("reading input"));
+        assert_eq!(err.to_string(), "reading input: I/O error: signal");
+        assert!(err.is_interrupted());
+        assert!(!err.is_would_block());
+        assert!(matches!(err.root(), Error::Io(_)));
+
+        // The chain leads down to the io::Error
+        let io = err.source().unwrap().source().unwrap();
+        assert_eq!(io.to_string(), "signal");
+
+        // Errors other than I/O pass through unchanged
+        let result: Result<()> = Err(Error::Shutdown);
+        let err = result.context("reading input").unwrap_err();
+        assert!(matches!(err, Error::Shutdown));
+        assert_eq!(err.io_kind(), None);
+    }
+
     #[test]
     fn test_io_error_conversion() {
         let io_err = io::Error::new(io::ErrorKind::Other, "test error");
@@ -0,0 +1,226 @@
+//! Incremental escape sequence recognizer
+//!
+//! Input arrives byte by byte, and a sequence may be split across reads.
+//! `EscapeParser` follows the DEC/ECMA-48 sequence grammar (CSI with
+//! parameters and intermediates, SS3, and string sequences such as OSC and
+//! DCS ended by BEL or ST) so the reader knows exactly where a sequence
+//! ends instead of guessing from timing.
+//!
+//! String and intermediate sequences only come from the terminal in reply
+//! to a query. Otherwise ESC followed by a printable byte other than `[`
+//! and `O` is Alt+key, so the keys typed after it aren't swallowed.
+
+/// Result of feeding one byte to the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Progress {
+    /// More bytes are needed
+    Incomplete,
+    /// The byte ended the sequence
+    Complete,
+    /// The byte can't be part of the sequence; it belongs to the next key
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// After ESC
+    Escape,
+    /// ESC followed by intermediates (0x20-0x2f), waiting for the final byte
+    EscapeIntermediate,
+    /// ESC [ parameters and intermediates
+    Csi,
+    /// ESC [ [ (Linux console function keys): one more byte
+    LinuxFunction,
+    /// ESC O, optionally with a modifier digit
+    Ss3,
+    /// OSC, DCS or APC body
+    String,
+    /// ESC inside a string, expecting `\` to form ST
+    StringEscape,
+}
+
+/// Recognizes one escape sequence, starting after its ESC
+#[derive(Debug, Clone)]
+pub(crate) struct EscapeParser {
+    state: State,
+    // Bytes consumed after ESC
+    len: usize,
+    // Whether query replies (OSC, DCS, APC) may arrive
+    replies: bool,
+}
+
+impl Default for EscapeParser {
+    fn default() -> Self {
+        Self {
+            state: State::Escape,
+            len: 0,
+            replies: false,
+        }
+    }
+}
+
+impl EscapeParser {
+    /// Parser for a sequence whose ESC was just read
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parser for a sequence that may be a reply to a pending query
+    pub(crate) fn expecting_replies() -> Self {
+        Self {
+            replies: true,
+            ..Self::default()
+        }
+    }
+
+    /// Feed the next byte
+    pub(crate) fn advance(&mut self, byte: u8) -> Progress {
+        use Progress::*;
+
+        let progress = match self.state {
+            State::Escape => match byte {
+                b'[' => self.enter(State::Csi),
+                b'O' => self.enter(State::Ss3),
+                // OSC, DCS and APC arrive as terminal replies; SOS and PM
+                // never do, so ESC X and ESC ^ stay Alt+key
+                b']' | b'P' | b'_' if self.replies => self.enter(State::String),
+                0x20..=0x2f if self.replies => self.enter(State::EscapeIntermediate),
+                // ESC followed by any other key (e.g. Alt+key)
+                _ => Complete,
+            },
+            State::EscapeIntermediate => match byte {
+                0x20..=0x2f => Incomplete,
+                0x30..=0x7e => Complete,
+                _ => Invalid,
+            },
+            State::Csi => match byte {
+                b'[' if self.len == 1 => self.enter(State::LinuxFunction),
+                0x20..=0x3f => Incomplete,
+                0x40..=0x7e => Complete,
+                _ => Invalid,
+            },
+            State::LinuxFunction => match byte {
+                0x40..=0x7e => Complete,
+                _ => Invalid,
+            },
+            State::Ss3 => match byte {
+                // Old xterm modifier form, e.g. ESC O 5 P
+                b'0'..=b'9' => Incomplete,
+                0x40..=0x7e => Complete,
+                _ => Invalid,
+            },
+            State::String => match byte {
+                0x07 => Complete,
+                0x1b => self.enter(State::StringEscape),
+                _ => Incomplete,
+            },
+            State::StringEscape => match byte {
+                b'\\' => Complete,
+                _ => Invalid,
+            },
+        };
+
+        if progress != Invalid {
+            self.len += 1;
+        }
+        progress
+    }
+
+    /// Check if bytes after ESC have started a multi-byte sequence (as
+    /// opposed to a lone ESC, which may still turn out to be a key)
+    pub(crate) fn in_sequence(&self) -> bool {
+        self.state != State::Escape
+    }
+
+    fn enter(&mut self, state: State) -> Progress {
+        self.state = state;
+        Progress::Incomplete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Progress after feeding everything after the leading ESC
+    fn feed_to(mut parser: EscapeParser, seq: &[u8]) -> Vec<Progress> {
+        seq[1..].iter().map(|&b| parser.advance(b)).collect()
+    }
+
+    fn feed(seq: &[u8]) -> Vec<Progress> {
+        feed_to(EscapeParser::new(), seq)
+    }
+
+    fn complete(seq: &[u8]) -> bool {
+        feed(seq).last() == Some(&Progress::Complete)
+    }
+
+    fn complete_reply(seq: &[u8]) -> bool {
+        feed_to(EscapeParser::expecting_replies(), seq).last() == Some(&Progress::Complete)
+    }
+
+    #[test]
+    fn test_csi_and_ss3() {
+        assert!(complete(b"\x1b[A"));
+        assert!(complete(b"\x1b[1;5C"));
+        assert!(complete(b"\x1b[200~"));
+        assert!(complete(b"\x1b[97;1;1;65u"));
+        assert!(complete(b"\x1b[<0;10;5M"));
+        assert!(complete(b"\x1b[[A"));
+        assert!(complete(b"\x1bOP"));
+        assert!(complete(b"\x1bO5P"));
+        assert!(!complete(b"\x1b"));
+        assert!(!complete(b"\x1b["));
+        assert!(!complete(b"\x1b[1;5"));
+        assert!(!complete(b"\x1b[["));
+        assert!(!complete(b"\x1bO"));
+        assert!(complete(b"\x1bx"));
+    }
+
+    #[test]
+    fn test_strings() {
+        assert!(complete_reply(b"\x1b]11;rgb:0/0/0\x07"));
+        assert!(complete_reply(b"\x1bP>|xterm(390)\x1b\\"));
+        assert!(complete_reply(b"\x1b_Gi=1;OK\x1b\\"));
+        assert!(complete_reply(b"\x1b(B"));
+        // Bytes that would end a CSI don't end a string
+        assert!(!complete_reply(b"\x1b]0;title~"));
+        // SOS and PM never arrive as replies
+        assert!(complete_reply(b"\x1bX"));
+        assert!(complete_reply(b"\x1b^"));
+    }
+
+    #[test]
+    fn test_alt_punctuation() {
+        // Without a pending query these are Alt+key, ended after one byte
+        for seq in [&b"\x1b "[..], b"\x1b-", b"\x1b.", b"\x1b/"] {
+            assert_eq!(feed(seq), vec![Progress::Complete]);
+        }
+    }
+
+    #[test]
+    fn test_alt_string_introducers() {
+        // Alt+Shift+P, Alt+] and Alt+_ only start strings in a reply
+        for seq in [&b"\x1bP"[..], b"\x1b]", b"\x1b_"] {
+            assert_eq!(feed(seq), vec![Progress::Complete]);
+            assert_eq!(
+                feed_to(EscapeParser::expecting_replies(), seq),
+                vec![Progress::Incomplete]
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_byte_ends_sequence() {
+        assert_eq!(
+            feed(b"\x1b[1\x1b"),
+            vec![
+                Progress::Incomplete,
+                Progress::Incomplete,
+                Progress::Invalid
+            ]
+        );
+        assert_eq!(feed(b"\x1bOa").last(), Some(&Progress::Complete));
+        assert_eq!(feed(b"\x1bO\r").last(), Some(&Progress::Invalid));
+    }
+}
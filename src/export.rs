@@ -0,0 +1,214 @@
+//! Screen content as standalone text
+//!
+//! Converts captured lines to plain text, ANSI-colored text or an HTML
+//! fragment, for pasting terminal output into bug reports and docs or
+//! comparing it against golden files.
+
+use crate::attr::Attr;
+use crate::cell::Cell;
+use crate::color::Color;
+use crate::sgr::StyleEncoder;
+use crate::text::{Line, Span};
+use std::fmt::Write;
+
+/// Colors used in HTML output for `Color::Reset` (xterm's defaults)
+const HTML_DEFAULT_FG: (u8, u8, u8) = (229, 229, 229);
+const HTML_DEFAULT_BG: (u8, u8, u8) = (0, 0, 0);
+
+/// Attributes that draw something even on a blank cell
+const VISIBLE_ON_BLANK: Attr = Attr(
+    Attr::UNDERLINE.0
+        | Attr::DOUBLE_UNDERLINE.0
+        | Attr::REVERSE.0
+        | Attr::STRIKETHROUGH.0
+        | Attr::OVERLINE.0,
+);
+
+/// Output format of `export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Characters only
+    Plain,
+    /// Characters with SGR sequences, ending in the default style
+    Ansi,
+    /// A `<pre>` element with inline styles
+    Html,
+}
+
+/// Convert lines to text in `format`
+///
+/// Each line ends with a newline. Trailing blanks that show nothing (no
+/// background, underline or reverse video) are dropped.
+///
+/// ```
+/// use zaz::{ExportFormat, Line, export};
+///
+/// let line = Line::from("hello  ");
+/// assert_eq!(export(&[line], ExportFormat::Plain), "hello\n");
+/// ```
+pub fn export(lines: &[Line], format: ExportFormat) -> String {
+    let mut out = String::new();
+    if format == ExportFormat::Html {
+        let (fg, bg) = (hex(HTML_DEFAULT_FG), hex(HTML_DEFAULT_BG));
+        let _ = write!(out, "<pre style=\"color:{};background-color:{}\">", fg, bg);
+    }
+
+    let mut style = StyleEncoder::new();
+    for line in lines {
+        let line = trim_end(line);
+        for span in &line.spans {
+            match format {
+                ExportFormat::Plain => out.push_str(&span.text),
+                ExportFormat::Ansi => {
+                    style.encode(span.attr, span.fg, span.bg, &mut out);
+                    out.push_str(&span.text);
+                }
+                ExportFormat::Html => push_html_span(&mut out, span),
+            }
+        }
+        if format == ExportFormat::Ansi {
+            style.finish(&mut out);
+        }
+        out.push('\n');
+    }
+
+    if format == ExportFormat::Html {
+        out.push_str("</pre>\n");
+    }
+    out
+}
+
+/// The line without trailing blanks that show nothing
+fn trim_end(line: &Line) -> Line {
+    let mut cells = line.cells();
+    while cells.last().is_some_and(|cell: &Cell| {
+        cell.ch == ' ' && cell.bg == Color::Reset && !cell.attr.intersects(VISIBLE_ON_BLANK)
+    }) {
+        cells.pop();
+    }
+    Line::from_cells(&cells)
+}
+
+fn push_html_span(out: &mut String, span: &Span) {
+    let mut css = String::new();
+    let (mut fg, mut bg) = (
+        span.fg.to_rgb().unwrap_or(HTML_DEFAULT_FG),
+        span.bg.to_rgb().unwrap_or(HTML_DEFAULT_BG),
+    );
+    if span.attr.contains(Attr::REVERSE) {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+    if span.fg != Color::Reset || span.attr.contains(Attr::REVERSE) {
+        let _ = write!(css, "color:{};", hex(fg));
+    }
+    if span.bg != Color::Reset || span.attr.contains(Attr::REVERSE) {
+        let _ = write!(css, "background-color:{};", hex(bg));
+    }
+
+    let attr = span.attr;
+    if attr.contains(Attr::BOLD) {
+        css.push_str("font-weight:bold;");
+    }
+    if attr.contains(Attr::DIM) {
+        css.push_str("opacity:0.6;");
+    }
+    if attr.contains(Attr::ITALIC) {
+        css.push_str("font-style:italic;");
+    }
+    let decorations: Vec<&str> = [
+        (
+            Attr(Attr::UNDERLINE.0 | Attr::DOUBLE_UNDERLINE.0),
+            "underline",
+        ),
+        (Attr::STRIKETHROUGH, "line-through"),
+        (Attr::OVERLINE, "overline"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| attr.intersects(*flag))
+    .map(|(_, name)| name)
+    .collect();
+    if !decorations.is_empty() {
+        let _ = write!(css, "text-decoration:{};", decorations.join(" "));
+    }
+    if attr.contains(Attr::DOUBLE_UNDERLINE) {
+        css.push_str("text-decoration-style:double;");
+    }
+    if attr.contains(Attr::HIDDEN) {
+        css.push_str("visibility:hidden;");
+    }
+    if attr.contains(Attr::SUPERSCRIPT) {
+        css.push_str("vertical-align:super;");
+    } else if attr.contains(Attr::SUBSCRIPT) {
+        css.push_str("vertical-align:sub;");
+    }
+
+    if css.is_empty() {
+        push_escaped(out, &span.text);
+    } else {
+        css.pop();
+        let _ = write!(out, "<span style=\"{}\">", css);
+        push_escaped(out, &span.text);
+        out.push_str("</span>");
+    }
+}
+
+fn push_escaped(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            ch => out.push(ch),
+        }
+    }
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Line> {
+        let mut first = Line::from("a<b ");
+        first.push(Span::styled("ok", Attr::BOLD, Color::Green, Color::Reset));
+        first.push(Span::new("   "));
+        let mut second = Line::new();
+        second.push(Span::styled("  ", Attr::NORMAL, Color::Reset, Color::Blue));
+        vec![first, second, Line::new()]
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(export(&sample(), ExportFormat::Plain), "a<b ok\n  \n\n");
+    }
+
+    #[test]
+    fn test_ansi() {
+        assert_eq!(
+            export(&sample(), ExportFormat::Ansi),
+            "a<b \x1b[1;32mok\x1b[0m\n\x1b[44m  \x1b[0m\n\n"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let mut line = Line::new();
+        line.push(Span::styled(
+            "x",
+            Attr::REVERSE | Attr::UNDERLINE | Attr::STRIKETHROUGH,
+            Color::Reset,
+            Color::Rgb(1, 2, 3),
+        ));
+        let html = export(&[sample()[0].clone(), line], ExportFormat::Html);
+        assert_eq!(
+            html,
+            "<pre style=\"color:#e5e5e5;background-color:#000000\">a&lt;b \
+             <span style=\"color:#00cd00;font-weight:bold\">ok</span>\n\
+             <span style=\"color:#010203;background-color:#e5e5e5;\
+             text-decoration:underline line-through\">x</span>\n</pre>\n"
+        );
+    }
+}
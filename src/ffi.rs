@@ -2,11 +2,17 @@
 //!
 //! This module provides C-compatible exports for use with other languages.
 
-use std::ffi::CStr;
-use std::os::raw::c_char;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
 use std::ptr;
+use std::time::{Duration, Instant};
 
-use crate::{Attr, Color, Key, Screen};
+use crate::image::{ImageFormat, ImagePlacement, KittyImage, SixelImage};
+use crate::input::InputEvent;
+use crate::kitty::{KeyEventType, Modifiers};
+use crate::panel::PanelId;
+use crate::{Attr, Color, Error, Key, Result, Screen, Window};
 
 /// Opaque handle to a Screen
 #[repr(C)]
@@ -14,6 +20,109 @@ pub struct ZazScreen {
     _private: [u8; 0],
 }
 
+/// Error codes reported by zaz_last_error_code
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZazError {
+    /// No error recorded on this thread
+    Ok = 0,
+    /// NULL pointer, invalid UTF-8 or out-of-range value passed in
+    InvalidArgument = 1,
+    /// I/O error talking to the terminal
+    Io = 2,
+    /// The terminal device isn't a TTY
+    NotATty = 3,
+    /// Terminal not initialized, or already closed
+    NotInitialized = 4,
+    /// A screen is already open on this terminal
+    AlreadyInitialized = 5,
+    /// Color pair 0 or one that was never defined
+    InvalidColorPair = 6,
+    /// Unknown or removed panel id
+    InvalidPanel = 7,
+    /// Position outside the screen or window
+    InvalidCoordinates = 8,
+    /// Zero or too large window size
+    InvalidDimensions = 9,
+    /// Not supported on this platform
+    NotSupported = 10,
+    /// Input was shut down
+    Shutdown = 11,
+    /// Any other error; see zaz_last_error_message
+    Other = 12,
+}
+
+impl From<&Error> for ZazError {
+    fn from(err: &Error) -> Self {
+        match err.root() {
+            #[cfg(unix)]
+            Error::Io(e) if e.raw_os_error() == Some(libc::ENOTTY) => ZazError::NotATty,
+            Error::Io(_) => ZazError::Io,
+            Error::NotATty => ZazError::NotATty,
+            Error::NotInitialized => ZazError::NotInitialized,
+            Error::AlreadyInitialized => ZazError::AlreadyInitialized,
+            Error::InvalidColorPair(_) => ZazError::InvalidColorPair,
+            Error::InvalidPanel(_) => ZazError::InvalidPanel,
+            Error::InvalidCoordinates { .. } => ZazError::InvalidCoordinates,
+            Error::InvalidDimensions { .. } => ZazError::InvalidDimensions,
+            Error::NotSupported => ZazError::NotSupported,
+            Error::Shutdown => ZazError::Shutdown,
+            _ => ZazError::Other,
+        }
+    }
+}
+
+thread_local! {
+    // Like errno: kept until the next failing call on the same thread
+    static LAST_ERROR: RefCell<(ZazError, Option<CString>)> =
+        const { RefCell::new((ZazError::Ok, None)) };
+}
+
+fn set_last_error(code: ZazError, message: String) {
+    // Messages come from Display impls; drop any interior NUL just in case
+    let message = CString::new(message.replace('\0', "")).ok();
+    LAST_ERROR.with(|last| *last.borrow_mut() = (code, message));
+}
+
+/// Record `err` as this thread's last error and return -1
+fn fail(err: Error) -> i32 {
+    set_last_error(ZazError::from(&err), err.to_string());
+    -1
+}
+
+/// Record an invalid argument as this thread's last error and return -1
+fn invalid_argument(message: &str) -> i32 {
+    set_last_error(ZazError::InvalidArgument, message.to_string());
+    -1
+}
+
+/// Code of the last error on the calling thread
+///
+/// Only meaningful right after a call that reported failure: successful
+/// calls leave it unchanged.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_last_error_code() -> ZazError {
+    LAST_ERROR.with(|last| last.borrow().0)
+}
+
+/// Message for the last error on the calling thread, or NULL if none
+///
+/// The string is owned by the library and stays valid until the next
+/// failing call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| match &last.borrow().1 {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Forget the last error on the calling thread
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_clear_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = (ZazError::Ok, None));
+}
+
 /// Key tag for discriminated union
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -45,6 +154,17 @@ pub enum ZazKeyTag {
     F11,
     F12,
     Unknown,
+    Insert,
+    /// Control + character (`value.char_value`)
+    Ctrl,
+    /// Alt + character (`value.char_value`)
+    Alt,
+    Paste,
+    Mouse,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
 }
 
 /// Union for key value
@@ -171,11 +291,115 @@ impl From<Key> for ZazKey {
                 tag: ZazKeyTag::F12,
                 value: ZazKeyValue { char_value: 0 },
             },
-            _ => ZazKey {
-                tag: ZazKeyTag::Unknown,
-                value: ZazKeyValue { char_value: 0 },
+            Key::Insert => ZazKey::tag(ZazKeyTag::Insert),
+            Key::Ctrl(c) => ZazKey {
+                tag: ZazKeyTag::Ctrl,
+                value: ZazKeyValue {
+                    char_value: c as u32,
+                },
+            },
+            Key::Alt(c) => ZazKey {
+                tag: ZazKeyTag::Alt,
+                value: ZazKeyValue {
+                    char_value: c as u32,
+                },
+            },
+            Key::Paste(_) => ZazKey::tag(ZazKeyTag::Paste),
+            Key::Mouse(_) => ZazKey::tag(ZazKeyTag::Mouse),
+            Key::ScrollUp => ZazKey::tag(ZazKeyTag::ScrollUp),
+            Key::ScrollDown => ZazKey::tag(ZazKeyTag::ScrollDown),
+            Key::ScrollLeft => ZazKey::tag(ZazKeyTag::ScrollLeft),
+            Key::ScrollRight => ZazKey::tag(ZazKeyTag::ScrollRight),
+            Key::Modified(key, _) => (*key).into(),
+            Key::Enhanced(event) => match event.code {
+                9 => ZazKey::tag(ZazKeyTag::Tab),
+                13 => ZazKey::tag(ZazKeyTag::Enter),
+                27 => ZazKey::tag(ZazKeyTag::Escape),
+                127 => ZazKey::tag(ZazKeyTag::Backspace),
+                // Kitty reports functional keys in the private use area
+                code => match char::from_u32(code) {
+                    Some(c) if !('\u{E000}'..='\u{F8FF}').contains(&c) => Key::Char(c).into(),
+                    _ => ZazKey::tag(ZazKeyTag::Unknown),
+                },
             },
+            _ => ZazKey::tag(ZazKeyTag::Unknown),
+        }
+    }
+}
+
+impl ZazKey {
+    fn tag(tag: ZazKeyTag) -> Self {
+        ZazKey {
+            tag,
+            value: ZazKeyValue { char_value: 0 },
+        }
+    }
+}
+
+/// Kind of key event (only told apart with the kitty keyboard protocol)
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZazKeyEventType {
+    Press = 0,
+    Repeat,
+    Release,
+}
+
+/// A key together with what the terminal reported about it
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ZazKeyEvent {
+    pub key: ZazKey,
+    /// `ZAZ_MOD_*` bits held during the event
+    pub modifiers: u8,
+    pub event_type: ZazKeyEventType,
+    /// Kitty key code, or 0 if the key wasn't reported with the protocol
+    pub code: u32,
+    /// Kitty shifted key code, or 0
+    pub shifted_key: u32,
+    /// Kitty base layout key code, or 0
+    pub base_key: u32,
+}
+
+impl ZazKeyEvent {
+    /// Placeholder for events that carry no key
+    fn none() -> Self {
+        ZazKeyEvent {
+            key: ZazKey::tag(ZazKeyTag::Unknown),
+            modifiers: 0,
+            event_type: ZazKeyEventType::Press,
+            code: 0,
+            shifted_key: 0,
+            base_key: 0,
+        }
+    }
+}
+
+impl From<InputEvent> for ZazKeyEvent {
+    fn from(event: InputEvent) -> Self {
+        let mut out = ZazKeyEvent::none();
+        if event.repeat {
+            out.event_type = ZazKeyEventType::Repeat;
+        }
+        match &event.key {
+            Key::Enhanced(kitty) => {
+                out.modifiers = kitty.modifiers.bits();
+                out.event_type = match kitty.event_type {
+                    KeyEventType::Press => ZazKeyEventType::Press,
+                    KeyEventType::Repeat => ZazKeyEventType::Repeat,
+                    KeyEventType::Release => ZazKeyEventType::Release,
+                };
+                out.code = kitty.code;
+                out.shifted_key = kitty.shifted_key.unwrap_or(0);
+                out.base_key = kitty.base_key.unwrap_or(0);
+            }
+            Key::Modified(_, modifiers) => out.modifiers = modifiers.bits(),
+            Key::Ctrl(_) => out.modifiers = Modifiers::CTRL.bits(),
+            Key::Alt(_) => out.modifiers = Modifiers::ALT.bits(),
+            _ => {}
         }
+        out.key = event.key.into();
+        out
     }
 }
 
@@ -186,7 +410,10 @@ impl From<Key> for ZazKey {
 pub extern "C" fn zaz_init() -> *mut ZazScreen {
     match Screen::init() {
         Ok(screen) => Box::into_raw(Box::new(screen)) as *mut ZazScreen,
-        Err(_) => ptr::null_mut(),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        }
     }
 }
 
@@ -194,14 +421,14 @@ pub extern "C" fn zaz_init() -> *mut ZazScreen {
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_endwin(screen: *mut ZazScreen) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
         let screen = Box::from_raw(screen as *mut Screen);
         match screen.endwin() {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -210,14 +437,14 @@ pub extern "C" fn zaz_endwin(screen: *mut ZazScreen) -> i32 {
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_clear(screen: *mut ZazScreen) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
         let screen = &mut *(screen as *mut Screen);
         match screen.clear() {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -226,14 +453,14 @@ pub extern "C" fn zaz_clear(screen: *mut ZazScreen) -> i32 {
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_refresh(screen: *mut ZazScreen) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
         let screen = &mut *(screen as *mut Screen);
         match screen.refresh() {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -242,14 +469,14 @@ pub extern "C" fn zaz_refresh(screen: *mut ZazScreen) -> i32 {
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_move_cursor(screen: *mut ZazScreen, y: u16, x: u16) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
         let screen = &mut *(screen as *mut Screen);
         match screen.move_cursor(y, x) {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -258,7 +485,7 @@ pub extern "C" fn zaz_move_cursor(screen: *mut ZazScreen, y: u16, x: u16) -> i32
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_print(screen: *mut ZazScreen, text: *const c_char) -> i32 {
     if screen.is_null() || text.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
@@ -268,9 +495,9 @@ pub extern "C" fn zaz_print(screen: *mut ZazScreen, text: *const c_char) -> i32
         match c_str.to_str() {
             Ok(s) => match screen.print(s) {
                 Ok(_) => 0,
-                Err(_) => -1,
+                Err(e) => fail(e),
             },
-            Err(_) => -1,
+            Err(_) => invalid_argument("text is not valid UTF-8"),
         }
     }
 }
@@ -279,7 +506,7 @@ pub extern "C" fn zaz_print(screen: *mut ZazScreen, text: *const c_char) -> i32
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_mvprint(screen: *mut ZazScreen, y: u16, x: u16, text: *const c_char) -> i32 {
     if screen.is_null() || text.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
@@ -289,9 +516,9 @@ pub extern "C" fn zaz_mvprint(screen: *mut ZazScreen, y: u16, x: u16, text: *con
         match c_str.to_str() {
             Ok(s) => match screen.mvprint(y, x, s) {
                 Ok(_) => 0,
-                Err(_) => -1,
+                Err(e) => fail(e),
             },
-            Err(_) => -1,
+            Err(_) => invalid_argument("text is not valid UTF-8"),
         }
     }
 }
@@ -300,7 +527,7 @@ pub extern "C" fn zaz_mvprint(screen: *mut ZazScreen, y: u16, x: u16, text: *con
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_getch(screen: *mut ZazScreen, key_out: *mut ZazKey) -> i32 {
     if screen.is_null() || key_out.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
@@ -310,7 +537,7 @@ pub extern "C" fn zaz_getch(screen: *mut ZazScreen, key_out: *mut ZazKey) -> i32
                 *key_out = key.into();
                 0
             }
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -324,7 +551,7 @@ pub extern "C" fn zaz_getch_timeout(
     key_out: *mut ZazKey,
 ) -> i32 {
     if screen.is_null() || key_out.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
@@ -335,7 +562,7 @@ pub extern "C" fn zaz_getch_timeout(
                 1 // Key was pressed
             }
             Ok(None) => 0, // Timeout
-            Err(_) => -1,  // Error
+            Err(e) => fail(e),
         }
     }
 }
@@ -344,7 +571,7 @@ pub extern "C" fn zaz_getch_timeout(
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_set_fg_color(screen: *mut ZazScreen, r: u8, g: u8, b: u8) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
@@ -352,7 +579,7 @@ pub extern "C" fn zaz_set_fg_color(screen: *mut ZazScreen, r: u8, g: u8, b: u8)
         let color = Color::Rgb(r, g, b);
         match screen.set_fg(color) {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -361,7 +588,7 @@ pub extern "C" fn zaz_set_fg_color(screen: *mut ZazScreen, r: u8, g: u8, b: u8)
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_set_bg_color(screen: *mut ZazScreen, r: u8, g: u8, b: u8) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
 
     unsafe {
@@ -369,41 +596,72 @@ pub extern "C" fn zaz_set_bg_color(screen: *mut ZazScreen, r: u8, g: u8, b: u8)
         let color = Color::Rgb(r, g, b);
         match screen.set_bg(color) {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
 
-/// Turn on attribute (BOLD=1, DIM=2, ITALIC=4, UNDERLINE=8, BLINK=16, REVERSE=32, STRIKETHROUGH=128)
+/// Attribute flags, OR'd together in the `attr` arguments
+///
+/// The values are part of the ABI and never change; bits outside
+/// `YELLOW_ATTR_MASK` are rejected.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZazAttr {
+    Bold = 1,
+    Dim = 2,
+    Italic = 4,
+    Underline = 8,
+    Blink = 16,
+    Reverse = 32,
+    Hidden = 64,
+    Strikethrough = 128,
+    DoubleUnderline = 256,
+    Overline = 512,
+    Superscript = 1024,
+    Subscript = 2048,
+}
+
+/// Convert OR'd `ZazAttr` flags, or None if unknown bits are set
+fn attr_from_c(attr: u32) -> Option<Attr> {
+    (attr & !YELLOW_ATTR_MASK == 0).then_some(Attr(attr as u16))
+}
+
+/// Turn on attributes (`ZazAttr` flags OR'd together); unknown bits fail
+/// with `ZazError::InvalidArgument`
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_attron(screen: *mut ZazScreen, attr: u32) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
+    let Some(attr) = attr_from_c(attr) else {
+        return invalid_argument("unknown attribute bits");
+    };
 
     unsafe {
         let screen = &mut *(screen as *mut Screen);
-        let attr = Attr(attr as u16);
         match screen.attron(attr) {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
 
-/// Turn off attribute
+/// Turn off attributes (`ZazAttr` flags OR'd together)
 #[unsafe(no_mangle)]
 pub extern "C" fn zaz_attroff(screen: *mut ZazScreen, attr: u32) -> i32 {
     if screen.is_null() {
-        return -1;
+        return invalid_argument("NULL pointer argument");
     }
+    let Some(attr) = attr_from_c(attr) else {
+        return invalid_argument("unknown attribute bits");
+    };
 
     unsafe {
         let screen = &mut *(screen as *mut Screen);
-        let attr = Attr(attr as u16);
         match screen.attroff(attr) {
             Ok(_) => 0,
-            Err(_) => -1,
+            Err(e) => fail(e),
         }
     }
 }
@@ -419,7 +677,29 @@ pub extern "C" fn zaz_get_size(screen: *mut ZazScreen) -> u32 {
         let screen = &*(screen as *mut Screen);
         match screen.get_size() {
             Ok((height, width)) => ((height as u32) << 16) | (width as u32),
-            Err(_) => 0,
+            Err(e) => {
+                fail(e);
+                0
+            }
+        }
+    }
+}
+
+/// Change the screen size to `rows` x `cols`, for hosts that learn the
+/// size out of band; the next refresh repaints everything
+///
+/// Returns 0 on success, -1 on error.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_resize(screen: *mut ZazScreen, rows: u16, cols: u16) -> i32 {
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.resize(rows, cols) {
+            Ok(_) => 0,
+            Err(e) => fail(e),
         }
     }
 }
@@ -438,6 +718,7 @@ pub extern "C" fn zaz_render_mosaic(
     threshold: u8,
 ) -> *mut i8 {
     if data.is_null() || data_len == 0 {
+        invalid_argument("NULL or empty image data");
         return ptr::null_mut();
     }
 
@@ -451,7 +732,10 @@ pub extern "C" fn zaz_render_mosaic(
         // Convert to C string
         match std::ffi::CString::new(result) {
             Ok(c_str) => c_str.into_raw(),
-            Err(_) => ptr::null_mut(),
+            Err(e) => {
+                set_last_error(ZazError::Other, e.to_string());
+                ptr::null_mut()
+            }
         }
     }
 }
@@ -466,12 +750,823 @@ pub extern "C" fn zaz_free_string(s: *mut i8) {
     }
 }
 
-/// Constants for attributes
-pub const YELLOW_ATTR_BOLD: u32 = 1;
-pub const YELLOW_ATTR_DIM: u32 = 2;
-pub const YELLOW_ATTR_ITALIC: u32 = 4;
-pub const YELLOW_ATTR_UNDERLINE: u32 = 8;
-pub const YELLOW_ATTR_BLINK: u32 = 16;
-pub const YELLOW_ATTR_REVERSE: u32 = 32;
-pub const YELLOW_ATTR_HIDDEN: u32 = 64;
-pub const YELLOW_ATTR_STRIKETHROUGH: u32 = 128;
+/// Store a result in a caller-provided out-parameter
+fn put<T>(out: *mut T, value: T) {
+    unsafe { out.write(value) }
+}
+
+/// Get a key from input along with its modifiers and kitty protocol data
+/// Returns 0 on success, -1 on error
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_getch_event(screen: *mut ZazScreen, event_out: *mut ZazKeyEvent) -> i32 {
+    if screen.is_null() || event_out.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.getch_event() {
+            Ok(event) => {
+                put(event_out, event.into());
+                0
+            }
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Get a key event from input with timeout
+/// Returns 1 if a key was read (event_out is set), 0 if timeout, -1 on error
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_getch_event_timeout(
+    screen: *mut ZazScreen,
+    timeout_ms: u64,
+    event_out: *mut ZazKeyEvent,
+) -> i32 {
+    if screen.is_null() || event_out.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.getch_event_timeout(timeout_ms) {
+            Ok(Some(event)) => {
+                put(event_out, event.into());
+                1
+            }
+            Ok(None) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Kind of event passed to a zaz_run callback
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZazEventKind {
+    /// A key was read; see `key`
+    Key = 0,
+    /// The terminal changed size; see `rows` and `cols`
+    Resize,
+    /// A frame is due
+    Tick,
+}
+
+/// Event passed to a zaz_run callback
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ZazEvent {
+    pub kind: ZazEventKind,
+    /// The key, for ZazEventKind::Key
+    pub key: ZazKeyEvent,
+    /// Terminal size
+    pub rows: u16,
+    pub cols: u16,
+    /// Milliseconds since zaz_run started
+    pub elapsed_ms: u64,
+}
+
+/// Callback for zaz_run: return 0 to keep running, anything else to stop
+pub type ZazEventCallback = Option<
+    extern "C" fn(screen: *mut ZazScreen, event: *const ZazEvent, user_data: *mut c_void) -> i32,
+>;
+
+/// How often zaz_run checks for a size change when it has no ticks to wait for
+const RESIZE_POLL_MS: u64 = 100;
+
+/// Borrow the screen behind a handle for one call
+///
+/// Never held across a callback, which may itself call zaz_* functions.
+fn screen_mut<'a>(screen: *mut ZazScreen) -> &'a mut Screen {
+    unsafe { &mut *(screen as *mut Screen) }
+}
+
+/// Run an event loop: read keys, watch for resizes, tick `fps` times a
+/// second (0 for no ticks) and refresh after every batch of callbacks
+///
+/// Returns 0 once the callback asks to stop, -1 on error. The callback may
+/// draw on `screen` but must not end it.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_run(
+    screen: *mut ZazScreen,
+    callback: ZazEventCallback,
+    user_data: *mut c_void,
+    fps: u32,
+) -> i32 {
+    let Some(callback) = callback else {
+        return invalid_argument("NULL callback");
+    };
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+    match run_loop(screen, callback, user_data, fps) {
+        Ok(()) => 0,
+        Err(e) => fail(e),
+    }
+}
+
+fn run_loop(
+    screen: *mut ZazScreen,
+    callback: extern "C" fn(*mut ZazScreen, *const ZazEvent, *mut c_void) -> i32,
+    user_data: *mut c_void,
+    fps: u32,
+) -> Result<()> {
+    let frame = (fps > 0).then(|| Duration::from_secs(1) / fps);
+    let start = Instant::now();
+    let mut next_tick = start + frame.unwrap_or_default();
+    let (mut rows, mut cols) = screen_mut(screen).get_size()?;
+
+    loop {
+        let timeout_ms = match frame {
+            Some(_) => next_tick
+                .saturating_duration_since(Instant::now())
+                .as_millis() as u64,
+            None => RESIZE_POLL_MS,
+        };
+        let mut events = Vec::new();
+        if let Some(event) = screen_mut(screen).getch_event_timeout(timeout_ms)? {
+            events.push((ZazEventKind::Key, event.into()));
+        }
+        let size = screen_mut(screen).get_size()?;
+        if size != (rows, cols) {
+            (rows, cols) = size;
+            // The callback draws on a screen of the new size
+            screen_mut(screen).resize(rows, cols)?;
+            events.push((ZazEventKind::Resize, ZazKeyEvent::none()));
+        }
+        if let Some(frame) = frame {
+            let now = Instant::now();
+            if now >= next_tick {
+                events.push((ZazEventKind::Tick, ZazKeyEvent::none()));
+                // Skip missed frames rather than firing a burst of ticks
+                next_tick = (next_tick + frame).max(now);
+            }
+        }
+        if events.is_empty() {
+            continue;
+        }
+
+        for (kind, key) in events {
+            let event = ZazEvent {
+                kind,
+                key,
+                rows,
+                cols,
+                elapsed_ms: start.elapsed().as_millis() as u64,
+            };
+            if callback(screen, &event, user_data) != 0 {
+                return screen_mut(screen).refresh();
+            }
+        }
+        screen_mut(screen).refresh()?;
+    }
+}
+
+/// Opaque handle to a Window
+#[repr(C)]
+pub struct ZazWindow {
+    _private: [u8; 0],
+}
+
+/// Create a window of height x width at (y, x)
+///
+/// Returns NULL on error. Free with zaz_delwin, or hand to zaz_new_panel.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_newwin(
+    screen: *mut ZazScreen,
+    height: u16,
+    width: u16,
+    y: u16,
+    x: u16,
+) -> *mut ZazWindow {
+    if screen.is_null() {
+        invalid_argument("NULL pointer argument");
+        return ptr::null_mut();
+    }
+
+    unsafe {
+        let screen = &*(screen as *mut Screen);
+        match screen.newwin(height, width, y, x) {
+            Ok(window) => Box::into_raw(Box::new(window)) as *mut ZazWindow,
+            Err(e) => {
+                fail(e);
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Free a window created by zaz_newwin
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_delwin(window: *mut ZazWindow) {
+    if !window.is_null() {
+        unsafe {
+            drop(Box::from_raw(window as *mut Window));
+        }
+    }
+}
+
+/// Run `f` on a window, mapping the result to 0 / -1
+fn with_window(window: *mut ZazWindow, f: impl FnOnce(&mut Window) -> Result<()>) -> i32 {
+    if window.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    let window = unsafe { &mut *(window as *mut Window) };
+    match f(window) {
+        Ok(_) => 0,
+        Err(e) => fail(e),
+    }
+}
+
+/// Borrow a NUL-terminated UTF-8 string, None if it isn't valid UTF-8
+fn c_str<'a>(text: *const c_char) -> Option<&'a str> {
+    unsafe { CStr::from_ptr(text) }.to_str().ok()
+}
+
+/// Print string at the window's cursor position
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wprint(window: *mut ZazWindow, text: *const c_char) -> i32 {
+    if text.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    match c_str(text) {
+        Some(s) => with_window(window, |window| window.print(s)),
+        None => invalid_argument("text is not valid UTF-8"),
+    }
+}
+
+/// Print string at position (y, x) within the window
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wmvprint(window: *mut ZazWindow, y: u16, x: u16, text: *const c_char) -> i32 {
+    if text.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    match c_str(text) {
+        Some(s) => with_window(window, |window| window.mvprint(y, x, s)),
+        None => invalid_argument("text is not valid UTF-8"),
+    }
+}
+
+/// Draw a box around the edge of the window
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wdraw_box(window: *mut ZazWindow) -> i32 {
+    with_window(window, Window::draw_box)
+}
+
+/// Clear the window
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wclear(window: *mut ZazWindow) -> i32 {
+    with_window(window, Window::clear)
+}
+
+/// Turn on attributes in the window (`ZazAttr` flags OR'd together)
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wattron(window: *mut ZazWindow, attr: u32) -> i32 {
+    let Some(attr) = attr_from_c(attr) else {
+        return invalid_argument("unknown attribute bits");
+    };
+    with_window(window, |window| window.attron(attr))
+}
+
+/// Turn off attributes in the window (`ZazAttr` flags OR'd together)
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wattroff(window: *mut ZazWindow, attr: u32) -> i32 {
+    let Some(attr) = attr_from_c(attr) else {
+        return invalid_argument("unknown attribute bits");
+    };
+    with_window(window, |window| window.attroff(attr))
+}
+
+/// Set the window's foreground and background colors (see zaz_init_pair)
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wset_colors(window: *mut ZazWindow, fg: i32, bg: i32) -> i32 {
+    let (Some(fg), Some(bg)) = (color_from_c(fg), color_from_c(bg)) else {
+        return invalid_argument("invalid color");
+    };
+    with_window(window, |window| {
+        window.set_fg(fg)?;
+        window.set_bg(bg)
+    })
+}
+
+/// Queue the window for the next zaz_doupdate
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_wnoutrefresh(window: *mut ZazWindow) -> i32 {
+    with_window(window, Window::wnoutrefresh)
+}
+
+/// Draw every queued window and panel on the screen and refresh
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_doupdate(screen: *mut ZazScreen) -> i32 {
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.doupdate() {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Put a window on top of the screen's panel stack
+///
+/// Takes ownership of the window: don't use or free it afterwards, reach it
+/// through zaz_panel_window instead. Returns the panel id, or -1 on error.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_new_panel(screen: *mut ZazScreen, window: *mut ZazWindow) -> i64 {
+    if screen.is_null() || window.is_null() {
+        return invalid_argument("NULL pointer argument").into();
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        let window = Box::from_raw(window as *mut Window);
+        match screen.new_panel(*window) {
+            Ok(id) => id.get() as i64,
+            Err(e) => fail(e).into(),
+        }
+    }
+}
+
+/// Borrow the window of a panel, valid until the panel is deleted
+///
+/// Returns NULL if there is no such panel. Don't pass it to zaz_delwin.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_panel_window(screen: *mut ZazScreen, panel: i64) -> *mut ZazWindow {
+    if screen.is_null() || panel < 0 {
+        invalid_argument("NULL screen or negative panel id");
+        return ptr::null_mut();
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen
+            .panels_mut()
+            .get_mut(PanelId::from_raw(panel as usize))
+        {
+            Some(panel) => panel.window_mut() as *mut Window as *mut ZazWindow,
+            None => {
+                fail(Error::InvalidPanel(panel as usize));
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Run `f` on the screen's panel stack with a panel id, mapping the result
+/// to 0 / -1
+fn with_panel(
+    screen: *mut ZazScreen,
+    panel: i64,
+    f: impl FnOnce(&mut crate::PanelManager, PanelId) -> Result<()>,
+) -> i32 {
+    if screen.is_null() || panel < 0 {
+        return invalid_argument("NULL screen or negative panel id");
+    }
+
+    let screen = unsafe { &mut *(screen as *mut Screen) };
+    match f(screen.panels_mut(), PanelId::from_raw(panel as usize)) {
+        Ok(_) => 0,
+        Err(e) => fail(e),
+    }
+}
+
+/// Remove a panel and free its window
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_del_panel(screen: *mut ZazScreen, panel: i64) -> i32 {
+    with_panel(screen, panel, |panels, id| panels.remove(id).map(drop))
+}
+
+/// Show a hidden panel
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_panel_show(screen: *mut ZazScreen, panel: i64) -> i32 {
+    with_panel(screen, panel, |panels, id| match panels.get_mut(id) {
+        Some(panel) => panel.show(),
+        None => Err(Error::InvalidPanel(id.get())),
+    })
+}
+
+/// Hide a panel without removing it
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_panel_hide(screen: *mut ZazScreen, panel: i64) -> i32 {
+    with_panel(screen, panel, |panels, id| match panels.get_mut(id) {
+        Some(panel) => panel.hide(),
+        None => Err(Error::InvalidPanel(id.get())),
+    })
+}
+
+/// Move a panel to the top of the stack
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_panel_top(screen: *mut ZazScreen, panel: i64) -> i32 {
+    with_panel(screen, panel, |panels, id| panels.top(id))
+}
+
+/// Move a panel to the bottom of the stack
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_panel_bottom(screen: *mut ZazScreen, panel: i64) -> i32 {
+    with_panel(screen, panel, |panels, id| panels.bottom(id))
+}
+
+/// Queue every visible panel in stacking order; follow with zaz_doupdate
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_update_panels(screen: *mut ZazScreen) -> i32 {
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.update_panels() {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Terminal default color, for zaz_init_pair and zaz_wset_colors
+pub const ZAZ_COLOR_DEFAULT: i32 = -1;
+/// Flag marking a color as 0xRRGGBB rather than a palette index
+pub const ZAZ_COLOR_RGB: i32 = 0x0100_0000;
+
+/// Decode a C color: ZAZ_COLOR_DEFAULT, a palette index 0-255, or
+/// ZAZ_COLOR_RGB | 0xRRGGBB
+fn color_from_c(color: i32) -> Option<Color> {
+    match color {
+        ZAZ_COLOR_DEFAULT => Some(Color::Reset),
+        0..=255 => Some(Color::Ansi256(color as u8)),
+        _ if color & !0x00FF_FFFF == ZAZ_COLOR_RGB => Some(Color::Rgb(
+            (color >> 16) as u8,
+            (color >> 8) as u8,
+            color as u8,
+        )),
+        _ => None,
+    }
+}
+
+/// Define color pair `pair` (1 or more) as foreground `fg` on background `bg`
+///
+/// Colors are ZAZ_COLOR_DEFAULT, a palette index 0-255 or
+/// ZAZ_COLOR_RGB | 0xRRGGBB.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_init_pair(screen: *mut ZazScreen, pair: u16, fg: i32, bg: i32) -> i32 {
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+    let (Some(fg), Some(bg)) = (color_from_c(fg), color_from_c(bg)) else {
+        return invalid_argument("invalid color");
+    };
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.init_pair(pair, fg, bg) {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Draw with the colors of a pair defined by zaz_init_pair
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_color_pair(screen: *mut ZazScreen, pair: u16) -> i32 {
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.color_pair(pair) {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Draw a box around the edge of the screen
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_draw_box(screen: *mut ZazScreen) -> i32 {
+    if screen.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        match screen.draw_box() {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Borrow `len` bytes from C
+fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    unsafe { std::slice::from_raw_parts(data, len) }
+}
+
+/// Image data formats for zaz_display_kitty_image
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum ZazImageFormat {
+    Png = 0,
+    Rgb,
+    Rgba,
+}
+
+/// Show an image with the kitty graphics protocol at cell (y, x), scaled to
+/// cols x rows cells (0 = natural size)
+///
+/// width_px and height_px are required for RGB and RGBA data. The image is
+/// written by the next refresh. Returns 0 on success, -1 on error.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_display_kitty_image(
+    screen: *mut ZazScreen,
+    data: *const u8,
+    data_len: usize,
+    format: ZazImageFormat,
+    width_px: u32,
+    height_px: u32,
+    y: u16,
+    x: u16,
+    cols: u16,
+    rows: u16,
+) -> i32 {
+    if screen.is_null() || data.is_null() || data_len == 0 {
+        return invalid_argument("NULL screen or empty image data");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        let data = bytes(data, data_len);
+        let format = match format {
+            ZazImageFormat::Png => ImageFormat::Png,
+            ZazImageFormat::Rgb => ImageFormat::Rgb,
+            ZazImageFormat::Rgba => ImageFormat::Rgba,
+        };
+        let mut placement = ImagePlacement::at(x, y);
+        if cols > 0 && rows > 0 {
+            placement = placement.with_size(cols, rows);
+        }
+        let mut image = KittyImage::new(data, format).placement(placement);
+        if width_px > 0 && height_px > 0 {
+            image = image.with_pixel_size(width_px, height_px);
+        }
+        match screen.display_kitty_image(&image) {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Show RGB data (3 bytes per pixel) as a sixel image at the cursor position
+///
+/// The image is written by the next refresh. Returns 0 on success, -1 on
+/// error.
+#[unsafe(no_mangle)]
+pub extern "C" fn zaz_display_sixel(
+    screen: *mut ZazScreen,
+    data: *const u8,
+    data_len: usize,
+    width: u32,
+    height: u32,
+) -> i32 {
+    if screen.is_null() || data.is_null() {
+        return invalid_argument("NULL pointer argument");
+    }
+    if (data_len as u64) < width as u64 * height as u64 * 3 {
+        return invalid_argument("image data shorter than width * height * 3");
+    }
+
+    unsafe {
+        let screen = &mut *(screen as *mut Screen);
+        let data = bytes(data, data_len);
+        match screen.display_sixel_image(&SixelImage::from_rgb(data, width, height)) {
+            Ok(_) => 0,
+            Err(e) => fail(e),
+        }
+    }
+}
+
+/// Constants for attributes (see `ZazAttr`)
+pub const YELLOW_ATTR_BOLD: u32 = ZazAttr::Bold as u32;
+pub const YELLOW_ATTR_DIM: u32 = ZazAttr::Dim as u32;
+pub const YELLOW_ATTR_ITALIC: u32 = ZazAttr::Italic as u32;
+pub const YELLOW_ATTR_UNDERLINE: u32 = ZazAttr::Underline as u32;
+pub const YELLOW_ATTR_BLINK: u32 = ZazAttr::Blink as u32;
+pub const YELLOW_ATTR_REVERSE: u32 = ZazAttr::Reverse as u32;
+pub const YELLOW_ATTR_HIDDEN: u32 = ZazAttr::Hidden as u32;
+pub const YELLOW_ATTR_STRIKETHROUGH: u32 = ZazAttr::Strikethrough as u32;
+pub const YELLOW_ATTR_DOUBLE_UNDERLINE: u32 = ZazAttr::DoubleUnderline as u32;
+pub const YELLOW_ATTR_OVERLINE: u32 = ZazAttr::Overline as u32;
+pub const YELLOW_ATTR_SUPERSCRIPT: u32 = ZazAttr::Superscript as u32;
+pub const YELLOW_ATTR_SUBSCRIPT: u32 = ZazAttr::Subscript as u32;
+/// Every attribute bit
+pub const YELLOW_ATTR_MASK: u32 = Attr::ALL.bits() as u32;
+
+/// Constants for key modifiers (ZazKeyEvent::modifiers)
+pub const YELLOW_MOD_SHIFT: u8 = 1;
+pub const YELLOW_MOD_ALT: u8 = 2;
+pub const YELLOW_MOD_CTRL: u8 = 4;
+pub const YELLOW_MOD_SUPER: u8 = 8;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kitty::KeyEvent;
+    use std::time::Instant;
+
+    #[test]
+    fn test_color_from_c() {
+        assert_eq!(color_from_c(ZAZ_COLOR_DEFAULT), Some(Color::Reset));
+        assert_eq!(color_from_c(196), Some(Color::Ansi256(196)));
+        assert_eq!(
+            color_from_c(ZAZ_COLOR_RGB | 0x102030),
+            Some(Color::Rgb(0x10, 0x20, 0x30))
+        );
+        assert_eq!(color_from_c(256), None);
+        assert_eq!(color_from_c(-2), None);
+    }
+
+    #[test]
+    fn test_key_event_from_kitty() {
+        let mut kitty = KeyEvent::with_modifiers('a' as u32, Modifiers::CTRL);
+        kitty.event_type = KeyEventType::Release;
+        let event: ZazKeyEvent = InputEvent {
+            key: Key::Enhanced(kitty),
+            time: Instant::now(),
+            repeat: false,
+        }
+        .into();
+
+        assert!(matches!(event.key.tag, ZazKeyTag::Char));
+        assert_eq!(unsafe { event.key.value.char_value }, 'a' as u32);
+        assert_eq!(event.modifiers, YELLOW_MOD_CTRL);
+        assert_eq!(event.event_type, ZazKeyEventType::Release);
+        assert_eq!(event.code, 'a' as u32);
+
+        let event: ZazKeyEvent = InputEvent {
+            key: Key::Modified(Box::new(Key::Right), Modifiers::SHIFT),
+            time: Instant::now(),
+            repeat: true,
+        }
+        .into();
+        assert!(matches!(event.key.tag, ZazKeyTag::ArrowRight));
+        assert_eq!(event.modifiers, YELLOW_MOD_SHIFT);
+        assert_eq!(event.event_type, ZazKeyEventType::Repeat);
+        assert_eq!(event.code, 0);
+    }
+
+    #[test]
+    fn test_last_error() {
+        let screen = Box::into_raw(Box::new(Screen::with_size(5, 10))) as *mut ZazScreen;
+        let message = || {
+            unsafe { CStr::from_ptr(zaz_last_error_message()) }
+                .to_str()
+                .unwrap()
+        };
+
+        zaz_clear_error();
+        assert_eq!(zaz_last_error_code(), ZazError::Ok);
+        assert!(zaz_last_error_message().is_null());
+
+        assert_eq!(zaz_print(screen, ptr::null()), -1);
+        assert_eq!(zaz_last_error_code(), ZazError::InvalidArgument);
+        assert_eq!(message(), "NULL pointer argument");
+
+        // Success leaves the last error in place
+        assert_eq!(zaz_clear(screen), 0);
+        assert_eq!(zaz_last_error_code(), ZazError::InvalidArgument);
+
+        assert_eq!(zaz_color_pair(screen, 7), -1);
+        assert_eq!(zaz_last_error_code(), ZazError::InvalidColorPair);
+        assert_eq!(message(), "Invalid color pair ID: 7");
+
+        // Each thread has its own last error
+        std::thread::spawn(|| assert_eq!(zaz_last_error_code(), ZazError::Ok))
+            .join()
+            .unwrap();
+
+        zaz_clear_error();
+        drop(unsafe { Box::from_raw(screen as *mut Screen) });
+    }
+
+    #[test]
+    fn test_attr_from_c() {
+        assert_eq!(
+            attr_from_c(YELLOW_ATTR_BOLD | YELLOW_ATTR_HIDDEN),
+            Some(Attr::BOLD | Attr::HIDDEN)
+        );
+        assert_eq!(attr_from_c(YELLOW_ATTR_SUBSCRIPT), Some(Attr::SUBSCRIPT));
+        assert_eq!(attr_from_c(YELLOW_ATTR_MASK), Some(Attr::ALL));
+        // Bits Attr doesn't have, including ones lost to truncation
+        assert_eq!(attr_from_c(4096), None);
+        assert_eq!(attr_from_c(0x1_0001), None);
+
+        zaz_clear_error();
+        assert_eq!(zaz_wattron(std::ptr::null_mut(), 1 << 20), -1);
+        assert_eq!(zaz_last_error_code(), ZazError::InvalidArgument);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let not_a_tty = Error::Io(std::io::Error::from_raw_os_error(libc::ENOTTY));
+        assert_eq!(ZazError::from(&not_a_tty), ZazError::NotATty);
+        let io = Error::Io(std::io::Error::other("broken pipe"));
+        assert_eq!(ZazError::from(&io), ZazError::Io);
+        assert_eq!(
+            ZazError::from(&Error::InvalidCoordinates { y: 1, x: 2 }),
+            ZazError::InvalidCoordinates
+        );
+        assert_eq!(ZazError::from(&Error::RendererClosed), ZazError::Other);
+        let wrapped = Error::Context {
+            context: "initializing terminal",
+            source: Box::new(not_a_tty),
+        };
+        assert_eq!(ZazError::from(&wrapped), ZazError::NotATty);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_loop() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        extern "C" fn record(
+            screen: *mut ZazScreen,
+            event: *const ZazEvent,
+            user_data: *mut c_void,
+        ) -> i32 {
+            let seen = unsafe { &mut *(user_data as *mut Vec<u32>) };
+            let event = unsafe { &*event };
+            if event.kind != ZazEventKind::Key {
+                return 0;
+            }
+            let ch = unsafe { event.key.key.value.char_value };
+            seen.push(ch);
+            assert_eq!(zaz_mvprint(screen, 0, 0, c"drawn".as_ptr()), 0);
+            (ch == 'q' as u32) as i32
+        }
+
+        let (device, mut peer) = UnixStream::pair().unwrap();
+        let scr = Screen::on_device(device.try_clone().unwrap(), device).unwrap();
+        let screen = Box::into_raw(Box::new(scr)) as *mut ZazScreen;
+
+        assert_eq!(zaz_run(screen, None, ptr::null_mut(), 0), -1);
+        assert_eq!(zaz_last_error_code(), ZazError::InvalidArgument);
+
+        peer.write_all(b"aq").unwrap();
+        let mut seen: Vec<u32> = Vec::new();
+        let user_data = &mut seen as *mut Vec<u32> as *mut c_void;
+        assert_eq!(zaz_run(screen, Some(record), user_data, 200), 0);
+        assert_eq!(seen, vec!['a' as u32, 'q' as u32]);
+
+        let scr = unsafe { Box::from_raw(screen as *mut Screen) };
+        assert_eq!(scr.read_str(0, 0, 5).unwrap(), "drawn");
+        zaz_clear_error();
+    }
+
+    #[test]
+    fn test_resize() {
+        let screen = Box::into_raw(Box::new(Screen::with_size(10, 20))) as *mut ZazScreen;
+        assert_eq!(zaz_resize(screen, 12, 40), 0);
+        assert_eq!(zaz_get_size(screen), (12 << 16) | 40);
+        assert_eq!(zaz_resize(screen, 0, 40), -1);
+        assert_eq!(zaz_last_error_code(), ZazError::InvalidDimensions);
+        assert_eq!(zaz_resize(ptr::null_mut(), 12, 40), -1);
+
+        drop(unsafe { Box::from_raw(screen as *mut Screen) });
+        zaz_clear_error();
+    }
+
+    #[test]
+    fn test_windows_and_panels() {
+        let screen = Box::into_raw(Box::new(Screen::with_size(10, 20))) as *mut ZazScreen;
+
+        let window = zaz_newwin(screen, 3, 8, 1, 2);
+        assert!(!window.is_null());
+        assert_eq!(zaz_wdraw_box(window), 0);
+        assert_eq!(zaz_wmvprint(window, 1, 1, c"panel".as_ptr()), 0);
+
+        let panel = zaz_new_panel(screen, window);
+        assert!(panel >= 0);
+        assert!(!zaz_panel_window(screen, panel).is_null());
+        assert!(zaz_panel_window(screen, panel + 1).is_null());
+        assert_eq!(zaz_panel_hide(screen, panel), 0);
+        assert_eq!(zaz_panel_show(screen, panel), 0);
+        assert_eq!(zaz_panel_top(screen, panel), 0);
+        assert_eq!(zaz_panel_top(screen, panel + 1), -1);
+
+        assert_eq!(zaz_init_pair(screen, 1, 2, ZAZ_COLOR_DEFAULT), 0);
+        assert_eq!(zaz_init_pair(screen, 0, 2, 3), -1);
+        assert_eq!(zaz_color_pair(screen, 1), 0);
+        assert_eq!(zaz_del_panel(screen, panel), 0);
+        assert_eq!(zaz_del_panel(screen, panel), -1);
+
+        let screen = unsafe { Box::from_raw(screen as *mut Screen) };
+        assert_eq!(screen.current_style().fg, Color::Ansi256(2));
+    }
+}
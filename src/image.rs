@@ -358,7 +358,7 @@ fn match_color_to_palette(r: u8, g: u8, b: u8) -> usize {
 }
 
 /// Simple base64 encoding
-fn base64_encode(data: &[u8]) -> String {
+pub(crate) fn base64_encode(data: &[u8]) -> String {
     const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
     let mut result = String::new();
 
@@ -1,4 +1,6 @@
-use crate::kitty::KeyEvent;
+use crate::kitty::{KeyEvent, KeyEventType, Modifiers};
+use crate::mouse::MouseEvent;
+use std::time::{Duration, Instant};
 
 /// Keyboard input key
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -29,6 +31,19 @@ pub enum Key {
     Alt(char),
     /// Enhanced key event from Kitty keyboard protocol
     Enhanced(KeyEvent),
+    /// Text received via bracketed paste (empty if a paste handler consumed it)
+    Paste(String),
+    /// Mouse button, motion or wheel event (with mouse reporting enabled)
+    Mouse(MouseEvent),
+    /// Mouse wheel turns, when reported as keys
+    /// (see `Screen::set_mouse_wheel_keys`)
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    /// A key pressed with modifiers, from legacy CSI sequences such as
+    /// `ESC [ 1 ; 5 C` (Ctrl+Right)
+    Modified(Box<Key>, Modifiers),
     /// Unknown/unsupported key
     Unknown,
 }
@@ -52,44 +67,206 @@ impl Key {
             }
         }
 
-        // ESC [ sequences
-        if seq.len() >= 3 && seq[0] == 27 && seq[1] == b'[' {
-            return match seq[2] {
-                b'A' => Some(Key::Up),
-                b'B' => Some(Key::Down),
-                b'C' => Some(Key::Right),
-                b'D' => Some(Key::Left),
-                b'H' => Some(Key::Home),
-                b'F' => Some(Key::End),
-                b'1' if seq.len() >= 4 => match seq[3] {
-                    b'~' => Some(Key::Home),
-                    b'1'..=b'9' if seq.len() >= 5 && seq[4] == b'~' => {
-                        Some(Key::F(seq[3] - b'0' + 10))
-                    }
-                    _ => None,
-                },
-                b'2' if seq.len() >= 4 && seq[3] == b'~' => Some(Key::Insert),
-                b'3' if seq.len() >= 4 && seq[3] == b'~' => Some(Key::Delete),
-                b'4' if seq.len() >= 4 && seq[3] == b'~' => Some(Key::End),
-                b'5' if seq.len() >= 4 && seq[3] == b'~' => Some(Key::PageUp),
-                b'6' if seq.len() >= 4 && seq[3] == b'~' => Some(Key::PageDown),
-                _ => None,
-            };
+        if let Some(event) = MouseEvent::from_sgr(seq) {
+            return Some(Key::Mouse(event));
+        }
+
+        // Modified keys: ESC [ 1 ; mods X or ESC [ n ; mods ~
+        if let Some(key) = Self::from_modified_csi(seq) {
+            return Some(key);
         }
 
-        // ESC O sequences (function keys)
-        if seq.len() >= 3 && seq[0] == 27 && seq[1] == b'O' {
-            return match seq[2] {
-                b'P' => Some(Key::F(1)),
-                b'Q' => Some(Key::F(2)),
-                b'R' => Some(Key::F(3)),
-                b'S' => Some(Key::F(4)),
-                _ => None,
-            };
+        match seq {
+            // ESC [ n ~: editing keypad and function keys
+            [27, b'[', number @ .., b'~'] => {
+                let number = std::str::from_utf8(number).ok()?.parse().ok()?;
+                return Self::from_tilde_number(number);
+            }
+            // ESC [ X: cursor keys and xterm Home/End
+            [27, b'[', last] => {
+                return match last {
+                    b'A' => Some(Key::Up),
+                    b'B' => Some(Key::Down),
+                    b'C' => Some(Key::Right),
+                    b'D' => Some(Key::Left),
+                    b'H' => Some(Key::Home),
+                    b'F' => Some(Key::End),
+                    _ => None,
+                };
+            }
+            // ESC O X: F1-F4, and cursor keys and keypad in application mode
+            [27, b'O', last] => return Self::from_ss3(*last),
+            _ => {}
         }
 
         None
     }
+
+    /// Split into the key and the modifiers held with it
+    ///
+    /// `Ctrl` and `Alt` keys become their character with the modifier, so
+    /// `Key::Ctrl('x')` and `ESC [ 1 ; 5 C` can be matched the same way.
+    pub fn modified(&self) -> (Key, Modifiers) {
+        match self {
+            Key::Modified(key, modifiers) => ((**key).clone(), *modifiers),
+            Key::Ctrl(ch) => (Key::Char(*ch), Modifiers::CTRL),
+            Key::Alt(ch) => (Key::Char(*ch), Modifiers::ALT),
+            key => (key.clone(), Modifiers::empty()),
+        }
+    }
+
+    /// The same key with Alt held (for ESC-prefixed input)
+    pub(crate) fn with_alt(self) -> Key {
+        match self {
+            Key::Char(ch) => Key::Alt(ch),
+            Key::Ctrl(ch) => {
+                Key::Modified(Box::new(Key::Char(ch)), Modifiers::CTRL | Modifiers::ALT)
+            }
+            Key::Modified(key, modifiers) => Key::Modified(key, modifiers | Modifiers::ALT),
+            key @ (Key::Alt(_)
+            | Key::Enhanced(_)
+            | Key::Paste(_)
+            | Key::Mouse(_)
+            | Key::Unknown) => key,
+            key => Key::Modified(Box::new(key), Modifiers::ALT),
+        }
+    }
+
+    /// Parse a CSI sequence with an xterm modifier parameter (1 + the
+    /// modifier bits: 2 Shift, 3 Alt, 5 Ctrl, ...)
+    fn from_modified_csi(seq: &[u8]) -> Option<Self> {
+        let [27, b'[', params @ .., last] = seq else {
+            return None;
+        };
+        let params = std::str::from_utf8(params).ok()?;
+        let (number, mods) = params.split_once(';')?;
+        let number: u8 = number.parse().ok()?;
+        let mods: u8 = mods.parse().ok()?;
+
+        let key = match (number, last) {
+            (1, b'A') => Key::Up,
+            (1, b'B') => Key::Down,
+            (1, b'C') => Key::Right,
+            (1, b'D') => Key::Left,
+            (1, b'H') => Key::Home,
+            (1, b'F') => Key::End,
+            (1, b'P') => Key::F(1),
+            (1, b'Q') => Key::F(2),
+            (1, b'R') => Key::F(3),
+            (1, b'S') => Key::F(4),
+            (n, b'~') => Self::from_tilde_number(n)?,
+            _ => return None,
+        };
+
+        let modifiers = Modifiers::from_bits(mods.checked_sub(1)?)?;
+        if modifiers.is_empty() {
+            Some(key)
+        } else {
+            Some(Key::Modified(Box::new(key), modifiers))
+        }
+    }
+
+    /// Key of an `ESC O X` sequence
+    fn from_ss3(last: u8) -> Option<Self> {
+        Some(match last {
+            b'P' => Key::F(1),
+            b'Q' => Key::F(2),
+            b'R' => Key::F(3),
+            b'S' => Key::F(4),
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            b'H' => Key::Home,
+            b'F' => Key::End,
+            b'M' => Key::Enter,
+            // Application keypad: digits and operators
+            b'p'..=b'y' => Key::Char((last - b'p' + b'0') as char),
+            b'j' => Key::Char('*'),
+            b'k' => Key::Char('+'),
+            b'l' => Key::Char(','),
+            b'm' => Key::Char('-'),
+            b'n' => Key::Char('.'),
+            b'o' => Key::Char('/'),
+            b'X' => Key::Char('='),
+            _ => return None,
+        })
+    }
+
+    /// Key of a vt220-style `ESC [ n ~` sequence
+    fn from_tilde_number(n: u8) -> Option<Self> {
+        Some(match n {
+            1 | 7 => Key::Home,
+            2 => Key::Insert,
+            3 => Key::Delete,
+            4 | 8 => Key::End,
+            5 => Key::PageUp,
+            6 => Key::PageDown,
+            11..=15 => Key::F(n - 10),
+            17..=21 => Key::F(n - 11),
+            23 | 24 => Key::F(n - 12),
+            _ => return None,
+        })
+    }
+}
+
+/// Default gap below which a repeated key counts as auto-repeat
+pub(crate) const REPEAT_THRESHOLD_MS: u64 = 100;
+
+/// A key with the time it was read and whether it is an auto-repeat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputEvent {
+    /// The key read
+    pub key: Key,
+    /// When the key was read, from the screen's clock
+    pub time: Instant,
+    /// The key is held down and repeating
+    ///
+    /// Reported by the terminal with the kitty protocol; otherwise guessed
+    /// from the same key arriving again within the repeat threshold.
+    pub repeat: bool,
+}
+
+/// Guesses auto-repeat from key timing when the terminal doesn't report it
+#[derive(Debug, Clone)]
+pub(crate) struct RepeatDetector {
+    last: Option<(Key, Instant)>,
+    pub(crate) threshold: Duration,
+}
+
+impl Default for RepeatDetector {
+    fn default() -> Self {
+        Self {
+            last: None,
+            threshold: Duration::from_millis(REPEAT_THRESHOLD_MS),
+        }
+    }
+}
+
+impl RepeatDetector {
+    /// Wrap `key` read at `time` into an event, deciding if it repeats
+    pub(crate) fn event(&mut self, key: Key, time: Instant) -> InputEvent {
+        let repeat = match &key {
+            // The kitty protocol reports repeats itself
+            Key::Enhanced(event) => {
+                self.last = None;
+                event.event_type == KeyEventType::Repeat
+            }
+            // Pastes, mouse events and unknown input are never held down
+            Key::Paste(_) | Key::Mouse(_) | Key::Unknown => {
+                self.last = None;
+                false
+            }
+            _ => {
+                let repeat = self.last.as_ref().is_some_and(|(last, at)| {
+                    last == &key && time.saturating_duration_since(*at) <= self.threshold
+                });
+                self.last = Some((key.clone(), time));
+                repeat
+            }
+        };
+        InputEvent { key, time, repeat }
+    }
 }
 
 #[cfg(test)]
@@ -207,8 +384,8 @@ mod tests {
 
     #[test]
     fn test_kitty_protocol_with_modifiers() {
-        // Ctrl+Shift+A: ESC [ 65 ; 5 u (modifier 1+4=5)
-        let seq = b"\x1b[65;5u";
+        // Ctrl+Shift+A: ESC [ 65 ; 6 u (modifier 1+1+4=6)
+        let seq = b"\x1b[65;6u";
         let key = Key::from_escape_sequence(seq);
 
         assert!(matches!(key, Some(Key::Enhanced(_))));
@@ -222,8 +399,8 @@ mod tests {
 
     #[test]
     fn test_kitty_protocol_with_release() {
-        // 'A' release: ESC [ 65 ; 0 ; 3 u
-        let seq = b"\x1b[65;0;3u";
+        // 'A' release: ESC [ 65 ; 1:3 u
+        let seq = b"\x1b[65;1:3u";
         let key = Key::from_escape_sequence(seq);
 
         assert!(matches!(key, Some(Key::Enhanced(_))));
@@ -235,8 +412,8 @@ mod tests {
 
     #[test]
     fn test_kitty_protocol_with_repeat() {
-        // 'A' repeat: ESC [ 65 ; 0 ; 2 u
-        let seq = b"\x1b[65;0;2u";
+        // 'A' repeat: ESC [ 65 ; 1:2 u
+        let seq = b"\x1b[65;1:2u";
         let key = Key::from_escape_sequence(seq);
 
         assert!(matches!(key, Some(Key::Enhanced(_))));
@@ -249,8 +426,8 @@ mod tests {
     #[test]
     fn test_kitty_protocol_complex() {
         // Complex sequence with modifiers, event type, and shifted key
-        // 'a' with Shift (shifted to 'A'): ESC [ 97 ; 1 ; 1 ; 65 u
-        let seq = b"\x1b[97;1;1;65u";
+        // 'a' with Shift (shifted to 'A'): ESC [ 97:65 ; 2:1 u
+        let seq = b"\x1b[97:65;2:1u";
         let key = Key::from_escape_sequence(seq);
 
         assert!(matches!(key, Some(Key::Enhanced(_))));
@@ -264,8 +441,8 @@ mod tests {
 
     #[test]
     fn test_kitty_protocol_ctrl_alt() {
-        // Ctrl+Alt+X: ESC [ 120 ; 6 u (modifier 4+2=6)
-        let seq = b"\x1b[120;6u";
+        // Ctrl+Alt+X: ESC [ 120 ; 7 u (modifier 1+4+2=7)
+        let seq = b"\x1b[120;7u";
         let key = Key::from_escape_sequence(seq);
 
         assert!(matches!(key, Some(Key::Enhanced(_))));
@@ -290,4 +467,83 @@ mod tests {
             Some(Key::F(1))
         );
     }
+
+    #[test]
+    fn test_repeat_detection() {
+        let mut detector = RepeatDetector::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert!(!detector.event(Key::Char('j'), at(0)).repeat);
+        // Held key: the terminal sends it again every ~30ms
+        assert!(detector.event(Key::Char('j'), at(30)).repeat);
+        assert!(detector.event(Key::Char('j'), at(60)).repeat);
+        // A different key, or the same key after a pause, is a new press
+        assert!(!detector.event(Key::Char('k'), at(70)).repeat);
+        assert!(!detector.event(Key::Char('k'), at(500)).repeat);
+
+        let mut event = KeyEvent::new(106);
+        event.event_type = KeyEventType::Repeat;
+        assert!(detector.event(Key::Enhanced(event), at(0)).repeat);
+        assert!(!detector.event(Key::Char('k'), at(510)).repeat);
+    }
+
+    #[test]
+    fn test_modified_csi_sequences() {
+        let ctrl_right = Key::from_escape_sequence(b"\x1b[1;5C").unwrap();
+        assert_eq!(
+            ctrl_right,
+            Key::Modified(Box::new(Key::Right), Modifiers::CTRL)
+        );
+        assert_eq!(
+            Key::from_escape_sequence(b"\x1b[3;2~"),
+            Some(Key::Modified(Box::new(Key::Delete), Modifiers::SHIFT))
+        );
+        assert_eq!(
+            Key::from_escape_sequence(b"\x1b[5;5~").unwrap().modified(),
+            (Key::PageUp, Modifiers::CTRL)
+        );
+        assert_eq!(
+            Key::from_escape_sequence(b"\x1b[24;4~").unwrap().modified(),
+            (Key::F(12), Modifiers::SHIFT | Modifiers::ALT)
+        );
+        assert_eq!(
+            Key::from_escape_sequence(b"\x1b[1;3P").unwrap().modified(),
+            (Key::F(1), Modifiers::ALT)
+        );
+        // No modifiers (1) is the plain key
+        assert_eq!(Key::from_escape_sequence(b"\x1b[1;1A"), Some(Key::Up));
+
+        assert_eq!(Key::from_escape_sequence(b"\x1b[1;0C"), None);
+        assert_eq!(Key::from_escape_sequence(b"\x1b[9;5~"), None);
+        assert_eq!(Key::Ctrl('x').modified(), (Key::Char('x'), Modifiers::CTRL));
+    }
+
+    #[test]
+    fn test_function_and_keypad_keys() {
+        let f = |n: u8| Key::from_escape_sequence(format!("\x1b[{}~", n).as_bytes());
+        let keys: Vec<_> = [15, 17, 18, 19, 20, 21, 23, 24]
+            .map(f)
+            .into_iter()
+            .collect();
+        assert_eq!(keys, (5..=12).map(|n| Some(Key::F(n))).collect::<Vec<_>>());
+        assert_eq!(f(11), Some(Key::F(1)));
+        assert_eq!(f(16), None);
+        assert_eq!(f(22), None);
+
+        // xterm and vt220 Home/End variants
+        assert_eq!(f(1), Some(Key::Home));
+        assert_eq!(f(4), Some(Key::End));
+        assert_eq!(f(7), Some(Key::Home));
+        assert_eq!(f(8), Some(Key::End));
+        assert_eq!(Key::from_escape_sequence(b"\x1bOH"), Some(Key::Home));
+        assert_eq!(Key::from_escape_sequence(b"\x1bOF"), Some(Key::End));
+
+        // Application keypad
+        assert_eq!(Key::from_escape_sequence(b"\x1bOM"), Some(Key::Enter));
+        assert_eq!(Key::from_escape_sequence(b"\x1bOA"), Some(Key::Up));
+        assert_eq!(Key::from_escape_sequence(b"\x1bOD"), Some(Key::Left));
+        assert_eq!(Key::from_escape_sequence(b"\x1bOq"), Some(Key::Char('1')));
+        assert_eq!(Key::from_escape_sequence(b"\x1bOk"), Some(Key::Char('+')));
+    }
 }
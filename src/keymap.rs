@@ -0,0 +1,456 @@
+/// Input translation table
+///
+/// Terminals disagree about what bytes a key sends: Home may arrive as
+/// `ESC [ H`, `ESC [ 1 ~`, `ESC O H` or `ESC [ 7 ~`, Backspace as 0x7f or
+/// 0x08, and so on. A `KeyMap` normalizes these raw sequences to canonical
+/// `Key` values before the generic escape sequence parser runs.
+use crate::input::Key;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Sequences sent by common terminals (xterm, kitty, alacritty, tmux/screen,
+/// rxvt, the Linux console and Windows Terminal), in both normal and
+/// application cursor/keypad modes
+const DEFAULT_SEQUENCES: &[(&[u8], Key)] = &[
+    // Backspace: DEL on most terminals, BS on some (Windows Terminal, old xterm configs)
+    (b"\x7f", Key::Backspace),
+    (b"\x08", Key::Backspace),
+    (b"\r", Key::Enter),
+    (b"\n", Key::Enter),
+    (b"\t", Key::Tab),
+    // Arrows, normal and application cursor mode
+    (b"\x1b[A", Key::Up),
+    (b"\x1b[B", Key::Down),
+    (b"\x1b[C", Key::Right),
+    (b"\x1b[D", Key::Left),
+    (b"\x1bOA", Key::Up),
+    (b"\x1bOB", Key::Down),
+    (b"\x1bOC", Key::Right),
+    (b"\x1bOD", Key::Left),
+    // Home/End: xterm, vt220/tmux, application mode, rxvt
+    (b"\x1b[H", Key::Home),
+    (b"\x1b[F", Key::End),
+    (b"\x1b[1~", Key::Home),
+    (b"\x1b[4~", Key::End),
+    (b"\x1bOH", Key::Home),
+    (b"\x1bOF", Key::End),
+    (b"\x1b[7~", Key::Home),
+    (b"\x1b[8~", Key::End),
+    // Editing keypad
+    (b"\x1b[2~", Key::Insert),
+    (b"\x1b[3~", Key::Delete),
+    (b"\x1b[5~", Key::PageUp),
+    (b"\x1b[6~", Key::PageDown),
+    // Keypad Enter in application keypad mode
+    (b"\x1bOM", Key::Enter),
+    // F1-F4: SS3 (xterm), vt220/rxvt tilde form, Linux console
+    (b"\x1bOP", Key::F(1)),
+    (b"\x1bOQ", Key::F(2)),
+    (b"\x1bOR", Key::F(3)),
+    (b"\x1bOS", Key::F(4)),
+    (b"\x1b[11~", Key::F(1)),
+    (b"\x1b[12~", Key::F(2)),
+    (b"\x1b[13~", Key::F(3)),
+    (b"\x1b[14~", Key::F(4)),
+    (b"\x1b[[A", Key::F(1)),
+    (b"\x1b[[B", Key::F(2)),
+    (b"\x1b[[C", Key::F(3)),
+    (b"\x1b[[D", Key::F(4)),
+    (b"\x1b[[E", Key::F(5)),
+    // F5-F12 (note the gaps at 16 and 22)
+    (b"\x1b[15~", Key::F(5)),
+    (b"\x1b[17~", Key::F(6)),
+    (b"\x1b[18~", Key::F(7)),
+    (b"\x1b[19~", Key::F(8)),
+    (b"\x1b[20~", Key::F(9)),
+    (b"\x1b[21~", Key::F(10)),
+    (b"\x1b[23~", Key::F(11)),
+    (b"\x1b[24~", Key::F(12)),
+];
+
+/// Terminfo string capability indices for keys, with the key they produce
+const TERMINFO_KEYS: &[(usize, Key)] = &[
+    (55, Key::Backspace), // kbs
+    (59, Key::Delete),    // kdch1
+    (61, Key::Down),      // kcud1
+    (66, Key::F(1)),      // kf1
+    (67, Key::F(10)),     // kf10
+    (68, Key::F(2)),      // kf2
+    (69, Key::F(3)),      // kf3
+    (70, Key::F(4)),      // kf4
+    (71, Key::F(5)),      // kf5
+    (72, Key::F(6)),      // kf6
+    (73, Key::F(7)),      // kf7
+    (74, Key::F(8)),      // kf8
+    (75, Key::F(9)),      // kf9
+    (76, Key::Home),      // khome
+    (77, Key::Insert),    // kich1
+    (79, Key::Left),      // kcub1
+    (81, Key::PageDown),  // knp
+    (82, Key::PageUp),    // kpp
+    (83, Key::Right),     // kcuf1
+    (87, Key::Up),        // kcuu1
+    (164, Key::End),      // kend
+    (165, Key::Enter),    // kent
+    (216, Key::F(11)),    // kf11
+    (217, Key::F(12)),    // kf12
+];
+
+/// Translation table from raw input sequences to keys
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    entries: HashMap<Vec<u8>, Key>,
+}
+
+impl KeyMap {
+    /// Create an empty key map (every sequence falls through to the generic parser)
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Create a key map for the terminal named by `$TERM`
+    pub fn from_env() -> Self {
+        match std::env::var("TERM") {
+            Ok(term) => Self::for_terminal(&term),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Create a key map with the built-in table plus the terminal's terminfo
+    /// key capabilities, if a compiled entry for `term` can be found
+    pub fn for_terminal(term: &str) -> Self {
+        let mut map = Self::default();
+
+        if let Some(data) = find_terminfo(term).and_then(|path| std::fs::read(path).ok()) {
+            map.load_terminfo(&data);
+        }
+
+        map
+    }
+
+    /// Add key sequences from a compiled terminfo entry
+    ///
+    /// Returns false (leaving the map unchanged) if the data isn't a valid
+    /// terminfo entry.
+    pub fn load_terminfo(&mut self, data: &[u8]) -> bool {
+        let Some(strings) = parse_terminfo_strings(data) else {
+            return false;
+        };
+
+        for (index, key) in TERMINFO_KEYS {
+            if let Some(Some(seq)) = strings.get(*index)
+                && !seq.is_empty()
+            {
+                self.entries.insert(seq.clone(), key.clone());
+            }
+        }
+
+        true
+    }
+
+    /// Map a sequence to a key, replacing any existing mapping
+    pub fn insert(&mut self, seq: &[u8], key: Key) -> Option<Key> {
+        self.entries.insert(seq.to_vec(), key)
+    }
+
+    /// Remove the mapping for a sequence
+    pub fn remove(&mut self, seq: &[u8]) -> Option<Key> {
+        self.entries.remove(seq)
+    }
+
+    /// Look up the key for an exact sequence
+    pub fn lookup(&self, seq: &[u8]) -> Option<&Key> {
+        self.entries.get(seq)
+    }
+
+    /// Check if `seq` is a strict prefix of some mapped sequence
+    pub fn is_prefix(&self, seq: &[u8]) -> bool {
+        self.entries
+            .keys()
+            .any(|s| s.len() > seq.len() && s.starts_with(seq))
+    }
+
+    /// Number of mapped sequences
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for KeyMap {
+    /// The built-in table of sequences from common terminals
+    fn default() -> Self {
+        let mut map = Self::new();
+        for (seq, key) in DEFAULT_SEQUENCES {
+            map.insert(seq, key.clone());
+        }
+        map
+    }
+}
+
+/// Locate a compiled terminfo entry using the standard search path
+fn find_terminfo(term: &str) -> Option<PathBuf> {
+    let first = term.chars().next()?;
+    if term.contains('/') {
+        return None;
+    }
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(dir) = std::env::var("TERMINFO") {
+        dirs.push(dir.into());
+    }
+    if let Ok(home) = std::env::var("HOME") {
+        dirs.push(Path::new(&home).join(".terminfo"));
+    }
+    if let Ok(list) = std::env::var("TERMINFO_DIRS") {
+        dirs.extend(list.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    }
+    dirs.extend(
+        [
+            "/etc/terminfo",
+            "/lib/terminfo",
+            "/usr/share/terminfo",
+            "/usr/lib/terminfo",
+        ]
+        .map(PathBuf::from),
+    );
+
+    dirs.into_iter().find_map(|dir| {
+        // Entries live in a directory named by the first letter, or its hex
+        // code on case-insensitive filesystems (macOS)
+        [
+            dir.join(first.to_string()).join(term),
+            dir.join(format!("{:x}", first as u32)).join(term),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+    })
+}
+
+/// Parse the string capabilities of a compiled terminfo entry
+///
+/// Supports both the legacy (16-bit numbers) and extended (32-bit numbers)
+/// formats. Absent capabilities are `None`.
+fn parse_terminfo_strings(data: &[u8]) -> Option<Vec<Option<Vec<u8>>>> {
+    let read_i16 = |offset: usize| -> Option<i16> {
+        data.get(offset..offset + 2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+    };
+
+    let number_size = match read_i16(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+
+    let header: Vec<usize> = (1..6)
+        .map(|i| read_i16(i * 2).and_then(|v| usize::try_from(v).ok()))
+        .collect::<Option<_>>()?;
+    let (names_size, bool_count, num_count, str_count, table_size) =
+        (header[0], header[1], header[2], header[3], header[4]);
+
+    let mut offset = 12 + names_size + bool_count;
+    if offset % 2 == 1 {
+        offset += 1; // Numbers are aligned to an even byte
+    }
+    offset += num_count * number_size;
+
+    let offsets_start = offset;
+    let table_start = offsets_start + str_count * 2;
+    let table = data.get(table_start..table_start + table_size)?;
+
+    (0..str_count)
+        .map(|i| {
+            let pos = read_i16(offsets_start + i * 2)?;
+            if pos < 0 {
+                return Some(None); // Absent or cancelled
+            }
+
+            let rest = table.get(pos as usize..)?;
+            let end = rest.iter().position(|&b| b == 0)?;
+            Some(Some(rest[..end].to_vec()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sequences captured from real terminals, in default (non-kitty) mode
+    const CORPUS: &[(&str, &[u8], Key)] = &[
+        // xterm
+        ("xterm", b"\x1b[A", Key::Up),
+        ("xterm", b"\x1bOA", Key::Up),
+        ("xterm", b"\x1b[H", Key::Home),
+        ("xterm", b"\x1b[F", Key::End),
+        ("xterm", b"\x1bOH", Key::Home),
+        ("xterm", b"\x1bOF", Key::End),
+        ("xterm", b"\x1bOP", Key::F(1)),
+        ("xterm", b"\x1b[15~", Key::F(5)),
+        ("xterm", b"\x1b[24~", Key::F(12)),
+        ("xterm", b"\x1bOM", Key::Enter),
+        ("xterm", b"\x7f", Key::Backspace),
+        // kitty (legacy encoding)
+        ("kitty", b"\x1b[H", Key::Home),
+        ("kitty", b"\x1b[F", Key::End),
+        ("kitty", b"\x1b[3~", Key::Delete),
+        ("kitty", b"\x1bOQ", Key::F(2)),
+        ("kitty", b"\x1b[23~", Key::F(11)),
+        ("kitty", b"\x7f", Key::Backspace),
+        // alacritty
+        ("alacritty", b"\x1bOH", Key::Home),
+        ("alacritty", b"\x1bOF", Key::End),
+        ("alacritty", b"\x1b[5~", Key::PageUp),
+        ("alacritty", b"\x1b[6~", Key::PageDown),
+        ("alacritty", b"\x1b[2~", Key::Insert),
+        ("alacritty", b"\x7f", Key::Backspace),
+        // tmux (TERM=screen / tmux-256color)
+        ("tmux", b"\x1b[1~", Key::Home),
+        ("tmux", b"\x1b[4~", Key::End),
+        ("tmux", b"\x1bOA", Key::Up),
+        ("tmux", b"\x1bOD", Key::Left),
+        ("tmux", b"\x1bOP", Key::F(1)),
+        ("tmux", b"\x1b[19~", Key::F(8)),
+        // Windows Terminal
+        ("windows-terminal", b"\x1b[H", Key::Home),
+        ("windows-terminal", b"\x1b[F", Key::End),
+        ("windows-terminal", b"\x08", Key::Backspace),
+        ("windows-terminal", b"\r", Key::Enter),
+        ("windows-terminal", b"\x1b[21~", Key::F(10)),
+        // rxvt
+        ("rxvt", b"\x1b[7~", Key::Home),
+        ("rxvt", b"\x1b[8~", Key::End),
+        ("rxvt", b"\x1b[11~", Key::F(1)),
+        ("rxvt", b"\x1b[14~", Key::F(4)),
+        // Linux console
+        ("linux", b"\x1b[[A", Key::F(1)),
+        ("linux", b"\x1b[[E", Key::F(5)),
+        ("linux", b"\x1b[1~", Key::Home),
+    ];
+
+    #[test]
+    fn test_corpus() {
+        let map = KeyMap::default();
+        for (terminal, seq, key) in CORPUS {
+            assert_eq!(
+                map.lookup(seq),
+                Some(key),
+                "{} sequence {:?}",
+                terminal,
+                String::from_utf8_lossy(seq)
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut map = KeyMap::new();
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(b"\x1b[1;5A", Key::Ctrl('a')), None);
+        assert_eq!(map.lookup(b"\x1b[1;5A"), Some(&Key::Ctrl('a')));
+
+        assert_eq!(map.remove(b"\x1b[1;5A"), Some(Key::Ctrl('a')));
+        assert_eq!(map.lookup(b"\x1b[1;5A"), None);
+    }
+
+    #[test]
+    fn test_override_backspace() {
+        // Apps that want Ctrl+H distinct from Backspace can drop the mapping
+        let mut map = KeyMap::default();
+        map.remove(b"\x08");
+        assert_eq!(map.lookup(b"\x08"), None);
+        assert_eq!(map.lookup(b"\x7f"), Some(&Key::Backspace));
+    }
+
+    #[test]
+    fn test_is_prefix() {
+        let map = KeyMap::default();
+        assert!(map.is_prefix(b"\x1b"));
+        assert!(map.is_prefix(b"\x1b[1"));
+        assert!(!map.is_prefix(b"\x1b[1~"));
+        assert!(!map.is_prefix(b"x"));
+    }
+
+    /// Build a minimal legacy-format terminfo entry
+    fn terminfo(strings: &[(usize, &[u8])]) -> Vec<u8> {
+        let names = b"test|test terminal\0";
+        let bools = [1u8];
+        let numbers = [80i16, 24];
+        let str_count = strings.iter().map(|(i, _)| i + 1).max().unwrap_or(0);
+
+        let mut offsets = vec![-1i16; str_count];
+        let mut table = Vec::new();
+        for (index, value) in strings {
+            offsets[*index] = table.len() as i16;
+            table.extend_from_slice(value);
+            table.push(0);
+        }
+
+        let mut data = Vec::new();
+        for v in [
+            0o432,
+            names.len() as i16,
+            bools.len() as i16,
+            numbers.len() as i16,
+            str_count as i16,
+            table.len() as i16,
+        ] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(names);
+        data.extend_from_slice(&bools);
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+        for n in numbers {
+            data.extend_from_slice(&n.to_le_bytes());
+        }
+        for o in offsets {
+            data.extend_from_slice(&o.to_le_bytes());
+        }
+        data.extend_from_slice(&table);
+        data
+    }
+
+    #[test]
+    fn test_load_terminfo() {
+        let data = terminfo(&[(55, b"\x08"), (76, b"\x1b[99~"), (164, b"\x1b[98~")]);
+
+        let mut map = KeyMap::new();
+        assert!(map.load_terminfo(&data));
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.lookup(b"\x08"), Some(&Key::Backspace));
+        assert_eq!(map.lookup(b"\x1b[99~"), Some(&Key::Home));
+        assert_eq!(map.lookup(b"\x1b[98~"), Some(&Key::End));
+    }
+
+    #[test]
+    fn test_load_terminfo_invalid() {
+        let mut map = KeyMap::default();
+        let before = map.len();
+
+        assert!(!map.load_terminfo(b""));
+        assert!(!map.load_terminfo(b"not a terminfo entry"));
+
+        // Truncated string table
+        let mut data = terminfo(&[(76, b"\x1b[H")]);
+        data.truncate(data.len() - 2);
+        assert!(!map.load_terminfo(&data));
+
+        assert_eq!(map.len(), before);
+    }
+
+    #[test]
+    fn test_unknown_terminal_uses_defaults() {
+        let map = KeyMap::for_terminal("no-such-terminal-zaz");
+        assert_eq!(map.len(), KeyMap::default().len());
+    }
+}
@@ -1,9 +1,11 @@
-/// Kitty keyboard protocol support
-///
-/// The kitty keyboard protocol is a progressive enhancement protocol that allows
-/// terminals to report more detailed keyboard information.
-///
-/// Specification: https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+//! Kitty keyboard protocol support
+//!
+//! The kitty keyboard protocol is a progressive enhancement protocol that allows
+//! terminals to report more detailed keyboard information.
+//!
+//! Specification: https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+
+use crate::query::Reply;
 use bitflags::bitflags;
 
 bitflags! {
@@ -118,7 +120,11 @@ impl KeyEvent {
     }
 
     /// Parse Kitty keyboard protocol sequence
-    /// Format: CSI unicode ; modifiers ; event_type ; shifted_key ; base_layout_key u
+    ///
+    /// Layout: CSI unicode:shifted_key:base_layout_key ; modifiers:event_type ; text u
+    ///
+    /// Every field but the key code is optional. Text is a `:`-separated
+    /// list of code points.
     pub(crate) fn from_sequence(seq: &[u8]) -> Option<Self> {
         // Must start with ESC [ and end with 'u'
         if seq.len() < 4 || seq[0] != 27 || seq[1] != b'[' || seq[seq.len() - 1] != b'u' {
@@ -130,41 +136,32 @@ impl KeyEvent {
         let params_str = std::str::from_utf8(params).ok()?;
 
         let parts: Vec<&str> = params_str.split(';').collect();
-
-        if parts.is_empty() {
+        if parts.len() > 3 {
             return None;
         }
 
-        let code = parts[0].parse::<u32>().ok()?;
-
-        let modifiers = if parts.len() > 1 {
-            let mod_val = parts[1].parse::<u8>().ok()?;
-            Modifiers::from_bits(mod_val).unwrap_or_default()
-        } else {
-            Modifiers::empty()
-        };
-
-        let event_type = if parts.len() > 2 {
-            match parts[2].parse::<u8>().ok()? {
-                1 => KeyEventType::Press,
-                2 => KeyEventType::Repeat,
-                3 => KeyEventType::Release,
-                _ => KeyEventType::Press,
+        let mut keys = parts[0].split(':');
+        let code = keys.next()?.parse::<u32>().ok()?;
+        let shifted_key = parse_optional_code(keys.next());
+        let base_key = parse_optional_code(keys.next());
+
+        let mut mods = parts.get(1).copied().unwrap_or("").split(':');
+        let modifiers = match mods.next() {
+            // The field is 1 + the modifier bits
+            Some(value) if !value.is_empty() => {
+                let value = value.parse::<u16>().ok()?.saturating_sub(1);
+                Modifiers::from_bits_truncate(value as u8)
             }
-        } else {
-            KeyEventType::Press
+            _ => Modifiers::empty(),
         };
-
-        let shifted_key = if parts.len() > 3 && !parts[3].is_empty() {
-            parts[3].parse::<u32>().ok()
-        } else {
-            None
+        let event_type = match mods.next() {
+            Some(value) if !value.is_empty() => parse_event_type(value)?,
+            _ => KeyEventType::Press,
         };
 
-        let base_key = if parts.len() > 4 && !parts[4].is_empty() {
-            parts[4].parse::<u32>().ok()
-        } else {
-            None
+        let text = match parts.get(2) {
+            Some(field) if !field.is_empty() => Some(parse_text(field)?),
+            _ => None,
         };
 
         Some(KeyEvent {
@@ -173,11 +170,33 @@ impl KeyEvent {
             event_type,
             shifted_key,
             base_key,
-            text: None,
+            text,
         })
     }
 }
 
+/// Event type parameter: 1 press, 2 repeat, 3 release (others count as press)
+fn parse_event_type(value: &str) -> Option<KeyEventType> {
+    Some(match value.parse::<u8>().ok()? {
+        2 => KeyEventType::Repeat,
+        3 => KeyEventType::Release,
+        _ => KeyEventType::Press,
+    })
+}
+
+/// Optional key code (None when absent, empty or malformed)
+fn parse_optional_code(value: Option<&str>) -> Option<u32> {
+    value.and_then(|value| value.parse::<u32>().ok())
+}
+
+/// Associated text sent as `:`-separated code points
+fn parse_text(field: &str) -> Option<String> {
+    field
+        .split(':')
+        .map(|cp| char::from_u32(cp.parse::<u32>().ok()?))
+        .collect()
+}
+
 /// Generate escape sequence to enable Kitty keyboard protocol
 pub(crate) fn enable_sequence(flags: KittyFlags) -> String {
     format!("\x1b[>{flags}u", flags = flags.bits())
@@ -198,6 +217,26 @@ pub(crate) fn pop_sequence() -> String {
     "\x1b[<1u".to_string()
 }
 
+/// Query for the keyboard flags in effect (`CSI ? u`)
+pub(crate) const QUERY_SEQUENCE: &str = "\x1b[?u";
+
+/// Flags reported in reply to `QUERY_SEQUENCE` (`CSI ? flags u`), None if
+/// the terminal didn't answer
+pub(crate) fn flags_from_replies(replies: &[Reply]) -> Option<KittyFlags> {
+    replies.iter().find_map(|reply| match reply {
+        Reply::Csi(body) => {
+            let flags = body.strip_prefix('?')?.strip_suffix('u')?;
+            Some(KittyFlags::from_bits_truncate(flags.parse().ok()?))
+        }
+        _ => None,
+    })
+}
+
+/// Generate escape sequence to pop `count` keyboard modes at once
+pub(crate) fn pop_many_sequence(count: usize) -> String {
+    format!("\x1b[<{count}u")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,6 +250,23 @@ mod tests {
         assert_eq!(flags.bits(), 3);
     }
 
+    #[test]
+    fn test_flags_from_replies() {
+        let da1 = Reply::Csi("?62;22c".to_string());
+        assert_eq!(flags_from_replies(std::slice::from_ref(&da1)), None);
+
+        let replies = [Reply::Csi("?11u".to_string()), da1];
+        assert_eq!(
+            flags_from_replies(&replies),
+            Some(KittyFlags::DISAMBIGUATE | KittyFlags::EVENT_TYPES | KittyFlags::ALL_AS_ESCAPES)
+        );
+        // Supported, with no flags enabled yet
+        assert_eq!(
+            flags_from_replies(&[Reply::Csi("?0u".to_string())]),
+            Some(KittyFlags::empty())
+        );
+    }
+
     #[test]
     fn test_default_flags() {
         let flags = KittyFlags::default();
@@ -256,8 +312,8 @@ mod tests {
 
     #[test]
     fn test_parse_sequence_with_modifiers() {
-        // ESC [ 65 ; 5 u ('A' with Ctrl+Shift, modifier value 1+4=5)
-        let seq = b"\x1b[65;5u";
+        // ESC [ 65 ; 6 u ('A' with Ctrl+Shift, modifier value 1+1+4=6)
+        let seq = b"\x1b[65;6u";
         let event = KeyEvent::from_sequence(seq).unwrap();
         assert_eq!(event.code, 65);
         assert!(event.is_ctrl());
@@ -267,8 +323,8 @@ mod tests {
 
     #[test]
     fn test_parse_sequence_with_event_type() {
-        // ESC [ 65 ; 5 ; 2 u ('A' with Ctrl+Shift, repeat event)
-        let seq = b"\x1b[65;5;2u";
+        // ESC [ 65 ; 6:2 u ('A' with Ctrl+Shift, repeat event)
+        let seq = b"\x1b[65;6:2u";
         let event = KeyEvent::from_sequence(seq).unwrap();
         assert_eq!(event.code, 65);
         assert!(event.is_ctrl());
@@ -278,8 +334,8 @@ mod tests {
 
     #[test]
     fn test_parse_sequence_with_release() {
-        // ESC [ 65 ; 0 ; 3 u ('A' release event)
-        let seq = b"\x1b[65;0;3u";
+        // ESC [ 65 ; 1:3 u ('A' release event)
+        let seq = b"\x1b[65;1:3u";
         let event = KeyEvent::from_sequence(seq).unwrap();
         assert_eq!(event.code, 65);
         assert_eq!(event.event_type, KeyEventType::Release);
@@ -287,14 +343,32 @@ mod tests {
 
     #[test]
     fn test_parse_sequence_with_shifted_key() {
-        // ESC [ 97 ; 1 ; 1 ; 65 u ('a' with shift, shifted to 'A')
-        let seq = b"\x1b[97;1;1;65u";
+        // ESC [ 97:65 ; 2 u ('a' with shift, shifted to 'A')
+        let seq = b"\x1b[97:65;2u";
         let event = KeyEvent::from_sequence(seq).unwrap();
         assert_eq!(event.code, 97);
         assert!(event.is_shift());
         assert_eq!(event.shifted_key, Some(65));
     }
 
+    #[test]
+    fn test_modifier_field_offset() {
+        // Ctrl+S: 1 + 4
+        let event = KeyEvent::from_sequence(b"\x1b[115;5u").unwrap();
+        assert_eq!(event.modifiers, Modifiers::CTRL);
+
+        // 1 alone means no modifiers
+        let event = KeyEvent::from_sequence(b"\x1b[115;1u").unwrap();
+        assert_eq!(event.modifiers, Modifiers::empty());
+
+        // Caps Lock and Num Lock are reported as modifiers too
+        let event = KeyEvent::from_sequence(b"\x1b[115;197u").unwrap();
+        assert_eq!(
+            event.modifiers,
+            Modifiers::CTRL | Modifiers::CAPS_LOCK | Modifiers::NUM_LOCK
+        );
+    }
+
     #[test]
     fn test_parse_invalid_sequence() {
         assert!(KeyEvent::from_sequence(b"").is_none());
@@ -341,4 +415,49 @@ mod tests {
         assert_ne!(KeyEventType::Press, KeyEventType::Release);
         assert_ne!(KeyEventType::Repeat, KeyEventType::Release);
     }
+
+    #[test]
+    fn test_parse_alternate_keys() {
+        // Shift+a on a US layout: code, shifted key, base layout key
+        let event = KeyEvent::from_sequence(b"\x1b[97:65:97;2u").unwrap();
+        assert_eq!(event.code, 97);
+        assert!(event.is_shift());
+        assert_eq!(event.shifted_key, Some(65));
+        assert_eq!(event.base_key, Some(97));
+
+        // Cyrillic layout: no shifted key, base key is the US 'q'
+        let event = KeyEvent::from_sequence(b"\x1b[1081::113u").unwrap();
+        assert_eq!(event.shifted_key, None);
+        assert_eq!(event.base_key, Some(113));
+
+        // Modifiers with event type as a sub-parameter
+        let event = KeyEvent::from_sequence(b"\x1b[97;5:3u").unwrap();
+        assert!(event.is_ctrl());
+        assert_eq!(event.event_type, KeyEventType::Release);
+    }
+
+    #[test]
+    fn test_parse_associated_text() {
+        let event = KeyEvent::from_sequence(b"\x1b[97;;97u").unwrap();
+        assert_eq!(event.modifiers, Modifiers::empty());
+        assert_eq!(event.text.as_deref(), Some("a"));
+
+        // Composed text from an IME spans several code points
+        let event = KeyEvent::from_sequence(b"\x1b[101:69;1:1;69:769u").unwrap();
+        assert_eq!(event.text.as_deref(), Some("E\u{301}"));
+
+        // Modified keys carry text too, without alternate keys (flags 8|16)
+        let event = KeyEvent::from_sequence(b"\x1b[97;2;65u").unwrap();
+        assert!(event.is_shift());
+        assert_eq!(event.event_type, KeyEventType::Press);
+        assert_eq!(event.text.as_deref(), Some("A"));
+
+        let event = KeyEvent::from_sequence(b"\x1b[101;2;69:769u").unwrap();
+        assert!(event.is_shift());
+        assert_eq!(event.shifted_key, None);
+        assert_eq!(event.text.as_deref(), Some("E\u{301}"));
+
+        assert!(KeyEvent::from_sequence(b"\x1b[97;;x1u").is_none());
+        assert!(KeyEvent::from_sequence(b"\x1b[97;;55296u").is_none());
+    }
 }
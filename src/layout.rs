@@ -0,0 +1,301 @@
+//! Rectangular screen regions and splitting them into parts
+
+/// A rectangle in cell coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Rect {
+    pub y: u16,
+    pub x: u16,
+    pub height: u16,
+    pub width: u16,
+}
+
+impl Rect {
+    /// Create a rectangle (argument order matches `newwin`'s position then size)
+    pub const fn new(y: u16, x: u16, height: u16, width: u16) -> Self {
+        Self {
+            y,
+            x,
+            height,
+            width,
+        }
+    }
+
+    /// Row just below the rectangle
+    pub const fn bottom(&self) -> u16 {
+        self.y.saturating_add(self.height)
+    }
+
+    /// Column just right of the rectangle
+    pub const fn right(&self) -> u16 {
+        self.x.saturating_add(self.width)
+    }
+
+    /// Number of cells covered
+    pub const fn area(&self) -> usize {
+        self.height as usize * self.width as usize
+    }
+
+    /// Check if the rectangle covers no cells
+    pub const fn is_empty(&self) -> bool {
+        self.height == 0 || self.width == 0
+    }
+
+    /// Check if a cell lies inside the rectangle
+    pub const fn contains(&self, y: u16, x: u16) -> bool {
+        y >= self.y && y < self.bottom() && x >= self.x && x < self.right()
+    }
+
+    /// Overlapping part of two rectangles (empty if they don't overlap)
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let y = self.y.max(other.y);
+        let x = self.x.max(other.x);
+        let bottom = self.bottom().min(other.bottom());
+        let right = self.right().min(other.right());
+
+        Rect::new(y, x, bottom.saturating_sub(y), right.saturating_sub(x))
+    }
+
+    /// Rectangle of the given size centered in this one (shrunk to fit)
+    pub fn centered(&self, height: u16, width: u16) -> Rect {
+        let height = height.min(self.height);
+        let width = width.min(self.width);
+        Rect::new(
+            self.y + (self.height - height) / 2,
+            self.x + (self.width - width) / 2,
+            height,
+            width,
+        )
+    }
+}
+
+/// Axis along which `Layout` places its parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Side by side, left to right
+    Horizontal,
+    /// Stacked, top to bottom
+    Vertical,
+}
+
+/// Size of one part of a `Layout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// Exactly this many cells
+    Fixed(u16),
+    /// This percentage of the space
+    Percent(u16),
+    /// At least this many cells, growing like `Weight(1)`
+    Min(u16),
+    /// A share of the space left by the other parts
+    Weight(u16),
+}
+
+/// Splits a rectangle into parts by constraints
+///
+/// ```
+/// use zaz::{Constraint, Layout, Rect};
+///
+/// let parts = Layout::vertical([
+///     Constraint::Fixed(1),
+///     Constraint::Min(5),
+///     Constraint::Fixed(1),
+/// ])
+/// .split(Rect::new(0, 0, 24, 80));
+/// assert_eq!(parts[1], Rect::new(1, 0, 22, 80));
+/// assert_eq!(parts[2], Rect::new(23, 0, 1, 80));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    direction: Direction,
+    constraints: Vec<Constraint>,
+    margin: u16,
+    spacing: u16,
+}
+
+impl Layout {
+    /// Parts side by side
+    pub fn horizontal(constraints: impl IntoIterator<Item = Constraint>) -> Self {
+        Self::new(Direction::Horizontal, constraints)
+    }
+
+    /// Parts stacked vertically
+    pub fn vertical(constraints: impl IntoIterator<Item = Constraint>) -> Self {
+        Self::new(Direction::Vertical, constraints)
+    }
+
+    /// Parts along `direction`
+    pub fn new(direction: Direction, constraints: impl IntoIterator<Item = Constraint>) -> Self {
+        Self {
+            direction,
+            constraints: constraints.into_iter().collect(),
+            margin: 0,
+            spacing: 0,
+        }
+    }
+
+    /// Empty cells kept around all parts
+    pub fn margin(mut self, margin: u16) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Empty cells between neighbouring parts
+    pub fn spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// One rectangle per constraint, in order
+    ///
+    /// Fixed, percentage and minimum sizes are taken first; the rest is
+    /// shared by the `Min` and `Weight` parts. If the space runs out,
+    /// the last parts shrink (down to empty).
+    pub fn split(&self, area: Rect) -> Vec<Rect> {
+        let inner = Rect::new(
+            area.y.saturating_add(self.margin),
+            area.x.saturating_add(self.margin),
+            area.height.saturating_sub(self.margin.saturating_mul(2)),
+            area.width.saturating_sub(self.margin.saturating_mul(2)),
+        );
+        let (start, length) = match self.direction {
+            Direction::Horizontal => (inner.x, inner.width),
+            Direction::Vertical => (inner.y, inner.height),
+        };
+
+        let count = self.constraints.len() as u16;
+        let gaps = self.spacing.saturating_mul(count.saturating_sub(1));
+        let space = length.saturating_sub(gaps) as u32;
+
+        let mut sizes: Vec<u32> = self
+            .constraints
+            .iter()
+            .map(|c| match *c {
+                Constraint::Fixed(n) | Constraint::Min(n) => n as u32,
+                Constraint::Percent(p) => space * p.min(100) as u32 / 100,
+                Constraint::Weight(_) => 0,
+            })
+            .collect();
+
+        let weights: Vec<u32> = self
+            .constraints
+            .iter()
+            .map(|c| match *c {
+                Constraint::Min(_) => 1,
+                Constraint::Weight(w) => w as u32,
+                _ => 0,
+            })
+            .collect();
+        let total_weight: u32 = weights.iter().sum();
+        let left = space.saturating_sub(sizes.iter().sum());
+
+        if total_weight > 0 && left > 0 {
+            let mut given = 0;
+            for (size, &weight) in sizes.iter_mut().zip(&weights) {
+                let share = left * weight / total_weight;
+                *size += share;
+                given += share;
+            }
+            // Rounding leftovers go to the first flexible parts
+            for (size, _) in sizes.iter_mut().zip(&weights).filter(|(_, w)| **w > 0) {
+                if given == left {
+                    break;
+                }
+                *size += 1;
+                given += 1;
+            }
+        }
+
+        let end = start as u32 + length as u32;
+        let mut pos = start as u32;
+        sizes
+            .iter()
+            .map(|&size| {
+                let size = size.min(end - pos);
+                let (offset, size16) = (pos as u16, size as u16);
+                pos = (pos + size + self.spacing as u32).min(end);
+                match self.direction {
+                    Direction::Horizontal => Rect::new(inner.y, offset, inner.height, size16),
+                    Direction::Vertical => Rect::new(offset, inner.x, size16, inner.width),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_bounds() {
+        let rect = Rect::new(2, 3, 4, 5);
+        assert_eq!(rect.bottom(), 6);
+        assert_eq!(rect.right(), 8);
+        assert_eq!(rect.area(), 20);
+        assert!(rect.contains(2, 3));
+        assert!(rect.contains(5, 7));
+        assert!(!rect.contains(6, 7));
+        assert!(!rect.contains(2, 8));
+        assert!(Rect::new(0, 0, 0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_rect_intersection() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, 8, 10, 10);
+        assert_eq!(a.intersection(&b), Rect::new(5, 8, 5, 2));
+
+        let disjoint = Rect::new(20, 20, 2, 2);
+        assert!(a.intersection(&disjoint).is_empty());
+    }
+
+    #[test]
+    fn test_rect_centered() {
+        let area = Rect::new(2, 4, 10, 20);
+        assert_eq!(area.centered(4, 8), Rect::new(5, 10, 4, 8));
+        assert_eq!(area.centered(5, 9), Rect::new(4, 9, 5, 9));
+        assert_eq!(area.centered(30, 30), area);
+    }
+
+    #[test]
+    fn test_split_constraints() {
+        let area = Rect::new(0, 0, 10, 100);
+        let parts = Layout::horizontal([
+            Constraint::Fixed(10),
+            Constraint::Percent(20),
+            Constraint::Weight(2),
+            Constraint::Min(5),
+        ])
+        .split(area);
+
+        // 100 - 10 - 20 - 5 = 65 shared 2:1 with the Min part
+        let widths: Vec<u16> = parts.iter().map(|r| r.width).collect();
+        assert_eq!(widths, vec![10, 20, 44, 26]);
+        assert_eq!(parts[2].x, 30);
+        assert_eq!(parts[3].right(), 100);
+    }
+
+    #[test]
+    fn test_split_margin_spacing_and_overflow() {
+        let parts = Layout::vertical([Constraint::Weight(1), Constraint::Weight(1)])
+            .margin(1)
+            .spacing(1)
+            .split(Rect::new(0, 0, 10, 20));
+        assert_eq!(parts, vec![Rect::new(1, 1, 4, 18), Rect::new(6, 1, 3, 18)]);
+
+        // Not enough room: later parts shrink
+        let parts = Layout::vertical([Constraint::Fixed(4), Constraint::Fixed(4)])
+            .split(Rect::new(0, 0, 6, 10));
+        assert_eq!(parts[1], Rect::new(4, 0, 2, 10));
+
+        let parts = Layout::vertical([Constraint::Fixed(4), Constraint::Fixed(4)])
+            .split(Rect::new(0, 0, 3, 10));
+        assert!(parts[1].is_empty());
+
+        // A margin wider than the area leaves nothing
+        let parts = Layout::vertical([Constraint::Weight(1)])
+            .margin(u16::MAX)
+            .split(Rect::new(0, 0, 10, 20));
+        assert!(parts[0].is_empty());
+    }
+}
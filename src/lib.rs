@@ -16,24 +16,55 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
+mod a11y;
 mod acs;
+mod ansi;
 mod attr;
 mod backend;
+mod buffer;
+mod builder;
+mod caps;
 mod cell;
+mod clock;
 mod color;
+#[cfg(feature = "debug-console")]
+mod console;
 mod delta;
+mod effects;
 mod error;
+mod escape;
+mod export;
 mod image;
 mod input;
+mod keymap;
 mod kitty;
+mod layout;
+mod macros;
+mod monitor;
 mod mosaic;
+mod mouse;
 mod panel;
+mod paste;
 mod platform_io;
+mod query;
+mod render;
 mod screen;
+mod sgr;
+mod shortcut;
+mod signals;
+mod slk;
+mod snapshot;
+mod stats;
+mod style;
+mod text;
+mod tuning;
+mod wakeup;
+mod widgets;
 mod window;
 
 pub mod ffi;
 
+pub use a11y::{Announcement, Priority};
 pub use acs::{
     ACS_BLOCK, ACS_BOARD, ACS_BTEE, ACS_BULLET, ACS_CKBOARD, ACS_DARROW, ACS_DEGREE, ACS_DIAMOND,
     ACS_GEQUAL, ACS_HLINE, ACS_LANTERN, ACS_LARROW, ACS_LEQUAL, ACS_LLCORNER, ACS_LRCORNER,
@@ -41,23 +72,49 @@ pub use acs::{
     ACS_S7, ACS_S9, ACS_STERLING, ACS_TTEE, ACS_UARROW, ACS_ULCORNER, ACS_URCORNER, ACS_VLINE,
     AcsChar,
 };
-pub use attr::Attr;
+pub use attr::{Attr, AttrPolicy};
+pub use builder::{NonTty, ScreenBuilder};
+pub use caps::{Capabilities, Notify, Passthrough};
 pub use cell::Cell;
-pub use color::{Color, ColorPair};
+pub use clock::{Clock, Rng};
+pub use color::{Color, ColorPair, Gradient};
+#[cfg(feature = "debug-console")]
+pub use console::DebugConsole;
 pub use error::{Error, Result};
+pub use export::{ExportFormat, export};
 pub use image::{ImageFormat, ImagePlacement, ImageProtocol, KittyImage, SixelImage};
-pub use input::Key;
+pub use input::{InputEvent, Key};
+pub use keymap::KeyMap;
 pub use kitty::{KeyEvent, KeyEventType, KittyFlags, Modifiers};
-pub use mosaic::{MosaicConfig, SymbolSet, render_mosaic};
-pub use panel::Panel;
-pub use screen::Screen;
+pub use layout::{Constraint, Direction, Layout, Rect};
+pub use monitor::{ActivityMonitor, MonitorEvent};
+pub use mosaic::{Dither, MosaicConfig, MosaicMode, Resample, SymbolSet, render_mosaic};
+pub use mouse::{MouseButton, MouseEvent, MouseEventKind, PointerShape};
+pub use panel::{Panel, PanelId, PanelManager};
+pub use query::{DefaultColors, TerminalIdentity};
+pub use render::{DrawHandle, Renderer};
+pub use screen::{RefreshOrder, Screen};
+pub use shortcut::Shortcut;
+pub use slk::{Justify, SlkLayout, SoftLabels};
+pub use snapshot::Snapshot;
+pub use stats::RefreshStats;
+pub use style::{Style, Theme};
+pub use text::{Line, Span};
+pub use tuning::RefreshProfile;
+pub use wakeup::ShutdownHandle;
+pub use widgets::{
+    BarChart, Canvas, Column, Dialog, FilePicker, InputDialog, InputLine, ItemState, LineChart,
+    ListView, MessageBox, ProgressBar, Sparkline, Spinner, TabBar, Table, TextArea, TextView,
+    confirm, file_picker, input_dialog, message_box, render_scrollbar, run_dialog,
+};
 pub use window::Window;
 
 // Re-export internal modules for benchmarking purposes
 #[doc(hidden)]
 pub mod __bench {
+    pub use crate::buffer::Buffer;
     pub use crate::cell::Cell;
-    pub use crate::delta::{DirtyRegion, detect_scrolls, find_line_diff, hash_line};
+    pub use crate::delta::{DirtyRegion, detect_scrolls, find_line_diff, hash_line, rehash_span};
 }
 
 // Re-export I/O functions for benchmarking
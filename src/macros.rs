@@ -0,0 +1,34 @@
+//! Formatting macros
+//!
+//! `printw!` and `mvprintw!` format like `format!` but write straight into
+//! a `Screen` or `Window`, reusing a buffer the target keeps instead of
+//! allocating a `String` per call.
+
+/// Print formatted text at the cursor of a `Screen` or `Window`
+///
+/// ```no_run
+/// # let mut scr = zaz::Screen::init()?;
+/// let frames = 42;
+/// zaz::printw!(scr, "frames: {frames}")?;
+/// # Ok::<(), zaz::Error>(())
+/// ```
+#[macro_export]
+macro_rules! printw {
+    ($target:expr, $($arg:tt)*) => {
+        $target.printw(::std::format_args!($($arg)*))
+    };
+}
+
+/// Move the cursor of a `Screen` or `Window` and print formatted text
+///
+/// ```no_run
+/// # let mut scr = zaz::Screen::init()?;
+/// zaz::mvprintw!(scr, 0, 0, "{:>5.1}%", 99.5)?;
+/// # Ok::<(), zaz::Error>(())
+/// ```
+#[macro_export]
+macro_rules! mvprintw {
+    ($target:expr, $y:expr, $x:expr, $($arg:tt)*) => {
+        $target.mvprintw($y, $x, ::std::format_args!($($arg)*))
+    };
+}
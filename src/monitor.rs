@@ -0,0 +1,283 @@
+//! Bell and activity detection for embedded terminal output
+//!
+//! Multiplexer-style UIs host other programs in panes and want to flag panes
+//! that rang the bell, posted a notification or printed something while not
+//! focused. Feed a pane's pty output through an `ActivityMonitor` to get
+//! those events without interpreting the full terminal stream.
+
+/// Longest OSC payload kept for parsing; longer strings are skipped
+const MAX_OSC_LEN: usize = 4096;
+
+/// Event detected in a pane's output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorEvent {
+    /// Visible output arrived (reported once until `clear_activity`)
+    Activity,
+    /// BEL outside of a control string
+    Bell,
+    /// Desktop notification request (OSC 9 or OSC 777;notify)
+    Notification { title: String, body: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+    Osc,
+    OscEscape,
+    /// DCS, APC, PM, SOS: ignored up to ST
+    String,
+    StringEscape,
+}
+
+/// Streaming scanner for bells, notifications and activity
+#[derive(Debug, Clone)]
+pub struct ActivityMonitor {
+    state: State,
+    osc: Vec<u8>,
+    osc_overflow: bool,
+    active: bool,
+}
+
+impl ActivityMonitor {
+    /// Create a monitor with no activity recorded
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            osc: Vec::new(),
+            osc_overflow: false,
+            active: false,
+        }
+    }
+
+    /// Scan a chunk of output, returning the events it contains
+    ///
+    /// Sequences may be split across chunks.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<MonitorEvent> {
+        let mut events = Vec::new();
+
+        for &byte in data {
+            match self.state {
+                State::Ground => match byte {
+                    0x07 => events.push(MonitorEvent::Bell),
+                    0x1b => self.state = State::Escape,
+                    // Printable text (including UTF-8 continuation bytes)
+                    b if b >= 0x20 && b != 0x7f => self.mark_active(&mut events),
+                    _ => {}
+                },
+                State::Escape => self.state = self.escape(byte),
+                State::Csi => {
+                    if (0x40..=0x7e).contains(&byte) {
+                        self.state = State::Ground;
+                    } else if byte == 0x1b {
+                        self.state = State::Escape;
+                    }
+                }
+                State::Osc => match byte {
+                    0x07 => self.finish_osc(&mut events),
+                    0x1b => self.state = State::OscEscape,
+                    _ => {
+                        if self.osc.len() < MAX_OSC_LEN {
+                            self.osc.push(byte);
+                        } else {
+                            self.osc_overflow = true;
+                        }
+                    }
+                },
+                State::OscEscape => {
+                    if byte == b'\\' {
+                        self.finish_osc(&mut events);
+                    } else {
+                        // Aborted OSC: the ESC starts a new sequence
+                        self.state = self.escape(byte);
+                    }
+                }
+                State::String => {
+                    if byte == 0x1b {
+                        self.state = State::StringEscape;
+                    } else if byte == 0x07 {
+                        self.state = State::Ground;
+                    }
+                }
+                State::StringEscape => {
+                    self.state = if byte == b'\\' {
+                        State::Ground
+                    } else {
+                        State::String
+                    };
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Check if activity was seen since the last `clear_activity`
+    pub fn has_activity(&self) -> bool {
+        self.active
+    }
+
+    /// Forget recorded activity (e.g. when the pane gets focus)
+    pub fn clear_activity(&mut self) {
+        self.active = false;
+    }
+
+    /// State after ESC followed by `byte`
+    fn escape(&mut self, byte: u8) -> State {
+        match byte {
+            b'[' => State::Csi,
+            b']' => {
+                self.osc.clear();
+                self.osc_overflow = false;
+                State::Osc
+            }
+            b'P' | b'_' | b'^' | b'X' => State::String,
+            0x1b => State::Escape,
+            _ => State::Ground,
+        }
+    }
+
+    fn mark_active(&mut self, events: &mut Vec<MonitorEvent>) {
+        if !self.active {
+            self.active = true;
+            events.push(MonitorEvent::Activity);
+        }
+    }
+
+    fn finish_osc(&mut self, events: &mut Vec<MonitorEvent>) {
+        self.state = State::Ground;
+        if self.osc_overflow {
+            return;
+        }
+
+        let payload = String::from_utf8_lossy(&self.osc);
+        let mut parts = payload.splitn(2, ';');
+
+        match (parts.next(), parts.next()) {
+            // OSC 9 ; body (iTerm2/ConEmu style; 9;4 is a progress report)
+            (Some("9"), Some(body)) if !body.starts_with("4;") => {
+                events.push(MonitorEvent::Notification {
+                    title: String::new(),
+                    body: body.to_string(),
+                });
+            }
+            // OSC 777 ; notify ; title ; body (rxvt/VTE style)
+            (Some("777"), Some(rest)) => {
+                let mut fields = rest.splitn(3, ';');
+                if fields.next() == Some("notify") {
+                    events.push(MonitorEvent::Notification {
+                        title: fields.next().unwrap_or_default().to_string(),
+                        body: fields.next().unwrap_or_default().to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for ActivityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bell() {
+        let mut monitor = ActivityMonitor::new();
+        assert_eq!(monitor.feed(b"\x07"), vec![MonitorEvent::Bell]);
+        assert!(!monitor.has_activity());
+    }
+
+    #[test]
+    fn test_activity_reported_once() {
+        let mut monitor = ActivityMonitor::new();
+        assert_eq!(monitor.feed(b"hello"), vec![MonitorEvent::Activity]);
+        assert!(monitor.feed(b" world\r\n").is_empty());
+        assert!(monitor.has_activity());
+
+        monitor.clear_activity();
+        assert_eq!(monitor.feed(b"again"), vec![MonitorEvent::Activity]);
+    }
+
+    #[test]
+    fn test_control_sequences_are_not_activity() {
+        let mut monitor = ActivityMonitor::new();
+        assert!(monitor.feed(b"\x1b[2J\x1b[1;1H\x1b[0m\r\n").is_empty());
+        assert!(monitor.feed(b"\x1b]0;window title\x07").is_empty());
+        assert!(monitor.feed(b"\x1bP+q544e\x1b\\").is_empty());
+        assert!(!monitor.has_activity());
+    }
+
+    #[test]
+    fn test_bel_terminating_osc_is_not_a_bell() {
+        let mut monitor = ActivityMonitor::new();
+        assert!(monitor.feed(b"\x1b]2;title\x07").is_empty());
+        assert_eq!(
+            monitor.feed(b"\x1b]2;title\x1b\\\x07"),
+            vec![MonitorEvent::Bell]
+        );
+    }
+
+    #[test]
+    fn test_osc9_notification() {
+        let mut monitor = ActivityMonitor::new();
+        assert_eq!(
+            monitor.feed(b"\x1b]9;Build finished\x1b\\"),
+            vec![MonitorEvent::Notification {
+                title: String::new(),
+                body: "Build finished".to_string()
+            }]
+        );
+
+        // Progress reports aren't notifications
+        assert!(monitor.feed(b"\x1b]9;4;1;50\x07").is_empty());
+    }
+
+    #[test]
+    fn test_osc777_notification() {
+        let mut monitor = ActivityMonitor::new();
+        assert_eq!(
+            monitor.feed(b"\x1b]777;notify;make;done; 0 errors\x07"),
+            vec![MonitorEvent::Notification {
+                title: "make".to_string(),
+                body: "done; 0 errors".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_split_across_chunks() {
+        let mut monitor = ActivityMonitor::new();
+        let input = b"\x1b[31m\x1b]9;hi\x1b\\\x07ok";
+
+        let events: Vec<_> = input.iter().flat_map(|b| monitor.feed(&[*b])).collect();
+        assert_eq!(
+            events,
+            vec![
+                MonitorEvent::Notification {
+                    title: String::new(),
+                    body: "hi".to_string()
+                },
+                MonitorEvent::Bell,
+                MonitorEvent::Activity,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oversized_osc_ignored() {
+        let mut monitor = ActivityMonitor::new();
+        let mut input = b"\x1b]9;".to_vec();
+        input.extend(std::iter::repeat_n(b'x', MAX_OSC_LEN * 2));
+        input.push(0x07);
+
+        assert!(monitor.feed(&input).is_empty());
+        assert_eq!(monitor.feed(b"\x07"), vec![MonitorEvent::Bell]);
+    }
+}
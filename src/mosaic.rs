@@ -2,7 +2,9 @@
 ///
 /// Converts raw pixel data into terminal-displayable Unicode art using
 /// block characters (▀▄█ etc.) with ANSI color codes.
+use smallvec::SmallVec;
 use std::fmt::Write;
+use std::sync::OnceLock;
 
 /// Unicode block character with coverage information
 #[derive(Debug, Clone, Copy)]
@@ -97,6 +99,39 @@ pub enum SymbolSet {
     All,
 }
 
+/// How pixel colors are mapped onto block characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MosaicMode {
+    /// Split each 2x2 block by a luminance threshold (high contrast, loses gradients)
+    Threshold,
+    /// Keep full color: half blocks take the top pixels as fg and the bottom
+    /// pixels as bg of '▀', while Quarter/All pick the block whose fg/bg split
+    /// has the smallest least-squares color error
+    Color,
+}
+
+/// Resampling filter used to scale the source image to the output grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resample {
+    /// Nearest neighbor (fast, aliases badly when downscaling)
+    Nearest,
+    /// Bilinear (triangle) filter widened to the scale factor
+    Bilinear,
+    /// Lanczos filter with a 3-lobe window (sharpest, slowest)
+    Lanczos3,
+}
+
+/// Dithering applied when thresholding pixels (only used by `MosaicMode::Threshold`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dither {
+    /// Plain threshold
+    None,
+    /// 4x4 Bayer matrix
+    Ordered,
+    /// Floyd–Steinberg error diffusion
+    FloydSteinberg,
+}
+
 /// Configuration for mosaic rendering
 #[derive(Debug, Clone)]
 pub struct MosaicConfig {
@@ -108,6 +143,14 @@ pub struct MosaicConfig {
     pub threshold: u8,
     /// Which symbol set to use
     pub symbols: SymbolSet,
+    /// Color mapping mode
+    pub mode: MosaicMode,
+    /// Resampling filter for scaling
+    pub resample: Resample,
+    /// Dithering used for thresholding
+    pub dither: Dither,
+    /// Scale and average colors in linear light instead of raw sRGB values
+    pub gamma_correct: bool,
 }
 
 impl Default for MosaicConfig {
@@ -117,6 +160,10 @@ impl Default for MosaicConfig {
             height: 0,
             threshold: 128,
             symbols: SymbolSet::Half,
+            mode: MosaicMode::Threshold,
+            resample: Resample::Bilinear,
+            dither: Dither::None,
+            gamma_correct: true,
         }
     }
 }
@@ -147,6 +194,30 @@ impl MosaicConfig {
         self.symbols = symbols;
         self
     }
+
+    /// Set color mapping mode
+    pub fn mode(mut self, mode: MosaicMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set resampling filter
+    pub fn resample(mut self, resample: Resample) -> Self {
+        self.resample = resample;
+        self
+    }
+
+    /// Set dithering method
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Enable or disable gamma-correct scaling and averaging
+    pub fn gamma_correct(mut self, enabled: bool) -> Self {
+        self.gamma_correct = enabled;
+        self
+    }
 }
 
 /// RGB color
@@ -180,6 +251,14 @@ impl Rgb {
     }
 }
 
+/// Squared Euclidean distance between two colors
+fn color_distance_sq(a: Rgb, b: Rgb) -> u32 {
+    let dr = a.r as i32 - b.r as i32;
+    let dg = a.g as i32 - b.g as i32;
+    let db = a.b as i32 - b.b as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
 /// Average multiple RGB colors
 fn average_colors(colors: &[Rgb]) -> Rgb {
     if colors.is_empty() {
@@ -204,6 +283,67 @@ fn average_colors(colors: &[Rgb]) -> Rgb {
     )
 }
 
+/// Average colors in linear light, which keeps mixes of dark and bright
+/// pixels from turning out too dark
+fn average_colors_linear(colors: &[Rgb]) -> Rgb {
+    if colors.is_empty() {
+        return Rgb::new(0, 0, 0);
+    }
+
+    let lut = srgb_to_linear_lut();
+    let mut sum = [0f32; 3];
+
+    for c in colors {
+        sum[0] += lut[c.r as usize];
+        sum[1] += lut[c.g as usize];
+        sum[2] += lut[c.b as usize];
+    }
+
+    let count = colors.len() as f32;
+    Rgb::new(
+        linear_to_srgb(sum[0] / count),
+        linear_to_srgb(sum[1] / count),
+        linear_to_srgb(sum[2] / count),
+    )
+}
+
+/// Average colors, in linear light if requested
+fn mix_colors(colors: &[Rgb], gamma_correct: bool) -> Rgb {
+    if gamma_correct {
+        average_colors_linear(colors)
+    } else {
+        average_colors(colors)
+    }
+}
+
+/// Lookup table from 8-bit sRGB to linear light (0.0-1.0)
+fn srgb_to_linear_lut() -> &'static [f32; 256] {
+    static LUT: OnceLock<[f32; 256]> = OnceLock::new();
+    LUT.get_or_init(|| {
+        let mut lut = [0f32; 256];
+        for (i, v) in lut.iter_mut().enumerate() {
+            let c = i as f32 / 255.0;
+            *v = if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            };
+        }
+        lut
+    })
+}
+
+/// Convert linear light (0.0-1.0) back to 8-bit sRGB
+fn linear_to_srgb(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let c = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0 + 0.5) as u8
+}
+
 /// Render RGB image data as Unicode block art
 ///
 /// # Arguments
@@ -240,14 +380,37 @@ pub fn render_mosaic(data: &[u8], width: usize, height: usize, config: &MosaicCo
         ((out_width as f32 * height as f32 / width as f32) / 2.0).max(1.0) as usize
     };
 
+    let resized_width = out_width * 2;
+    let resized_height = out_height * 2;
+
     // Resize image if needed
-    let resized = if width != out_width * 2 || height != out_height * 2 {
-        resize_image(data, width, height, out_width * 2, out_height * 2)
+    let resized = if width != resized_width || height != resized_height {
+        resize_image(
+            data,
+            width,
+            height,
+            resized_width,
+            resized_height,
+            config.resample,
+            config.gamma_correct,
+        )
     } else {
         data.to_vec()
     };
 
-    let resized_width = out_width * 2;
+    // Threshold (and dither) the whole image up front so error diffusion can
+    // carry across block boundaries
+    let mask = if config.mode == MosaicMode::Threshold {
+        threshold_mask(
+            &resized,
+            resized_width,
+            resized_height,
+            config.threshold,
+            config.dither,
+        )
+    } else {
+        Vec::new()
+    };
 
     // Select block set
     let mut blocks = HALF_BLOCKS.to_vec();
@@ -263,57 +426,30 @@ pub fn render_mosaic(data: &[u8], width: usize, height: usize, config: &MosaicCo
     // Process image in 2x2 blocks (each becomes one terminal cell)
     for block_y in 0..out_height {
         for block_x in 0..out_width {
-            // Extract 2x2 pixel block
+            // Extract 2x2 pixel block (upper-left, upper-right, lower-left, lower-right)
             let px_y = block_y * 2;
             let px_x = block_x * 2;
-
-            let mut pixels = [[Rgb::new(0, 0, 0); 2]; 2];
-            for dy in 0..2 {
-                for dx in 0..2 {
-                    let y = px_y + dy;
-                    let x = px_x + dx;
-                    if y < out_height * 2 && x < resized_width {
-                        let offset = (y * resized_width + x) * 3;
-                        if offset + 2 < resized.len() {
-                            pixels[dy][dx] =
-                                Rgb::new(resized[offset], resized[offset + 1], resized[offset + 2]);
-                        }
-                    }
-                }
-            }
-
-            // Determine which pixels are "set" based on threshold
-            let mask = [
-                [
-                    pixels[0][0].luminance() >= config.threshold,
-                    pixels[0][1].luminance() >= config.threshold,
-                ],
-                [
-                    pixels[1][0].luminance() >= config.threshold,
-                    pixels[1][1].luminance() >= config.threshold,
-                ],
+            let index = [
+                px_y * resized_width + px_x,
+                px_y * resized_width + px_x + 1,
+                (px_y + 1) * resized_width + px_x,
+                (px_y + 1) * resized_width + px_x + 1,
             ];
+            let pixels = index.map(|i| pixel_at(&resized, i));
 
-            // Find best matching block
-            let pixel_mask_flat = [mask[0][0], mask[0][1], mask[1][0], mask[1][1]];
-            let best_block = find_best_block(&pixel_mask_flat, &blocks);
-
-            // Determine foreground and background colors
-            let mut fg_pixels = Vec::new();
-            let mut bg_pixels = Vec::new();
-
-            for i in 0..4 {
-                let y = i / 2;
-                let x = i % 2;
-                if best_block.coverage[i] {
-                    fg_pixels.push(pixels[y][x]);
-                } else {
-                    bg_pixels.push(pixels[y][x]);
+            let gamma = config.gamma_correct;
+            let (best_block, fg_color, bg_color) = match config.mode {
+                MosaicMode::Threshold => {
+                    let set = index.map(|i| mask.get(i).copied().unwrap_or(false));
+                    threshold_block(&pixels, &set, &blocks, gamma)
                 }
-            }
-
-            let fg_color = average_colors(&fg_pixels);
-            let bg_color = average_colors(&bg_pixels);
+                MosaicMode::Color if config.symbols == SymbolSet::Half => (
+                    HALF_BLOCKS[0],
+                    mix_colors(&pixels[..2], gamma),
+                    mix_colors(&pixels[2..], gamma),
+                ),
+                MosaicMode::Color => fit_block(&pixels, &blocks, gamma),
+            };
 
             // Write cell with colors
             write!(
@@ -333,6 +469,132 @@ pub fn render_mosaic(data: &[u8], width: usize, height: usize, config: &MosaicCo
     output
 }
 
+/// Pick the block matching the thresholded pixels, then color it with the
+/// averages of the covered and uncovered pixels
+fn threshold_block(
+    pixels: &[Rgb; 4],
+    set: &[bool; 4],
+    blocks: &[Block],
+    gamma_correct: bool,
+) -> (Block, Rgb, Rgb) {
+    let block = find_best_block(set, blocks);
+    let (fg, bg) = split_colors(pixels, &block, gamma_correct);
+    (block, fg, bg)
+}
+
+/// Pick the block whose fg/bg partition minimizes the squared color error
+/// when each side is painted with its mean color
+fn fit_block(pixels: &[Rgb; 4], blocks: &[Block], gamma_correct: bool) -> (Block, Rgb, Rgb) {
+    let mut best = (blocks[0], Rgb::new(0, 0, 0), Rgb::new(0, 0, 0));
+    let mut best_error = u32::MAX;
+
+    for block in blocks {
+        let (fg, bg) = split_colors(pixels, block, gamma_correct);
+        let error: u32 = pixels
+            .iter()
+            .zip(block.coverage)
+            .map(|(&p, covered)| color_distance_sq(p, if covered { fg } else { bg }))
+            .sum();
+
+        if error < best_error {
+            best_error = error;
+            best = (*block, fg, bg);
+        }
+
+        if error == 0 {
+            break; // Perfect fit
+        }
+    }
+
+    best
+}
+
+/// Average the covered pixels (fg) and uncovered pixels (bg) of a block
+fn split_colors(pixels: &[Rgb; 4], block: &Block, gamma_correct: bool) -> (Rgb, Rgb) {
+    let mut fg_pixels: SmallVec<[Rgb; 4]> = SmallVec::new();
+    let mut bg_pixels: SmallVec<[Rgb; 4]> = SmallVec::new();
+
+    for (&pixel, covered) in pixels.iter().zip(block.coverage) {
+        if covered {
+            fg_pixels.push(pixel);
+        } else {
+            bg_pixels.push(pixel);
+        }
+    }
+
+    (
+        mix_colors(&fg_pixels, gamma_correct),
+        mix_colors(&bg_pixels, gamma_correct),
+    )
+}
+
+/// Read the pixel at a flat index, black if out of range
+fn pixel_at(data: &[u8], index: usize) -> Rgb {
+    let offset = index * 3;
+    if offset + 2 < data.len() {
+        Rgb::new(data[offset], data[offset + 1], data[offset + 2])
+    } else {
+        Rgb::new(0, 0, 0)
+    }
+}
+
+/// 4x4 Bayer matrix for ordered dithering
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Decide which pixels are "set" by comparing luminance against the threshold
+fn threshold_mask(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    threshold: u8,
+    dither: Dither,
+) -> Vec<bool> {
+    let threshold = threshold as f32;
+    let luminance = |i: usize| pixel_at(data, i).luminance() as f32;
+
+    match dither {
+        Dither::None => (0..width * height)
+            .map(|i| luminance(i) >= threshold)
+            .collect(),
+        Dither::Ordered => (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                // Spread the threshold over one full quantization step
+                let offset = (BAYER_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+                luminance(i) + offset * 255.0 >= threshold
+            })
+            .collect(),
+        Dither::FloydSteinberg => {
+            let mut values: Vec<f32> = (0..width * height).map(luminance).collect();
+            let mut mask = vec![false; width * height];
+
+            for y in 0..height {
+                for x in 0..width {
+                    let i = y * width + x;
+                    let set = values[i] >= threshold;
+                    mask[i] = set;
+
+                    let error = values[i] - if set { 255.0 } else { 0.0 };
+                    if x + 1 < width {
+                        values[i + 1] += error * 7.0 / 16.0;
+                    }
+                    if y + 1 < height {
+                        if x > 0 {
+                            values[i + width - 1] += error * 3.0 / 16.0;
+                        }
+                        values[i + width] += error * 5.0 / 16.0;
+                        if x + 1 < width {
+                            values[i + width + 1] += error / 16.0;
+                        }
+                    }
+                }
+            }
+
+            mask
+        }
+    }
+}
+
 /// Find the block character that best matches the pixel mask
 fn find_best_block(mask: &[bool; 4], blocks: &[Block]) -> Block {
     let mut best = blocks[0];
@@ -359,8 +621,131 @@ fn find_best_block(mask: &[bool; 4], blocks: &[Block]) -> Block {
     best
 }
 
+/// Resize RGB image data with the given filter
+///
+/// Non-nearest filters are separable and widen their kernel by the scale
+/// factor when downscaling, so every source pixel contributes to the result.
+fn resize_image(
+    data: &[u8],
+    src_w: usize,
+    src_h: usize,
+    dst_w: usize,
+    dst_h: usize,
+    filter: Resample,
+    gamma_correct: bool,
+) -> Vec<u8> {
+    if filter == Resample::Nearest || src_w == 0 || src_h == 0 {
+        return resize_nearest(data, src_w, src_h, dst_w, dst_h);
+    }
+
+    // Decode to floating point, in linear light if requested
+    let lut = srgb_to_linear_lut();
+    let src: Vec<f32> = (0..src_w * src_h * 3)
+        .map(|i| {
+            let v = data.get(i).copied().unwrap_or(0);
+            if gamma_correct {
+                lut[v as usize]
+            } else {
+                v as f32 / 255.0
+            }
+        })
+        .collect();
+
+    // Horizontal pass: src_w x src_h -> dst_w x src_h
+    let taps_x = filter_taps(src_w, dst_w, filter);
+    let mut horizontal = vec![0f32; dst_w * src_h * 3];
+    for y in 0..src_h {
+        for (x, (start, weights)) in taps_x.iter().enumerate() {
+            for (k, w) in weights.iter().enumerate() {
+                let s = (y * src_w + start + k) * 3;
+                let d = (y * dst_w + x) * 3;
+                for c in 0..3 {
+                    horizontal[d + c] += src[s + c] * w;
+                }
+            }
+        }
+    }
+
+    // Vertical pass: dst_w x src_h -> dst_w x dst_h
+    let taps_y = filter_taps(src_h, dst_h, filter);
+    let mut result = vec![0u8; dst_w * dst_h * 3];
+    for (y, (start, weights)) in taps_y.iter().enumerate() {
+        for x in 0..dst_w {
+            let mut sum = [0f32; 3];
+            for (k, w) in weights.iter().enumerate() {
+                let s = ((start + k) * dst_w + x) * 3;
+                for c in 0..3 {
+                    sum[c] += horizontal[s + c] * w;
+                }
+            }
+
+            let d = (y * dst_w + x) * 3;
+            for c in 0..3 {
+                result[d + c] = if gamma_correct {
+                    linear_to_srgb(sum[c])
+                } else {
+                    (sum[c].clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+                };
+            }
+        }
+    }
+
+    result
+}
+
+/// Compute the first source index and normalized weights for each output index
+fn filter_taps(src_len: usize, dst_len: usize, filter: Resample) -> Vec<(usize, Vec<f32>)> {
+    let (radius, kernel): (f32, fn(f32) -> f32) = match filter {
+        Resample::Nearest | Resample::Bilinear => (1.0, |x| (1.0 - x.abs()).max(0.0)),
+        Resample::Lanczos3 => (3.0, |x| {
+            if x.abs() >= 3.0 {
+                0.0
+            } else {
+                sinc(x) * sinc(x / 3.0)
+            }
+        }),
+    };
+
+    let scale = src_len as f32 / dst_len as f32;
+    let stretch = scale.max(1.0);
+    let support = radius * stretch;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale - 0.5;
+            let first = ((center - support).floor().max(0.0)) as usize;
+            let last = ((center + support).ceil() as usize).min(src_len - 1);
+
+            let mut weights: Vec<f32> = (first..=last)
+                .map(|j| kernel((j as f32 - center) / stretch))
+                .collect();
+            let total: f32 = weights.iter().sum();
+            if total.abs() > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= total);
+            } else {
+                // Degenerate kernel: fall back to the closest pixel
+                weights.iter_mut().for_each(|w| *w = 0.0);
+                let nearest = (center.round().max(0.0) as usize).clamp(first, last);
+                weights[nearest - first] = 1.0;
+            }
+
+            (first, weights)
+        })
+        .collect()
+}
+
+/// Normalized sinc function
+fn sinc(x: f32) -> f32 {
+    if x.abs() < f32::EPSILON {
+        1.0
+    } else {
+        let px = std::f32::consts::PI * x;
+        px.sin() / px
+    }
+}
+
 /// Simple nearest-neighbor image resizing
-fn resize_image(data: &[u8], src_w: usize, src_h: usize, dst_w: usize, dst_h: usize) -> Vec<u8> {
+fn resize_nearest(data: &[u8], src_w: usize, src_h: usize, dst_w: usize, dst_h: usize) -> Vec<u8> {
     let mut result = vec![0u8; dst_w * dst_h * 3];
 
     for dst_y in 0..dst_h {
@@ -447,11 +832,131 @@ mod tests {
         assert_eq!(best.ch, '▀');
     }
 
+    #[test]
+    fn test_color_mode_half_uses_top_and_bottom() {
+        // 2x2 image: red top row, blue bottom row
+        let data = vec![255u8, 0, 0, 255, 0, 0, 0, 0, 255, 0, 0, 255];
+        let config = MosaicConfig::with_width(1).mode(MosaicMode::Color);
+        let art = render_mosaic(&data, 2, 2, &config);
+        assert_eq!(art, "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_color_mode_keeps_dark_gradients() {
+        // Two dark shades that a threshold of 128 would collapse into one
+        let data = vec![10u8, 10, 10, 10, 10, 10, 60, 60, 60, 60, 60, 60];
+        let config = MosaicConfig::with_width(1).mode(MosaicMode::Color);
+        let art = render_mosaic(&data, 2, 2, &config);
+        assert!(art.contains("38;2;10;10;10"));
+        assert!(art.contains("48;2;60;60;60"));
+    }
+
+    #[test]
+    fn test_fit_block_quadrant() {
+        let white = Rgb::new(255, 255, 255);
+        let black = Rgb::new(0, 0, 0);
+        let mut blocks = HALF_BLOCKS.to_vec();
+        blocks.extend_from_slice(QUARTER_BLOCKS);
+        blocks.extend_from_slice(COMPLEX_BLOCKS);
+
+        // Only the upper-left pixel differs
+        let (block, fg, bg) = fit_block(&[white, black, black, black], &blocks, true);
+        assert_eq!(block.ch, '▘');
+        assert_eq!((fg.r, bg.r), (255, 0));
+
+        // Diagonal pattern
+        let (block, _, _) = fit_block(&[white, black, black, white], &blocks, true);
+        assert_eq!(block.ch, '▚');
+    }
+
+    #[test]
+    fn test_config_mode_default() {
+        assert_eq!(MosaicConfig::default().mode, MosaicMode::Threshold);
+    }
+
     #[test]
     fn test_resize_image() {
         // 2x2 image -> 4x4
         let data = vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
-        let resized = resize_image(&data, 2, 2, 4, 4);
-        assert_eq!(resized.len(), 4 * 4 * 3);
+        for filter in [Resample::Nearest, Resample::Bilinear, Resample::Lanczos3] {
+            let resized = resize_image(&data, 2, 2, 4, 4, filter, true);
+            assert_eq!(resized.len(), 4 * 4 * 3);
+        }
+    }
+
+    #[test]
+    fn test_resize_gamma_correct_downscale() {
+        // Black and white pixel averaged into one
+        let data = vec![0, 0, 0, 255, 255, 255];
+        let linear = resize_image(&data, 2, 1, 1, 1, Resample::Bilinear, true);
+        let naive = resize_image(&data, 2, 1, 1, 1, Resample::Bilinear, false);
+
+        assert_eq!(linear, vec![188, 188, 188]);
+        assert_eq!(naive, vec![128, 128, 128]);
+    }
+
+    #[test]
+    fn test_resize_uses_all_source_pixels() {
+        // A single white pixel in an 8x1 row must not vanish when downscaled
+        let mut data = vec![0u8; 8 * 3];
+        data[3 * 3..3 * 3 + 3].copy_from_slice(&[255, 255, 255]);
+
+        let nearest = resize_image(&data, 8, 1, 2, 1, Resample::Nearest, true);
+        assert!(nearest.iter().all(|&v| v == 0));
+
+        for filter in [Resample::Bilinear, Resample::Lanczos3] {
+            let smooth = resize_image(&data, 8, 1, 2, 1, filter, true);
+            assert!(smooth[0] > 0);
+        }
+    }
+
+    #[test]
+    fn test_resize_constant_image_is_stable() {
+        let data = vec![90u8, 120, 200].repeat(7 * 5);
+        for filter in [Resample::Bilinear, Resample::Lanczos3] {
+            let resized = resize_image(&data, 7, 5, 3, 2, filter, true);
+            for px in resized.chunks(3) {
+                assert!((px[0] as i32 - 90).abs() <= 1);
+                assert!((px[1] as i32 - 120).abs() <= 1);
+                assert!((px[2] as i32 - 200).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_average_colors_linear() {
+        let colors = vec![Rgb::new(0, 0, 0), Rgb::new(255, 255, 255)];
+        let avg = average_colors_linear(&colors);
+        assert_eq!((avg.r, avg.g, avg.b), (188, 188, 188));
+    }
+
+    #[test]
+    fn test_dither_mid_gray() {
+        let data = vec![128u8].repeat(8 * 8 * 3);
+
+        let plain = threshold_mask(&data, 8, 8, 129, Dither::None);
+        assert!(plain.iter().all(|&set| !set));
+
+        for dither in [Dither::Ordered, Dither::FloydSteinberg] {
+            let mask = threshold_mask(&data, 8, 8, 129, dither);
+            let set = mask.iter().filter(|&&s| s).count();
+            assert!((24..=40).contains(&set), "{:?} set {} of 64", dither, set);
+        }
+    }
+
+    #[test]
+    fn test_config_dither_and_resample() {
+        let config = MosaicConfig::with_width(10)
+            .dither(Dither::FloydSteinberg)
+            .resample(Resample::Lanczos3)
+            .gamma_correct(false);
+
+        assert_eq!(config.dither, Dither::FloydSteinberg);
+        assert_eq!(config.resample, Resample::Lanczos3);
+        assert!(!config.gamma_correct);
+
+        let data = vec![200u8, 100, 50].repeat(16 * 16);
+        let art = render_mosaic(&data, 16, 16, &config);
+        assert_eq!(art.lines().count(), 5);
     }
 }
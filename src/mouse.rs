@@ -0,0 +1,234 @@
+//! Mouse input in the SGR (1006) encoding
+//!
+//! With mouse reporting on, the terminal sends `ESC [ < b ; x ; y M` for
+//! presses, motion and wheel turns, and `... m` for releases. `b` packs the
+//! button in its low bits, Shift/Alt/Ctrl in 4/8/16, motion in 32, and wheel
+//! "buttons" from 64.
+
+use crate::input::Key;
+use crate::kitty::Modifiers;
+
+/// Mouse reporting modes enabled by `Screen::enable_mouse` (button events,
+/// motion while a button is held, SGR encoding)
+pub(crate) const ENABLE_MOUSE: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1006h";
+/// Turns off what `ENABLE_MOUSE` turned on
+pub(crate) const DISABLE_MOUSE: &str = "\x1b[?1006l\x1b[?1002l\x1b[?1000l";
+
+/// Mouse pointer shape set with OSC 22 (`Screen::set_pointer_shape`)
+///
+/// Shapes use the CSS cursor names understood by kitty, foot and wezterm;
+/// other terminals ignore the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerShape {
+    /// The terminal's usual pointer
+    #[default]
+    Default,
+    /// I-beam, over editable or selectable text
+    Text,
+    /// Hand, over links and buttons
+    Pointer,
+    Crosshair,
+    Move,
+    NotAllowed,
+    Wait,
+    Progress,
+    Help,
+    /// Resizing horizontally
+    EwResize,
+    /// Resizing vertically
+    NsResize,
+    /// Resizing a column boundary
+    ColResize,
+    /// Resizing a row boundary
+    RowResize,
+    Grab,
+    Grabbing,
+}
+
+impl PointerShape {
+    /// CSS name sent in OSC 22
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Text => "text",
+            Self::Pointer => "pointer",
+            Self::Crosshair => "crosshair",
+            Self::Move => "move",
+            Self::NotAllowed => "not-allowed",
+            Self::Wait => "wait",
+            Self::Progress => "progress",
+            Self::Help => "help",
+            Self::EwResize => "ew-resize",
+            Self::NsResize => "ns-resize",
+            Self::ColResize => "col-resize",
+            Self::RowResize => "row-resize",
+            Self::Grab => "grab",
+            Self::Grabbing => "grabbing",
+        }
+    }
+
+    /// OSC 22 sequence selecting the shape
+    pub(crate) fn sequence(self) -> String {
+        format!("\x1b]22;{}\x1b\\", self.name())
+    }
+}
+
+/// A mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+/// What happened with the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEventKind {
+    /// Button pressed
+    Press(MouseButton),
+    /// Button released
+    Release(MouseButton),
+    /// Moved with a button held
+    Drag(MouseButton),
+    /// Wheel turned away from the user
+    ScrollUp,
+    /// Wheel turned towards the user
+    ScrollDown,
+    /// Horizontal wheel (or tilt) to the left
+    ScrollLeft,
+    /// Horizontal wheel (or tilt) to the right
+    ScrollRight,
+}
+
+/// A mouse event at a screen cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    /// Row, from 0 at the first line below any reserved header
+    pub row: u16,
+    /// Column, from 0
+    pub col: u16,
+    /// Shift, Alt and Ctrl held during the event
+    pub modifiers: Modifiers,
+}
+
+impl MouseEvent {
+    /// Parse an SGR mouse report (`ESC [ < b ; x ; y M` or `m`)
+    pub(crate) fn from_sgr(seq: &[u8]) -> Option<Self> {
+        let [27, b'[', b'<', params @ .., last @ (b'M' | b'm')] = seq else {
+            return None;
+        };
+        let mut params = std::str::from_utf8(params).ok()?.split(';');
+        let code: u16 = params.next()?.parse().ok()?;
+        let col: u16 = params.next()?.parse().ok()?;
+        let row: u16 = params.next()?.parse().ok()?;
+        if params.next().is_some() {
+            return None;
+        }
+
+        let button = match code & 0b11 {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Middle),
+            2 => Some(MouseButton::Right),
+            _ => None,
+        };
+        let kind = if code & 64 != 0 {
+            match code & 0b11 {
+                0 => MouseEventKind::ScrollUp,
+                1 => MouseEventKind::ScrollDown,
+                2 => MouseEventKind::ScrollLeft,
+                _ => MouseEventKind::ScrollRight,
+            }
+        } else if *last == b'm' {
+            MouseEventKind::Release(button?)
+        } else if code & 32 != 0 {
+            MouseEventKind::Drag(button?)
+        } else {
+            MouseEventKind::Press(button?)
+        };
+
+        let mut modifiers = Modifiers::empty();
+        modifiers.set(Modifiers::SHIFT, code & 4 != 0);
+        modifiers.set(Modifiers::ALT, code & 8 != 0);
+        modifiers.set(Modifiers::CTRL, code & 16 != 0);
+
+        Some(Self {
+            kind,
+            row: row.saturating_sub(1),
+            col: col.saturating_sub(1),
+            modifiers,
+        })
+    }
+
+    /// The scroll key for a wheel event
+    pub fn scroll_key(&self) -> Option<Key> {
+        match self.kind {
+            MouseEventKind::ScrollUp => Some(Key::ScrollUp),
+            MouseEventKind::ScrollDown => Some(Key::ScrollDown),
+            MouseEventKind::ScrollLeft => Some(Key::ScrollLeft),
+            MouseEventKind::ScrollRight => Some(Key::ScrollRight),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buttons() {
+        let event = MouseEvent::from_sgr(b"\x1b[<0;10;5M").unwrap();
+        assert_eq!(event.kind, MouseEventKind::Press(MouseButton::Left));
+        assert_eq!((event.row, event.col), (4, 9));
+
+        let event = MouseEvent::from_sgr(b"\x1b[<2;1;1m").unwrap();
+        assert_eq!(event.kind, MouseEventKind::Release(MouseButton::Right));
+
+        // Ctrl+drag with the middle button
+        let event = MouseEvent::from_sgr(b"\x1b[<49;3;3M").unwrap();
+        assert_eq!(event.kind, MouseEventKind::Drag(MouseButton::Middle));
+        assert_eq!(event.modifiers, Modifiers::CTRL);
+        assert_eq!(event.scroll_key(), None);
+    }
+
+    #[test]
+    fn test_pointer_shape() {
+        assert_eq!(PointerShape::default(), PointerShape::Default);
+        assert_eq!(PointerShape::Text.sequence(), "\x1b]22;text\x1b\\");
+        assert_eq!(PointerShape::NotAllowed.name(), "not-allowed");
+    }
+
+    #[test]
+    fn test_wheel() {
+        let kinds: Vec<_> = (64..=67)
+            .map(|code| {
+                let seq = format!("\x1b[<{};1;1M", code);
+                MouseEvent::from_sgr(seq.as_bytes()).unwrap().kind
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                MouseEventKind::ScrollUp,
+                MouseEventKind::ScrollDown,
+                MouseEventKind::ScrollLeft,
+                MouseEventKind::ScrollRight,
+            ]
+        );
+
+        // Shift+wheel
+        let event = MouseEvent::from_sgr(b"\x1b[<69;1;1M").unwrap();
+        assert_eq!(event.modifiers, Modifiers::SHIFT);
+        assert_eq!(event.scroll_key(), Some(Key::ScrollDown));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(MouseEvent::from_sgr(b"\x1b[<0;1M"), None);
+        assert_eq!(MouseEvent::from_sgr(b"\x1b[<0;1;1;1M"), None);
+        assert_eq!(MouseEvent::from_sgr(b"\x1b[0;1;1M"), None);
+        // Motion without a button isn't reported in button-event mode
+        assert_eq!(MouseEvent::from_sgr(b"\x1b[<35;1;1M"), None);
+    }
+}
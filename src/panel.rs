@@ -1,29 +1,52 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::layout::Rect;
 /// Panel - manages layered windows with z-ordering
 ///
 /// Panels provide a way to manage overlapping windows with automatic
-/// z-order handling and efficient updates.
+/// z-order handling and efficient updates. Each `Screen` owns a
+/// `PanelManager` that keeps the stacking order for its panels.
 use crate::window::Window;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Mutex, OnceLock};
 
+// Legacy process-wide stack used by the deprecated `Panel::top`/`Panel::bottom`
 static PANEL_STACK: OnceLock<Mutex<Vec<usize>>> = OnceLock::new();
+static NEXT_GLOBAL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Identifier of a panel registered with a `PanelManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PanelId(usize);
+
+impl PanelId {
+    /// Get the raw id value
+    pub fn get(&self) -> usize {
+        self.0
+    }
+
+    /// Rebuild an id from its raw value, as handed across the C API
+    pub(crate) fn from_raw(id: usize) -> Self {
+        Self(id)
+    }
+}
 
 /// A panel wraps a window and provides z-ordering
 pub struct Panel {
     window: Window,
-    panel_id: usize,
     hidden: bool,
+    // Only set once the deprecated global stack has been used
+    global_id: Option<usize>,
 }
 
 impl Panel {
     /// Create a new panel from a window
+    ///
+    /// The panel is not part of any stack until it is added to a
+    /// `PanelManager` (see `Screen::new_panel`).
     pub fn new(window: Window) -> Result<Self> {
-        let panel_id = Self::register_panel();
-
         Ok(Self {
             window,
-            panel_id,
             hidden: false,
+            global_id: None,
         })
     }
 
@@ -37,27 +60,38 @@ impl Panel {
         &mut self.window
     }
 
-    /// Move this panel to the top of the stack
+    /// Screen area covered by the panel's window
+    pub fn rect(&self) -> Rect {
+        self.window.rect()
+    }
+
+    /// Move this panel to the top of the global stack
+    #[deprecated(note = "the global panel stack is shared by every Screen; use PanelManager::top")]
     pub fn top(&mut self) -> Result<()> {
+        let id = self.global_id();
         let stack = PANEL_STACK.get_or_init(|| Mutex::new(Vec::new()));
         let mut guard = stack.lock().unwrap();
 
-        if let Some(pos) = guard.iter().position(|&id| id == self.panel_id) {
+        if let Some(pos) = guard.iter().position(|&other| other == id) {
             guard.remove(pos);
-            guard.push(self.panel_id);
+            guard.push(id);
         }
 
         Ok(())
     }
 
-    /// Move this panel to the bottom of the stack
+    /// Move this panel to the bottom of the global stack
+    #[deprecated(
+        note = "the global panel stack is shared by every Screen; use PanelManager::bottom"
+    )]
     pub fn bottom(&mut self) -> Result<()> {
+        let id = self.global_id();
         let stack = PANEL_STACK.get_or_init(|| Mutex::new(Vec::new()));
         let mut guard = stack.lock().unwrap();
 
-        if let Some(pos) = guard.iter().position(|&id| id == self.panel_id) {
+        if let Some(pos) = guard.iter().position(|&other| other == id) {
             guard.remove(pos);
-            guard.insert(0, self.panel_id);
+            guard.insert(0, id);
         }
 
         Ok(())
@@ -98,31 +132,203 @@ impl Panel {
         }
     }
 
-    fn register_panel() -> usize {
-        let stack = PANEL_STACK.get_or_init(|| Mutex::new(Vec::new()));
-        let mut guard = stack.lock().unwrap();
+    /// Register with the legacy global stack on first use
+    fn global_id(&mut self) -> usize {
+        if let Some(id) = self.global_id {
+            return id;
+        }
 
-        let id = guard.len();
-        guard.push(id);
+        // Ids are never reused, so a dropped panel can't alias a live one
+        let id = NEXT_GLOBAL_ID.fetch_add(1, Ordering::Relaxed);
+        let stack = PANEL_STACK.get_or_init(|| Mutex::new(Vec::new()));
+        stack.lock().unwrap().push(id);
+        self.global_id = Some(id);
         id
     }
 }
 
 impl Drop for Panel {
     fn drop(&mut self) {
+        let Some(id) = self.global_id else {
+            return;
+        };
+
         let stack = PANEL_STACK.get_or_init(|| Mutex::new(Vec::new()));
         let mut guard = stack.lock().unwrap();
 
-        if let Some(pos) = guard.iter().position(|&id| id == self.panel_id) {
+        if let Some(pos) = guard.iter().position(|&other| other == id) {
             guard.remove(pos);
         }
     }
 }
 
+/// Owns a set of panels and their stacking order (bottom to top)
+///
+/// Iteration always goes from the bottom-most to the top-most panel, which
+/// is the order a compositor must draw them in.
+#[derive(Default)]
+pub struct PanelManager {
+    // Bottom of the stack first
+    stack: Vec<(PanelId, Panel)>,
+    next_id: usize,
+}
+
+impl PanelManager {
+    /// Create an empty panel stack
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a panel on top of the stack
+    pub fn add(&mut self, panel: Panel) -> PanelId {
+        let id = PanelId(self.next_id);
+        self.next_id += 1;
+        self.stack.push((id, panel));
+        id
+    }
+
+    /// Remove a panel from the stack, handing it back to the caller
+    pub fn remove(&mut self, id: PanelId) -> Result<Panel> {
+        let pos = self.position(id)?;
+        Ok(self.stack.remove(pos).1)
+    }
+
+    /// Get a panel by id
+    pub fn get(&self, id: PanelId) -> Option<&Panel> {
+        let (_, panel) = self.stack.iter().find(|(p, _)| *p == id)?;
+        Some(panel)
+    }
+
+    /// Get a mutable panel by id
+    pub fn get_mut(&mut self, id: PanelId) -> Option<&mut Panel> {
+        self.stack
+            .iter_mut()
+            .find(|(p, _)| *p == id)
+            .map(|(_, panel)| panel)
+    }
+
+    /// Number of panels in the stack
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Check if the stack is empty
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Move a panel to the top of the stack
+    pub fn top(&mut self, id: PanelId) -> Result<()> {
+        let pos = self.position(id)?;
+        let entry = self.stack.remove(pos);
+        self.stack.push(entry);
+        Ok(())
+    }
+
+    /// Move a panel to the bottom of the stack
+    pub fn bottom(&mut self, id: PanelId) -> Result<()> {
+        let pos = self.position(id)?;
+        let entry = self.stack.remove(pos);
+        self.stack.insert(0, entry);
+        Ok(())
+    }
+
+    /// Place a panel directly above another one
+    pub fn move_above(&mut self, id: PanelId, target: PanelId) -> Result<()> {
+        let pos = self.position(id)?;
+        self.position(target)?;
+        if id == target {
+            return Ok(());
+        }
+
+        let entry = self.stack.remove(pos);
+        let target_pos = self.position(target)?;
+        self.stack.insert(target_pos + 1, entry);
+        Ok(())
+    }
+
+    /// Place a panel directly below another one
+    pub fn move_below(&mut self, id: PanelId, target: PanelId) -> Result<()> {
+        let pos = self.position(id)?;
+        self.position(target)?;
+        if id == target {
+            return Ok(());
+        }
+
+        let entry = self.stack.remove(pos);
+        let target_pos = self.position(target)?;
+        self.stack.insert(target_pos, entry);
+        Ok(())
+    }
+
+    /// Replace the window shown by a panel, keeping its place in the stack
+    ///
+    /// Returns the previous window.
+    pub fn replace(&mut self, id: PanelId, window: Window) -> Result<Window> {
+        let panel = self.get_mut(id).ok_or(Error::InvalidPanel(id.0))?;
+        Ok(std::mem::replace(&mut panel.window, window))
+    }
+
+    /// Panel directly above the given one, if any
+    pub fn above(&self, id: PanelId) -> Option<PanelId> {
+        let pos = self.position(id).ok()?;
+        self.stack.get(pos + 1).map(|(p, _)| *p)
+    }
+
+    /// Panel directly below the given one, if any
+    pub fn below(&self, id: PanelId) -> Option<PanelId> {
+        let pos = self.position(id).ok()?;
+        pos.checked_sub(1).map(|p| self.stack[p].0)
+    }
+
+    /// Top-most panel
+    pub fn top_panel(&self) -> Option<PanelId> {
+        self.stack.last().map(|(id, _)| *id)
+    }
+
+    /// Bottom-most panel
+    pub fn bottom_panel(&self) -> Option<PanelId> {
+        self.stack.first().map(|(id, _)| *id)
+    }
+
+    /// Iterate over panels from bottom to top
+    pub fn iter(&self) -> impl Iterator<Item = (PanelId, &Panel)> {
+        self.stack.iter().map(|(id, panel)| (*id, panel))
+    }
+
+    /// Iterate mutably over panels from bottom to top
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (PanelId, &mut Panel)> {
+        self.stack.iter_mut().map(|(id, panel)| (*id, panel))
+    }
+
+    /// Queue every visible panel for output, bottom to top (like update_panels)
+    pub fn update(&mut self) -> Result<()> {
+        for (_, panel) in &mut self.stack {
+            panel.wnoutrefresh()?;
+        }
+        Ok(())
+    }
+
+    fn position(&self, id: PanelId) -> Result<usize> {
+        self.stack
+            .iter()
+            .position(|(p, _)| *p == id)
+            .ok_or(Error::InvalidPanel(id.0))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn panel(y: u16) -> Panel {
+        Panel::new(Window::new(10, 20, y, 0).unwrap()).unwrap()
+    }
+
+    fn order(manager: &PanelManager) -> Vec<PanelId> {
+        manager.iter().map(|(id, _)| id).collect()
+    }
+
     #[test]
     fn test_panel_creation() {
         let win = Window::new(10, 20, 5, 5).unwrap();
@@ -158,17 +364,86 @@ mod tests {
 
     #[test]
     fn test_panel_z_order() {
-        let win1 = Window::new(10, 20, 0, 0).unwrap();
-        let win2 = Window::new(10, 20, 5, 5).unwrap();
+        let mut manager = PanelManager::new();
+        let a = manager.add(panel(0));
+        let b = manager.add(panel(1));
+        let c = manager.add(panel(2));
+        assert_eq!(order(&manager), vec![a, b, c]);
+
+        manager.top(a).unwrap();
+        assert_eq!(order(&manager), vec![b, c, a]);
+
+        manager.bottom(c).unwrap();
+        assert_eq!(order(&manager), vec![c, b, a]);
+        assert_eq!(manager.top_panel(), Some(a));
+        assert_eq!(manager.bottom_panel(), Some(c));
+    }
+
+    #[test]
+    fn test_panel_move_above_below() {
+        let mut manager = PanelManager::new();
+        let a = manager.add(panel(0));
+        let b = manager.add(panel(1));
+        let c = manager.add(panel(2));
+
+        manager.move_above(a, b).unwrap();
+        assert_eq!(order(&manager), vec![b, a, c]);
 
-        let mut panel1 = Panel::new(win1).unwrap();
-        let mut panel2 = Panel::new(win2).unwrap();
+        manager.move_below(c, b).unwrap();
+        assert_eq!(order(&manager), vec![c, b, a]);
 
-        panel1.top().unwrap();
-        panel2.bottom().unwrap();
+        assert_eq!(manager.above(c), Some(b));
+        assert_eq!(manager.below(c), None);
+        assert_eq!(manager.above(a), None);
+    }
+
+    #[test]
+    fn test_panel_replace_window() {
+        let mut manager = PanelManager::new();
+        let a = manager.add(panel(0));
+        let b = manager.add(panel(1));
+
+        let window = Window::new(3, 4, 7, 8).unwrap();
+        let old = manager.replace(a, window).unwrap();
+        assert_eq!(old.get_position(), (0, 0));
+        assert_eq!(manager.get(a).unwrap().window().get_size(), (3, 4));
+        assert_eq!(order(&manager), vec![a, b]);
+    }
 
-        // Just verify no panics
-        assert_eq!(panel1.panel_id, 0);
-        assert_eq!(panel2.panel_id, 1);
+    #[test]
+    fn test_panel_ids_not_reused() {
+        let mut manager = PanelManager::new();
+        let a = manager.add(panel(0));
+        manager.remove(a).unwrap();
+        let b = manager.add(panel(1));
+
+        assert_ne!(a, b);
+        assert!(manager.get(a).is_none());
+        assert!(matches!(manager.top(a), Err(Error::InvalidPanel(_))));
+    }
+
+    #[test]
+    fn test_panel_invalid_target_keeps_panel() {
+        let mut manager = PanelManager::new();
+        let a = manager.add(panel(0));
+        let b = manager.add(panel(1));
+        manager.remove(b).unwrap();
+
+        assert!(manager.move_above(a, b).is_err());
+        assert!(manager.move_below(a, b).is_err());
+        assert_eq!(order(&manager), vec![a]);
+    }
+
+    #[test]
+    fn test_managers_are_independent() {
+        let mut first = PanelManager::new();
+        let mut second = PanelManager::new();
+        let a = first.add(panel(0));
+        let b = second.add(panel(0));
+
+        // Ids are only meaningful within their own manager
+        assert_eq!(a, b);
+        first.remove(a).unwrap();
+        assert_eq!(second.len(), 1);
     }
 }
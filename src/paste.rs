@@ -0,0 +1,303 @@
+/// Bracketed paste support
+///
+/// When bracketed paste mode is enabled the terminal wraps pasted text in
+/// `ESC [ 200 ~` ... `ESC [ 201 ~`. Instead of feeding megabytes of pasted
+/// text through the per-key parser, the reader slurps everything up to the
+/// end marker in buffer-sized chunks and delivers it as a single
+/// `Key::Paste` event (or streams it to a handler).
+use crate::error::Result;
+use std::io::{self, BufRead};
+
+/// Sequence the terminal sends before pasted text
+pub(crate) const PASTE_START: &[u8] = b"\x1b[200~";
+/// Sequence the terminal sends after pasted text
+pub(crate) const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Default maximum size of a buffered paste (8 MiB)
+pub(crate) const DEFAULT_PASTE_LIMIT: usize = 8 * 1024 * 1024;
+
+/// Callback receiving pasted text incrementally
+pub(crate) type PasteHandler = Box<dyn FnMut(&str) + Send>;
+
+/// How pasted text is delivered
+pub(crate) struct PasteOptions {
+    /// Maximum bytes kept for a buffered paste; the rest is discarded
+    pub(crate) limit: usize,
+    /// Streaming handler; when set, text goes here instead of the paste event
+    pub(crate) handler: Option<PasteHandler>,
+}
+
+impl Default for PasteOptions {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_PASTE_LIMIT,
+            handler: None,
+        }
+    }
+}
+
+/// Read pasted text up to and including the end marker
+///
+/// The start marker must already have been consumed. Only bytes up to the
+/// end marker are consumed from `reader`, so input typed after the paste is
+/// left for the key parser. Invalid UTF-8 is replaced with U+FFFD.
+///
+/// `wait` is called before each read and returns false once no more input
+/// is coming, so a paste whose end marker is lost ends with what arrived.
+pub(crate) fn read_paste<R: BufRead>(
+    reader: &mut R,
+    options: &mut PasteOptions,
+    mut wait: impl FnMut(&R) -> Result<bool>,
+) -> Result<String> {
+    let mut sink = PasteSink {
+        pending: Vec::new(),
+        content: String::new(),
+        limit: options.limit,
+        handler: options.handler.as_mut(),
+    };
+
+    // Bytes of the end marker matched so far (held back until confirmed)
+    let mut matched = 0;
+
+    loop {
+        if !wait(reader)? {
+            break; // Timed out before the end marker: keep what we have
+        }
+        let chunk = match reader.fill_buf() {
+            Ok(chunk) => chunk,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if chunk.is_empty() {
+            break; // EOF before the end marker: keep what we have
+        }
+
+        let mut run_start = 0;
+        let mut end = None;
+
+        for (i, &byte) in chunk.iter().enumerate() {
+            if byte == PASTE_END[matched] {
+                if matched == 0 {
+                    sink.push(&chunk[run_start..i]);
+                }
+                matched += 1;
+                if matched == PASTE_END.len() {
+                    end = Some(i + 1);
+                    break;
+                }
+            } else if matched > 0 {
+                // False alarm: the held back bytes were pasted text
+                sink.push(&PASTE_END[..matched]);
+                if byte == PASTE_END[0] {
+                    matched = 1;
+                    run_start = i + 1;
+                } else {
+                    matched = 0;
+                    run_start = i;
+                }
+            }
+        }
+
+        let consumed = match end {
+            Some(end) => end,
+            None => {
+                if matched == 0 {
+                    sink.push(&chunk[run_start..]);
+                }
+                chunk.len()
+            }
+        };
+        reader.consume(consumed);
+
+        if end.is_some() {
+            sink.flush(true);
+            return Ok(sink.content);
+        }
+        sink.flush(false);
+    }
+
+    sink.push(&PASTE_END[..matched]);
+    sink.flush(true);
+    Ok(sink.content)
+}
+
+/// Accumulates pasted bytes and decodes them in chunks
+struct PasteSink<'a> {
+    pending: Vec<u8>,
+    content: String,
+    limit: usize,
+    handler: Option<&'a mut PasteHandler>,
+}
+
+impl PasteSink<'_> {
+    fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    /// Decode pending bytes, keeping an incomplete trailing character unless
+    /// this is the final flush
+    fn flush(&mut self, last: bool) {
+        let pending = std::mem::take(&mut self.pending);
+        let mut rest = &pending[..];
+
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.deliver(text);
+                    rest = &[];
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    self.deliver(std::str::from_utf8(valid).unwrap_or_default());
+
+                    match e.error_len() {
+                        Some(len) => {
+                            self.deliver("\u{FFFD}");
+                            rest = &after[len..];
+                        }
+                        None if last => {
+                            self.deliver("\u{FFFD}");
+                            rest = &[];
+                        }
+                        None => {
+                            // Incomplete character split across chunks
+                            self.pending.extend_from_slice(after);
+                            rest = &[];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn deliver(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        if let Some(handler) = self.handler.as_mut() {
+            handler(text);
+            return;
+        }
+
+        let room = self.limit.saturating_sub(self.content.len());
+        if text.len() <= room {
+            self.content.push_str(text);
+        } else {
+            let mut cut = room;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            self.content.push_str(&text[..cut]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor, Read};
+    use std::sync::{Arc, Mutex};
+
+    fn paste(input: &[u8], options: &mut PasteOptions) -> (String, Vec<u8>) {
+        // Tiny buffer so markers and characters straddle chunk boundaries
+        let mut reader = BufReader::with_capacity(3, Cursor::new(input.to_vec()));
+        let text = read_paste(&mut reader, options, |_| Ok(true)).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        (text, rest)
+    }
+
+    #[test]
+    fn test_simple_paste() {
+        let (text, rest) = paste(b"hello\nworld\x1b[201~x", &mut PasteOptions::default());
+        assert_eq!(text, "hello\nworld");
+        assert_eq!(rest, b"x"); // Input after the paste is left alone
+    }
+
+    #[test]
+    fn test_escape_sequences_inside_paste() {
+        let input = b"a\x1b[A\x1b[201\x1b\x1b[20b\x1b[201~";
+        let (text, rest) = paste(input, &mut PasteOptions::default());
+        assert_eq!(text, "a\x1b[A\x1b[201\x1b\x1b[20b");
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_utf8_across_chunks() {
+        let input = "héllo wörld ✓ 🎉\x1b[201~".as_bytes();
+        let (text, _) = paste(input, &mut PasteOptions::default());
+        assert_eq!(text, "héllo wörld ✓ 🎉");
+    }
+
+    #[test]
+    fn test_invalid_utf8_replaced() {
+        let (text, _) = paste(b"a\xffb\xe2\x82\x1b[201~", &mut PasteOptions::default());
+        assert_eq!(text, "a\u{FFFD}b\u{FFFD}");
+    }
+
+    #[test]
+    fn test_missing_end_marker() {
+        let (text, _) = paste(b"partial\x1b[20", &mut PasteOptions::default());
+        assert_eq!(text, "partial\x1b[20");
+    }
+
+    #[test]
+    fn test_timeout_ends_paste() {
+        let mut reader = Cursor::new(b"partial\x1b[20".to_vec());
+        let mut reads = 0;
+        let text = read_paste(&mut reader, &mut PasteOptions::default(), |_| {
+            reads += 1;
+            Ok(reads == 1)
+        })
+        .unwrap();
+        assert_eq!(text, "partial\x1b[20");
+    }
+
+    #[test]
+    fn test_limit_truncates_but_consumes() {
+        let mut options = PasteOptions {
+            limit: 4,
+            handler: None,
+        };
+        let (text, rest) = paste("abcdé\x1b[201~z".as_bytes(), &mut options);
+        assert_eq!(text, "abcd");
+        assert_eq!(rest, b"z");
+
+        // Never split a character
+        options.limit = 5;
+        let (text, _) = paste("abcdé\x1b[201~".as_bytes(), &mut options);
+        assert_eq!(text, "abcd");
+    }
+
+    #[test]
+    fn test_streaming_handler() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let mut options = PasteOptions {
+            limit: 1,
+            handler: Some(Box::new(move |chunk: &str| {
+                sink.lock().unwrap().push(chunk.to_string())
+            })),
+        };
+
+        let (text, _) = paste("streamed ✓ text\x1b[201~".as_bytes(), &mut options);
+        assert!(text.is_empty());
+
+        let chunks = received.lock().unwrap();
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), "streamed ✓ text");
+    }
+
+    #[test]
+    fn test_large_paste() {
+        let body = "0123456789abcdef".repeat(256 * 1024); // 4 MiB
+        let mut input = body.clone().into_bytes();
+        input.extend_from_slice(PASTE_END);
+
+        let mut reader = BufReader::new(Cursor::new(input));
+        let text = read_paste(&mut reader, &mut PasteOptions::default(), |_| Ok(true)).unwrap();
+        assert_eq!(text.len(), body.len());
+        assert!(text == body);
+    }
+}
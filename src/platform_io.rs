@@ -2,10 +2,15 @@
 ///
 /// This module provides optimized, direct I/O operations that bypass
 /// standard library buffering for maximum performance.
+use smallvec::SmallVec;
+use std::collections::VecDeque;
 use std::io;
+use std::time::{Duration, Instant};
 
 #[cfg(unix)]
 use std::os::unix::io::RawFd;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
 
 /// Get the file descriptor to write to (stdout in production, /dev/null in tests)
 #[cfg(all(unix, test))]
@@ -37,13 +42,24 @@ fn get_output_fd() -> RawFd {
 /// by eliminating redundant buffering and reducing syscall overhead.
 #[cfg(unix)]
 pub fn write_stdout(buf: &[u8]) -> io::Result<usize> {
+    write_fd(get_output_fd(), buf)
+}
+
+/// Write all bytes to `fd`, retrying on partial writes
+#[cfg(unix)]
+pub(crate) fn write_all_fd(fd: RawFd, buf: &[u8]) -> io::Result<()> {
+    write_fd(fd, buf)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_fd(fd: RawFd, buf: &[u8]) -> io::Result<usize> {
     if buf.is_empty() {
         return Ok(0);
     }
 
     let mut total_written = 0;
     let mut remaining = buf;
-    let fd = get_output_fd();
 
     // Handle partial writes and interruptions
     while !remaining.is_empty() {
@@ -91,10 +107,362 @@ pub fn write_all_stdout(buf: &[u8]) -> io::Result<()> {
     Ok(())
 }
 
+/// Queued bytes from which `Screen` starts writing a frame before it's
+/// complete
+const DEFAULT_FLUSH_THRESHOLD: usize = 64 * 1024;
+
+/// Queued chunks smaller than this are appended to instead of adding a
+/// new chunk, keeping the iovec count low
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Most buffers passed to one writev call
+#[cfg(unix)]
+const MAX_IOVECS: usize = 64;
+
+/// Terminal output queue, written with vectored writes
+///
+/// Without a write timeout, `flush` blocks until everything is written, like
+/// `write_all_stdout`. With one, the fd is switched to non-blocking mode while
+/// flushing: when the terminal stops reading (e.g. a stalled SSH link),
+/// `flush` gives up after the timeout with `ErrorKind::WouldBlock`, and the
+/// unwritten bytes stay queued, in order, for the next flush.
+pub(crate) struct OutputWriter {
+    #[cfg(unix)]
+    fd: RawFd,
+    chunks: VecDeque<Vec<u8>>,
+    // Bytes of the front chunk already written
+    offset: usize,
+    queued: usize,
+    flush_threshold: usize,
+    write_timeout: Option<Duration>,
+}
+
+impl OutputWriter {
+    /// Writer for the terminal (/dev/null in tests)
+    pub(crate) fn stdout() -> Self {
+        Self {
+            #[cfg(unix)]
+            fd: get_output_fd(),
+            chunks: VecDeque::new(),
+            offset: 0,
+            queued: 0,
+            flush_threshold: DEFAULT_FLUSH_THRESHOLD,
+            write_timeout: None,
+        }
+    }
+
+    /// Writer for another fd
+    #[cfg(unix)]
+    pub(crate) fn with_fd(fd: RawFd) -> Self {
+        Self {
+            fd,
+            ..Self::stdout()
+        }
+    }
+
+    /// Bytes queued but not yet written
+    pub(crate) fn queued(&self) -> usize {
+        self.queued
+    }
+
+    pub(crate) fn flush_threshold(&self) -> usize {
+        self.flush_threshold
+    }
+
+    pub(crate) fn set_flush_threshold(&mut self, bytes: usize) {
+        self.flush_threshold = bytes.max(1);
+    }
+
+    pub(crate) fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+
+    /// Append bytes to the queue
+    pub(crate) fn queue(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        match self.chunks.back_mut() {
+            Some(last) if last.len() + bytes.len() <= CHUNK_SIZE => last.extend_from_slice(bytes),
+            _ => self.chunks.push_back(bytes.to_vec()),
+        }
+        self.queued += bytes.len();
+    }
+
+    /// Write everything queued
+    #[cfg(unix)]
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        if self.queued == 0 {
+            return Ok(());
+        }
+
+        let deadline = self.write_timeout.map(|timeout| Instant::now() + timeout);
+        let _guard = match deadline {
+            Some(_) => Some(NonBlocking::set(self.fd)?),
+            None => None,
+        };
+
+        while self.queued > 0 {
+            let iovecs: SmallVec<[libc::iovec; MAX_IOVECS]> = self
+                .chunks
+                .iter()
+                .take(MAX_IOVECS)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let start = if i == 0 { self.offset } else { 0 };
+                    libc::iovec {
+                        iov_base: chunk[start..].as_ptr() as *mut libc::c_void,
+                        iov_len: chunk.len() - start,
+                    }
+                })
+                .collect();
+
+            let written =
+                unsafe { libc::writev(self.fd, iovecs.as_ptr(), iovecs.len() as libc::c_int) };
+            if written < 0 {
+                let err = io::Error::last_os_error();
+                match err.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    io::ErrorKind::WouldBlock => match deadline {
+                        Some(deadline) if wait_writable(self.fd, deadline)? => continue,
+                        Some(_) => return Err(io::ErrorKind::WouldBlock.into()),
+                        None => return Err(err),
+                    },
+                    _ => return Err(err),
+                }
+            }
+            self.consume(written as usize);
+        }
+        Ok(())
+    }
+
+    /// Write everything queued
+    #[cfg(not(unix))]
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        use std::io::Write;
+
+        let mut stdout = std::io::stdout();
+        while let Some(chunk) = self.chunks.front() {
+            stdout.write_all(&chunk[self.offset..])?;
+            self.consume(chunk.len() - self.offset);
+        }
+        stdout.flush()
+    }
+
+    /// Drop `n` written bytes from the front of the queue
+    fn consume(&mut self, mut n: usize) {
+        self.queued -= n;
+        while n > 0 {
+            let front = self.chunks[0].len() - self.offset;
+            if n < front {
+                self.offset += n;
+                return;
+            }
+            n -= front;
+            self.chunks.pop_front();
+            self.offset = 0;
+        }
+    }
+}
+
+/// The fd a flush put in non-blocking mode (-1 for none) and its flags
+/// before; O_NONBLOCK is shared with every process using the terminal, so
+/// the signal handlers take it off while the terminal is given away
+#[cfg(unix)]
+static NONBLOCKING_FD: AtomicI32 = AtomicI32::new(-1);
+#[cfg(unix)]
+static NONBLOCKING_FLAGS: AtomicI32 = AtomicI32::new(0);
+
+/// Take the fd of a running flush out of non-blocking mode, or put it
+/// back; async-signal-safe
+#[cfg(unix)]
+pub(crate) fn set_output_blocking(blocking: bool) {
+    let fd = NONBLOCKING_FD.load(Ordering::SeqCst);
+    if fd < 0 {
+        return;
+    }
+    let flags = NONBLOCKING_FLAGS.load(Ordering::SeqCst);
+    let flags = if blocking {
+        flags
+    } else {
+        flags | libc::O_NONBLOCK
+    };
+    unsafe { libc::fcntl(fd, libc::F_SETFL, flags) };
+}
+
+/// Puts an fd in non-blocking mode until dropped
+#[cfg(unix)]
+struct NonBlocking {
+    fd: RawFd,
+    flags: libc::c_int,
+}
+
+#[cfg(unix)]
+impl NonBlocking {
+    fn set(fd: RawFd) -> io::Result<Self> {
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        NONBLOCKING_FLAGS.store(flags, Ordering::SeqCst);
+        NONBLOCKING_FD.store(fd, Ordering::SeqCst);
+        if unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+            NONBLOCKING_FD.store(-1, Ordering::SeqCst);
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, flags })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for NonBlocking {
+    fn drop(&mut self) {
+        NONBLOCKING_FD.store(-1, Ordering::SeqCst);
+        unsafe { libc::fcntl(self.fd, libc::F_SETFL, self.flags) };
+    }
+}
+
+/// Wait until `fd` accepts more output; false if `deadline` passed first
+#[cfg(unix)]
+fn wait_writable(fd: RawFd, deadline: Instant) -> io::Result<bool> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        let timeout = remaining.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut pfd, 1, timeout) } {
+            n if n > 0 => return Ok(true),
+            0 => return Ok(false),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+/// Size of the input read buffer
+#[cfg(unix)]
+const INPUT_BUFFER_SIZE: usize = 8192;
+
+/// Buffered reader over a raw input fd
+///
+/// Unlike `std::io::Stdin`, this can report how many bytes are already
+/// buffered, so callers know when `poll()` on the fd would miss input that
+/// has been read from the kernel but not yet consumed.
+#[cfg(unix)]
+pub(crate) struct InputReader {
+    fd: RawFd,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl InputReader {
+    pub(crate) fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            buf: vec![0; INPUT_BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// The fd being read
+    pub(crate) fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    /// Number of bytes read from the fd but not yet consumed
+    pub(crate) fn buffered(&self) -> usize {
+        self.len - self.pos
+    }
+
+    /// Put bytes back in front of the buffered input
+    pub(crate) fn unread(&mut self, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let mut combined = Vec::with_capacity(data.len() + self.buffered());
+        combined.extend_from_slice(data);
+        combined.extend_from_slice(&self.buf[self.pos..self.len]);
+
+        self.len = combined.len();
+        self.pos = 0;
+        if self.len > self.buf.len() {
+            self.buf = combined.into_boxed_slice();
+        } else {
+            self.buf[..self.len].copy_from_slice(&combined);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl io::Read for InputReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        use io::BufRead;
+
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+#[cfg(unix)]
+impl io::BufRead for InputReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos >= self.len {
+            let n = unsafe {
+                libc::read(
+                    self.fd,
+                    self.buf.as_mut_ptr() as *mut libc::c_void,
+                    self.buf.len(),
+                )
+            };
+
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+
+            self.pos = 0;
+            self.len = n as usize;
+            if n == 0 {
+                break; // EOF
+            }
+        }
+
+        Ok(&self.buf[self.pos..self.len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.len);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Tests putting fds in non-blocking mode share `NONBLOCKING_FD`
+    #[cfg(unix)]
+    static NONBLOCKING: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
     #[test]
     fn test_write_stdout_empty() {
         let result = write_stdout(&[]);
@@ -127,6 +495,126 @@ mod tests {
         assert_eq!(result.unwrap(), large_buf.len());
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_input_reader_buffering() {
+        use std::io::{BufRead, Read};
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let data = b"abc";
+        unsafe { libc::write(fds[1], data.as_ptr() as *const libc::c_void, data.len()) };
+
+        let mut reader = InputReader::new(fds[0]);
+        assert_eq!(reader.buffered(), 0);
+
+        let mut byte = [0u8; 1];
+        assert_eq!(reader.read(&mut byte).unwrap(), 1);
+        assert_eq!(&byte, b"a");
+        assert_eq!(reader.buffered(), 2); // Rest is buffered, fd is drained
+
+        assert_eq!(reader.fill_buf().unwrap(), b"bc");
+        reader.consume(2);
+        assert_eq!(reader.buffered(), 0);
+
+        reader.unread(b"xy");
+        assert_eq!(reader.fill_buf().unwrap(), b"xy");
+        reader.consume(1);
+        reader.unread(b"w");
+        assert_eq!(reader.fill_buf().unwrap(), b"wy");
+        reader.consume(2);
+
+        // EOF once the write end is closed
+        unsafe { libc::close(fds[1]) };
+        assert_eq!(reader.read(&mut byte).unwrap(), 0);
+        unsafe { libc::close(fds[0]) };
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_writer_chunks() {
+        let mut out = OutputWriter::stdout();
+        out.queue(b"abc");
+        out.queue(b"def");
+        assert_eq!(out.chunks.len(), 1);
+        out.queue(&vec![b'x'; CHUNK_SIZE]);
+        assert_eq!(out.chunks.len(), 2);
+        assert_eq!(out.queued(), CHUNK_SIZE + 6);
+
+        out.consume(4);
+        assert_eq!((out.offset, out.queued()), (4, CHUNK_SIZE + 2));
+        out.consume(3);
+        assert_eq!((out.chunks.len(), out.offset), (1, 1));
+
+        out.flush().unwrap();
+        assert_eq!(out.queued(), 0);
+        assert!(out.chunks.is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_writer_stall() {
+        use std::io::Read;
+        use std::os::unix::io::FromRawFd;
+
+        let _serial = NONBLOCKING.lock().unwrap();
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let mut reader = unsafe { std::fs::File::from_raw_fd(fds[0]) };
+
+        // Nobody reads the pipe, so it fills up and the flush gives up
+        let mut out = OutputWriter::with_fd(fds[1]);
+        out.set_write_timeout(Some(Duration::from_millis(10)));
+        let frame = vec![b'a'; 256 * 1024];
+        out.queue(&frame);
+        let err = out.flush().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        let left = out.queued();
+        assert!(left > 0 && left < frame.len());
+
+        // The fd is blocking again
+        let flags = unsafe { libc::fcntl(fds[1], libc::F_GETFL) };
+        assert_eq!(flags & libc::O_NONBLOCK, 0);
+
+        // Once the terminal catches up, the rest goes out
+        let drain = std::thread::spawn(move || {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).unwrap();
+            data.len()
+        });
+        out.set_write_timeout(None);
+        out.flush().unwrap();
+        assert_eq!(out.queued(), 0);
+        unsafe { libc::close(fds[1]) };
+        assert_eq!(drain.join().unwrap(), frame.len());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_set_output_blocking() {
+        let _serial = NONBLOCKING.lock().unwrap();
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let nonblocking = || unsafe { libc::fcntl(fds[1], libc::F_GETFL) } & libc::O_NONBLOCK != 0;
+
+        // As a signal handler does during a flush
+        let guard = NonBlocking::set(fds[1]).unwrap();
+        set_output_blocking(true);
+        assert!(!nonblocking());
+        set_output_blocking(false);
+        assert!(nonblocking());
+        drop(guard);
+        assert!(!nonblocking());
+
+        // Nothing to do outside a flush
+        set_output_blocking(false);
+        assert!(!nonblocking());
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+
     // Note: We can't easily test error conditions without mocking,
     // but the retry logic for EINTR is covered by the implementation
 }
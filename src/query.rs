@@ -0,0 +1,312 @@
+//! Terminal queries and their replies
+//!
+//! Queries are written to the terminal followed by a primary device
+//! attributes request (DA1). Every terminal answers DA1, so once its reply
+//! arrives any earlier query without a reply is known to be unsupported,
+//! without waiting for the full timeout.
+
+use crate::color::Color;
+
+/// Primary device attributes request, used to delimit query replies
+pub(crate) const DA1_QUERY: &str = "\x1b[c";
+
+/// XTVERSION and secondary device attributes (DA2) requests; DA1 follows
+/// every query
+pub(crate) const IDENTITY_QUERY: &str = "\x1b[>0q\x1b[>c";
+
+/// How long to wait for query replies
+pub(crate) const QUERY_TIMEOUT_MS: u64 = 200;
+
+/// A reply sequence sent by the terminal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Reply {
+    /// OSC payload (`ESC ] payload ST`)
+    Osc(String),
+    /// CSI private reply such as `?62;22c` or `>1;10;0c`
+    Csi(String),
+    /// DCS payload (`ESC P payload ST`)
+    Dcs(String),
+}
+
+impl Reply {
+    /// Check if this is the DA1 reply (`CSI ? ... c`)
+    pub(crate) fn is_primary_attributes(&self) -> bool {
+        matches!(self, Reply::Csi(s) if s.starts_with('?') && s.ends_with('c'))
+    }
+}
+
+/// Separate reply sequences from other input (e.g. keys typed meanwhile)
+///
+/// Incomplete sequences at the end are left in the other input.
+pub(crate) fn split_replies(data: &[u8]) -> (Vec<Reply>, Vec<u8>) {
+    let mut replies = Vec::new();
+    let mut other = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        match scan_reply(&data[i..]) {
+            Some((reply, len)) => {
+                replies.push(reply);
+                i += len;
+            }
+            None => {
+                other.push(data[i]);
+                i += 1;
+            }
+        }
+    }
+
+    (replies, other)
+}
+
+/// Parse one reply at the start of `data`, returning it and its length
+fn scan_reply(data: &[u8]) -> Option<(Reply, usize)> {
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+
+    match data {
+        [0x1b, b']', rest @ ..] | [0x1b, b'P', rest @ ..] => {
+            // String terminated by BEL or ST
+            let (end, term_len) = rest.iter().enumerate().find_map(|(i, &b)| match b {
+                0x07 => Some((i, 1)),
+                0x1b if rest.get(i + 1) == Some(&b'\\') => Some((i, 2)),
+                _ => None,
+            })?;
+            let payload = text(&rest[..end]);
+            let reply = if data[1] == b']' {
+                Reply::Osc(payload)
+            } else {
+                Reply::Dcs(payload)
+            };
+            Some((reply, 2 + end + term_len))
+        }
+        // Only private CSI replies; other CSI sequences are keys
+        [0x1b, b'[', marker @ (b'?' | b'>'), rest @ ..] => {
+            let end = rest.iter().position(|b| (0x40..=0x7e).contains(b))?;
+            let mut body = vec![*marker];
+            body.extend_from_slice(&rest[..=end]);
+            Some((Reply::Csi(text(&body)), 4 + end))
+        }
+        _ => None,
+    }
+}
+
+/// Parse an X11 color spec (`rgb:R/G/B` with 1-4 hex digits, or `#RRGGBB`)
+pub(crate) fn parse_color_spec(spec: &str) -> Option<Color> {
+    if let Some(rgb) = spec.strip_prefix("rgb:") {
+        let mut channels = rgb.split('/').map(|hex| {
+            if hex.is_empty() || hex.len() > 4 {
+                return None;
+            }
+            let value = u32::from_str_radix(hex, 16).ok()?;
+            let max = (1u32 << (4 * hex.len())) - 1;
+            Some(((value * 255 + max / 2) / max) as u8)
+        });
+        let (r, g, b) = (channels.next()??, channels.next()??, channels.next()??);
+        return channels.next().is_none().then_some(Color::Rgb(r, g, b));
+    }
+
+    let hex = spec.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// The terminal's default foreground and background colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DefaultColors {
+    /// Default foreground (None if the terminal didn't answer)
+    pub fg: Option<Color>,
+    /// Default background (None if the terminal didn't answer)
+    pub bg: Option<Color>,
+}
+
+impl DefaultColors {
+    /// Collect OSC 10/11 answers from a set of replies
+    pub(crate) fn from_replies(replies: &[Reply]) -> Self {
+        let mut colors = Self::default();
+        for reply in replies {
+            if let Reply::Osc(payload) = reply
+                && let Some((code, spec)) = payload.split_once(';')
+            {
+                match code {
+                    "10" => colors.fg = parse_color_spec(spec),
+                    "11" => colors.bg = parse_color_spec(spec),
+                    _ => {}
+                }
+            }
+        }
+        colors
+    }
+
+    /// Whether the background is dark (None if unknown)
+    pub fn is_dark(&self) -> Option<bool> {
+        match self.bg? {
+            Color::Rgb(r, g, b) => {
+                // Rec. 709 relative luminance
+                let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+                Some(luma < 128.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What the terminal says about itself in reply to XTVERSION, DA1 and DA2
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TerminalIdentity {
+    /// Program name from XTVERSION, e.g. `kitty` or `XTerm` (None if the
+    /// terminal didn't answer)
+    pub name: Option<String>,
+    /// Program version from XTVERSION
+    pub version: Option<String>,
+    /// DA1 conformance level, e.g. 62 for a VT220-class terminal
+    pub conformance: Option<u16>,
+    /// Other DA1 attributes, e.g. 4 for sixel graphics or 22 for ANSI color
+    pub attributes: Vec<u16>,
+    /// DA2 terminal type, e.g. 1 for VT220 or 41 for VT420
+    pub terminal_type: Option<u16>,
+    /// DA2 firmware version, e.g. xterm's patch number
+    pub firmware: Option<u32>,
+}
+
+impl TerminalIdentity {
+    /// Collect the XTVERSION, DA1 and DA2 answers from a set of replies
+    pub(crate) fn from_replies(replies: &[Reply]) -> Self {
+        let mut identity = Self::default();
+        let params =
+            |body: &str| -> Vec<u32> { body.split(';').filter_map(|p| p.parse().ok()).collect() };
+        for reply in replies {
+            match reply {
+                Reply::Dcs(payload) => {
+                    if let Some(text) = payload.strip_prefix(">|") {
+                        let (name, version) = split_version(text);
+                        identity.name = Some(name.to_string());
+                        identity.version = version.map(str::to_string);
+                    }
+                }
+                Reply::Csi(body) => {
+                    if let Some(da1) = body.strip_prefix('?').and_then(|b| b.strip_suffix('c')) {
+                        let mut codes = params(da1).into_iter().map(|code| code as u16);
+                        identity.conformance = codes.next();
+                        identity.attributes = codes.collect();
+                    } else if let Some(da2) =
+                        body.strip_prefix('>').and_then(|b| b.strip_suffix('c'))
+                    {
+                        let values = params(da2);
+                        identity.terminal_type = values.first().map(|&v| v as u16);
+                        identity.firmware = values.get(1).copied();
+                    }
+                }
+                Reply::Osc(_) => {}
+            }
+        }
+        identity
+    }
+
+    /// Check if DA1 listed attribute `code`
+    pub fn has_attribute(&self, code: u16) -> bool {
+        self.attributes.contains(&code)
+    }
+}
+
+/// Split an XTVERSION text such as `kitty(0.35.2)` or `WezTerm 20240203`
+/// into name and version
+fn split_version(text: &str) -> (&str, Option<&str>) {
+    if let Some((name, rest)) = text.split_once('(') {
+        return (name.trim(), Some(rest.trim_end_matches(')')));
+    }
+    match text.split_once(' ') {
+        Some((name, version)) => (name, Some(version.trim())),
+        None => (text, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_replies() {
+        let data = b"a\x1b]11;rgb:0000/0000/0000\x1b\\\x1b[A\x1b[?62;22cb\x1bP>|kitty(0.35)\x1b\\";
+        let (replies, other) = split_replies(data);
+
+        assert_eq!(
+            replies,
+            vec![
+                Reply::Osc("11;rgb:0000/0000/0000".to_string()),
+                Reply::Csi("?62;22c".to_string()),
+                Reply::Dcs(">|kitty(0.35)".to_string()),
+            ]
+        );
+        assert!(replies[1].is_primary_attributes());
+        // Keys typed during the query are kept
+        assert_eq!(other, b"a\x1b[Ab");
+    }
+
+    #[test]
+    fn test_incomplete_reply_kept_as_input() {
+        let (replies, other) = split_replies(b"\x1b]10;rgb:ff");
+        assert!(replies.is_empty());
+        assert_eq!(other, b"\x1b]10;rgb:ff");
+    }
+
+    #[test]
+    fn test_parse_color_spec() {
+        assert_eq!(
+            parse_color_spec("rgb:ffff/8080/0000"),
+            Some(Color::Rgb(255, 128, 0))
+        );
+        assert_eq!(parse_color_spec("rgb:f/8/0"), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(parse_color_spec("#1e1e2e"), Some(Color::Rgb(30, 30, 46)));
+
+        assert_eq!(parse_color_spec("rgb:ff/ff"), None);
+        assert_eq!(parse_color_spec("rgb:ff/ff/ff/ff"), None);
+        assert_eq!(parse_color_spec("rgb:fffff/0/0"), None);
+        assert_eq!(parse_color_spec("#12345"), None);
+        assert_eq!(parse_color_spec("red"), None);
+    }
+
+    #[test]
+    fn test_default_colors() {
+        let replies = [
+            Reply::Osc("10;rgb:dcdc/dcdc/dcdc".to_string()),
+            Reply::Osc("11;rgb:1c1c/1c1c/1c1c".to_string()),
+            Reply::Csi("?62c".to_string()),
+        ];
+        let colors = DefaultColors::from_replies(&replies);
+        assert_eq!(colors.fg, Some(Color::Rgb(220, 220, 220)));
+        assert_eq!(colors.bg, Some(Color::Rgb(28, 28, 28)));
+        assert_eq!(colors.is_dark(), Some(true));
+
+        assert_eq!(DefaultColors::default().is_dark(), None);
+    }
+
+    #[test]
+    fn test_terminal_identity() {
+        let replies = [
+            Reply::Dcs(">|kitty(0.35.2)".to_string()),
+            Reply::Csi(">1;4000;29c".to_string()),
+            Reply::Csi("?62;4;22c".to_string()),
+        ];
+        let identity = TerminalIdentity::from_replies(&replies);
+        assert_eq!(identity.name.as_deref(), Some("kitty"));
+        assert_eq!(identity.version.as_deref(), Some("0.35.2"));
+        assert_eq!(identity.conformance, Some(62));
+        assert!(identity.has_attribute(4) && !identity.has_attribute(1));
+        assert_eq!(
+            (identity.terminal_type, identity.firmware),
+            (Some(1), Some(4000))
+        );
+
+        let identity =
+            TerminalIdentity::from_replies(&[Reply::Dcs(">|WezTerm 20240203".to_string())]);
+        assert_eq!(identity.name.as_deref(), Some("WezTerm"));
+        assert_eq!(identity.version.as_deref(), Some("20240203"));
+        assert_eq!(
+            TerminalIdentity::from_replies(&[]),
+            TerminalIdentity::default()
+        );
+    }
+}
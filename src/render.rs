@@ -0,0 +1,339 @@
+//! Drawing from several threads
+//!
+//! A `Screen` is drawn on by one thread. Other threads get a `DrawHandle`
+//! from that thread's `Renderer` and send it draw commands over a channel;
+//! the render thread applies them to the screen and refreshes, so each
+//! batch of commands reaches the terminal as one frame.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use zaz::{Renderer, Screen};
+//!
+//! let mut scr = Screen::init()?;
+//! let mut renderer = Renderer::new();
+//!
+//! let handle = renderer.handle();
+//! std::thread::spawn(move || {
+//!     for i in 0..100 {
+//!         handle.print(0, 0, format!("working: {}%", i)).ok();
+//!         std::thread::sleep(Duration::from_millis(50));
+//!     }
+//! });
+//!
+//! while scr.getch_timeout(0)?.is_none() {
+//!     renderer.wait(&mut scr, Duration::from_millis(16))?;
+//! }
+//! scr.endwin()?;
+//! # Ok::<(), zaz::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::screen::Screen;
+use crate::style::Style;
+use crate::text::Line;
+use crate::window::Window;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+/// Boxed closure run on the render thread
+type ScreenFn = Box<dyn FnOnce(&mut Screen) -> Result<()> + Send>;
+
+/// A drawing operation sent to the render thread
+enum DrawCommand {
+    Print {
+        y: u16,
+        x: u16,
+        text: String,
+        style: Option<Style>,
+    },
+    Lines {
+        y: u16,
+        x: u16,
+        lines: Vec<Line>,
+    },
+    Window(Box<Window>),
+    Clear,
+    Call(ScreenFn),
+}
+
+/// Applies draw commands sent from other threads to a screen
+///
+/// Lives on the thread that owns the `Screen`.
+pub struct Renderer {
+    receiver: Receiver<DrawCommand>,
+    sender: Sender<DrawCommand>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer {
+    /// Most commands applied by one `apply`, `render` or `wait` call
+    ///
+    /// Commands past it stay queued for the next call, so handles sending
+    /// without pause can't keep the render thread from refreshing or
+    /// reading input.
+    pub const MAX_BATCH: usize = 1024;
+
+    /// Renderer with no handles yet
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { receiver, sender }
+    }
+
+    /// A handle for sending draw commands, to move to another thread
+    pub fn handle(&self) -> DrawHandle {
+        DrawHandle {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Apply the commands sent so far, up to `MAX_BATCH`, without
+    /// refreshing
+    ///
+    /// Returns the number of commands applied. A failing command doesn't
+    /// stop the ones after it; the first error is returned once all have
+    /// run.
+    pub fn apply(&mut self, scr: &mut Screen) -> Result<usize> {
+        let (applied, error) = self.apply_all(scr, None);
+        error.map_or(Ok(applied), Err)
+    }
+
+    /// Apply the commands sent so far (up to `MAX_BATCH`), then refresh if
+    /// there were any
+    ///
+    /// Commands are applied as by `apply`; the refresh happens even if one
+    /// of them failed.
+    pub fn render(&mut self, scr: &mut Screen) -> Result<usize> {
+        let (applied, error) = self.apply_all(scr, None);
+        if applied > 0 {
+            scr.refresh()?;
+        }
+        error.map_or(Ok(applied), Err)
+    }
+
+    /// Wait up to `timeout` for a command, then render it and the other
+    /// commands sent by then, up to `MAX_BATCH` in all
+    pub fn wait(&mut self, scr: &mut Screen, timeout: Duration) -> Result<usize> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(command) => {
+                let (applied, error) = self.apply_all(scr, Some(command));
+                scr.refresh()?;
+                error.map_or(Ok(applied), Err)
+            }
+            // The renderer keeps a sender, so the channel never disconnects
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => Ok(0),
+        }
+    }
+
+    /// Apply `first`, then the commands sent so far, `MAX_BATCH` in all at
+    /// most, returning how many succeeded and the first error
+    fn apply_all(
+        &mut self,
+        scr: &mut Screen,
+        first: Option<DrawCommand>,
+    ) -> (usize, Option<Error>) {
+        let mut applied = 0;
+        let mut error = None;
+        for command in first
+            .into_iter()
+            .chain(std::iter::from_fn(|| self.receiver.try_recv().ok()))
+            .take(Self::MAX_BATCH)
+        {
+            match command.apply(scr) {
+                Ok(()) => applied += 1,
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        (applied, error)
+    }
+}
+
+/// Sends draw commands to a `Renderer` from any thread
+///
+/// Cheap to clone. Commands from one handle are applied in order. Sending
+/// fails with `Error::RendererClosed` once the renderer is dropped.
+#[derive(Clone)]
+pub struct DrawHandle {
+    sender: Sender<DrawCommand>,
+}
+
+impl DrawHandle {
+    /// Print `text` at (y, x) in the screen's current style
+    pub fn print(&self, y: u16, x: u16, text: impl Into<String>) -> Result<()> {
+        self.send(DrawCommand::Print {
+            y,
+            x,
+            text: text.into(),
+            style: None,
+        })
+    }
+
+    /// Print `text` at (y, x) in `style`
+    pub fn print_styled(
+        &self,
+        y: u16,
+        x: u16,
+        text: impl Into<String>,
+        style: Style,
+    ) -> Result<()> {
+        self.send(DrawCommand::Print {
+            y,
+            x,
+            text: text.into(),
+            style: Some(style),
+        })
+    }
+
+    /// Draw styled lines with their top-left corner at (y, x)
+    pub fn put_lines(&self, y: u16, x: u16, lines: Vec<Line>) -> Result<()> {
+        self.send(DrawCommand::Lines { y, x, lines })
+    }
+
+    /// Copy the cells of a window drawn on this thread onto the screen
+    pub fn window(&self, window: Window) -> Result<()> {
+        self.send(DrawCommand::Window(Box::new(window)))
+    }
+
+    /// Clear the screen
+    pub fn clear(&self) -> Result<()> {
+        self.send(DrawCommand::Clear)
+    }
+
+    /// Run `f` on the render thread with the screen
+    pub fn call(&self, f: impl FnOnce(&mut Screen) -> Result<()> + Send + 'static) -> Result<()> {
+        self.send(DrawCommand::Call(Box::new(f)))
+    }
+
+    fn send(&self, command: DrawCommand) -> Result<()> {
+        self.sender.send(command).map_err(|_| Error::RendererClosed)
+    }
+}
+
+impl DrawCommand {
+    fn apply(self, scr: &mut Screen) -> Result<()> {
+        match self {
+            DrawCommand::Print { y, x, text, style } => match style {
+                Some(style) => {
+                    let saved = scr.current_style();
+                    scr.apply_style(&style)?;
+                    let result = scr.mvprint(y, x, &text);
+                    scr.apply_style(&saved)?;
+                    result
+                }
+                None => scr.mvprint(y, x, &text),
+            },
+            DrawCommand::Lines { y, x, lines } => scr.put_lines(y, x, &lines),
+            DrawCommand::Window(window) => scr.merge_window(&window),
+            DrawCommand::Clear => scr.clear(),
+            DrawCommand::Call(f) => f(scr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attr::Attr;
+    use crate::color::Color;
+
+    #[test]
+    fn test_commands_from_threads() {
+        let mut scr = Screen::with_size(5, 20);
+        scr.hold_refresh();
+        let mut renderer = Renderer::new();
+
+        let workers: Vec<_> = (0..3)
+            .map(|i| {
+                let handle = renderer.handle();
+                std::thread::spawn(move || handle.print(i, 0, format!("worker {}", i)))
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+
+        assert_eq!(renderer.render(&mut scr).unwrap(), 3);
+        for i in 0..3 {
+            assert_eq!(scr.read_str(i, 0, 8).unwrap(), format!("worker {}", i));
+        }
+        assert_eq!(renderer.render(&mut scr).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_styled_print_and_window() {
+        let mut scr = Screen::with_size(5, 20);
+        scr.hold_refresh();
+        let mut renderer = Renderer::new();
+        let handle = renderer.handle();
+
+        let style = Style::new().fg(Color::Red).attr(Attr::BOLD);
+        handle.print_styled(0, 0, "red", style).unwrap();
+        handle.print(0, 4, "plain").unwrap();
+        let mut window = Window::new(1, 3, 2, 1).unwrap();
+        window.print("win").unwrap();
+        handle.window(window).unwrap();
+        handle.call(|scr| scr.mvprint(4, 0, "called")).unwrap();
+
+        assert_eq!(
+            renderer.wait(&mut scr, Duration::from_millis(10)).unwrap(),
+            4
+        );
+        assert_eq!(scr.cell_at(0, 0).unwrap().fg, Color::Red);
+        assert_eq!(scr.cell_at(0, 4).unwrap().fg, Color::Reset);
+        assert_eq!(scr.read_str(2, 1, 3).unwrap(), "win");
+        assert_eq!(scr.read_str(4, 0, 6).unwrap(), "called");
+        assert_eq!(
+            renderer.wait(&mut scr, Duration::from_millis(1)).unwrap(),
+            0
+        );
+
+        drop(renderer);
+        assert!(matches!(handle.clear(), Err(Error::RendererClosed)));
+    }
+
+    #[test]
+    fn test_batches_are_capped() {
+        let mut scr = Screen::with_size(5, 20);
+        scr.hold_refresh();
+        let mut renderer = Renderer::new();
+        let handle = renderer.handle();
+
+        for _ in 0..Renderer::MAX_BATCH + 5 {
+            handle.clear().unwrap();
+        }
+        assert_eq!(
+            renderer.wait(&mut scr, Duration::from_millis(10)).unwrap(),
+            Renderer::MAX_BATCH
+        );
+        // The rest stay queued for the next call
+        assert_eq!(renderer.render(&mut scr).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_failing_command_doesnt_stop_the_rest() {
+        let mut scr = Screen::with_size(5, 20);
+        scr.hold_refresh();
+        let mut renderer = Renderer::new();
+        let handle = renderer.handle();
+
+        handle.print(0, 0, "before").unwrap();
+        handle.call(|_| Err(Error::NotInitialized)).unwrap();
+        handle.call(|_| Err(Error::RendererClosed)).unwrap();
+        handle.print(1, 0, "after").unwrap();
+
+        // The first error is returned once every command has run
+        assert!(matches!(
+            renderer.render(&mut scr),
+            Err(Error::NotInitialized)
+        ));
+        assert_eq!(scr.read_str(0, 0, 6).unwrap(), "before");
+        assert_eq!(scr.read_str(1, 0, 5).unwrap(), "after");
+        assert_eq!(renderer.render(&mut scr).unwrap(), 0);
+    }
+}
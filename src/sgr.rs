@@ -0,0 +1,275 @@
+//! SGR (style) encoding shared by `Screen` and `Window`
+
+use crate::attr::Attr;
+use crate::color::Color;
+use smallvec::SmallVec;
+
+/// SGR parameter for each attribute, in emission order
+const SGR_CODES: &[(Attr, &str)] = &[
+    (Attr::BOLD, "1"),
+    (Attr::DIM, "2"),
+    (Attr::ITALIC, "3"),
+    (Attr::UNDERLINE, "4"),
+    (Attr::BLINK, "5"),
+    (Attr::REVERSE, "7"),
+    (Attr::HIDDEN, "8"),
+    (Attr::STRIKETHROUGH, "9"),
+    (Attr::DOUBLE_UNDERLINE, "21"),
+    (Attr::OVERLINE, "53"),
+    // mintty; ignored by terminals without support
+    (Attr::SUPERSCRIPT, "73"),
+    (Attr::SUBSCRIPT, "74"),
+];
+
+/// SGR parameters turning attributes off, with every attribute each clears
+const SGR_OFF_CODES: &[(Attr, &str)] = &[
+    (Attr(Attr::BOLD.0 | Attr::DIM.0), "22"),
+    (Attr::ITALIC, "23"),
+    (Attr(Attr::UNDERLINE.0 | Attr::DOUBLE_UNDERLINE.0), "24"),
+    (Attr::BLINK, "25"),
+    (Attr::REVERSE, "27"),
+    (Attr::HIDDEN, "28"),
+    (Attr::STRIKETHROUGH, "29"),
+    (Attr::OVERLINE, "55"),
+    (Attr(Attr::SUPERSCRIPT.0 | Attr::SUBSCRIPT.0), "75"),
+];
+
+/// Attributes and colors the terminal draws with
+type Sgr = (Attr, Color, Color);
+
+/// The terminal's style after a reset
+const DEFAULT_SGR: Sgr = (Attr::NORMAL, Color::Reset, Color::Reset);
+
+/// Writes the SGR sequences that switch the terminal between styles
+///
+/// Tracks the terminal's current style and emits the shorter of two
+/// encodings: the minimal change (attributes turned off and on, colors
+/// that differ) or a reset followed by everything that isn't the default.
+/// Nothing is written when the style doesn't change.
+#[derive(Debug, Clone)]
+pub(crate) struct StyleEncoder {
+    current: Sgr,
+    diff: SmallVec<[u8; 64]>,
+    reset: SmallVec<[u8; 64]>,
+    color_buf: String,
+}
+
+impl Default for StyleEncoder {
+    fn default() -> Self {
+        Self {
+            current: DEFAULT_SGR,
+            diff: SmallVec::new(),
+            reset: SmallVec::new(),
+            color_buf: String::with_capacity(20),
+        }
+    }
+}
+
+impl StyleEncoder {
+    /// Encoder for a terminal in the default style
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switch to `attr`, `fg` and `bg`, appending the sequence to `out`
+    pub(crate) fn encode(&mut self, attr: Attr, fg: Color, bg: Color, out: &mut String) {
+        let target = (attr, fg, bg);
+        if self.current == target {
+            return;
+        }
+
+        // Reset, then everything that isn't the default
+        self.reset.clear();
+        self.reset.push(b'0');
+        let mut needs_separator = true;
+        push_attr_codes(attr, &mut self.reset, &mut needs_separator);
+        if fg != Color::Reset {
+            push_color(&mut self.reset, &mut self.color_buf, fg, true);
+        }
+        if bg != Color::Reset {
+            push_color(&mut self.reset, &mut self.color_buf, bg, false);
+        }
+
+        // Minimal change: turn removed attributes off (some codes clear two
+        // attributes, which are turned back on if still wanted), then added
+        // ones on
+        let (old_attr, old_fg, old_bg) = self.current;
+        self.diff.clear();
+        let mut needs_separator = false;
+        let removed = old_attr & !attr;
+        let mut cleared = Attr::NORMAL;
+        for &(group, code) in SGR_OFF_CODES {
+            if removed.intersects(group) {
+                if needs_separator {
+                    self.diff.push(b';');
+                }
+                self.diff.extend_from_slice(code.as_bytes());
+                needs_separator = true;
+                cleared = cleared | group;
+            }
+        }
+        let still_on = old_attr & !cleared;
+        push_attr_codes(attr & !still_on, &mut self.diff, &mut needs_separator);
+        if fg != old_fg {
+            push_color(&mut self.diff, &mut self.color_buf, fg, true);
+        }
+        if bg != old_bg {
+            push_color(&mut self.diff, &mut self.color_buf, bg, false);
+        }
+
+        let sequence = if self.diff.len() < self.reset.len() {
+            &self.diff
+        } else {
+            &self.reset
+        };
+
+        out.push_str("\x1b[");
+        out.push_str(std::str::from_utf8(sequence).unwrap());
+        out.push('m');
+        self.current = target;
+    }
+
+    /// Return the terminal to the default style, so output written by
+    /// others starts from a known state
+    pub(crate) fn finish(&mut self, out: &mut String) {
+        if self.current != DEFAULT_SGR {
+            out.push_str("\x1b[0m");
+            self.current = DEFAULT_SGR;
+        }
+    }
+
+    /// Note that the terminal was reset to the default style by other means
+    pub(crate) fn assume_default(&mut self) {
+        self.current = DEFAULT_SGR;
+    }
+}
+
+/// Append a color's SGR parameters to `buf`, `;`-separated from what's
+/// already there
+fn push_color(buf: &mut SmallVec<[u8; 64]>, scratch: &mut String, color: Color, foreground: bool) {
+    scratch.clear();
+    if foreground {
+        color.write_ansi_fg(scratch);
+    } else {
+        color.write_ansi_bg(scratch);
+    }
+    if !buf.is_empty() {
+        buf.push(b';');
+    }
+    buf.extend_from_slice(scratch.as_bytes());
+}
+
+/// SGR parameters for the attributes set in `attr`
+pub(crate) fn attr_codes(attr: Attr) -> impl Iterator<Item = &'static str> {
+    SGR_CODES
+        .iter()
+        .filter(move |(flag, _)| attr.contains(*flag))
+        .map(|(_, code)| *code)
+}
+
+/// Append the SGR parameters for `attr` to `buf`, `;`-separated
+///
+/// `needs_separator` tells whether `buf` already holds a parameter and is
+/// updated for whatever follows.
+pub(crate) fn push_attr_codes(
+    attr: Attr,
+    buf: &mut SmallVec<[u8; 64]>,
+    needs_separator: &mut bool,
+) {
+    for code in attr_codes(attr) {
+        if *needs_separator {
+            buf.push(b';');
+        }
+        buf.extend_from_slice(code.as_bytes());
+        *needs_separator = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(attr: Attr) -> String {
+        let mut buf = SmallVec::new();
+        let mut needs_separator = false;
+        push_attr_codes(attr, &mut buf, &mut needs_separator);
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_encode_combinations() {
+        assert_eq!(encode(Attr::NORMAL), "");
+        assert_eq!(encode(Attr::DIM | Attr::STRIKETHROUGH), "2;9");
+        assert_eq!(encode(Attr::DOUBLE_UNDERLINE | Attr::OVERLINE), "21;53");
+        assert_eq!(encode(Attr::BOLD | Attr::SUPERSCRIPT), "1;73");
+        assert_eq!(encode(Attr::SUBSCRIPT), "74");
+    }
+
+    /// Sequences written switching through `styles` from the default
+    fn transitions(styles: &[(Attr, Color, Color)]) -> Vec<String> {
+        let mut encoder = StyleEncoder::new();
+        styles
+            .iter()
+            .map(|&(attr, fg, bg)| {
+                let mut out = String::new();
+                encoder.encode(attr, fg, bg, &mut out);
+                out
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_encoder_minimal_changes() {
+        let bold = (Attr::BOLD, Color::Reset, Color::Reset);
+        let bold_red = (Attr::BOLD, Color::Red, Color::Reset);
+        let italic_red = (Attr::ITALIC, Color::Red, Color::Reset);
+        let normal_red = (Attr::NORMAL, Color::Red, Color::Reset);
+        assert_eq!(
+            transitions(&[bold, bold, bold_red, italic_red, normal_red]),
+            vec!["\x1b[1m", "", "\x1b[31m", "\x1b[22;3m", "\x1b[23m"]
+        );
+    }
+
+    #[test]
+    fn test_encoder_prefers_reset_when_shorter() {
+        let styled = (
+            Attr::BOLD | Attr::UNDERLINE,
+            Color::Rgb(1, 2, 3),
+            Color::Blue,
+        );
+        let plain = (Attr::NORMAL, Color::Reset, Color::Reset);
+        assert_eq!(
+            transitions(&[styled, plain]),
+            vec!["\x1b[1;4;38;2;1;2;3;44m", "\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn test_encoder_shared_off_codes() {
+        // 22 clears both bold and dim; dim is turned back on
+        let both = (Attr::BOLD | Attr::DIM, Color::Red, Color::Reset);
+        let dim = (Attr::DIM, Color::Red, Color::Reset);
+        assert_eq!(transitions(&[both, dim])[1], "\x1b[22;2m");
+    }
+
+    #[test]
+    fn test_encoder_finish() {
+        let mut encoder = StyleEncoder::new();
+        let mut out = String::new();
+        encoder.finish(&mut out);
+        assert_eq!(out, "");
+        encoder.encode(Attr::NORMAL, Color::Green, Color::Reset, &mut out);
+        encoder.finish(&mut out);
+        assert_eq!(out, "\x1b[32m\x1b[0m");
+    }
+
+    #[test]
+    fn test_separator_continues() {
+        let mut buf: SmallVec<[u8; 64]> = SmallVec::new();
+        buf.push(b'0');
+        let mut needs_separator = true;
+        push_attr_codes(Attr::BOLD, &mut buf, &mut needs_separator);
+        assert_eq!(&buf[..], b"0;1");
+        assert!(needs_separator);
+    }
+}
@@ -0,0 +1,289 @@
+//! Keyboard shortcuts written as strings like `"ctrl+shift+p"`
+//!
+//! A `Shortcut` matches a key however the terminal encoded it: legacy
+//! control characters (`Key::Ctrl`), ESC-prefixed Alt keys, modifier-encoded
+//! CSI sequences (`Key::Modified`) and kitty protocol events (`Key::Enhanced`).
+
+use crate::error::{Error, Result};
+use crate::input::Key;
+use crate::kitty::{KeyEvent, KeyEventType, Modifiers};
+use std::fmt;
+use std::str::FromStr;
+
+/// Modifiers that take part in matching (lock keys are ignored)
+const MATCHED_MODIFIERS: Modifiers = Modifiers::SHIFT
+    .union(Modifiers::ALT)
+    .union(Modifiers::CTRL)
+    .union(Modifiers::SUPER);
+
+/// Named keys accepted in shortcut strings; the first name is canonical
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("enter", Key::Enter),
+    ("return", Key::Enter),
+    ("esc", Key::Escape),
+    ("escape", Key::Escape),
+    ("tab", Key::Tab),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("del", Key::Delete),
+    ("insert", Key::Insert),
+    ("ins", Key::Insert),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pgup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("pgdn", Key::PageDown),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("space", Key::Char(' ')),
+    ("plus", Key::Char('+')),
+];
+
+/// A key with the modifiers that must be held
+///
+/// ```
+/// use zaz::{Key, Shortcut};
+///
+/// let palette: Shortcut = "ctrl+shift+p".parse()?;
+/// let save: Shortcut = "ctrl+s".parse()?;
+/// assert!(save.matches(&Key::Ctrl('s')));
+/// assert!(!palette.matches(&Key::Ctrl('p')));
+/// assert_eq!(palette.to_string(), "ctrl+shift+p");
+/// # Ok::<(), zaz::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    key: Key,
+    modifiers: Modifiers,
+}
+
+impl Shortcut {
+    /// Shortcut for `key` with `modifiers` held
+    ///
+    /// Letters are case-insensitive: `Key::Char('P')` means Shift+p.
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        let (key, extra) = normalize(key);
+        Self {
+            key,
+            modifiers: (modifiers | extra) & MATCHED_MODIFIERS,
+        }
+    }
+
+    /// The key, without modifiers
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// The modifiers that must be held
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Check if `key` is this shortcut
+    ///
+    /// Modifiers must match exactly. Kitty release events never match.
+    /// Legacy control characters can't carry Shift, so `Key::Ctrl('p')`
+    /// only matches `ctrl+p`.
+    pub fn matches(&self, key: &Key) -> bool {
+        let (key, modifiers) = match key {
+            Key::Enhanced(event) => match enhanced_key(event) {
+                Some(pair) => pair,
+                None => return false,
+            },
+            key => key.modified(),
+        };
+        let (key, extra) = normalize(key);
+        key == self.key && (modifiers | extra) & MATCHED_MODIFIERS == self.modifiers
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = Error;
+
+    /// Parse `+`-separated modifiers and a key, e.g. `"alt+enter"`, `"f5"`
+    /// or `"ctrl+pgup"`
+    ///
+    /// Names are case-insensitive. Modifiers: `ctrl`/`control`, `shift`,
+    /// `alt`/`option`, `super`/`cmd`/`win`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidShortcut(s.to_string());
+        let text = s.trim().to_lowercase();
+        // A trailing "+" is the plus key itself (as in "ctrl++")
+        let (mods, key) = match text.strip_suffix("++") {
+            Some(rest) => (rest, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", &text)),
+        };
+
+        let mut modifiers = Modifiers::empty();
+        for name in mods.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name {
+                "ctrl" | "control" => Modifiers::CTRL,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                "super" | "cmd" | "win" => Modifiers::SUPER,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let key = if let Some((_, key)) = KEY_NAMES.iter().find(|(name, _)| *name == key) {
+            key.clone()
+        } else if let Some(n) = key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            if !(1..=24).contains(&n) {
+                return Err(invalid());
+            }
+            Key::F(n)
+        } else {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Key::Char(ch),
+                _ => return Err(invalid()),
+            }
+        };
+        Ok(Shortcut::new(key, modifiers))
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (Modifiers::CTRL, "ctrl"),
+            (Modifiers::ALT, "alt"),
+            (Modifiers::SHIFT, "shift"),
+            (Modifiers::SUPER, "super"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match &self.key {
+            Key::F(n) => write!(f, "f{}", n),
+            Key::Char(ch) if *ch != ' ' && *ch != '+' => write!(f, "{}", ch),
+            key => {
+                let name = KEY_NAMES
+                    .iter()
+                    .find(|(_, k)| k == key)
+                    .map(|(name, _)| *name);
+                write!(f, "{}", name.unwrap_or("?"))
+            }
+        }
+    }
+}
+
+/// Uppercase letters as Shift+letter
+fn normalize(key: Key) -> (Key, Modifiers) {
+    match key {
+        Key::Char(ch) if ch.is_uppercase() => (
+            Key::Char(ch.to_lowercase().next().unwrap_or(ch)),
+            Modifiers::SHIFT,
+        ),
+        key => (key, Modifiers::empty()),
+    }
+}
+
+/// Key and modifiers of a kitty protocol event, None for releases and keys
+/// without a `Key` equivalent
+pub(crate) fn enhanced_key(event: &KeyEvent) -> Option<(Key, Modifiers)> {
+    if event.event_type == KeyEventType::Release {
+        return None;
+    }
+    let key = match event.code {
+        9 => Key::Tab,
+        13 => Key::Enter,
+        27 => Key::Escape,
+        127 => Key::Backspace,
+        57348 => Key::Insert,
+        57349 => Key::Delete,
+        57350 => Key::Left,
+        57351 => Key::Right,
+        57352 => Key::Up,
+        57353 => Key::Down,
+        57354 => Key::PageUp,
+        57355 => Key::PageDown,
+        57356 => Key::Home,
+        57357 => Key::End,
+        code @ 57364..=57387 => Key::F((code - 57363) as u8),
+        code => Key::Char(char::from_u32(code)?),
+    };
+    Some((key, event.modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortcut(s: &str) -> Shortcut {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            shortcut("Ctrl+Shift+P"),
+            Shortcut::new(Key::Char('p'), Modifiers::CTRL | Modifiers::SHIFT)
+        );
+        assert_eq!(
+            shortcut("alt+enter"),
+            Shortcut::new(Key::Enter, Modifiers::ALT)
+        );
+        assert_eq!(shortcut("ctrl+pgup").key(), &Key::PageUp);
+        assert_eq!(shortcut("f12").key(), &Key::F(12));
+        assert_eq!(shortcut("ctrl++").key(), &Key::Char('+'));
+        assert_eq!(shortcut("?").modifiers(), Modifiers::empty());
+
+        assert!("hyper+x".parse::<Shortcut>().is_err());
+        assert!("ctrl+abc".parse::<Shortcut>().is_err());
+        assert!("f30".parse::<Shortcut>().is_err());
+        assert!("".parse::<Shortcut>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for s in [
+            "ctrl+shift+p",
+            "alt+enter",
+            "ctrl+pageup",
+            "f5",
+            "space",
+            "ctrl+plus",
+        ] {
+            assert_eq!(shortcut(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_matches_legacy_keys() {
+        assert!(shortcut("ctrl+s").matches(&Key::Ctrl('s')));
+        assert!(!shortcut("ctrl+s").matches(&Key::Char('s')));
+        assert!(shortcut("shift+p").matches(&Key::Char('P')));
+        assert!(shortcut("alt+shift+p").matches(&Key::Alt('P')));
+        assert!(shortcut("alt+x").matches(&Key::Alt('x')));
+        assert!(
+            shortcut("ctrl+right").matches(&Key::Modified(Box::new(Key::Right), Modifiers::CTRL))
+        );
+        assert!(!shortcut("right").matches(&Key::Modified(Box::new(Key::Right), Modifiers::CTRL)));
+        assert!(shortcut("f5").matches(&Key::F(5)));
+    }
+
+    #[test]
+    fn test_matches_kitty_events() {
+        let event = |code, modifiers| Key::Enhanced(KeyEvent::with_modifiers(code, modifiers));
+
+        let palette = shortcut("ctrl+shift+p");
+        assert!(palette.matches(&event(112, Modifiers::CTRL | Modifiers::SHIFT)));
+        // Caps Lock doesn't get in the way
+        assert!(palette.matches(&event(
+            112,
+            Modifiers::CTRL | Modifiers::SHIFT | Modifiers::CAPS_LOCK
+        )));
+        assert!(!palette.matches(&event(112, Modifiers::CTRL)));
+        assert!(shortcut("alt+enter").matches(&event(13, Modifiers::ALT)));
+        assert!(shortcut("f1").matches(&event(57364, Modifiers::empty())));
+
+        let mut release = KeyEvent::with_modifiers(112, Modifiers::CTRL | Modifiers::SHIFT);
+        release.event_type = KeyEventType::Release;
+        assert!(!palette.matches(&Key::Enhanced(release)));
+    }
+}
@@ -0,0 +1,402 @@
+//! Restoring the terminal when a signal ends the process
+//!
+//! With `ScreenBuilder::signals(true)`, Ctrl+C raises SIGINT, whose default
+//! action would leave the shell in raw mode on the alternate screen. While
+//! a screen is active, SIGINT, SIGQUIT and SIGTERM instead run a handler
+//! that puts back the terminal settings saved at init, pops the Kitty
+//! keyboard modes the screen pushed, turns off mouse reporting and
+//! bracketed paste, shows the cursor and leaves the alternate screen, then
+//! lets the signal take its default action. If the app had a handler of its
+//! own, that one runs instead; when it returns the program goes on, so the
+//! screen is entered again as after a stop. Signals the app ignored stay
+//! ignored.
+//!
+//! Ctrl+Z (SIGTSTP) restores the terminal the same way and stops the
+//! process. When it is continued, the handler puts raw mode and the
+//! alternate screen back and flags the resume; the screen then turns its
+//! input modes back on, pushes its keyboard modes again and repaints
+//! everything on its next refresh. The handlers only make async-signal-safe
+//! calls.
+
+#[cfg(unix)]
+use std::ffi::c_void;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(unix)]
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+
+/// Resets style, mouse reporting and bracketed paste, shows the cursor and
+/// leaves the alternate screen
+#[cfg(unix)]
+const RESTORE_SEQUENCE: &[u8] =
+    b"\x1b[0m\x1b[?1006l\x1b[?1002l\x1b[?1000l\x1b[?2004l\x1b[?25h\x1b[?1049l";
+
+/// Enters the alternate screen and hides the cursor
+#[cfg(unix)]
+const ENTER_SEQUENCE: &[u8] = b"\x1b[?1049h\x1b[?25l";
+
+/// `RESTORE_SEQUENCE` for terminals without an alternate screen: clears
+/// the normal one instead
+#[cfg(unix)]
+const RESTORE_MAIN_SEQUENCE: &[u8] =
+    b"\x1b[0m\x1b[?1006l\x1b[?1002l\x1b[?1000l\x1b[?2004l\x1b[?25h\x1b[2J\x1b[H";
+
+/// `ENTER_SEQUENCE` for terminals without an alternate screen
+#[cfg(unix)]
+const ENTER_MAIN_SEQUENCE: &[u8] = b"\x1b[?25l";
+
+/// Signals that end the process and get the restoring handler
+#[cfg(unix)]
+const RESTORED_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGQUIT, libc::SIGTERM];
+
+// Read by the handler, so plain atomics rather than locks
+#[cfg(unix)]
+static TTY_FD: AtomicI32 = AtomicI32::new(-1);
+#[cfg(unix)]
+static OUTPUT_FD: AtomicI32 = AtomicI32::new(libc::STDOUT_FILENO);
+#[cfg(unix)]
+static HAVE_TERMIOS: AtomicBool = AtomicBool::new(false);
+#[cfg(unix)]
+static ALT_SCREEN: AtomicBool = AtomicBool::new(true);
+#[cfg(unix)]
+static mut SAVED_TERMIOS: Option<libc::termios> = None;
+#[cfg(unix)]
+static mut RAW_TERMIOS: Option<libc::termios> = None;
+/// Pops the screen's Kitty keyboard modes, the first `KEYBOARD_RESTORE_LEN`
+/// bytes; written before the restore sequence
+#[cfg(unix)]
+static mut KEYBOARD_RESTORE: [u8; 64] = [0; 64];
+#[cfg(unix)]
+static KEYBOARD_RESTORE_LEN: AtomicUsize = AtomicUsize::new(0);
+/// Set after the process was stopped by SIGTSTP and continued
+#[cfg(unix)]
+static RESUMED: AtomicBool = AtomicBool::new(false);
+/// Dispositions of `RESTORED_SIGNALS` before `install`, for the handler to
+/// chain to; a copy of `PREVIOUS`, which it can't lock
+#[cfg(unix)]
+static mut CHAINED: [Option<libc::sigaction>; 3] = [None; 3];
+
+// Dispositions replaced by `install`, put back by `uninstall`
+#[cfg(unix)]
+static PREVIOUS: Mutex<Vec<(libc::c_int, libc::sigaction)>> = Mutex::new(Vec::new());
+
+/// Install the restoring handlers for a terminal read from `fd` and drawn
+/// on `output_fd`, whose settings before raw mode were `original` (None if
+/// it isn't a TTY), on the alternate screen if `alt_screen`
+///
+/// The handlers serve one terminal: returns false, doing nothing, if they
+/// are already installed for another.
+#[cfg(unix)]
+pub(crate) fn install(
+    fd: RawFd,
+    output_fd: RawFd,
+    original: Option<&libc::termios>,
+    alt_screen: bool,
+) -> bool {
+    let mut previous = PREVIOUS.lock().unwrap();
+    if !previous.is_empty() {
+        return false;
+    }
+
+    HAVE_TERMIOS.store(false, Ordering::SeqCst);
+    if let Some(termios) = original {
+        // No handler is installed yet, so nothing reads these concurrently.
+        // Raw mode is already on, so the current settings are the raw ones.
+        unsafe {
+            let mut raw: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut raw) == 0 {
+                SAVED_TERMIOS = Some(*termios);
+                RAW_TERMIOS = Some(raw);
+                HAVE_TERMIOS.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+    TTY_FD.store(fd, Ordering::SeqCst);
+    OUTPUT_FD.store(output_fd, Ordering::SeqCst);
+    ALT_SCREEN.store(alt_screen, Ordering::SeqCst);
+
+    // Save the dispositions to chain to before any handler can run
+    for (i, &signal) in RESTORED_SIGNALS.iter().enumerate() {
+        unsafe {
+            let mut old: libc::sigaction = std::mem::zeroed();
+            let saved = libc::sigaction(signal, std::ptr::null(), &mut old) == 0;
+            CHAINED[i] = saved.then_some(old);
+        }
+    }
+
+    let handlers = RESTORED_SIGNALS.map(|signal| {
+        let handler: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut c_void) =
+            restore_and_chain;
+        (signal, handler as usize, libc::SA_SIGINFO)
+    });
+    let stop: extern "C" fn(libc::c_int) = stop_and_resume;
+    for (signal, handler, flags) in handlers
+        .into_iter()
+        .chain([(libc::SIGTSTP, stop as usize, 0)])
+    {
+        let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+        if set_handler(signal, handler, flags, &mut old) {
+            previous.push((signal, old));
+        }
+    }
+    true
+}
+
+/// Check if the process was continued after a stop since the last call
+#[cfg(unix)]
+pub(crate) fn take_resumed() -> bool {
+    RESUMED.swap(false, Ordering::SeqCst)
+}
+
+/// Check if a resume is waiting for `take_resumed`, without clearing it
+#[cfg(unix)]
+pub(crate) fn resume_pending() -> bool {
+    RESUMED.load(Ordering::SeqCst)
+}
+
+/// Set `handler` (a function pointer) for `signal` with `flags`, saving
+/// the old disposition to `old` unless it's null
+#[cfg(unix)]
+fn set_handler(
+    signal: libc::c_int,
+    handler: usize,
+    flags: libc::c_int,
+    old: *mut libc::sigaction,
+) -> bool {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = flags;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(signal, &action, old) == 0
+    }
+}
+
+/// Set the sequence popping the screen's Kitty keyboard modes (empty for
+/// none); one too long for the handlers' buffer is left out
+#[cfg(unix)]
+pub(crate) fn set_keyboard_restore(sequence: &[u8]) {
+    // The handlers see no sequence while it's replaced
+    KEYBOARD_RESTORE_LEN.store(0, Ordering::SeqCst);
+    unsafe {
+        let buffer = &mut *std::ptr::addr_of_mut!(KEYBOARD_RESTORE);
+        if sequence.len() > buffer.len() {
+            return;
+        }
+        buffer[..sequence.len()].copy_from_slice(sequence);
+    }
+    KEYBOARD_RESTORE_LEN.store(sequence.len(), Ordering::SeqCst);
+}
+
+/// Put back the dispositions replaced by `install`
+#[cfg(unix)]
+pub(crate) fn uninstall() {
+    KEYBOARD_RESTORE_LEN.store(0, Ordering::SeqCst);
+    let mut previous = PREVIOUS.lock().unwrap();
+    for (signal, old) in previous.drain(..) {
+        unsafe { libc::sigaction(signal, &old, std::ptr::null_mut()) };
+    }
+}
+
+/// Apply saved terminal settings (if any) and write `sequence`
+#[cfg(unix)]
+unsafe fn switch_terminal(termios: *const Option<libc::termios>, sequence: &[u8]) {
+    unsafe {
+        if HAVE_TERMIOS.load(Ordering::SeqCst)
+            && let Some(termios) = &*termios
+        {
+            libc::tcsetattr(TTY_FD.load(Ordering::SeqCst), libc::TCSANOW, termios);
+        }
+        libc::write(
+            OUTPUT_FD.load(Ordering::SeqCst),
+            sequence.as_ptr() as *const libc::c_void,
+            sequence.len(),
+        );
+    }
+}
+
+/// Pop the keyboard modes and apply the settings from before raw mode,
+/// leaving the output blocking for the shell
+#[cfg(unix)]
+unsafe fn leave_terminal(restore: &[u8]) {
+    unsafe {
+        crate::platform_io::set_output_blocking(true);
+        libc::write(
+            OUTPUT_FD.load(Ordering::SeqCst),
+            std::ptr::addr_of!(KEYBOARD_RESTORE) as *const libc::c_void,
+            KEYBOARD_RESTORE_LEN.load(Ordering::SeqCst),
+        );
+        switch_terminal(std::ptr::addr_of!(SAVED_TERMIOS), restore);
+    }
+}
+
+/// Apply raw mode and write `enter`, then let an interrupted flush go on
+/// without blocking
+#[cfg(unix)]
+unsafe fn enter_terminal(enter: &[u8]) {
+    unsafe {
+        switch_terminal(std::ptr::addr_of!(RAW_TERMIOS), enter);
+        crate::platform_io::set_output_blocking(false);
+    }
+}
+
+/// The sequences entering and leaving the screen the handlers serve
+#[cfg(unix)]
+fn sequences() -> (&'static [u8], &'static [u8]) {
+    if ALT_SCREEN.load(Ordering::SeqCst) {
+        (ENTER_SEQUENCE, RESTORE_SEQUENCE)
+    } else {
+        (ENTER_MAIN_SEQUENCE, RESTORE_MAIN_SEQUENCE)
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn restore_and_chain(
+    signal: libc::c_int,
+    info: *mut libc::siginfo_t,
+    context: *mut c_void,
+) {
+    unsafe {
+        let previous = RESTORED_SIGNALS
+            .iter()
+            .position(|&s| s == signal)
+            .and_then(|i| (*std::ptr::addr_of!(CHAINED))[i]);
+        let handler = previous.map_or(libc::SIG_DFL, |action| action.sa_sigaction);
+        if handler == libc::SIG_IGN {
+            return;
+        }
+
+        let (enter, restore) = sequences();
+        leave_terminal(restore);
+        let Some(action) = previous.filter(|_| handler != libc::SIG_DFL) else {
+            // Taken once this handler returns
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+            return;
+        };
+
+        if action.sa_flags & libc::SA_SIGINFO != 0 {
+            let chained: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut c_void) =
+                std::mem::transmute(handler);
+            chained(signal, info, context);
+        } else {
+            let chained: extern "C" fn(libc::c_int) = std::mem::transmute(handler);
+            chained(signal);
+        }
+
+        // The app's handler returned and the program goes on
+        enter_terminal(enter);
+        RESUMED.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn stop_and_resume(_: libc::c_int) {
+    unsafe {
+        let (enter, restore) = sequences();
+        leave_terminal(restore);
+
+        // Stop for real: SIGTSTP is blocked while its handler runs
+        libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+        let mut mask: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut mask);
+        libc::sigaddset(&mut mask, libc::SIGTSTP);
+        libc::sigprocmask(libc::SIG_UNBLOCK, &mask, std::ptr::null_mut());
+        libc::raise(libc::SIGTSTP);
+
+        // Continued
+        enter_terminal(enter);
+        let stop: extern "C" fn(libc::c_int) = stop_and_resume;
+        set_handler(libc::SIGTSTP, stop as usize, 0, std::ptr::null_mut());
+        RESUMED.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn uninstall() {}
+
+#[cfg(not(unix))]
+pub(crate) fn take_resumed() -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub(crate) fn resume_pending() -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    // Tests replace process-wide dispositions, so they run one at a time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn handler_of(signal: libc::c_int) -> usize {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(signal, std::ptr::null(), &mut action);
+            action.sa_sigaction
+        }
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let _serial = SERIAL.lock().unwrap();
+        let before = handler_of(libc::SIGQUIT);
+        assert!(install(-1, 1, None, true));
+        assert_eq!(
+            handler_of(libc::SIGQUIT),
+            restore_and_chain as extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut c_void)
+                as usize
+        );
+        assert_eq!(
+            handler_of(libc::SIGTSTP),
+            stop_and_resume as extern "C" fn(libc::c_int) as usize
+        );
+        // Installing twice keeps the first saved dispositions
+        assert!(!install(-1, 1, None, true));
+        uninstall();
+        assert_eq!(handler_of(libc::SIGQUIT), before);
+    }
+
+    #[test]
+    fn test_chain_to_previous_handler() {
+        static HANDLED: AtomicBool = AtomicBool::new(false);
+        extern "C" fn app_handler(_: libc::c_int) {
+            HANDLED.store(true, Ordering::SeqCst);
+        }
+
+        let _serial = SERIAL.lock().unwrap();
+        let mut original: libc::sigaction = unsafe { std::mem::zeroed() };
+        let app: extern "C" fn(libc::c_int) = app_handler;
+        assert!(set_handler(libc::SIGTERM, app as usize, 0, &mut original));
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        assert!(install(-1, fds[1], None, true));
+        set_keyboard_restore(b"\x1b[<2u");
+        unsafe { libc::raise(libc::SIGTERM) };
+        assert!(HANDLED.load(Ordering::SeqCst));
+        // Back on the screen afterwards
+        assert!(take_resumed());
+
+        // Keyboard modes are popped before the screen is left, and
+        // bracketed paste is turned off
+        let mut written = [0u8; 64];
+        let n = unsafe { libc::read(fds[0], written.as_mut_ptr() as *mut c_void, 64) };
+        let written = &written[..n as usize];
+        assert!(written.starts_with(b"\x1b[<2u\x1b[0m"));
+        assert!(written.windows(8).any(|w| w == b"\x1b[?2004l"));
+
+        uninstall();
+        assert_eq!(handler_of(libc::SIGTERM), app as usize);
+        unsafe {
+            libc::sigaction(libc::SIGTERM, &original, std::ptr::null_mut());
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Soft-label keys: a bar of function-key labels on the bottom line
+//!
+//! The curses `slk_*` API. The bar lives in lines reserved with
+//! `Screen::reserve_lines`, so the screen's own drawing never covers it.
+
+use crate::attr::Attr;
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::screen::Screen;
+use crate::style::Style;
+use crate::window::Window;
+use unicode_width::UnicodeWidthChar;
+
+/// Arrangement of the labels (the `slk_init` format)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlkLayout {
+    /// 8 labels in groups of 3, 2 and 3
+    #[default]
+    Groups323,
+    /// 8 labels in two groups of 4
+    Groups44,
+    /// 12 labels in three groups of 4
+    Groups444,
+    /// 12 labels in three groups of 4, with a line of key names above
+    Groups444Index,
+}
+
+impl SlkLayout {
+    fn groups(self) -> &'static [usize] {
+        match self {
+            SlkLayout::Groups323 => &[3, 2, 3],
+            SlkLayout::Groups44 => &[4, 4],
+            SlkLayout::Groups444 | SlkLayout::Groups444Index => &[4, 4, 4],
+        }
+    }
+
+    /// Number of labels
+    pub fn count(self) -> usize {
+        self.groups().iter().sum()
+    }
+
+    /// Maximum label width in cells
+    pub fn label_width(self) -> usize {
+        match self {
+            SlkLayout::Groups323 | SlkLayout::Groups44 => 8,
+            SlkLayout::Groups444 | SlkLayout::Groups444Index => 5,
+        }
+    }
+
+    /// Lines taken at the bottom of the terminal
+    pub fn lines(self) -> u16 {
+        match self {
+            SlkLayout::Groups444Index => 2,
+            _ => 1,
+        }
+    }
+
+    /// Column of each label on a line `cols` wide
+    ///
+    /// Labels within a group are one cell apart; the remaining space is
+    /// spread evenly between the groups, which stay at least one cell apart.
+    pub(crate) fn positions(self, cols: u16) -> Vec<u16> {
+        let groups = self.groups();
+        let width = self.label_width();
+        let used: usize = groups.iter().map(|&n| n * width + n - 1).sum();
+        let gaps = groups.len() - 1;
+        let free = (cols as usize).saturating_sub(used);
+
+        let mut positions = Vec::with_capacity(self.count());
+        let mut x = 0;
+        for (i, &n) in groups.iter().enumerate() {
+            for _ in 0..n {
+                positions.push(x.min(u16::MAX as usize) as u16);
+                x += width + 1;
+            }
+            if i < gaps {
+                // Leftmost gaps take the remainder
+                let share = free / gaps + usize::from(i < free % gaps);
+                x += share.max(1) - 1;
+            }
+        }
+        positions
+    }
+}
+
+/// Placement of a label within its field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Justify {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Justify {
+    /// Pad or truncate `text` to exactly `width` cells
+    ///
+    /// A wide character that would straddle the edge is dropped and its
+    /// cell padded instead.
+    pub(crate) fn pad(self, text: &str, width: usize) -> String {
+        let mut used = 0;
+        let text: String = text
+            .chars()
+            .take_while(|ch| {
+                let w = ch.width().unwrap_or(0);
+                let fits = used + w <= width;
+                if fits {
+                    used += w;
+                }
+                fits
+            })
+            .collect();
+        let space = width - used;
+        let left = match self {
+            Justify::Left => 0,
+            Justify::Center => space / 2,
+            Justify::Right => space,
+        };
+        format!("{}{}{}", " ".repeat(left), text, " ".repeat(space - left))
+    }
+}
+
+/// The soft-label bar
+pub struct SoftLabels {
+    layout: SlkLayout,
+    labels: Vec<(String, Justify)>,
+    window: Window,
+    style: Style,
+    hidden: bool,
+}
+
+impl SoftLabels {
+    /// Reserve the bottom line(s) of `scr` for labels (`slk_init`)
+    ///
+    /// Replaces any footer reserved with `Screen::reserve_lines`; header
+    /// lines are kept.
+    pub fn init(scr: &mut Screen, layout: SlkLayout) -> Result<Self> {
+        let (top, _) = scr.reserved_lines();
+        let (_, footer) = scr.reserve_lines(top, layout.lines())?;
+        let window = footer.ok_or(Error::NotSupported)?;
+
+        Ok(Self {
+            layout,
+            labels: vec![(String::new(), Justify::Left); layout.count()],
+            window,
+            style: Style::new().attr(Attr::REVERSE),
+            hidden: false,
+        })
+    }
+
+    /// Layout chosen at init
+    pub fn layout(&self) -> SlkLayout {
+        self.layout
+    }
+
+    /// Set label `n` (1-based, like the function key it stands for)
+    ///
+    /// Text longer than the layout's label width is truncated.
+    pub fn set(&mut self, n: usize, label: &str, justify: Justify) -> Result<()> {
+        let slot = n
+            .checked_sub(1)
+            .and_then(|i| self.labels.get_mut(i))
+            .ok_or(Error::InvalidSoftLabel(n))?;
+        *slot = (label.to_string(), justify);
+        Ok(())
+    }
+
+    /// Text of label `n` (1-based)
+    pub fn label(&self, n: usize) -> Option<&str> {
+        let (text, _) = self.labels.get(n.checked_sub(1)?)?;
+        Some(text)
+    }
+
+    /// Style the labels are drawn in (reverse video by default)
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    /// Hide the labels until `restore` (`slk_clear`)
+    pub fn clear(&mut self) {
+        self.hidden = true;
+    }
+
+    /// Show the labels again after `clear` (`slk_restore`)
+    pub fn restore(&mut self) {
+        self.hidden = false;
+    }
+
+    /// Check if the labels are shown
+    pub fn is_visible(&self) -> bool {
+        !self.hidden
+    }
+
+    /// Draw the labels and write them to the terminal (`slk_refresh`)
+    pub fn refresh(&mut self) -> Result<()> {
+        self.draw()?;
+        self.window.refresh()
+    }
+
+    /// Draw the labels into the update buffer for `Screen::doupdate`
+    /// (`slk_noutrefresh`)
+    pub fn noutrefresh(&mut self) -> Result<()> {
+        self.draw()?;
+        self.window.wnoutrefresh()
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        self.window.attrset(Attr::NORMAL)?;
+        self.window.set_fg(Color::Reset)?;
+        self.window.set_bg(Color::Reset)?;
+        self.window.clear()?;
+        if self.hidden {
+            return Ok(());
+        }
+
+        let (height, cols) = self.window.get_size();
+        let width = self.layout.label_width();
+        let row = height - 1;
+        let positions = self.layout.positions(cols);
+
+        if self.layout == SlkLayout::Groups444Index {
+            for (i, &x) in positions.iter().enumerate() {
+                let name = Justify::Left.pad(&format!("F{}", i + 1), width);
+                self.put(0, x, &name)?;
+            }
+        }
+
+        self.window.attrset(self.style.attr)?;
+        self.window.set_fg(self.style.fg)?;
+        self.window.set_bg(self.style.bg)?;
+        let fields: Vec<String> = self
+            .labels
+            .iter()
+            .map(|(text, justify)| justify.pad(text, width))
+            .collect();
+        for (field, &x) in fields.iter().zip(&positions) {
+            self.put(row, x, field)?;
+        }
+        Ok(())
+    }
+
+    /// Print `text` at (y, x), clipped to the bar
+    fn put(&mut self, y: u16, x: u16, text: &str) -> Result<()> {
+        let (_, cols) = self.window.get_size();
+        if x < cols {
+            let visible: String = text.chars().take((cols - x) as usize).collect();
+            self.window.mvprint(y, x, &visible)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Rect;
+
+    #[test]
+    fn test_positions() {
+        // 3-2-3 on 80 columns: groups of 26, 17 and 26 cells, 11 cells between
+        assert_eq!(
+            SlkLayout::Groups323.positions(80),
+            vec![0, 9, 18, 32, 41, 54, 63, 72]
+        );
+        assert_eq!(SlkLayout::Groups44.positions(80)[4], 45);
+        assert_eq!(SlkLayout::Groups444Index.positions(80).len(), 12);
+
+        // Too narrow: labels simply follow each other
+        assert_eq!(SlkLayout::Groups44.positions(10)[..2], [0, 9]);
+    }
+
+    #[test]
+    fn test_justify() {
+        assert_eq!(Justify::Left.pad("Help", 8), "Help    ");
+        assert_eq!(Justify::Center.pad("Help", 8), "  Help  ");
+        assert_eq!(Justify::Right.pad("Help", 8), "    Help");
+        assert_eq!(Justify::Left.pad("Overlong", 5), "Overl");
+        assert_eq!(Justify::Right.pad("日本語", 5), " 日本");
+    }
+
+    #[test]
+    fn test_init_and_set() {
+        let mut scr = Screen::with_size(24, 80);
+        scr.reserve_lines(1, 3).unwrap();
+
+        let mut slk = SoftLabels::init(&mut scr, SlkLayout::Groups444Index).unwrap();
+        assert_eq!(scr.reserved_lines(), (1, 2));
+        assert_eq!(slk.window.rect(), Rect::new(22, 0, 2, 80));
+
+        slk.set(12, "Quit", Justify::Right).unwrap();
+        assert_eq!(slk.label(12), Some("Quit"));
+        assert_eq!(slk.label(1), Some(""));
+        assert_eq!(slk.label(13), None);
+        assert!(matches!(
+            slk.set(0, "x", Justify::Left),
+            Err(Error::InvalidSoftLabel(0))
+        ));
+        assert!(slk.set(13, "x", Justify::Left).is_err());
+
+        slk.clear();
+        assert!(!slk.is_visible());
+        slk.restore();
+        assert!(slk.is_visible());
+    }
+}
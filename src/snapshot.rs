@@ -0,0 +1,310 @@
+//! Saved copies of the screen's content
+//!
+//! A `Snapshot` holds the drawn cells and the cursor position, so a view
+//! can be put back after something else was drawn over it (a help page, a
+//! dialog) without the application redrawing it from scratch.
+//!
+//! Snapshots are also saved to files (`Screen::dump_to`). The format is
+//! little-endian binary and versioned:
+//!
+//! ```text
+//! "ZAZSCR" version:u8 rows:u16 cols:u16 cursor_y:u16 cursor_x:u16
+//! rows * cols cells of: char:u32 attr:u16 fg:[u8; 4] bg:[u8; 4]
+//!                       marks:u8 marks * char:u32
+//! ```
+//!
+//! The marks are the rest of the cell's grapheme cluster (combining marks,
+//! joined emoji). Version 1 dumps have no marks and are still read.
+//!
+//! A color is a tag byte and three data bytes: 0 is the terminal default,
+//! 1-16 the named colors from black to bright white, 17 a 256-color index
+//! in the first data byte, and 18 an RGB value.
+
+use crate::attr::Attr;
+use crate::buffer::Buffer;
+use crate::cell::Cell;
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::export::{ExportFormat, export};
+use crate::text::Line;
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 6] = b"ZAZSCR";
+const VERSION: u8 = 2;
+/// Bytes per cell in a dump, before its marks
+const CELL_SIZE: usize = 15;
+/// Most cells a dump may hold; a larger size in the header is taken as
+/// corrupt
+const MAX_CELLS: usize = 1 << 24;
+
+/// Named colors in tag order, starting at tag 1
+const NAMED_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// A copy of the screen's cells and cursor, taken by `Screen::snapshot`
+///
+/// ```no_run
+/// # use zaz::Screen;
+/// # let mut scr = Screen::init()?;
+/// let view = scr.snapshot();
+/// scr.clear()?;
+/// scr.mvprint(0, 0, "Help: press any key")?;
+/// scr.refresh()?;
+/// scr.getch()?;
+/// // Only the cells the help page changed are redrawn
+/// scr.restore(&view)?;
+/// scr.refresh()?;
+/// # Ok::<(), zaz::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    cells: Buffer,
+    cursor: (u16, u16),
+}
+
+impl Snapshot {
+    pub(crate) fn new(cells: Buffer, cursor: (u16, u16)) -> Self {
+        Self { cells, cursor }
+    }
+
+    /// Number of rows
+    pub fn rows(&self) -> u16 {
+        self.cells.len() as u16
+    }
+
+    /// Number of columns
+    pub fn cols(&self) -> u16 {
+        self.cells.width() as u16
+    }
+
+    /// The cell at (y, x), None outside the snapshot
+    pub fn cell(&self, y: u16, x: u16) -> Option<&Cell> {
+        if y >= self.rows() || x >= self.cols() {
+            return None;
+        }
+        Some(&self.cells[y as usize][x as usize])
+    }
+
+    /// Cursor position (y, x) when the snapshot was taken
+    pub fn cursor(&self) -> (u16, u16) {
+        self.cursor
+    }
+
+    /// Cells of row `y`
+    pub(crate) fn row(&self, y: usize) -> &[Cell] {
+        &self.cells[y]
+    }
+
+    /// The content as text in `format` (see `export`)
+    pub fn export(&self, format: ExportFormat) -> String {
+        let lines: Vec<Line> = self.cells.rows().map(Line::from_cells).collect();
+        export(&lines, format)
+    }
+
+    /// Write the snapshot in the dump format
+    pub fn write_to(&self, out: &mut impl Write) -> Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        for n in [self.rows(), self.cols(), self.cursor.0, self.cursor.1] {
+            out.write_all(&n.to_le_bytes())?;
+        }
+
+        let mut record = [0u8; CELL_SIZE];
+        for cell in self.cells.cells() {
+            // A cluster longer than the count allows keeps its first marks
+            let marks = &cell.combining()[..cell.combining().len().min(255)];
+            record[0..4].copy_from_slice(&(cell.ch as u32).to_le_bytes());
+            record[4..6].copy_from_slice(&cell.attr.0.to_le_bytes());
+            record[6..10].copy_from_slice(&encode_color(cell.fg));
+            record[10..14].copy_from_slice(&encode_color(cell.bg));
+            record[14] = marks.len() as u8;
+            out.write_all(&record)?;
+            for &mark in marks {
+                out.write_all(&(mark as u32).to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a snapshot in the dump format
+    pub fn read_from(input: &mut impl Read) -> Result<Self> {
+        let mut header = [0u8; 15];
+        input.read_exact(&mut header)?;
+        if &header[..6] != MAGIC {
+            return Err(Error::InvalidDump("not a screen dump".to_string()));
+        }
+        let version = header[6];
+        if version != 1 && version != VERSION {
+            return Err(Error::InvalidDump(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        // Version 1 cells end before the mark count
+        let record_size = if version == 1 {
+            CELL_SIZE - 1
+        } else {
+            CELL_SIZE
+        };
+        let field = |i: usize| u16::from_le_bytes([header[7 + i * 2], header[8 + i * 2]]);
+        let (rows, cols) = (field(0) as usize, field(1) as usize);
+        let cursor = (field(2), field(3));
+        if rows * cols > MAX_CELLS {
+            return Err(Error::InvalidDump(format!(
+                "screen size {}x{} too large",
+                rows, cols
+            )));
+        }
+
+        // Read every cell before allocating the grid, so the header can't
+        // ask for more memory than the input holds
+        let mut read = Vec::new();
+        let mut record = [0u8; CELL_SIZE];
+        for _ in 0..rows * cols {
+            input.read_exact(&mut record[..record_size])?;
+            let ch = read_char(&record[0..4])?;
+            let attr = Attr(u16::from_le_bytes([record[4], record[5]]));
+            let fg = decode_color(&record[6..10])?;
+            let bg = decode_color(&record[10..14])?;
+            let mut cell = Cell::with_style(ch, attr, fg, bg);
+            if version != 1 {
+                let mut mark = [0u8; 4];
+                for _ in 0..record[14] {
+                    input.read_exact(&mut mark)?;
+                    cell.push_combining(read_char(&mark)?);
+                }
+            }
+            read.push(cell);
+        }
+
+        let mut cells = Buffer::new(rows, cols, Cell::blank());
+        for (cell, read) in cells.rows_mut().flatten().zip(read) {
+            *cell = read;
+        }
+        Ok(Self::new(cells, cursor))
+    }
+}
+
+fn read_char(bytes: &[u8]) -> Result<char> {
+    let code = u32::from_le_bytes(bytes.try_into().unwrap());
+    char::from_u32(code).ok_or_else(|| Error::InvalidDump(format!("invalid character {:#x}", code)))
+}
+
+fn encode_color(color: Color) -> [u8; 4] {
+    match color {
+        Color::Reset => [0, 0, 0, 0],
+        Color::Ansi256(n) => [17, n, 0, 0],
+        Color::Rgb(r, g, b) => [18, r, g, b],
+        named => {
+            let index = NAMED_COLORS.iter().position(|&c| c == named).unwrap_or(0);
+            [index as u8 + 1, 0, 0, 0]
+        }
+    }
+}
+
+fn decode_color(bytes: &[u8]) -> Result<Color> {
+    Ok(match bytes[0] {
+        0 => Color::Reset,
+        tag @ 1..=16 => NAMED_COLORS[tag as usize - 1],
+        17 => Color::Ansi256(bytes[1]),
+        18 => Color::Rgb(bytes[1], bytes[2], bytes[3]),
+        tag => return Err(Error::InvalidDump(format!("invalid color tag {}", tag))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Snapshot {
+        let mut cells = Buffer::new(2, 3, Cell::blank());
+        cells[0][0] = Cell::with_style('é', Attr::BOLD | Attr::ITALIC, Color::Red, Color::Reset);
+        cells[0][1] = Cell::with_style('字', Attr::NORMAL, Color::Ansi256(200), Color::BrightWhite);
+        cells[1][2] = Cell::with_style('x', Attr::REVERSE, Color::Rgb(1, 2, 3), Color::Black);
+        cells[1][0].push_combining('\u{301}');
+        Snapshot::new(cells, (1, 2))
+    }
+
+    #[test]
+    fn test_dump_round_trip() {
+        let snapshot = sample();
+        let mut bytes = Vec::new();
+        snapshot.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 15 + 6 * CELL_SIZE + 4);
+        assert_eq!(&bytes[..7], b"ZAZSCR\x02");
+        assert_eq!(
+            Snapshot::read_from(&mut bytes.as_slice()).unwrap(),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn test_read_version_1() {
+        let mut bytes = b"ZAZSCR\x01\x01\x00\x01\x00\x00\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(&('a' as u32).to_le_bytes());
+        bytes.extend_from_slice(&[1, 0, 2, 0, 0, 0, 0, 0, 0, 0]);
+        let snapshot = Snapshot::read_from(&mut bytes.as_slice()).unwrap();
+        let cell = snapshot.cell(0, 0).unwrap();
+        assert_eq!((cell.ch, cell.attr, cell.fg), ('a', Attr(1), Color::Red));
+    }
+
+    #[test]
+    fn test_invalid_dumps() {
+        let mut bytes = Vec::new();
+        sample().write_to(&mut bytes).unwrap();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            Snapshot::read_from(&mut bad_magic.as_slice()),
+            Err(Error::InvalidDump(_))
+        ));
+
+        let mut bad_color = bytes.clone();
+        bad_color[15 + 6] = 99;
+        assert!(matches!(
+            Snapshot::read_from(&mut bad_color.as_slice()),
+            Err(Error::InvalidDump(_))
+        ));
+
+        // Cut short
+        assert!(matches!(
+            Snapshot::read_from(&mut &bytes[..20]),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_oversized_header() {
+        let mut bytes = Vec::new();
+        sample().write_to(&mut bytes).unwrap();
+        bytes[7..11].copy_from_slice(&[0x60, 0xea, 0x60, 0xea]);
+        assert!(matches!(
+            Snapshot::read_from(&mut bytes.as_slice()),
+            Err(Error::InvalidDump(_))
+        ));
+
+        // Within the limit but far more than the input holds
+        bytes[7..11].copy_from_slice(&[0x00, 0x10, 0x00, 0x10]);
+        assert!(matches!(
+            Snapshot::read_from(&mut bytes.as_slice()),
+            Err(Error::Io(_))
+        ));
+    }
+}
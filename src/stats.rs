@@ -0,0 +1,34 @@
+//! Per-frame refresh statistics
+
+use std::time::Duration;
+
+/// What the last `Screen::refresh` did, for verifying the delta
+/// optimizations pay off for a given workload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// Bytes written to the terminal
+    pub bytes_written: usize,
+    /// Cells emitted (including cells covered by ECH/REP runs)
+    pub cells_changed: usize,
+    /// Lines that produced output
+    pub lines_touched: usize,
+    /// Scroll operations (insert/delete line) used
+    pub scroll_ops: usize,
+    /// Time spent hashing lines, detecting scrolls and diffing lines
+    pub diff_time: Duration,
+    /// Time spent encoding and writing output
+    pub emit_time: Duration,
+    /// Whether the refresh was cut short by pending input
+    pub aborted: bool,
+    /// Whether the terminal stopped taking output (see
+    /// `Screen::set_write_timeout`); a refresh that stalls before writing
+    /// anything leaves every change pending
+    pub stalled: bool,
+}
+
+impl RefreshStats {
+    /// Total time spent in refresh
+    pub fn total_time(&self) -> Duration {
+        self.diff_time + self.emit_time
+    }
+}
@@ -0,0 +1,130 @@
+//! Named styles for consistent theming
+
+use crate::attr::Attr;
+use crate::cell::Cell;
+use crate::color::Color;
+use std::collections::HashMap;
+
+/// Attributes and colors applied together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub attr: Attr,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Style {
+    /// Plain style (no attributes, default colors)
+    pub fn new() -> Self {
+        Self {
+            attr: Attr::NORMAL,
+            fg: Color::Reset,
+            bg: Color::Reset,
+        }
+    }
+
+    /// Set the attributes
+    pub fn attr(mut self, attr: Attr) -> Self {
+        self.attr = attr;
+        self
+    }
+
+    /// Set the foreground color
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = color;
+        self
+    }
+
+    /// Set the background color
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = color;
+        self
+    }
+
+    /// Style of a cell
+    pub fn of(cell: &Cell) -> Self {
+        Self {
+            attr: cell.attr,
+            fg: cell.fg,
+            bg: cell.bg,
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Styles looked up by semantic name ("title", "error", "selection", ...)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+    styles: HashMap<String, Style>,
+}
+
+impl Theme {
+    /// Create an empty theme
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a named style
+    pub fn with(mut self, name: impl Into<String>, style: Style) -> Self {
+        self.set(name, style);
+        self
+    }
+
+    /// Add or replace a named style in place
+    pub fn set(&mut self, name: impl Into<String>, style: Style) {
+        self.styles.insert(name.into(), style);
+    }
+
+    /// Look up a style by name
+    pub fn get(&self, name: &str) -> Option<Style> {
+        self.styles.get(name).copied()
+    }
+
+    /// Iterate over the named styles
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Style)> {
+        self.styles
+            .iter()
+            .map(|(name, style)| (name.as_str(), *style))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_builder() {
+        let style = Style::new().attr(Attr::BOLD).fg(Color::Red);
+        assert_eq!(style.attr, Attr::BOLD);
+        assert_eq!(style.fg, Color::Red);
+        assert_eq!(style.bg, Color::Reset);
+        assert_eq!(Style::default(), Style::new());
+
+        let cell = Cell::with_style('x', Attr::ITALIC, Color::Blue, Color::Black);
+        assert_eq!(
+            Style::of(&cell),
+            Style::new()
+                .attr(Attr::ITALIC)
+                .fg(Color::Blue)
+                .bg(Color::Black)
+        );
+    }
+
+    #[test]
+    fn test_theme_lookup() {
+        let mut theme = Theme::new()
+            .with("title", Style::new().attr(Attr::BOLD))
+            .with("error", Style::new().fg(Color::Red));
+        assert_eq!(theme.get("error"), Some(Style::new().fg(Color::Red)));
+        assert_eq!(theme.get("missing"), None);
+
+        theme.set("error", Style::new().fg(Color::BrightRed));
+        assert_eq!(theme.get("error").unwrap().fg, Color::BrightRed);
+        assert_eq!(theme.iter().count(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+//! Styled text made of spans
+
+use crate::attr::Attr;
+use crate::cell::{Cell, graphemes};
+use crate::color::Color;
+
+/// A run of text sharing one style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub attr: Attr,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Span {
+    /// Unstyled text
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::styled(text, Attr::NORMAL, Color::Reset, Color::Reset)
+    }
+
+    /// Text with attributes and colors
+    pub fn styled(text: impl Into<String>, attr: Attr, fg: Color, bg: Color) -> Self {
+        Self {
+            text: text.into(),
+            attr,
+            fg,
+            bg,
+        }
+    }
+
+    fn same_style(&self, cell: &Cell) -> bool {
+        self.attr == cell.attr && self.fg == cell.fg && self.bg == cell.bg
+    }
+}
+
+/// A single line of styled text
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Line {
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    /// Create an empty line
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a line from cells, merging neighbours with the same style
+    pub fn from_cells(cells: &[Cell]) -> Self {
+        let mut spans: Vec<Span> = Vec::new();
+
+        for cell in cells {
+            match spans.last_mut() {
+                Some(span) if span.same_style(cell) => cell.push_grapheme(&mut span.text),
+                _ => spans.push(Span::styled(cell.grapheme(), cell.attr, cell.fg, cell.bg)),
+            }
+        }
+
+        Self { spans }
+    }
+
+    /// Append a span
+    pub fn push(&mut self, span: Span) {
+        self.spans.push(span);
+    }
+
+    /// Expand the line back into one cell per grapheme cluster
+    pub fn cells(&self) -> Vec<Cell> {
+        self.spans
+            .iter()
+            .flat_map(|span| {
+                graphemes(&span.text).map(|(grapheme, _)| {
+                    let mut cell = Cell::from_grapheme(grapheme);
+                    (cell.attr, cell.fg, cell.bg) = (span.attr, span.fg, span.bg);
+                    cell
+                })
+            })
+            .collect()
+    }
+
+    /// Text without styling
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Number of cells the line occupies
+    pub fn width(&self) -> usize {
+        self.spans
+            .iter()
+            .map(|span| graphemes(&span.text).count())
+            .sum()
+    }
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Self {
+        Self {
+            spans: vec![Span::new(text)],
+        }
+    }
+}
+
+impl From<Span> for Line {
+    fn from(span: Span) -> Self {
+        Self { spans: vec![span] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_cells_merges_runs() {
+        let bold = |ch| Cell::with_style(ch, Attr::BOLD, Color::Red, Color::Reset);
+        let cells = [bold('a'), bold('b'), Cell::new(' '), bold('c')];
+
+        let line = Line::from_cells(&cells);
+        assert_eq!(line.spans.len(), 3);
+        assert_eq!(line.spans[0].text, "ab");
+        assert_eq!(line.spans[0].fg, Color::Red);
+        assert_eq!(line.text(), "ab c");
+        assert_eq!(line.width(), 4);
+
+        // Round trip
+        assert_eq!(line.cells(), cells);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let line = Line::from("héllo");
+        assert_eq!(line.width(), 5);
+        assert_eq!(line.cells()[1].ch, 'é');
+        assert!(Line::new().cells().is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Refresh strategy profiles and auto-tuning
+//!
+//! The refresh loop trades CPU time, output size and input latency through
+//! a few knobs: how often it checks for input, how long a blank run must be
+//! before ECH replaces it, and whether it looks for scrolled lines. A
+//! `RefreshProfile` picks them for a known trade-off, or measures the first
+//! frames and picks them from the terminal's real throughput.
+
+use crate::stats::RefreshStats;
+use std::time::Duration;
+
+/// Frames with output measured before `RefreshProfile::Adaptive` settles
+const SAMPLE_FRAMES: usize = 30;
+
+/// Below this throughput (bytes per second) the link is treated as slow,
+/// e.g. a serial line or a congested SSH session
+const SLOW_THROUGHPUT: f64 = 256.0 * 1024.0;
+
+/// How the refresh loop balances latency, bandwidth and CPU time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RefreshProfile {
+    /// Check for input after every line and skip scroll detection, so
+    /// frames start sooner and yield to input quickly
+    LowLatency,
+    /// Use the shortest output: ECH for short blank runs, scroll detection
+    /// always on and fewer input checks
+    LowBandwidth,
+    /// The defaults: input checks every 5 lines, ECH from 8 blanks
+    #[default]
+    Balanced,
+    /// Start balanced, then tune from the cost of the first frames
+    Adaptive,
+}
+
+/// Knobs used by the refresh loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Tuning {
+    /// Lines emitted between checks for pending input
+    pub(crate) check_interval: usize,
+    /// Shortest blank run erased with ECH instead of written as spaces
+    pub(crate) ech_threshold: usize,
+    /// Look for scrolled lines to move with IL/DL
+    pub(crate) detect_scrolls: bool,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            check_interval: 5,
+            ech_threshold: 8,
+            detect_scrolls: true,
+        }
+    }
+}
+
+impl RefreshProfile {
+    /// Knobs for the profile (`Adaptive` starts from `Balanced`)
+    pub(crate) fn tuning(self) -> Tuning {
+        match self {
+            Self::LowLatency => Tuning {
+                check_interval: 1,
+                detect_scrolls: false,
+                ..Tuning::default()
+            },
+            // `CSI n X` takes at most 5 bytes for runs this short
+            Self::LowBandwidth => Tuning {
+                check_interval: 20,
+                ech_threshold: 5,
+                detect_scrolls: true,
+            },
+            Self::Balanced | Self::Adaptive => Tuning::default(),
+        }
+    }
+}
+
+/// Totals over the measured frames of `RefreshProfile::Adaptive`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AutoTuner {
+    frames: usize,
+    bytes: usize,
+    lines: usize,
+    scroll_ops: usize,
+    diff_time: Duration,
+    emit_time: Duration,
+}
+
+impl AutoTuner {
+    /// Record a frame; returns the tuning once enough frames were measured
+    ///
+    /// Frames without output, and aborted or stalled ones, say little about
+    /// the terminal and are left out.
+    pub(crate) fn record(&mut self, stats: &RefreshStats) -> Option<Tuning> {
+        if stats.bytes_written == 0 || stats.aborted || stats.stalled {
+            return None;
+        }
+        self.frames += 1;
+        self.bytes += stats.bytes_written;
+        self.lines += stats.lines_touched;
+        self.scroll_ops += stats.scroll_ops;
+        self.diff_time += stats.diff_time;
+        self.emit_time += stats.emit_time;
+        (self.frames >= SAMPLE_FRAMES).then(|| self.tuning())
+    }
+
+    /// Tuning for the frames measured so far
+    fn tuning(&self) -> Tuning {
+        let emit_secs = self.emit_time.as_secs_f64().max(1e-6);
+        if self.bytes as f64 / emit_secs < SLOW_THROUGHPUT {
+            return RefreshProfile::LowBandwidth.tuning();
+        }
+
+        // Check for input about once per millisecond of output, and keep
+        // scroll detection only while it pays for its own cost
+        let lines_per_ms = self.lines as f64 / (emit_secs * 1000.0);
+        Tuning {
+            check_interval: (lines_per_ms as usize).clamp(1, 50),
+            detect_scrolls: self.scroll_ops > 0 || self.diff_time <= self.emit_time,
+            ..Tuning::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(bytes: usize, lines: usize, diff_us: u64, emit_us: u64) -> RefreshStats {
+        RefreshStats {
+            bytes_written: bytes,
+            lines_touched: lines,
+            diff_time: Duration::from_micros(diff_us),
+            emit_time: Duration::from_micros(emit_us),
+            ..RefreshStats::default()
+        }
+    }
+
+    fn settle(stats: RefreshStats) -> Tuning {
+        let mut tuner = AutoTuner::default();
+        for _ in 1..SAMPLE_FRAMES {
+            assert_eq!(tuner.record(&stats), None);
+        }
+        assert_eq!(tuner.record(&RefreshStats::default()), None);
+        tuner.record(&stats).unwrap()
+    }
+
+    #[test]
+    fn test_profiles() {
+        assert_eq!(RefreshProfile::default().tuning(), Tuning::default());
+        assert_eq!(RefreshProfile::LowLatency.tuning().check_interval, 1);
+        assert!(!RefreshProfile::LowLatency.tuning().detect_scrolls);
+        assert_eq!(RefreshProfile::LowBandwidth.tuning().ech_threshold, 5);
+    }
+
+    #[test]
+    fn test_slow_terminal() {
+        // 4 KB per 100ms: a slow link
+        let tuning = settle(frame(4000, 24, 10, 100_000));
+        assert_eq!(tuning, RefreshProfile::LowBandwidth.tuning());
+    }
+
+    #[test]
+    fn test_fast_terminal() {
+        // 24 lines in 2ms, with diffing costing more than emitting
+        let tuning = settle(frame(8000, 24, 3000, 2000));
+        assert_eq!(tuning.check_interval, 12);
+        assert!(!tuning.detect_scrolls);
+
+        let mut scrolling = frame(8000, 24, 3000, 2000);
+        scrolling.scroll_ops = 1;
+        assert!(settle(scrolling).detect_scrolls);
+    }
+}
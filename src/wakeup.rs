@@ -0,0 +1,155 @@
+//! Shutdown signalling for blocking reads
+//!
+//! `getch` blocks in `poll()`. To let another thread interrupt it, every
+//! screen owns a self-pipe whose read end is polled alongside the input fd;
+//! shutting down writes a byte to the pipe, waking the reader.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+
+/// Self-pipe used to wake blocked input reads
+pub(crate) struct Wakeup {
+    triggered: AtomicBool,
+    #[cfg(unix)]
+    pipe: Option<(RawFd, RawFd)>,
+}
+
+impl Wakeup {
+    pub(crate) fn new() -> Self {
+        Self {
+            triggered: AtomicBool::new(false),
+            #[cfg(unix)]
+            pipe: Self::open_pipe(),
+        }
+    }
+
+    #[cfg(unix)]
+    fn open_pipe() -> Option<(RawFd, RawFd)> {
+        let mut fds = [0 as RawFd; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return None;
+        }
+
+        for fd in fds {
+            unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFL);
+                libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            }
+        }
+
+        Some((fds[0], fds[1]))
+    }
+
+    /// Fd that becomes readable once shutdown is triggered
+    #[cfg(unix)]
+    pub(crate) fn fd(&self) -> Option<RawFd> {
+        self.pipe.map(|(read, _)| read)
+    }
+
+    /// Trigger shutdown, waking any blocked reader
+    ///
+    /// The pipe is never drained, so every later poll wakes immediately too.
+    pub(crate) fn trigger(&self) {
+        if self.triggered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        #[cfg(unix)]
+        if let Some((_, write)) = self.pipe {
+            unsafe {
+                libc::write(write, [1u8].as_ptr() as *const libc::c_void, 1);
+            }
+        }
+    }
+
+    pub(crate) fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for Wakeup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some((read, write)) = self.pipe.take() {
+            unsafe {
+                libc::close(read);
+                libc::close(write);
+            }
+        }
+    }
+}
+
+/// Handle for shutting down a screen from another thread
+///
+/// Shutting down makes any blocked or future `getch`/`getch_timeout` call on
+/// the screen return `Error::Shutdown`.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    wakeup: Arc<Wakeup>,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new(wakeup: Arc<Wakeup>) -> Self {
+        Self { wakeup }
+    }
+
+    /// Shut down the screen's input, waking blocked reads
+    pub fn shutdown(&self) {
+        self.wakeup.trigger();
+    }
+
+    /// Check if shutdown has been requested
+    pub fn is_shutdown(&self) -> bool {
+        self.wakeup.is_triggered()
+    }
+}
+
+impl std::fmt::Debug for ShutdownHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownHandle")
+            .field("shutdown", &self.is_shutdown())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger() {
+        let wakeup = Arc::new(Wakeup::new());
+        let handle = ShutdownHandle::new(wakeup.clone());
+        assert!(!handle.is_shutdown());
+
+        handle.shutdown();
+        handle.shutdown(); // Idempotent
+        assert!(wakeup.is_triggered());
+        assert!(handle.clone().is_shutdown());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pipe_readable_after_trigger() {
+        let wakeup = Wakeup::new();
+        let fd = wakeup.fd().unwrap();
+
+        let poll = |fd| {
+            let mut fds = [libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            }];
+            unsafe { libc::poll(fds.as_mut_ptr(), 1, 0) }
+        };
+
+        assert_eq!(poll(fd), 0);
+        wakeup.trigger();
+        assert_eq!(poll(fd), 1);
+        assert_eq!(poll(fd), 1); // Stays readable
+    }
+}
@@ -0,0 +1,743 @@
+//! Charts of numeric series: sparkline, bar chart and line chart
+
+use super::{Canvas, ellipsize};
+use crate::acs::{ACS_HLINE, ACS_LLCORNER, ACS_VLINE, AcsChar};
+use crate::color::Color;
+use crate::error::Result;
+use crate::layout::Rect;
+use crate::style::Style;
+
+/// Vertical blocks by eighths of a cell filled
+const LEVELS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// ASCII stand-ins for `LEVELS`, for terminals without Unicode
+const ASCII_LEVELS: [char; 9] = [' ', '.', '.', '-', '-', '=', '=', '#', '#'];
+
+/// Braille dot bits by position in a cell, `[row][column]`
+const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Colors by value: a value takes the color of the highest threshold it
+/// reaches
+#[derive(Debug, Clone, Default)]
+struct Thresholds(Vec<(f64, Color)>);
+
+impl Thresholds {
+    fn add(&mut self, value: f64, color: Color) {
+        let index = self.0.partition_point(|&(t, _)| t <= value);
+        self.0.insert(index, (value, color));
+    }
+
+    /// `base` with the foreground of the threshold `value` reaches, if any
+    fn style(&self, value: f64, base: Style) -> Style {
+        match self.0.iter().rev().find(|&&(t, _)| value >= t) {
+            Some(&(_, color)) => base.fg(color),
+            None => base,
+        }
+    }
+}
+
+/// Sparkline: a compact series of vertical bars, newest on the right
+///
+/// Bars start from zero and are drawn in eighth-cell steps, over as many
+/// rows as the area has.
+#[derive(Debug, Clone)]
+pub struct Sparkline {
+    data: Vec<f64>,
+    capacity: usize,
+    max: Option<f64>,
+    style: Style,
+    thresholds: Thresholds,
+}
+
+impl Sparkline {
+    /// Create an empty sparkline keeping the last 1024 values
+    pub fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            capacity: 1024,
+            max: None,
+            style: Style::new(),
+            thresholds: Thresholds::default(),
+        }
+    }
+
+    /// Number of values kept by `push`
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self.trim();
+        self
+    }
+
+    /// Value drawn as a full bar (by default, the largest value shown)
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Style of the bars
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Draw values at or above `value` in `color`
+    pub fn threshold(mut self, value: f64, color: Color) -> Self {
+        self.thresholds.add(value, color);
+        self
+    }
+
+    /// The values kept, oldest first
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Replace the values
+    pub fn set_data(&mut self, data: impl IntoIterator<Item = f64>) {
+        self.data = data.into_iter().collect();
+        self.trim();
+    }
+
+    /// Append a value, dropping the oldest beyond the capacity
+    pub fn push(&mut self, value: f64) {
+        self.data.push(value);
+        self.trim();
+    }
+
+    /// Draw the most recent values that fit into `area`
+    pub fn render(&self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let width = area.width as usize;
+        let rows = area.height as usize;
+        let shown = &self.data[self.data.len().saturating_sub(width)..];
+        let max = self
+            .max
+            .unwrap_or_else(|| shown.iter().copied().fold(0.0, f64::max));
+        let unicode = canvas.unicode();
+
+        for row in 0..rows {
+            let below = (rows - 1 - row) * 8;
+            let mut cells = vec![(' ', Style::new()); width - shown.len()];
+            cells.extend(shown.iter().map(|&value| {
+                let fill = eighths(value, max, rows).saturating_sub(below);
+                (
+                    level(fill, unicode),
+                    self.thresholds.style(value, self.style),
+                )
+            }));
+            put_cells(canvas, area.y + row as u16, area.x, &cells)?;
+        }
+        Ok(())
+    }
+
+    fn trim(&mut self) {
+        let excess = self.data.len().saturating_sub(self.capacity);
+        self.data.drain(..excess);
+    }
+}
+
+impl Default for Sparkline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Vertical bar chart with a value and a label under each bar
+#[derive(Debug, Clone)]
+pub struct BarChart {
+    bars: Vec<(String, f64)>,
+    bar_width: u16,
+    gap: u16,
+    max: Option<f64>,
+    show_values: bool,
+    style: Style,
+    label_style: Style,
+    thresholds: Thresholds,
+}
+
+impl BarChart {
+    /// Create a chart with no bars
+    pub fn new() -> Self {
+        Self {
+            bars: Vec::new(),
+            bar_width: 3,
+            gap: 1,
+            max: None,
+            show_values: true,
+            style: Style::new(),
+            label_style: Style::new(),
+            thresholds: Thresholds::default(),
+        }
+    }
+
+    /// Add a bar
+    pub fn bar(mut self, label: impl Into<String>, value: f64) -> Self {
+        self.bars.push((label.into(), value));
+        self
+    }
+
+    /// Width of each bar in cells (3 by default)
+    pub fn bar_width(mut self, width: u16) -> Self {
+        self.bar_width = width.max(1);
+        self
+    }
+
+    /// Cells between bars (1 by default)
+    pub fn gap(mut self, gap: u16) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Value drawn as a full bar (by default, the largest value)
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Show or hide the values under the bars
+    pub fn show_values(mut self, show: bool) -> Self {
+        self.show_values = show;
+        self
+    }
+
+    /// Style of the bars
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Style of the values and labels
+    pub fn label_style(mut self, style: Style) -> Self {
+        self.label_style = style;
+        self
+    }
+
+    /// Draw bars with values at or above `value` in `color`
+    pub fn threshold(mut self, value: f64, color: Color) -> Self {
+        self.thresholds.add(value, color);
+        self
+    }
+
+    /// The bars (label, value)
+    pub fn bars(&self) -> &[(String, f64)] {
+        &self.bars
+    }
+
+    /// Replace the bars
+    pub fn set_bars<S: Into<String>>(&mut self, bars: impl IntoIterator<Item = (S, f64)>) {
+        self.bars = bars
+            .into_iter()
+            .map(|(label, value)| (label.into(), value))
+            .collect();
+    }
+
+    /// Draw the bars that fit into `area`, left to right
+    ///
+    /// The bottom row holds the labels and the one above it the values,
+    /// as far as the height allows.
+    pub fn render(&self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let width = area.width as usize;
+        let bar_width = self.bar_width as usize;
+        let step = bar_width + self.gap as usize;
+        let fit = if width >= bar_width {
+            (width - bar_width) / step + 1
+        } else {
+            0
+        };
+        let bars = &self.bars[..fit.min(self.bars.len())];
+
+        let has_labels = area.height >= 2;
+        let has_values = self.show_values && area.height >= 3;
+        let rows = area.height as usize - usize::from(has_labels) - usize::from(has_values);
+        let max = self
+            .max
+            .unwrap_or_else(|| bars.iter().map(|&(_, v)| v).fold(0.0, f64::max));
+        let unicode = canvas.unicode();
+
+        for row in 0..rows {
+            let below = (rows - 1 - row) * 8;
+            let mut cells = vec![(' ', Style::new()); width];
+            for (i, &(_, value)) in bars.iter().enumerate() {
+                let fill = eighths(value, max, rows).saturating_sub(below);
+                let cell = (
+                    level(fill, unicode),
+                    self.thresholds.style(value, self.style),
+                );
+                cells[i * step..][..bar_width].fill(cell);
+            }
+            put_cells(canvas, area.y + row as u16, area.x, &cells)?;
+        }
+
+        let mut y = area.y + rows as u16;
+        if has_values {
+            let values = bars.iter().map(|&(_, value)| format_value(value));
+            let text = bar_texts(values, width, bar_width, step, unicode);
+            canvas.put_str(y, area.x, &text, self.label_style)?;
+            y += 1;
+        }
+        if has_labels {
+            let labels = bars.iter().map(|(label, _)| label.clone());
+            let text = bar_texts(labels, width, bar_width, step, unicode);
+            canvas.put_str(y, area.x, &text, self.label_style)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for BarChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One series of a line chart
+#[derive(Debug, Clone)]
+struct Series {
+    name: String,
+    data: Vec<f64>,
+    style: Style,
+}
+
+/// Line chart drawn with braille dots, two by four to a cell
+///
+/// Points are spread evenly across the width, with the value range on a
+/// labelled axis to the left. Non-finite values leave a gap in the line.
+#[derive(Debug, Clone)]
+pub struct LineChart {
+    series: Vec<Series>,
+    y_range: Option<(f64, f64)>,
+    x_labels: Vec<String>,
+    axis_style: Style,
+    thresholds: Thresholds,
+}
+
+impl LineChart {
+    /// Create a chart with no series
+    pub fn new() -> Self {
+        Self {
+            series: Vec::new(),
+            y_range: None,
+            x_labels: Vec::new(),
+            axis_style: Style::new(),
+            thresholds: Thresholds::default(),
+        }
+    }
+
+    /// Add a series drawn in `style`; named series are listed in a legend
+    /// at the top right
+    pub fn series(
+        mut self,
+        name: impl Into<String>,
+        data: impl IntoIterator<Item = f64>,
+        style: Style,
+    ) -> Self {
+        self.series.push(Series {
+            name: name.into(),
+            data: data.into_iter().collect(),
+            style,
+        });
+        self
+    }
+
+    /// Fixed value range (by default, the range of the data)
+    pub fn y_range(mut self, min: f64, max: f64) -> Self {
+        self.y_range = Some((min.min(max), max.max(min)));
+        self
+    }
+
+    /// Labels spread evenly under the x axis, e.g. `["-60s", "now"]`
+    pub fn x_labels<S: Into<String>>(mut self, labels: impl IntoIterator<Item = S>) -> Self {
+        self.x_labels = labels.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Style of the axes and their labels
+    pub fn axis_style(mut self, style: Style) -> Self {
+        self.axis_style = style;
+        self
+    }
+
+    /// Draw points with values at or above `value` in `color`
+    pub fn threshold(mut self, value: f64, color: Color) -> Self {
+        self.thresholds.add(value, color);
+        self
+    }
+
+    /// Replace the data of series `index` (ignored if out of range)
+    pub fn set_data(&mut self, index: usize, data: impl IntoIterator<Item = f64>) {
+        if let Some(series) = self.series.get_mut(index) {
+            series.data = data.into_iter().collect();
+        }
+    }
+
+    /// Draw into `area`
+    pub fn render(&self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let (min, max) = self.bounds();
+        let labels = [format_value(max), format_value(min)];
+        let mut label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        if label_width + 2 > area.width as usize {
+            label_width = 0;
+        }
+        let has_x_labels = !self.x_labels.is_empty() && area.height >= 3;
+        let rows = area.height as usize - 1 - usize::from(has_x_labels);
+        let cols = area.width as usize - label_width - 1;
+        if rows == 0 || cols == 0 {
+            return Ok(());
+        }
+
+        let unicode = canvas.unicode();
+        let resolve = |acs: AcsChar| if unicode { acs.as_char() } else { acs.ascii() };
+        let axis_x = area.x + label_width as u16;
+
+        let mut grid = Braille::new(rows, cols);
+        for series in &self.series {
+            self.plot(&mut grid, series, min, max);
+        }
+
+        for row in 0..rows {
+            let y = area.y + row as u16;
+            let label = match row {
+                0 => labels[0].as_str(),
+                row if row == rows - 1 => labels[1].as_str(),
+                _ => "",
+            };
+            let axis = format!("{:>1$}{2}", label, label_width, resolve(ACS_VLINE));
+            canvas.put_str(y, area.x, &axis, self.axis_style)?;
+            let cells: Vec<_> = (0..cols).map(|col| grid.cell(row, col, unicode)).collect();
+            put_cells(canvas, y, axis_x + 1, &cells)?;
+        }
+
+        let y = area.y + rows as u16;
+        let axis = format!(
+            "{}{}{}",
+            " ".repeat(label_width),
+            resolve(ACS_LLCORNER),
+            resolve(ACS_HLINE).to_string().repeat(cols)
+        );
+        canvas.put_str(y, area.x, &axis, self.axis_style)?;
+        if has_x_labels {
+            let text = self.x_label_row(label_width + 1, cols);
+            canvas.put_str(y + 1, area.x, &text, self.axis_style)?;
+        }
+
+        self.render_legend(canvas, area.y, axis_x + 1, cols)
+    }
+
+    /// Value range shown: the fixed range, or that of the data widened so
+    /// it is never empty
+    fn bounds(&self) -> (f64, f64) {
+        if let Some(range) = self.y_range
+            && range.0 < range.1
+        {
+            return range;
+        }
+        let values = self
+            .series
+            .iter()
+            .flat_map(|s| s.data.iter().copied())
+            .filter(|v| v.is_finite());
+        let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+        if min > max {
+            (0.0, 1.0)
+        } else if min == max {
+            (min - 1.0, max + 1.0)
+        } else {
+            (min, max)
+        }
+    }
+
+    /// Draw `series` as lines between its points
+    fn plot(&self, grid: &mut Braille, series: &Series, min: f64, max: f64) {
+        let (width, height) = (grid.cols * 2, grid.rows * 4);
+        let last = series.data.len().saturating_sub(1);
+        let point = |i: usize, value: f64| {
+            let x = (i * (width - 1) + last / 2).checked_div(last).unwrap_or(0);
+            let y = ((max - value) / (max - min) * (height - 1) as f64)
+                .round()
+                .clamp(0.0, (height - 1) as f64) as usize;
+            (x, y)
+        };
+
+        let mut previous = None;
+        for (i, &value) in series.data.iter().enumerate() {
+            if !value.is_finite() {
+                previous = None;
+                continue;
+            }
+            let to = point(i, value);
+            let style = self.thresholds.style(value, series.style);
+            grid.line(previous.unwrap_or(to), to, style);
+            previous = Some(to);
+        }
+    }
+
+    /// The x labels laid out under `cols` plot columns starting at `start`:
+    /// the first left-aligned, the last right-aligned, the rest centered
+    fn x_label_row(&self, start: usize, cols: usize) -> String {
+        let mut row = vec![' '; start + cols];
+        let last = self.x_labels.len() - 1;
+        for (i, label) in self.x_labels.iter().enumerate() {
+            let len = label.chars().count();
+            let pos = start + (i * (cols - 1)).checked_div(last).unwrap_or(0);
+            let from = if i == 0 {
+                pos
+            } else if i == last {
+                (pos + 1).saturating_sub(len)
+            } else {
+                pos.saturating_sub(len / 2)
+            };
+            for (cell, ch) in row.iter_mut().skip(from).zip(label.chars()) {
+                *cell = ch;
+            }
+        }
+        row.into_iter().collect()
+    }
+
+    /// Names of the named series on the top row, right-aligned, each in
+    /// its style; skipped if they don't fit
+    fn render_legend(&self, canvas: &mut impl Canvas, y: u16, x: u16, cols: usize) -> Result<()> {
+        let named: Vec<&Series> = self.series.iter().filter(|s| !s.name.is_empty()).collect();
+        let width: usize = named.iter().map(|s| s.name.chars().count() + 1).sum();
+        if named.is_empty() || width > cols {
+            return Ok(());
+        }
+        let mut x = x + (cols - width) as u16;
+        for series in named {
+            let text = format!(" {}", series.name);
+            canvas.put_str(y, x, &text, series.style)?;
+            x += text.chars().count() as u16;
+        }
+        Ok(())
+    }
+}
+
+impl Default for LineChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Grid of braille cells, addressed by dot
+struct Braille {
+    rows: usize,
+    cols: usize,
+    dots: Vec<u8>,
+    styles: Vec<Style>,
+}
+
+impl Braille {
+    fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            dots: vec![0; rows * cols],
+            styles: vec![Style::new(); rows * cols],
+        }
+    }
+
+    /// Set the dot at (x, y); its cell takes `style`
+    fn set(&mut self, x: usize, y: usize, style: Style) {
+        let index = (y / 4) * self.cols + x / 2;
+        self.dots[index] |= DOTS[y % 4][x % 2];
+        self.styles[index] = style;
+    }
+
+    /// Set the dots on the line between two dots (Bresenham)
+    fn line(&mut self, from: (usize, usize), to: (usize, usize), style: Style) {
+        let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+        let (x1, y1) = (to.0 as isize, to.1 as isize);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut err = dx + dy;
+        loop {
+            self.set(x as usize, y as usize, style);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Character and style of a cell; `*` for any dots without Unicode
+    fn cell(&self, row: usize, col: usize, unicode: bool) -> (char, Style) {
+        let index = row * self.cols + col;
+        match self.dots[index] {
+            0 => (' ', Style::new()),
+            _ if !unicode => ('*', self.styles[index]),
+            dots => (
+                char::from_u32(0x2800 + dots as u32).unwrap_or('*'),
+                self.styles[index],
+            ),
+        }
+    }
+}
+
+/// Height in eighths of a cell of a bar for `value` over `rows` rows, with
+/// `max` filling them all
+fn eighths(value: f64, max: f64, rows: usize) -> usize {
+    if max.is_nan() || max <= 0.0 || value.is_nan() {
+        return 0;
+    }
+    ((value / max).clamp(0.0, 1.0) * (rows * 8) as f64).round() as usize
+}
+
+/// Cell of a bar filled `eighths` eighths (full from 8 up)
+fn level(eighths: usize, unicode: bool) -> char {
+    let eighths = eighths.min(8);
+    if unicode {
+        LEVELS[eighths]
+    } else {
+        ASCII_LEVELS[eighths]
+    }
+}
+
+/// A value as an axis or bar label: at most two decimals, without
+/// trailing zeros
+fn format_value(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    match text.trim_end_matches('0').trim_end_matches('.') {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// A row of `width` cells with each text centered under its bar
+fn bar_texts(
+    texts: impl Iterator<Item = String>,
+    width: usize,
+    bar_width: usize,
+    step: usize,
+    unicode: bool,
+) -> String {
+    let mut row = vec![' '; width];
+    for (i, text) in texts.enumerate() {
+        let text = ellipsize(&text, bar_width, unicode);
+        let from = i * step + (bar_width - text.chars().count()) / 2;
+        for (cell, ch) in row[from..].iter_mut().zip(text.chars()) {
+            *cell = ch;
+        }
+    }
+    row.into_iter().collect()
+}
+
+/// Draw `cells` at (y, x), one `put_str` per run of the same style
+fn put_cells(canvas: &mut impl Canvas, y: u16, x: u16, cells: &[(char, Style)]) -> Result<()> {
+    let mut start = 0;
+    while start < cells.len() {
+        let style = cells[start].1;
+        let end = cells[start..]
+            .iter()
+            .position(|&(_, s)| s != style)
+            .map_or(cells.len(), |len| start + len);
+        let text: String = cells[start..end].iter().map(|&(ch, _)| ch).collect();
+        canvas.put_str(y, x + start as u16, &text, style)?;
+        start = end;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attr::AttrPolicy;
+    use crate::caps::Capabilities;
+    use crate::screen::Screen;
+    use crate::window::Window;
+
+    fn text(scr: &Screen, area: Rect) -> Vec<String> {
+        scr.capture(area).iter().map(|line| line.text()).collect()
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut spark = Sparkline::new().capacity(9).threshold(7.0, Color::Red);
+        spark.set_data((0..9).map(f64::from));
+        let area = Rect::new(0, 0, 1, 10);
+        spark.render(&mut scr, area).unwrap();
+        assert_eq!(text(&scr, area), ["  ▁▂▃▄▅▆▇█"]);
+        let line = &scr.capture(area)[0];
+        assert_eq!(line.spans.last().unwrap().fg, Color::Red);
+
+        // Old values drop off; the newest stay on the right
+        spark.push(16.0);
+        assert_eq!(spark.data().len(), 9);
+        let area = Rect::new(2, 0, 2, 4);
+        spark.render(&mut scr, area).unwrap();
+        assert_eq!(text(&scr, area), ["   █", "▆▇██"]);
+    }
+
+    #[test]
+    fn test_bar_chart() {
+        let mut scr = Screen::with_size(24, 80);
+        let chart = BarChart::new().bar("a", 2.0).bar("b", 4.0).bar("cut", 1.5);
+        let area = Rect::new(0, 0, 4, 9);
+        chart.render(&mut scr, area).unwrap();
+        assert_eq!(
+            text(&scr, area),
+            ["    ███  ", "███ ███  ", " 2   4   ", " a   b   "]
+        );
+
+        assert_eq!(format_value(2.5), "2.5");
+        assert_eq!(format_value(100.0), "100");
+        assert_eq!(format_value(-0.001), "0");
+    }
+
+    #[test]
+    fn test_line_chart() {
+        let mut scr = Screen::with_size(24, 80);
+        let chart = LineChart::new()
+            .series("", [0.0, 1.0], Style::new())
+            .x_labels(["a", "b"]);
+        let area = Rect::new(0, 0, 3, 4);
+        chart.render(&mut scr, area).unwrap();
+        assert_eq!(text(&scr, area), ["1│⡠⠊", " └──", "  ab"]);
+
+        let mut win = Window::new(2, 4, 0, 0).unwrap();
+        win.set_capabilities(
+            Capabilities::default().unicode(false),
+            AttrPolicy::default(),
+        );
+        chart.render(&mut win, Rect::new(0, 0, 2, 4)).unwrap();
+        assert_eq!(win.read_str(0, 0, 4).unwrap(), "1|**");
+        assert_eq!(win.read_str(1, 0, 4).unwrap(), " +--");
+    }
+
+    #[test]
+    fn test_line_chart_legend_and_gaps() {
+        let mut scr = Screen::with_size(24, 80);
+        let style = Style::new().fg(Color::Green);
+        let chart = LineChart::new()
+            .series("cpu", [0.0, f64::NAN, 0.0, 0.0], style)
+            .y_range(0.0, 100.0)
+            .threshold(50.0, Color::Red);
+        let area = Rect::new(0, 0, 3, 12);
+        chart.render(&mut scr, area).unwrap();
+        assert_eq!(
+            text(&scr, area),
+            ["100│     cpu", "  0│⡀    ⣀⣀⣀", "   └────────"]
+        );
+        let line = &scr.capture(area)[1];
+        assert_eq!(line.spans.last().unwrap().fg, Color::Green);
+    }
+}
@@ -0,0 +1,649 @@
+//! Modal dialogs: message boxes, questions, text input and file picking
+
+use super::{Canvas, InputLine, ListView, ellipsis, ellipsize, key_modifiers};
+use crate::attr::Attr;
+use crate::error::Result;
+use crate::input::Key;
+use crate::kitty::Modifiers;
+use crate::layout::Rect;
+use crate::panel::PanelId;
+use crate::screen::Screen;
+use crate::snapshot::Snapshot;
+use crate::style::Style;
+use crate::window::Window;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How often a running dialog checks the terminal size, in milliseconds
+const RESIZE_POLL_MS: u64 = 100;
+
+/// A dialog shown by `run_dialog`
+pub trait Dialog {
+    /// What the dialog returns when closed
+    type Output;
+
+    /// Size (height, width) to show the dialog at, given the screen size
+    fn size(&self, screen: (u16, u16)) -> (u16, u16);
+
+    /// Draw the dialog onto its window
+    fn render(&mut self, win: &mut Window) -> Result<()>;
+
+    /// Handle a key; returns the result once the dialog is closed
+    fn handle_key(&mut self, key: &Key) -> Option<Self::Output>;
+}
+
+/// Show `dialog` centered on the screen until it's closed, and return its
+/// result
+///
+/// The dialog's window sits on top of the screen's panel stack while it
+/// runs and takes every key; afterwards the content under it is drawn
+/// again.
+pub fn run_dialog<D: Dialog>(scr: &mut Screen, dialog: &mut D) -> Result<D::Output> {
+    let snapshot = scr.snapshot();
+    let id = scr.new_panel(dialog_window(scr, dialog)?)?;
+
+    let result = modal_loop(scr, id, dialog, &snapshot);
+    scr.panels_mut().remove(id)?;
+    scr.restore(&snapshot)?;
+    scr.refresh()?;
+    result
+}
+
+/// Window for `dialog`, centered on the screen
+fn dialog_window<D: Dialog>(scr: &Screen, dialog: &D) -> Result<Window> {
+    let (rows, cols) = scr.dimensions();
+    let (height, width) = dialog.size((rows, cols));
+    let area = Rect::new(0, 0, rows, cols).centered(height.max(3), width.max(3));
+    // Windows are placed in terminal rows, which include reserved lines
+    let y = area.y + scr.reserved_lines().0;
+    scr.newwin(area.height, area.width, y, area.x)
+}
+
+/// Draw the dialog in panel `id` and feed it keys until it's closed
+///
+/// When the terminal changes size, the screen follows it and the dialog
+/// moves back to the center, over the content saved in `snapshot`.
+fn modal_loop<D: Dialog>(
+    scr: &mut Screen,
+    id: PanelId,
+    dialog: &mut D,
+    snapshot: &Snapshot,
+) -> Result<D::Output> {
+    let mut size = scr.get_size()?;
+    let mut redraw = true;
+    loop {
+        if redraw {
+            if let Some(panel) = scr.panels_mut().get_mut(id) {
+                dialog.render(panel.window_mut())?;
+            }
+            scr.update_panels()?;
+            scr.doupdate()?;
+        }
+
+        let key = scr.getch_timeout(RESIZE_POLL_MS)?;
+        redraw = key.is_some();
+        let current = scr.get_size()?;
+        if current != size {
+            size = current;
+            scr.resize(size.0, size.1)?;
+            scr.restore(snapshot)?;
+            let window = dialog_window(scr, dialog)?;
+            scr.panels_mut().replace(id, window)?;
+            redraw = true;
+        }
+        if let Some(output) = key.and_then(|key| dialog.handle_key(&key)) {
+            return Ok(output);
+        }
+    }
+}
+
+/// Show a message with a row of buttons
+///
+/// Returns the index of the chosen button, or `None` if the box was closed
+/// with Escape. Without buttons, an OK button is shown.
+pub fn message_box(
+    scr: &mut Screen,
+    title: &str,
+    text: &str,
+    buttons: &[&str],
+) -> Result<Option<usize>> {
+    run_dialog(scr, &mut MessageBox::new(title, text, buttons))
+}
+
+/// Ask a yes/no question; Escape counts as no
+pub fn confirm(scr: &mut Screen, title: &str, text: &str) -> Result<bool> {
+    let choice = message_box(scr, title, text, &["Yes", "No"])?;
+    Ok(choice == Some(0))
+}
+
+/// Ask for a line of text, starting with `initial`; `None` if cancelled
+pub fn input_dialog(
+    scr: &mut Screen,
+    title: &str,
+    prompt: &str,
+    initial: &str,
+) -> Result<Option<String>> {
+    run_dialog(scr, &mut InputDialog::new(title, prompt).text(initial))
+}
+
+/// Let the user pick a file, starting in `dir`; `None` if cancelled
+pub fn file_picker(
+    scr: &mut Screen,
+    title: &str,
+    dir: impl AsRef<Path>,
+) -> Result<Option<PathBuf>> {
+    run_dialog(scr, &mut FilePicker::new(title, dir.as_ref()))
+}
+
+/// Text with a row of buttons
+///
+/// Left/Right and Tab move between buttons, Enter or Space press the
+/// focused one, and a button's first letter presses it directly.
+pub struct MessageBox {
+    title: String,
+    text: String,
+    buttons: Vec<String>,
+    focused: usize,
+    focused_style: Style,
+}
+
+impl MessageBox {
+    /// Create a message box (with an OK button if `buttons` is empty)
+    pub fn new(title: impl Into<String>, text: impl Into<String>, buttons: &[&str]) -> Self {
+        let mut buttons: Vec<String> = buttons.iter().map(|b| b.to_string()).collect();
+        if buttons.is_empty() {
+            buttons.push("OK".to_string());
+        }
+        Self {
+            title: title.into(),
+            text: text.into(),
+            buttons,
+            focused: 0,
+            focused_style: Style::new().attr(Attr::REVERSE),
+        }
+    }
+
+    /// Style of the focused button (reverse video by default)
+    pub fn focused_style(mut self, style: Style) -> Self {
+        self.focused_style = style;
+        self
+    }
+
+    /// Index of the focused button
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    /// Width of the button row: `[ label ]` per button, one space apart
+    fn buttons_width(&self) -> usize {
+        let labels: usize = self.buttons.iter().map(|b| b.chars().count() + 4).sum();
+        labels + self.buttons.len() - 1
+    }
+}
+
+impl Dialog for MessageBox {
+    type Output = Option<usize>;
+
+    fn size(&self, (_, cols): (u16, u16)) -> (u16, u16) {
+        let longest = self.text.lines().map(|l| l.chars().count()).max();
+        let inner = longest
+            .unwrap_or(0)
+            .max(self.buttons_width())
+            .max(self.title.chars().count() + 2)
+            .min(cols.saturating_sub(4).max(1) as usize);
+        let lines = wrap_words(&self.text, inner).len();
+        ((lines + 4) as u16, (inner + 4) as u16)
+    }
+
+    fn render(&mut self, win: &mut Window) -> Result<()> {
+        draw_frame(win, &self.title)?;
+        let (height, width) = win.get_size();
+        let lines = wrap_words(&self.text, width.saturating_sub(4) as usize);
+        for (row, line) in lines.iter().enumerate() {
+            let y = 1 + row as u16;
+            if y + 2 >= height {
+                break;
+            }
+            win.put_str(y, 2, line, Style::new())?;
+        }
+
+        let mut x = width.saturating_sub(self.buttons_width() as u16) / 2;
+        for (i, label) in self.buttons.iter().enumerate() {
+            let text = format!("[ {} ]", label);
+            let style = if i == self.focused {
+                self.focused_style
+            } else {
+                Style::new()
+            };
+            win.put_str(height.saturating_sub(2), x, &text, style)?;
+            x += text.chars().count() as u16 + 1;
+        }
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: &Key) -> Option<Option<usize>> {
+        let count = self.buttons.len();
+        match key_modifiers(key)? {
+            (Key::Tab, Modifiers::SHIFT) | (Key::Left, _) => {
+                self.focused = (self.focused + count - 1) % count
+            }
+            (Key::Tab, _) | (Key::Right, _) => self.focused = (self.focused + 1) % count,
+            (Key::Enter, _) | (Key::Char(' '), _) => return Some(Some(self.focused)),
+            (Key::Escape, _) => return Some(None),
+            (Key::Char(ch), m) if m.is_empty() => {
+                let ch = ch.to_lowercase().next();
+                let hit = self
+                    .buttons
+                    .iter()
+                    .position(|b| b.chars().next().and_then(|c| c.to_lowercase().next()) == ch);
+                if hit.is_some() {
+                    return Some(hit);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// A prompt with a line of text input
+///
+/// Enter returns the text and Escape cancels; other keys edit the
+/// `InputLine`.
+pub struct InputDialog {
+    title: String,
+    prompt: String,
+    input: InputLine,
+}
+
+impl InputDialog {
+    /// Create an input dialog
+    pub fn new(title: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            prompt: prompt.into(),
+            input: InputLine::new(),
+        }
+    }
+
+    /// Start with `text` filled in
+    pub fn text(mut self, text: &str) -> Self {
+        self.input.set_text(text);
+        self
+    }
+
+    /// Edit with `input`, e.g. one with history or a completer
+    pub fn input(mut self, input: InputLine) -> Self {
+        self.input = input;
+        self
+    }
+}
+
+impl Dialog for InputDialog {
+    type Output = Option<String>;
+
+    fn size(&self, (_, cols): (u16, u16)) -> (u16, u16) {
+        let inner = (self.prompt.chars().count())
+            .max(self.title.chars().count() + 2)
+            .max(36);
+        (4, (inner + 4).min(cols as usize) as u16)
+    }
+
+    fn render(&mut self, win: &mut Window) -> Result<()> {
+        draw_frame(win, &self.title)?;
+        let (height, width) = win.get_size();
+        let inner = width.saturating_sub(4);
+        let prompt = ellipsize(&self.prompt, inner as usize, win.unicode());
+        win.put_str(1, 2, &prompt, Style::new())?;
+        if height >= 4 {
+            self.input.render(win, Rect::new(2, 2, 1, inner))?;
+        }
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: &Key) -> Option<Option<String>> {
+        match key_modifiers(key) {
+            Some((Key::Enter, _)) => Some(Some(self.input.submit())),
+            Some((Key::Escape, _)) => Some(None),
+            _ => {
+                self.input.handle_key(key);
+                None
+            }
+        }
+    }
+}
+
+/// A directory entry shown by `FilePicker`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    name: String,
+    dir: bool,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.dir {
+            write!(f, "{}/", self.name)
+        } else {
+            f.write_str(&self.name)
+        }
+    }
+}
+
+/// A directory browser for picking a file
+///
+/// Enter opens the selected directory or picks the selected file,
+/// Backspace goes to the parent directory and Escape cancels; the list
+/// scrolls like a `ListView`.
+pub struct FilePicker {
+    title: String,
+    dir: PathBuf,
+    list: ListView<Entry>,
+    show_hidden: bool,
+    error: Option<String>,
+}
+
+impl FilePicker {
+    /// Create a picker showing `dir`
+    pub fn new(title: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let mut picker = Self {
+            title: title.into(),
+            dir: std::fs::canonicalize(&dir).unwrap_or(dir),
+            list: ListView::new(Vec::new()),
+            show_hidden: false,
+            error: None,
+        };
+        picker.load();
+        picker
+    }
+
+    /// Show entries starting with `.` (hidden by default)
+    pub fn show_hidden(mut self, show: bool) -> Self {
+        self.show_hidden = show;
+        self.load();
+        self
+    }
+
+    /// The directory shown
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Read the entries of the current directory: `..`, then directories
+    /// and files, each sorted by name
+    fn load(&mut self) {
+        let mut entries = Vec::new();
+        self.error = None;
+        match std::fs::read_dir(&self.dir) {
+            Ok(read) => {
+                for entry in read.flatten() {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if name.starts_with('.') && !self.show_hidden {
+                        continue;
+                    }
+                    // Follow symlinks, so links to directories can be opened
+                    let dir = entry.path().is_dir();
+                    entries.push(Entry { name, dir });
+                }
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+        entries.sort_by(|a, b| b.dir.cmp(&a.dir).then_with(|| a.name.cmp(&b.name)));
+        if self.dir.parent().is_some() {
+            entries.insert(
+                0,
+                Entry {
+                    name: "..".to_string(),
+                    dir: true,
+                },
+            );
+        }
+        self.list.set_items(entries);
+        self.list.select(0);
+    }
+
+    /// Go to the parent directory, selecting the one we came from
+    fn go_up(&mut self) {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let child = self
+            .dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned());
+        self.dir = parent;
+        self.load();
+        if let Some(index) = self
+            .list
+            .items()
+            .iter()
+            .position(|e| e.dir && Some(&e.name) == child.as_ref())
+        {
+            self.list.select(index);
+        }
+    }
+}
+
+impl Dialog for FilePicker {
+    type Output = Option<PathBuf>;
+
+    fn size(&self, (rows, cols): (u16, u16)) -> (u16, u16) {
+        ((rows * 2 / 3).max(8), (cols * 2 / 3).max(30))
+    }
+
+    fn render(&mut self, win: &mut Window) -> Result<()> {
+        draw_frame(win, &self.title)?;
+        let (height, width) = win.get_size();
+        let inner = width.saturating_sub(4) as usize;
+        let unicode = win.unicode();
+        let (header, style) = match &self.error {
+            Some(error) => (
+                ellipsize(error, inner, unicode),
+                Style::new().attr(Attr::BOLD),
+            ),
+            None => (
+                ellipsize_start(&self.dir.to_string_lossy(), inner, unicode),
+                Style::new(),
+            ),
+        };
+        win.put_str(1, 2, &header, style)?;
+        let area = Rect::new(2, 1, height.saturating_sub(3), width.saturating_sub(2));
+        self.list.render(win, area)
+    }
+
+    fn handle_key(&mut self, key: &Key) -> Option<Option<PathBuf>> {
+        let (base, _) = key_modifiers(key)?;
+        match base {
+            Key::Escape => return Some(None),
+            Key::Backspace | Key::Left => self.go_up(),
+            Key::Enter | Key::Right => {
+                let entry = self.list.selected_item()?.clone();
+                if entry.name == ".." {
+                    self.go_up();
+                } else if entry.dir {
+                    self.dir.push(&entry.name);
+                    self.load();
+                } else if base == Key::Enter {
+                    return Some(Some(self.dir.join(&entry.name)));
+                }
+            }
+            _ => {
+                self.list.handle_key(key);
+            }
+        }
+        None
+    }
+}
+
+/// Clear `win` and draw its border, with `title` in the top edge
+fn draw_frame(win: &mut Window, title: &str) -> Result<()> {
+    win.clear()?;
+    win.draw_box()?;
+    let (_, width) = win.get_size();
+    if !title.is_empty() && width > 4 {
+        let title = format!(" {} ", ellipsize(title, width as usize - 4, win.unicode()));
+        let x = (width - title.chars().count() as u16) / 2;
+        win.put_str(0, x, &title, Style::new().attr(Attr::BOLD))?;
+    }
+    Ok(())
+}
+
+/// `text` cut to `width` characters from the start, beginning with an
+/// `ellipsis` if anything was cut (for paths, whose end matters most)
+fn ellipsize_start(text: &str, width: usize, unicode: bool) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut cut = String::from(ellipsis(unicode));
+    cut.extend(text.chars().skip(len + 1 - width));
+    cut
+}
+
+/// `text` broken into lines of at most `width` characters, at spaces where
+/// possible; line breaks in `text` are kept
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut len = 0;
+        for word in paragraph.split(' ') {
+            let mut word: Vec<char> = word.chars().collect();
+            if len > 0 && len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+                len = 0;
+            }
+            // Words longer than a line are split
+            while word.len() > width {
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if len > 0 {
+                line.push(' ');
+                len += 1;
+            }
+            line.extend(&word);
+            len += word.len();
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kitty::KeyEvent;
+
+    fn rows(win: &Window) -> Vec<String> {
+        let (height, width) = win.get_size();
+        (0..height)
+            .map(|y| win.read_str(y, 0, width).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_wrap_words() {
+        assert_eq!(wrap_words("a bb ccc dddd", 6), ["a bb", "ccc", "dddd"]);
+        assert_eq!(wrap_words("abcdefgh x", 3), ["abc", "def", "gh", "x"]);
+        assert_eq!(wrap_words("one\n\ntwo", 10), ["one", "", "two"]);
+        assert_eq!(ellipsize_start("/home/user/src", 8, true), "…ser/src");
+        assert_eq!(ellipsize_start("/home/user/src", 8, false), "~ser/src");
+    }
+
+    #[test]
+    fn test_message_box() {
+        let mut dialog = MessageBox::new("Save", "Save changes?", &["Yes", "No", "Cancel"]);
+        let (height, width) = dialog.size((24, 80));
+        assert_eq!((height, width), (5, 29));
+
+        let mut win = Window::new(height, width, 0, 0).unwrap();
+        dialog.render(&mut win).unwrap();
+        let text = rows(&win);
+        assert_eq!(text[0], "┌────────── Save ───────────┐");
+        assert_eq!(text[1], "│ Save changes?             │");
+        assert_eq!(text[3], "│ [ Yes ] [ No ] [ Cancel ] │");
+        assert_eq!(win.cell_at(3, 2).unwrap().attr(), Attr::REVERSE);
+
+        assert_eq!(dialog.handle_key(&Key::Left), None);
+        assert_eq!(dialog.focused(), 2);
+        assert_eq!(dialog.handle_key(&Key::Tab), None);
+        assert_eq!(dialog.handle_key(&Key::Enter), Some(Some(0)));
+        assert_eq!(dialog.handle_key(&Key::Char('N')), Some(Some(1)));
+        assert_eq!(dialog.handle_key(&Key::Escape), Some(None));
+    }
+
+    #[test]
+    fn test_input_dialog() {
+        let mut dialog = InputDialog::new("Rename", "New name:").text("old");
+        let (height, width) = dialog.size((24, 80));
+        let mut win = Window::new(height, width, 0, 0).unwrap();
+        assert_eq!(dialog.handle_key(&Key::Backspace), None);
+        assert_eq!(dialog.handle_key(&Key::Char('k')), None);
+        dialog.render(&mut win).unwrap();
+        assert!(rows(&win)[2].starts_with("│ olk "));
+        assert_eq!(
+            dialog.handle_key(&Key::Enter),
+            Some(Some("olk".to_string()))
+        );
+        assert_eq!(dialog.handle_key(&Key::Escape), Some(None));
+
+        // Kitty protocol keys close it too
+        let escape = Key::Enhanced(KeyEvent::new(27));
+        assert_eq!(dialog.handle_key(&escape), Some(None));
+        let enter = Key::Enhanced(KeyEvent::new(13));
+        dialog.handle_key(&Key::Char('x'));
+        assert_eq!(dialog.handle_key(&enter), Some(Some("x".to_string())));
+    }
+
+    #[test]
+    fn test_dialog_window_follows_resize() {
+        let mut scr = Screen::with_size(24, 80);
+        let dialog = MessageBox::new("Save", "Save changes?", &["Yes", "No", "Cancel"]);
+        let win = dialog_window(&scr, &dialog).unwrap();
+        assert_eq!(win.get_position(), (9, 25));
+
+        scr.resize(12, 40).unwrap();
+        let win = dialog_window(&scr, &dialog).unwrap();
+        assert_eq!(win.get_position(), (3, 5));
+    }
+
+    #[test]
+    fn test_file_picker() {
+        let root = std::env::temp_dir().join(format!("zaz-picker-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("b.txt"), "").unwrap();
+        std::fs::write(root.join(".hidden"), "").unwrap();
+        std::fs::write(root.join("sub/inner.txt"), "").unwrap();
+
+        let mut picker = FilePicker::new("Open", &root);
+        let names: Vec<String> = picker.list.items().iter().map(|e| e.to_string()).collect();
+        assert_eq!(names, ["../", "sub/", "b.txt"]);
+
+        picker.handle_key(&Key::Down);
+        assert_eq!(picker.handle_key(&Key::Enter), None);
+        assert!(picker.dir().ends_with("sub"));
+        picker.handle_key(&Key::Down);
+        let picked = picker.handle_key(&Key::Enter).unwrap().unwrap();
+        assert!(picked.ends_with("sub/inner.txt"));
+
+        picker.handle_key(&Key::Backspace);
+        assert_eq!(picker.list.selected_item().unwrap().name, "sub");
+        let escape = Key::Enhanced(KeyEvent::new(27));
+        assert_eq!(picker.handle_key(&escape), Some(None));
+        let mut win = Window::new(8, 30, 0, 0).unwrap();
+        picker.render(&mut win).unwrap();
+        assert!(rows(&win)[3].starts_with("│sub/"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,658 @@
+//! Single-line text editor with history and tab completion
+
+use super::{Canvas, key_modifiers};
+use crate::attr::Attr;
+use crate::error::{Result, ResultExt};
+use crate::input::Key;
+use crate::kitty::Modifiers;
+use crate::layout::Rect;
+use crate::style::Style;
+use std::path::Path;
+use unicode_width::UnicodeWidthChar;
+
+/// Killed texts kept for yanking
+const KILL_RING_SIZE: usize = 16;
+
+type Completer = Box<dyn Fn(&str) -> Vec<String>>;
+
+/// What the previous key did, for keys that continue it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LastEdit {
+    Other,
+    /// Consecutive kills join into one kill ring entry
+    Kill,
+    /// Alt-Y replaces the text yanked from `start` to the cursor with the
+    /// previous ring entry
+    Yank {
+        start: usize,
+        ring: usize,
+    },
+    /// Tab again cycles through the candidates
+    Complete,
+}
+
+/// A line editor for prompts, command palettes and REPLs
+///
+/// Keys follow readline: arrows, Home/End and Ctrl-A/E/B/F move, Alt-B/F
+/// and Ctrl/Alt+arrows move by word, Ctrl-K/U/W and Alt-D kill into the
+/// kill ring, Ctrl-Y yanks and Alt-Y cycles through older kills, Up/Down
+/// browse the history and Tab completes. Enter is left to the app, which
+/// calls `submit` to take the line and add it to the history.
+pub struct InputLine {
+    text: Vec<char>,
+    cursor: usize,
+    offset: usize,
+    prompt: String,
+    prompt_style: Style,
+    style: Style,
+    cursor_style: Style,
+    history: Vec<String>,
+    history_limit: usize,
+    // Entry shown while browsing the history, and the edit it replaced
+    history_index: Option<usize>,
+    draft: Vec<char>,
+    kill_ring: Vec<String>,
+    last_edit: LastEdit,
+    completer: Option<Completer>,
+    completions: Vec<String>,
+    completion: Option<usize>,
+    cursor_position: Option<(u16, u16)>,
+}
+
+impl InputLine {
+    /// Create an empty input line
+    pub fn new() -> Self {
+        Self {
+            text: Vec::new(),
+            cursor: 0,
+            offset: 0,
+            prompt: String::new(),
+            prompt_style: Style::new(),
+            style: Style::new(),
+            cursor_style: Style::new().attr(Attr::REVERSE),
+            history: Vec::new(),
+            history_limit: 1000,
+            history_index: None,
+            draft: Vec::new(),
+            kill_ring: Vec::new(),
+            last_edit: LastEdit::Other,
+            completer: None,
+            completions: Vec::new(),
+            completion: None,
+            cursor_position: None,
+        }
+    }
+
+    /// Text drawn before the input, e.g. `"> "`
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// Style of the prompt
+    pub fn prompt_style(mut self, style: Style) -> Self {
+        self.prompt_style = style;
+        self
+    }
+
+    /// Style of the input text
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Style of the cell under the cursor (reverse video by default; use
+    /// `Style::new()` to draw the terminal cursor instead, at
+    /// `cursor_position`)
+    pub fn cursor_style(mut self, style: Style) -> Self {
+        self.cursor_style = style;
+        self
+    }
+
+    /// Most history entries kept (1000 by default)
+    pub fn history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self.trim_history();
+        self
+    }
+
+    /// Complete with `completer`, called with the text before the cursor
+    /// and returning replacements for it
+    ///
+    /// A single candidate is inserted right away. With several, Tab first
+    /// inserts their common prefix, then cycles through them.
+    pub fn completer(mut self, completer: impl Fn(&str) -> Vec<String> + 'static) -> Self {
+        self.completer = Some(Box::new(completer));
+        self
+    }
+
+    /// The text being edited
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    /// Replace the text, moving the cursor to its end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = single_line(text);
+        self.cursor = self.text.len();
+    }
+
+    /// Cursor position, in characters
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Screen position of the cursor at the last render, for placing the
+    /// terminal cursor; `None` if it wasn't drawn
+    pub fn cursor_position(&self) -> Option<(u16, u16)> {
+        self.cursor_position
+    }
+
+    /// Candidates of the completion in progress, for showing them
+    pub fn completions(&self) -> &[String] {
+        &self.completions
+    }
+
+    /// Previous entries, oldest first
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Add an entry to the history (blank entries and repeats of the last
+    /// one are skipped)
+    pub fn add_history(&mut self, entry: &str) {
+        let entry = entry.trim_end();
+        if entry.trim().is_empty() || self.history.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        self.history.push(entry.to_string());
+        self.trim_history();
+    }
+
+    /// Add the entries of a history file, one per line
+    pub fn load_history(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let data = std::fs::read_to_string(path).context("loading history")?;
+        for line in data.lines() {
+            self.add_history(line);
+        }
+        Ok(())
+    }
+
+    /// Write the history to a file, one entry per line
+    pub fn save_history(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut data = String::new();
+        for entry in &self.history {
+            data.push_str(entry);
+            data.push('\n');
+        }
+        std::fs::write(path, data).context("saving history")
+    }
+
+    /// Take the text, adding it to the history and clearing the line
+    pub fn submit(&mut self) -> String {
+        let text = self.text();
+        self.add_history(&text);
+        self.text.clear();
+        self.cursor = 0;
+        self.offset = 0;
+        self.history_index = None;
+        self.last_edit = LastEdit::Other;
+        self.completions.clear();
+        text
+    }
+
+    /// Edit the line with `key`
+    ///
+    /// Returns true if the key was handled; Enter never is.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let last = std::mem::replace(&mut self.last_edit, LastEdit::Other);
+        if *key != Key::Tab {
+            self.completions.clear();
+        }
+
+        let Some((base, modifiers)) = key_modifiers(key) else {
+            self.last_edit = last;
+            return false;
+        };
+        let by_word = modifiers.intersects(Modifiers::CTRL | Modifiers::ALT);
+        match (base, modifiers) {
+            (Key::Char(ch), Modifiers::SHIFT) => self.insert(&[ch]),
+            (Key::Char(ch), m) if m.is_empty() => self.insert(&[ch]),
+            (Key::Paste(text), _) => self.insert(&single_line(&text)),
+            (Key::Left, _) if by_word => self.cursor = self.word_start(),
+            (Key::Right, _) if by_word => self.cursor = self.word_end(),
+            (Key::Char('b'), Modifiers::ALT) => self.cursor = self.word_start(),
+            (Key::Char('f'), Modifiers::ALT) => self.cursor = self.word_end(),
+            (Key::Left, _) | (Key::Char('b'), Modifiers::CTRL) => {
+                self.cursor = self.cursor.saturating_sub(1)
+            }
+            (Key::Right, _) | (Key::Char('f'), Modifiers::CTRL) => {
+                self.cursor = (self.cursor + 1).min(self.text.len())
+            }
+            (Key::Home, _) | (Key::Char('a'), Modifiers::CTRL) => self.cursor = 0,
+            (Key::End, _) | (Key::Char('e'), Modifiers::CTRL) => self.cursor = self.text.len(),
+            (Key::Backspace, _) if by_word => self.kill(self.word_start(), self.cursor, last),
+            (Key::Backspace, _) | (Key::Char('h'), Modifiers::CTRL) => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.text.remove(self.cursor);
+                }
+            }
+            (Key::Delete, _) | (Key::Char('d'), Modifiers::CTRL) => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+            }
+            (Key::Char('k'), Modifiers::CTRL) => self.kill(self.cursor, self.text.len(), last),
+            (Key::Char('u'), Modifiers::CTRL) => self.kill(0, self.cursor, last),
+            (Key::Char('w'), Modifiers::CTRL) => self.kill(self.word_start(), self.cursor, last),
+            (Key::Char('d'), Modifiers::ALT) => self.kill(self.cursor, self.word_end(), last),
+            (Key::Char('y'), Modifiers::CTRL) => self.yank(),
+            (Key::Char('y'), Modifiers::ALT) => self.yank_pop(last),
+            (Key::Up, _) | (Key::Char('p'), Modifiers::CTRL) => self.history_prev(),
+            (Key::Down, _) | (Key::Char('n'), Modifiers::CTRL) => self.history_next(),
+            (Key::Tab, m) if m.is_empty() && self.completer.is_some() => self.complete(last),
+            _ => {
+                self.last_edit = last;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Draw the prompt and input on the first row of `area`, scrolling
+    /// horizontally to keep the cursor visible
+    pub fn render(&mut self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        self.cursor_position = None;
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        // Everything is measured in columns, so wide characters take two
+        let (prompt, prompt_width) = fit(self.prompt.chars(), area.width as usize);
+        canvas.put_str(area.y, area.x, &prompt, self.prompt_style)?;
+        let width = area.width as usize - prompt_width;
+        if width == 0 {
+            return Ok(());
+        }
+
+        // Leave room for the cursor past the end of the text, and don't
+        // keep blank space on the right once the text got shorter
+        let columns = |chars: &[char]| chars.iter().map(|&ch| char_width(ch)).sum::<usize>();
+        let under = self.text.get(self.cursor).copied().unwrap_or(' ');
+        let cursor_end = columns(&self.text[..self.cursor]) + char_width(under).max(1);
+        self.offset = self.offset.min(self.cursor);
+        while self.offset < self.cursor && cursor_end - columns(&self.text[..self.offset]) > width {
+            self.offset += 1;
+        }
+        while self.offset > 0 && columns(&self.text[self.offset - 1..]) < width {
+            self.offset -= 1;
+        }
+
+        let x = area.x + prompt_width as u16;
+        let (visible, used) = fit(self.text[self.offset..].iter().copied(), width);
+        let padding = " ".repeat(width - used);
+        canvas.put_str(area.y, x, &(visible + &padding), self.style)?;
+
+        let cursor_x = x + columns(&self.text[self.offset..self.cursor]) as u16;
+        canvas.put_str(area.y, cursor_x, &under.to_string(), self.cursor_style)?;
+        self.cursor_position = Some((area.y, cursor_x));
+        Ok(())
+    }
+
+    fn insert(&mut self, chars: &[char]) {
+        self.text
+            .splice(self.cursor..self.cursor, chars.iter().copied());
+        self.cursor += chars.len();
+    }
+
+    /// Remove the text from `start` to `end` into the kill ring, joining it
+    /// to the last entry if the previous key killed too
+    fn kill(&mut self, start: usize, end: usize, last: LastEdit) {
+        self.last_edit = LastEdit::Kill;
+        if start >= end {
+            return;
+        }
+        let killed: String = self.text.drain(start..end).collect();
+        let backward = end == self.cursor;
+        self.cursor = start;
+
+        match self.kill_ring.last_mut() {
+            Some(entry) if last == LastEdit::Kill => {
+                if backward {
+                    entry.insert_str(0, &killed);
+                } else {
+                    entry.push_str(&killed);
+                }
+            }
+            _ => {
+                self.kill_ring.push(killed);
+                if self.kill_ring.len() > KILL_RING_SIZE {
+                    self.kill_ring.remove(0);
+                }
+            }
+        }
+    }
+
+    fn yank(&mut self) {
+        let Some(ring) = self.kill_ring.len().checked_sub(1) else {
+            return;
+        };
+        let start = self.cursor;
+        let chars: Vec<char> = self.kill_ring[ring].chars().collect();
+        self.insert(&chars);
+        self.last_edit = LastEdit::Yank { start, ring };
+    }
+
+    /// Replace the text just yanked with the kill before it
+    fn yank_pop(&mut self, last: LastEdit) {
+        let LastEdit::Yank { start, ring } = last else {
+            return;
+        };
+        let ring = ring.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+        let chars: Vec<char> = self.kill_ring[ring].chars().collect();
+        self.insert(&chars);
+        self.last_edit = LastEdit::Yank { start, ring };
+    }
+
+    fn history_prev(&mut self) {
+        let index = match self.history_index {
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = std::mem::take(&mut self.text);
+                self.history.len() - 1
+            }
+            Some(0) => return,
+            Some(i) => i - 1,
+        };
+        self.history_index = Some(index);
+        self.text = self.history[index].chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    fn history_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            self.text = self.history[index + 1].chars().collect();
+        } else {
+            self.history_index = None;
+            self.text = std::mem::take(&mut self.draft);
+        }
+        self.cursor = self.text.len();
+    }
+
+    fn complete(&mut self, last: LastEdit) {
+        self.last_edit = LastEdit::Complete;
+        if last == LastEdit::Complete && !self.completions.is_empty() {
+            let next = self
+                .completion
+                .map_or(0, |i| (i + 1) % self.completions.len());
+            self.completion = Some(next);
+            let candidate = self.completions[next].clone();
+            self.replace_before_cursor(&candidate);
+            return;
+        }
+
+        let before: String = self.text[..self.cursor].iter().collect();
+        let Some(completer) = &self.completer else {
+            return;
+        };
+        self.completions = completer(&before);
+        self.completion = None;
+        match self.completions.len() {
+            0 => {}
+            1 => {
+                let candidate = self.completions.remove(0);
+                self.replace_before_cursor(&candidate);
+            }
+            _ => {
+                let common = common_prefix(&self.completions);
+                if common.chars().count() > self.cursor {
+                    self.replace_before_cursor(&common);
+                } else {
+                    self.completion = Some(0);
+                    let candidate = self.completions[0].clone();
+                    self.replace_before_cursor(&candidate);
+                }
+            }
+        }
+    }
+
+    fn replace_before_cursor(&mut self, text: &str) {
+        let chars = single_line(text);
+        self.text.splice(..self.cursor, chars.iter().copied());
+        self.cursor = chars.len();
+    }
+
+    /// Start of the word before the cursor
+    fn word_start(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && !self.text[i - 1].is_alphanumeric() {
+            i -= 1;
+        }
+        while i > 0 && self.text[i - 1].is_alphanumeric() {
+            i -= 1;
+        }
+        i
+    }
+
+    /// End of the word after the cursor
+    fn word_end(&self) -> usize {
+        let mut i = self.cursor;
+        while i < self.text.len() && !self.text[i].is_alphanumeric() {
+            i += 1;
+        }
+        while i < self.text.len() && self.text[i].is_alphanumeric() {
+            i += 1;
+        }
+        i
+    }
+
+    fn trim_history(&mut self) {
+        let excess = self.history.len().saturating_sub(self.history_limit);
+        self.history.drain(..excess);
+    }
+}
+
+impl Default for InputLine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Characters of `text` for a single line: line breaks and tabs become
+/// spaces, other control characters are dropped
+fn single_line(text: &str) -> Vec<char> {
+    text.chars()
+        .filter_map(|ch| match ch {
+            '\n' | '\r' | '\t' => Some(' '),
+            ch if ch.is_control() => None,
+            ch => Some(ch),
+        })
+        .collect()
+}
+
+/// Longest prefix shared by all `candidates`
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix: Vec<char> = candidates[0].chars().collect();
+    for candidate in &candidates[1..] {
+        let shared = prefix
+            .iter()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(shared);
+    }
+    prefix.into_iter().collect()
+}
+
+/// Columns `ch` takes on screen
+fn char_width(ch: char) -> usize {
+    ch.width().unwrap_or(0)
+}
+
+/// The leading `chars` that fit in `width` columns, and the columns they
+/// take
+fn fit(chars: impl Iterator<Item = char>, width: usize) -> (String, usize) {
+    let mut text = String::new();
+    let mut used = 0;
+    for ch in chars {
+        let w = char_width(ch);
+        if used + w > width {
+            break;
+        }
+        text.push(ch);
+        used += w;
+    }
+    (text, used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::{Cell, cells_text};
+    use crate::kitty::KeyEvent;
+    use crate::screen::Screen;
+    use crate::window::Window;
+
+    fn typed(text: &str) -> InputLine {
+        let mut input = InputLine::new();
+        for ch in text.chars() {
+            input.handle_key(&Key::Char(ch));
+        }
+        input
+    }
+
+    #[test]
+    fn test_editing_and_kill_ring() {
+        let mut input = typed("hello big world");
+        input.handle_key(&Key::Ctrl('w'));
+        input.handle_key(&Key::Ctrl('w'));
+        assert_eq!(input.text(), "hello ");
+        // Both kills went into one entry
+        input.handle_key(&Key::Ctrl('y'));
+        assert_eq!(input.text(), "hello big world");
+
+        input.handle_key(&Key::Home);
+        input.handle_key(&Key::Alt('f'));
+        assert_eq!(input.cursor(), 5);
+        input.handle_key(&Key::Ctrl('k'));
+        input.handle_key(&Key::Ctrl('a'));
+        input.handle_key(&Key::Ctrl('y'));
+        assert_eq!(input.text(), " big worldhello");
+        input.handle_key(&Key::Alt('y'));
+        assert_eq!(input.text(), "big worldhello");
+
+        input.handle_key(&Key::Delete);
+        input.handle_key(&Key::Backspace);
+        input.handle_key(&Key::Paste("a\nb".to_string()));
+        assert_eq!(input.text(), "big worla bello");
+        assert!(!input.handle_key(&Key::Enter));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut input = typed("first");
+        assert_eq!(input.submit(), "first");
+        input.set_text("second");
+        input.submit();
+        input.set_text("second");
+        input.submit();
+        assert_eq!(input.history(), ["first", "second"]);
+
+        input.set_text("draft");
+        input.handle_key(&Key::Up);
+        input.handle_key(&Key::Up);
+        input.handle_key(&Key::Up);
+        assert_eq!(input.text(), "first");
+        input.handle_key(&Key::Down);
+        input.handle_key(&Key::Down);
+        assert_eq!(input.text(), "draft");
+
+        let path = std::env::temp_dir().join(format!("zaz-history-{}", std::process::id()));
+        input.save_history(&path).unwrap();
+        let mut other = InputLine::new().history_limit(1);
+        other.load_history(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(other.history(), ["second"]);
+    }
+
+    #[test]
+    fn test_completion() {
+        let commands = ["open", "opendir", "options", "quit"];
+        let mut input = InputLine::new().completer(move |before| {
+            commands
+                .iter()
+                .filter(|c| c.starts_with(before))
+                .map(|c| c.to_string())
+                .collect()
+        });
+        assert!(!typed("").handle_key(&Key::Tab));
+
+        input.handle_key(&Key::Char('q'));
+        input.handle_key(&Key::Tab);
+        assert_eq!(input.text(), "quit");
+
+        input.set_text("o");
+        input.handle_key(&Key::Tab);
+        assert_eq!(input.text(), "op");
+        assert_eq!(input.completions().len(), 3);
+        input.handle_key(&Key::Tab);
+        assert_eq!(input.text(), "open");
+        input.handle_key(&Key::Tab);
+        assert_eq!(input.text(), "opendir");
+        input.handle_key(&Key::Char(' '));
+        assert!(input.completions().is_empty());
+    }
+
+    #[test]
+    fn test_render_scrolls() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut input = typed("0123456789").prompt("> ");
+        let area = Rect::new(0, 0, 1, 8);
+        input.render(&mut scr, area).unwrap();
+        let line = &scr.capture(area)[0];
+        assert_eq!(line.text(), "> 56789 ");
+        assert_eq!(line.spans.last().unwrap().attr, Attr::REVERSE);
+        assert_eq!(input.cursor_position(), Some((0, 7)));
+
+        input.handle_key(&Key::Home);
+        input.render(&mut scr, area).unwrap();
+        assert_eq!(scr.capture(area)[0].text(), "> 012345");
+        assert_eq!(input.cursor_position(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_render_wide_characters() {
+        // Windows keep the second column of wide characters
+        let mut win = Window::new(1, 8, 0, 0).unwrap();
+        let mut input = typed("日本語日本語").prompt("> ");
+        let area = Rect::new(0, 0, 1, 8);
+        input.render(&mut win, area).unwrap();
+        let cells: Vec<Cell> = (0..8).map(|x| win.cell_at(0, x).unwrap().clone()).collect();
+        assert_eq!(cells_text(&cells), "> 本語  ");
+        assert_eq!(input.cursor_position(), Some((0, 6)));
+
+        input.handle_key(&Key::Home);
+        input.render(&mut win, area).unwrap();
+        let cells: Vec<Cell> = (0..8).map(|x| win.cell_at(0, x).unwrap().clone()).collect();
+        assert_eq!(cells_text(&cells), "> 日本語");
+        assert_eq!(input.cursor_position(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_kitty_keys() {
+        let mut input = typed("ab");
+        let event = |code: u32, modifiers| Key::Enhanced(KeyEvent::with_modifiers(code, modifiers));
+        assert!(input.handle_key(&event('c' as u32, Modifiers::empty())));
+        assert!(input.handle_key(&event('a' as u32, Modifiers::CTRL)));
+        assert_eq!(input.cursor(), 0);
+        assert!(input.handle_key(&event('k' as u32, Modifiers::CTRL)));
+        assert_eq!(input.text(), "");
+    }
+}
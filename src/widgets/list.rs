@@ -0,0 +1,293 @@
+//! Scrolling list with keyboard navigation and optional multi-select
+
+use super::{Canvas, Selection, put_line, render_scrollbar};
+use crate::acs::ACS_DIAMOND;
+use crate::attr::Attr;
+use crate::error::Result;
+use crate::input::Key;
+use crate::layout::{Direction, Rect};
+use crate::style::Style;
+use crate::text::Line;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+/// What an item render callback knows about the item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemState {
+    /// Position in the list
+    pub index: usize,
+    /// Under the cursor
+    pub selected: bool,
+    /// Marked in multi-select mode
+    pub marked: bool,
+}
+
+type RenderItem<T> = Box<dyn Fn(&T, ItemState) -> Line>;
+
+/// A list of items drawn one per row
+///
+/// Only the visible rows are rendered, so long lists stay cheap.
+pub struct ListView<T> {
+    items: Vec<T>,
+    selection: Selection,
+    marked: BTreeSet<usize>,
+    multi_select: bool,
+    show_scrollbar: bool,
+    selected_style: Style,
+    render_item: RenderItem<T>,
+}
+
+impl<T: Display> ListView<T> {
+    /// List drawing each item with its `Display` text
+    pub fn new(items: Vec<T>) -> Self {
+        Self::with_renderer(items, |item, _| Line::from(item.to_string().as_str()))
+    }
+}
+
+impl<T> ListView<T> {
+    /// List drawing each item with `render` (the selected style is added
+    /// on top of the returned line)
+    pub fn with_renderer(items: Vec<T>, render: impl Fn(&T, ItemState) -> Line + 'static) -> Self {
+        let mut selection = Selection::default();
+        selection.select(Some(0), items.len());
+        Self {
+            items,
+            selection,
+            marked: BTreeSet::new(),
+            multi_select: false,
+            show_scrollbar: true,
+            selected_style: Style::new().attr(Attr::REVERSE),
+            render_item: Box::new(render),
+        }
+    }
+
+    /// Allow marking several items with Space
+    pub fn multi_select(mut self, enabled: bool) -> Self {
+        self.multi_select = enabled;
+        self
+    }
+
+    /// Show a scrollbar when the items don't fit (on by default)
+    pub fn scrollbar(mut self, show: bool) -> Self {
+        self.show_scrollbar = show;
+        self
+    }
+
+    /// Style added to the selected row (reverse video by default)
+    pub fn selected_style(mut self, style: Style) -> Self {
+        self.selected_style = style;
+        self
+    }
+
+    /// The items
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Replace the items, clearing marks and keeping the cursor in range
+    pub fn set_items(&mut self, items: Vec<T>) {
+        self.items = items;
+        self.marked.clear();
+        let selected = self.selection.selected.or(Some(0));
+        self.selection.select(selected, self.items.len());
+    }
+
+    /// Index of the item under the cursor
+    pub fn selected(&self) -> Option<usize> {
+        self.selection.selected
+    }
+
+    /// Item under the cursor
+    pub fn selected_item(&self) -> Option<&T> {
+        self.items.get(self.selection.selected?)
+    }
+
+    /// Move the cursor to an item (clamped to the last item)
+    pub fn select(&mut self, index: usize) {
+        self.selection.select(Some(index), self.items.len());
+    }
+
+    /// Indices of marked items, in order
+    pub fn marked(&self) -> impl Iterator<Item = usize> + '_ {
+        self.marked.iter().copied()
+    }
+
+    /// Mark or unmark an item (ignored unless multi-select is on)
+    pub fn set_marked(&mut self, index: usize, marked: bool) {
+        if !self.multi_select || index >= self.items.len() {
+            return;
+        }
+        if marked {
+            self.marked.insert(index);
+        } else {
+            self.marked.remove(&index);
+        }
+    }
+
+    /// Navigate with arrows, PageUp/PageDown and Home/End; Space toggles
+    /// the mark in multi-select mode
+    ///
+    /// Returns true if the key was handled.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        match key {
+            Key::Char(' ') if self.multi_select => {
+                if let Some(index) = self.selection.selected {
+                    let marked = self.marked.contains(&index);
+                    self.set_marked(index, !marked);
+                }
+                true
+            }
+            _ => self.selection.handle_key(key, self.items.len()),
+        }
+    }
+
+    /// Draw the visible items into `area`, scrolling to keep the cursor
+    /// visible
+    pub fn render(&mut self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let height = area.height as usize;
+        let len = self.items.len();
+        self.selection.scroll_into_view(height, len);
+
+        let has_scrollbar = self.show_scrollbar && len > height && area.width > 1;
+        let gutter = if self.multi_select { 2 } else { 0 };
+        let width = area.width - u16::from(has_scrollbar);
+        let text_width = width.saturating_sub(gutter);
+
+        let unicode = canvas.unicode();
+        for row in 0..height {
+            let y = area.y + row as u16;
+            let index = self.selection.offset + row;
+            let Some(item) = self.items.get(index) else {
+                canvas.put_str(y, area.x, &" ".repeat(width as usize), Style::new())?;
+                continue;
+            };
+
+            let state = ItemState {
+                index,
+                selected: self.selection.selected == Some(index),
+                marked: self.marked.contains(&index),
+            };
+            let overlay = state.selected.then_some(self.selected_style);
+
+            if gutter > 0 {
+                let mark = if !state.marked {
+                    ' '
+                } else if unicode {
+                    ACS_DIAMOND.as_char()
+                } else {
+                    ACS_DIAMOND.ascii()
+                };
+                let style = overlay.unwrap_or_default();
+                canvas.put_str(y, area.x, &format!("{} ", mark), style)?;
+            }
+            let line = (self.render_item)(item, state);
+            put_line(canvas, y, area.x + gutter, &line, text_width, overlay)?;
+        }
+
+        if has_scrollbar {
+            render_scrollbar(
+                canvas,
+                area,
+                Direction::Vertical,
+                len,
+                height,
+                self.selection.offset,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attr::AttrPolicy;
+    use crate::caps::Capabilities;
+    use crate::color::Color;
+    use crate::screen::Screen;
+    use crate::text::Span;
+    use crate::window::Window;
+
+    #[test]
+    fn test_navigation_and_scrollbar() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut list = ListView::new((0..1000).collect());
+        assert_eq!(list.selected(), Some(0));
+
+        list.render(&mut scr, Rect::new(0, 0, 5, 10)).unwrap();
+        list.handle_key(&Key::End);
+        list.render(&mut scr, Rect::new(0, 0, 5, 10)).unwrap();
+        assert_eq!(list.selected_item(), Some(&999));
+
+        let lines = scr.capture(Rect::new(0, 0, 5, 10));
+        assert_eq!(lines[0].text(), "995      ░");
+        assert_eq!(lines[4].text(), "999      █");
+        assert_eq!(lines[4].spans[0].attr, Attr::REVERSE);
+        assert_eq!(lines[3].spans[0].attr, Attr::NORMAL);
+
+        list.handle_key(&Key::PageUp);
+        assert_eq!(list.selected(), Some(994));
+    }
+
+    #[test]
+    fn test_multi_select() {
+        let mut list = ListView::new(vec!["a", "b", "c"]);
+        assert!(!list.handle_key(&Key::Char(' ')));
+
+        let mut list = list.multi_select(true);
+        list.handle_key(&Key::Char(' '));
+        list.handle_key(&Key::Down);
+        list.handle_key(&Key::Down);
+        list.handle_key(&Key::Char(' '));
+        assert_eq!(list.marked().collect::<Vec<_>>(), vec![0, 2]);
+        list.handle_key(&Key::Char(' '));
+        assert_eq!(list.marked().collect::<Vec<_>>(), vec![0]);
+
+        let mut scr = Screen::with_size(24, 80);
+        list.render(&mut scr, Rect::new(0, 0, 4, 6)).unwrap();
+        let lines = scr.capture(Rect::new(0, 0, 4, 6));
+        assert_eq!(lines[0].text(), "◆ a   ");
+        assert_eq!(lines[3].text(), "      ");
+
+        // Without Unicode the mark falls back to ASCII
+        let mut win = Window::new(4, 6, 0, 0).unwrap();
+        win.set_capabilities(
+            Capabilities::default().unicode(false),
+            AttrPolicy::default(),
+        );
+        list.render(&mut win, Rect::new(0, 0, 4, 6)).unwrap();
+        assert_eq!(win.read_str(0, 0, 6).unwrap(), "+ a   ");
+
+        list.set_items(vec!["x"]);
+        assert_eq!(list.selected(), Some(0));
+        assert_eq!(list.marked().count(), 0);
+    }
+
+    #[test]
+    fn test_custom_renderer() {
+        let mut list = ListView::with_renderer(vec![(1, "ok"), (2, "error")], |item, state| {
+            let fg = if item.1 == "error" {
+                Color::Red
+            } else {
+                Color::Reset
+            };
+            let mut line = Line::new();
+            line.push(Span::new(format!("{}:", state.index)));
+            line.push(Span::styled(item.1, Attr::NORMAL, fg, Color::Reset));
+            line
+        });
+        list.select(1);
+
+        let mut scr = Screen::with_size(24, 80);
+        list.render(&mut scr, Rect::new(0, 0, 2, 8)).unwrap();
+        let line = &scr.capture(Rect::new(1, 0, 1, 8))[0];
+        assert_eq!(line.text(), "1:error ");
+        // The selected style is added on top of the item's own colors
+        assert_eq!(line.spans[1].fg, Color::Red);
+        assert_eq!(line.spans[1].attr, Attr::REVERSE);
+    }
+}
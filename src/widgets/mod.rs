@@ -0,0 +1,364 @@
+//! Ready-made widgets that draw onto a `Screen` or `Window`
+
+mod chart;
+mod dialog;
+mod inputline;
+mod list;
+mod progress;
+mod scrollbar;
+mod table;
+mod tabs;
+mod textarea;
+mod textview;
+
+pub use chart::{BarChart, LineChart, Sparkline};
+pub use dialog::{
+    Dialog, FilePicker, InputDialog, MessageBox, confirm, file_picker, input_dialog, message_box,
+    run_dialog,
+};
+pub use inputline::InputLine;
+pub use list::{ItemState, ListView};
+pub use progress::{ProgressBar, Spinner};
+pub use scrollbar::render_scrollbar;
+pub use table::{Column, Table};
+pub use tabs::TabBar;
+pub use textarea::TextArea;
+pub use textview::TextView;
+
+use crate::color::Color;
+use crate::error::Result;
+use crate::input::Key;
+use crate::kitty::Modifiers;
+use crate::screen::Screen;
+use crate::shortcut::enhanced_key;
+use crate::style::Style;
+use crate::text::Line;
+use crate::window::Window;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// A surface widgets draw on
+pub trait Canvas {
+    /// Drawable size (rows, cols)
+    fn size(&self) -> (u16, u16);
+
+    /// Draw `text` at (y, x) in `style`, clipped to the surface
+    ///
+    /// The surface's own drawing style is left unchanged.
+    fn put_str(&mut self, y: u16, x: u16, text: &str, style: Style) -> Result<()>;
+
+    /// Whether Unicode block and line characters can be drawn
+    fn unicode(&self) -> bool {
+        true
+    }
+}
+
+impl Canvas for Screen {
+    fn size(&self) -> (u16, u16) {
+        self.dimensions()
+    }
+
+    fn unicode(&self) -> bool {
+        self.capabilities().unicode
+    }
+
+    fn put_str(&mut self, y: u16, x: u16, text: &str, style: Style) -> Result<()> {
+        let (rows, cols) = self.dimensions();
+        if y >= rows || x >= cols {
+            return Ok(());
+        }
+        let saved = self.current_style();
+        self.apply_style(&style)?;
+        // Clipped even with autowrap on, so widgets stay inside their rect
+        self.move_cursor(y, x)?;
+        let result = self.print_clipped(text);
+        self.apply_style(&saved)?;
+        result
+    }
+}
+
+impl Canvas for Window {
+    fn size(&self) -> (u16, u16) {
+        self.get_size()
+    }
+
+    fn unicode(&self) -> bool {
+        self.unicode()
+    }
+
+    fn put_str(&mut self, y: u16, x: u16, text: &str, style: Style) -> Result<()> {
+        let (height, width) = self.get_size();
+        if y >= height || x >= width {
+            return Ok(());
+        }
+        let saved = self.current_style();
+        self.attrset(style.attr)?;
+        self.set_fg(style.fg)?;
+        self.set_bg(style.bg)?;
+        self.move_cursor(y, x)?;
+        let result = self.print_clipped(text);
+        self.attrset(saved.attr)?;
+        self.set_fg(saved.fg)?;
+        self.set_bg(saved.bg)?;
+        result
+    }
+}
+
+/// Mark for cut text: `…`, or `~` without Unicode
+pub(crate) fn ellipsis(unicode: bool) -> char {
+    if unicode { '…' } else { '~' }
+}
+
+/// `text` cut to `width` cells, ending in an `ellipsis` if anything was cut
+///
+/// A wide character that would straddle the cut is left out, so the
+/// result can be a cell narrower than `width`.
+pub(crate) fn ellipsize(text: &str, width: usize, unicode: bool) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut used = 0;
+    let mut cut: String = text
+        .chars()
+        .take_while(|ch| {
+            used += ch.width().unwrap_or(0);
+            used < width
+        })
+        .collect();
+    cut.push(ellipsis(unicode));
+    cut
+}
+
+/// Draw `line` at (y, x), clipped and padded to `width` cells
+///
+/// With `overlay`, its attributes are added to every span and its colors
+/// replace the spans' wherever they aren't `Color::Reset`.
+pub(crate) fn put_line(
+    canvas: &mut impl Canvas,
+    y: u16,
+    x: u16,
+    line: &Line,
+    width: u16,
+    overlay: Option<Style>,
+) -> Result<()> {
+    let apply = |span_style: Style| match overlay {
+        Some(o) => Style {
+            attr: span_style.attr | o.attr,
+            fg: if o.fg == Color::Reset {
+                span_style.fg
+            } else {
+                o.fg
+            },
+            bg: if o.bg == Color::Reset {
+                span_style.bg
+            } else {
+                o.bg
+            },
+        },
+        None => span_style,
+    };
+
+    // Columns by display width; a wide character that doesn't fit ends
+    // the line
+    let mut col = 0;
+    for span in &line.spans {
+        let mut text = String::new();
+        let mut used = 0;
+        let mut full = false;
+        for ch in span.text.chars() {
+            let w = ch.width().unwrap_or(0) as u16;
+            if col + used + w > width {
+                full = true;
+                break;
+            }
+            text.push(ch);
+            used += w;
+        }
+        let style = Style {
+            attr: span.attr,
+            fg: span.fg,
+            bg: span.bg,
+        };
+        canvas.put_str(y, x + col, &text, apply(style))?;
+        col += used;
+        if full {
+            break;
+        }
+    }
+    if col >= width {
+        return Ok(());
+    }
+    let fill = " ".repeat((width - col) as usize);
+    canvas.put_str(y, x + col, &fill, apply(Style::new()))
+}
+
+/// Key and the modifiers held with it, for editing widgets
+///
+/// Kitty protocol events map to their legacy key (with the shifted
+/// character when Shift is held), and lock modifiers are dropped; `None`
+/// for key releases and keys without a legacy equivalent.
+pub(crate) fn key_modifiers(key: &Key) -> Option<(Key, Modifiers)> {
+    let (key, modifiers) = match key {
+        Key::Enhanced(event) => {
+            let (key, modifiers) = enhanced_key(event)?;
+            match (key, event.shifted_key.and_then(char::from_u32)) {
+                (Key::Char(_), Some(shifted)) if event.is_shift() => {
+                    (Key::Char(shifted), modifiers)
+                }
+                (key, _) => (key, modifiers),
+            }
+        }
+        key => key.modified(),
+    };
+    Some((
+        key,
+        modifiers - (Modifiers::CAPS_LOCK | Modifiers::NUM_LOCK),
+    ))
+}
+
+/// Selected item and scroll offset of a scrolling list of items
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Selection {
+    pub(crate) selected: Option<usize>,
+    pub(crate) offset: usize,
+    // Rows shown by the last render, for paging
+    pub(crate) page: usize,
+}
+
+impl Selection {
+    /// Select `index` (clamped to `len` items), or nothing
+    pub(crate) fn select(&mut self, index: Option<usize>, len: usize) {
+        self.selected = match index {
+            Some(_) if len == 0 => None,
+            Some(i) => Some(i.min(len - 1)),
+            None => None,
+        };
+    }
+
+    /// Move the selection by `delta` items, starting at the first item if
+    /// nothing is selected
+    pub(crate) fn move_by(&mut self, delta: isize, len: usize) {
+        let next = match self.selected {
+            Some(i) => i.saturating_add_signed(delta),
+            None => 0,
+        };
+        self.select(Some(next), len);
+    }
+
+    /// Navigate with arrows, PageUp/PageDown and Home/End
+    ///
+    /// Returns true if the key was handled.
+    pub(crate) fn handle_key(&mut self, key: &Key, len: usize) -> bool {
+        let page = self.page.max(1) as isize;
+        match key {
+            Key::Up => self.move_by(-1, len),
+            Key::Down => self.move_by(1, len),
+            Key::PageUp => self.move_by(-page, len),
+            Key::PageDown => self.move_by(page, len),
+            Key::Home => self.select(Some(0), len),
+            Key::End => self.select(Some(len.saturating_sub(1)), len),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Adjust the offset so the selection is among `height` visible rows
+    pub(crate) fn scroll_into_view(&mut self, height: usize, len: usize) {
+        self.page = height;
+        if let Some(selected) = self.selected {
+            if selected < self.offset {
+                self.offset = selected;
+            } else if height > 0 && selected >= self.offset + height {
+                self.offset = selected + 1 - height;
+            }
+        }
+        self.offset = self.offset.min(len.saturating_sub(height));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attr::Attr;
+
+    #[test]
+    fn test_screen_put_str_clips() {
+        let mut scr = Screen::with_size(3, 6);
+        scr.set_autowrap(true);
+        scr.put_str(0, 3, "abcdef", Style::new()).unwrap();
+        assert_eq!(scr.read_str(0, 0, 6).unwrap(), "   abc");
+        assert_eq!(scr.read_str(1, 0, 6).unwrap(), "      ");
+    }
+
+    #[test]
+    fn test_window_put_str_clips_by_width() {
+        let mut win = Window::new(1, 6, 0, 0).unwrap();
+        win.put_str(0, 1, "日本語", Style::new().attr(Attr::BOLD))
+            .unwrap();
+        assert_eq!(win.read_str(0, 0, 6).unwrap(), " 日本 ");
+        assert_eq!(win.getyx(), (0, 5));
+        assert_eq!(win.current_style(), Style::new());
+    }
+
+    #[test]
+    fn test_ellipsize() {
+        assert_eq!(ellipsize("short", 8, true), "short");
+        assert_eq!(ellipsize("overflowing", 8, true), "overflo…");
+        assert_eq!(ellipsize("ab", 1, true), "…");
+        assert_eq!(ellipsize("ab", 0, true), "");
+        assert_eq!(ellipsize("日本語", 4, true), "日…");
+        assert_eq!(ellipsize("日本語", 5, true), "日本…");
+        assert_eq!(ellipsize("overflowing", 8, false), "overflo~");
+    }
+
+    #[test]
+    fn test_key_modifiers() {
+        use crate::kitty::KeyEvent;
+
+        assert_eq!(
+            key_modifiers(&Key::Ctrl('a')),
+            Some((Key::Char('a'), Modifiers::CTRL))
+        );
+        let mut event = KeyEvent::with_modifiers('z' as u32, Modifiers::CTRL | Modifiers::SHIFT);
+        event.shifted_key = Some('Z' as u32);
+        assert_eq!(
+            key_modifiers(&Key::Enhanced(event.clone())),
+            Some((Key::Char('Z'), Modifiers::CTRL | Modifiers::SHIFT))
+        );
+        event.modifiers = Modifiers::SHIFT | Modifiers::NUM_LOCK;
+        assert_eq!(
+            key_modifiers(&Key::Enhanced(event.clone())),
+            Some((Key::Char('Z'), Modifiers::SHIFT))
+        );
+        event.event_type = crate::kitty::KeyEventType::Release;
+        assert_eq!(key_modifiers(&Key::Enhanced(event)), None);
+    }
+
+    #[test]
+    fn test_selection_keys() {
+        let mut sel = Selection::default();
+        assert!(sel.handle_key(&Key::Down, 50));
+        assert_eq!(sel.selected, Some(0));
+
+        sel.scroll_into_view(10, 50);
+        sel.handle_key(&Key::PageDown, 50);
+        assert_eq!(sel.selected, Some(10));
+        sel.scroll_into_view(10, 50);
+        assert_eq!(sel.offset, 1);
+
+        sel.handle_key(&Key::End, 50);
+        sel.scroll_into_view(10, 50);
+        assert_eq!((sel.selected, sel.offset), (Some(49), 40));
+
+        sel.handle_key(&Key::Home, 50);
+        sel.handle_key(&Key::Up, 50);
+        sel.scroll_into_view(10, 50);
+        assert_eq!((sel.selected, sel.offset), (Some(0), 0));
+        assert!(!sel.handle_key(&Key::Char('x'), 50));
+
+        sel.select(Some(3), 0);
+        assert_eq!(sel.selected, None);
+    }
+}
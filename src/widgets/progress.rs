@@ -0,0 +1,291 @@
+//! Progress bar and spinner
+
+use super::Canvas;
+use crate::error::Result;
+use crate::layout::Rect;
+use crate::style::Style;
+
+/// Partial blocks by eighths of a cell filled
+const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// ASCII stand-ins for `EIGHTHS`, for terminals without Unicode
+const ASCII_EIGHTHS: [char; 8] = [' ', '-', '-', '-', '=', '=', '=', '='];
+
+/// Determinate progress bar with an optional label and percentage
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    progress: f32,
+    label: String,
+    show_percent: bool,
+    style: Style,
+}
+
+impl ProgressBar {
+    /// Create an empty bar showing its percentage
+    pub fn new() -> Self {
+        Self {
+            progress: 0.0,
+            label: String::new(),
+            show_percent: true,
+            style: Style::new(),
+        }
+    }
+
+    /// Text drawn before the bar
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Show or hide the percentage after the bar
+    pub fn show_percent(mut self, show: bool) -> Self {
+        self.show_percent = show;
+        self
+    }
+
+    /// Style of the bar itself (the fill is drawn in the foreground color)
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set progress (clamped to 0.0-1.0)
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.clamp(0.0, 1.0);
+    }
+
+    /// Set progress from a count of completed steps
+    pub fn set_steps(&mut self, done: u64, total: u64) {
+        let progress = if total == 0 {
+            1.0
+        } else {
+            done as f32 / total as f32
+        };
+        self.set_progress(progress);
+    }
+
+    /// Current progress (0.0-1.0)
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// Change the label
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    /// Draw on the first row of `area`
+    pub fn render(&self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let mut x = area.x;
+        let mut width = area.width as usize;
+        if !self.label.is_empty() {
+            let label: String = format!("{} ", self.label).chars().take(width).collect();
+            canvas.put_str(area.y, x, &label, Style::new())?;
+            let used = label.chars().count();
+            x += used as u16;
+            width -= used;
+        }
+
+        let percent = if self.show_percent {
+            format!(" {:>3}%", (self.progress * 100.0).round() as u32)
+        } else {
+            String::new()
+        };
+        let bar_width = width.saturating_sub(percent.len());
+
+        let bar = bar(self.progress, bar_width, canvas.unicode());
+        canvas.put_str(area.y, x, &bar, self.style)?;
+        canvas.put_str(area.y, x + bar_width as u16, &percent, Style::new())
+    }
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `width` cells filled to `progress` in eighth-cell steps
+fn bar(progress: f32, width: usize, unicode: bool) -> String {
+    let (block, partial) = if unicode {
+        ('█', EIGHTHS)
+    } else {
+        ('#', ASCII_EIGHTHS)
+    };
+    let eighths = (progress * width as f32 * 8.0).round() as usize;
+    let full = eighths / 8;
+    let mut bar: String = std::iter::repeat_n(block, full).collect();
+    if full < width {
+        bar.push(partial[eighths % 8]);
+        bar.extend(std::iter::repeat_n(' ', width - full - 1));
+    }
+    bar
+}
+
+/// Indeterminate activity indicator
+#[derive(Debug, Clone)]
+pub struct Spinner {
+    frames: Vec<String>,
+    frame: usize,
+    label: String,
+    style: Style,
+}
+
+impl Spinner {
+    /// Braille dots
+    pub const DOTS: &'static [&'static str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    /// ASCII line, for terminals without Unicode
+    pub const LINE: &'static [&'static str] = &["-", "\\", "|", "/"];
+    /// Quarter circle
+    pub const ARC: &'static [&'static str] = &["◜", "◝", "◞", "◟"];
+    /// Growing block
+    pub const BLOCKS: &'static [&'static str] =
+        &["▁", "▃", "▄", "▅", "▆", "▇", "█", "▇", "▆", "▅", "▄", "▃"];
+
+    /// Create a spinner using `Spinner::DOTS`
+    pub fn new() -> Self {
+        Self {
+            frames: Self::DOTS.iter().map(|f| f.to_string()).collect(),
+            frame: 0,
+            label: String::new(),
+            style: Style::new(),
+        }
+    }
+
+    /// Use a different frame set (ignored if empty)
+    pub fn frames(mut self, frames: &[&str]) -> Self {
+        if !frames.is_empty() {
+            self.frames = frames.iter().map(|f| f.to_string()).collect();
+            self.frame = 0;
+        }
+        self
+    }
+
+    /// Text drawn after the spinner
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Style of the spinner frame
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Change the label
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    /// Advance to the next frame
+    pub fn tick(&mut self) {
+        self.frame = (self.frame + 1) % self.frames.len();
+    }
+
+    /// Current frame
+    pub fn frame(&self) -> &str {
+        &self.frames[self.frame]
+    }
+
+    /// Draw on the first row of `area`
+    pub fn render(&self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let frame: String = self.frame().chars().take(area.width as usize).collect();
+        canvas.put_str(area.y, area.x, &frame, self.style)?;
+
+        let used = frame.chars().count() as u16;
+        if !self.label.is_empty() && used < area.width {
+            let label: String = format!(" {}", self.label)
+                .chars()
+                .take((area.width - used) as usize)
+                .collect();
+            canvas.put_str(area.y, area.x + used, &label, Style::new())?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attr::AttrPolicy;
+    use crate::caps::Capabilities;
+    use crate::color::Color;
+    use crate::screen::Screen;
+    use crate::window::Window;
+
+    fn row(scr: &Screen, y: u16, x: u16, width: u16) -> String {
+        scr.capture(Rect::new(y, x, 1, width))[0].text()
+    }
+
+    #[test]
+    fn test_fractional_fill() {
+        assert_eq!(bar(0.0, 4, true), "    ");
+        assert_eq!(bar(0.5, 4, true), "██  ");
+        assert_eq!(bar(0.5625, 4, true), "██▎ ");
+        assert_eq!(bar(1.0, 4, true), "████");
+        assert_eq!(bar(0.3, 0, true), "");
+
+        assert_eq!(bar(0.5625, 4, false), "##- ");
+        assert_eq!(bar(0.625, 4, false), "##= ");
+        assert_eq!(bar(1.0, 4, false), "####");
+    }
+
+    #[test]
+    fn test_progress_render() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut bar = ProgressBar::new()
+            .label("copy")
+            .style(Style::new().fg(Color::Green));
+        bar.set_steps(1, 4);
+        bar.render(&mut scr, Rect::new(2, 0, 1, 17)).unwrap();
+
+        assert_eq!(row(&scr, 2, 0, 17), "copy █▊       25%");
+        assert_eq!(
+            scr.capture(Rect::new(2, 5, 1, 1))[0].spans[0].fg,
+            Color::Green
+        );
+        // Drawing style is restored
+        assert_eq!(scr.current_style(), Style::new());
+
+        // Without Unicode the bar is drawn in ASCII
+        let mut win = Window::new(1, 17, 0, 0).unwrap();
+        win.set_capabilities(
+            Capabilities::default().unicode(false),
+            AttrPolicy::default(),
+        );
+        bar.render(&mut win, Rect::new(0, 0, 1, 17)).unwrap();
+        assert_eq!(win.read_str(0, 0, 17).unwrap(), "copy #=       25%");
+
+        bar.set_progress(7.0);
+        assert_eq!(bar.progress(), 1.0);
+    }
+
+    #[test]
+    fn test_spinner_cycles() {
+        let mut spinner = Spinner::new().frames(Spinner::LINE).label("wait");
+        assert_eq!(spinner.frame(), "-");
+        for _ in 0..5 {
+            spinner.tick();
+        }
+        assert_eq!(spinner.frame(), "\\");
+
+        let mut scr = Screen::with_size(24, 80);
+        spinner.render(&mut scr, Rect::new(0, 0, 1, 4)).unwrap();
+        assert_eq!(row(&scr, 0, 0, 5), "\\ wa ");
+    }
+}
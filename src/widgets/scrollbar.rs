@@ -0,0 +1,140 @@
+//! Proportional scrollbars
+
+use super::Canvas;
+use crate::acs::{ACS_BLOCK, ACS_CKBOARD, ascii_fallback};
+use crate::error::Result;
+use crate::layout::{Direction, Rect};
+use crate::style::Style;
+
+/// Draw a scrollbar for `viewport` of `total` items (rows or columns)
+/// shown from `offset`
+///
+/// A vertical bar fills the right column of `area`, a horizontal one its
+/// bottom row. The thumb is sized in half cells with Unicode block
+/// characters, or in whole cells with ASCII on terminals without Unicode.
+/// With everything visible, only the track is drawn.
+pub fn render_scrollbar(
+    canvas: &mut impl Canvas,
+    area: Rect,
+    direction: Direction,
+    total: usize,
+    viewport: usize,
+    offset: usize,
+) -> Result<()> {
+    if area.is_empty() {
+        return Ok(());
+    }
+    let (length, y, x) = match direction {
+        Direction::Vertical => (area.height, area.y, area.right() - 1),
+        Direction::Horizontal => (area.width, area.bottom() - 1, area.x),
+    };
+    let bar = scrollbar_cells(
+        direction,
+        total,
+        viewport,
+        offset,
+        length as usize,
+        canvas.unicode(),
+    );
+    match direction {
+        Direction::Vertical => {
+            for (row, ch) in bar.into_iter().enumerate() {
+                canvas.put_str(y + row as u16, x, &ch.to_string(), Style::new())?;
+            }
+            Ok(())
+        }
+        Direction::Horizontal => {
+            let text: String = bar.into_iter().collect();
+            canvas.put_str(y, x, &text, Style::new())
+        }
+    }
+}
+
+/// Cells of a scrollbar `length` cells long
+pub(crate) fn scrollbar_cells(
+    direction: Direction,
+    total: usize,
+    viewport: usize,
+    offset: usize,
+    length: usize,
+    unicode: bool,
+) -> Vec<char> {
+    let track = ACS_CKBOARD.as_char();
+    let mut bar = vec![track; length];
+    if total <= viewport || length == 0 {
+        return finish(bar, unicode);
+    }
+
+    // Thumb position and size in half cells, at least one cell long
+    let units = length * 2;
+    let thumb = (units * viewport / total).clamp(2, units);
+    let travel = units - thumb;
+    let scrollable = total - viewport;
+    let start = (offset.min(scrollable) * travel + scrollable / 2) / scrollable;
+    let filled = |unit: usize| (start..start + thumb).contains(&unit);
+
+    let (first_half, second_half) = match direction {
+        Direction::Vertical => ('▀', '▄'),
+        Direction::Horizontal => ('▌', '▐'),
+    };
+    for (i, cell) in bar.iter_mut().enumerate() {
+        *cell = match (filled(2 * i), filled(2 * i + 1)) {
+            (true, true) => ACS_BLOCK.as_char(),
+            (true, false) => first_half,
+            (false, true) => second_half,
+            (false, false) => track,
+        };
+    }
+    finish(bar, unicode)
+}
+
+/// The bar as drawn: half blocks round to whole ones in ASCII
+fn finish(bar: Vec<char>, unicode: bool) -> Vec<char> {
+    if unicode {
+        return bar;
+    }
+    bar.into_iter()
+        .map(|ch| match ch {
+            '▀' | '▄' | '▌' | '▐' => ascii_fallback(ACS_BLOCK.as_char()),
+            ch => ascii_fallback(ch),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::Screen;
+
+    fn bar(total: usize, viewport: usize, offset: usize, length: usize) -> String {
+        scrollbar_cells(Direction::Vertical, total, viewport, offset, length, true)
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_scrollbar_cells() {
+        assert_eq!(bar(4, 4, 0, 4), "░░░░");
+        assert_eq!(bar(40, 4, 0, 4), "█░░░");
+        assert_eq!(bar(40, 4, 36, 4), "░░░█");
+        assert_eq!(bar(8, 4, 2, 4), "░██░");
+        // Half-cell steps
+        assert_eq!(bar(8, 4, 1, 4), "▄█▀░");
+        assert_eq!(bar(100, 10, 50, 4), "░▄▀░");
+
+        let horizontal = scrollbar_cells(Direction::Horizontal, 8, 4, 1, 4, true);
+        assert_eq!(horizontal.into_iter().collect::<String>(), "▐█▌░");
+        let ascii = scrollbar_cells(Direction::Vertical, 8, 4, 1, 4, false);
+        assert_eq!(ascii.into_iter().collect::<String>(), "###:");
+    }
+
+    #[test]
+    fn test_render_scrollbar() {
+        let mut scr = Screen::with_size(24, 80);
+        let area = Rect::new(1, 2, 4, 6);
+        render_scrollbar(&mut scr, area, Direction::Vertical, 8, 4, 2).unwrap();
+        render_scrollbar(&mut scr, area, Direction::Horizontal, 12, 6, 6).unwrap();
+        let text: Vec<String> = scr.capture(area).iter().map(|l| l.text()).collect();
+        assert_eq!(text, ["     ░", "     █", "     █", "░░░███"]);
+    }
+}
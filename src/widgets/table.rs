@@ -0,0 +1,288 @@
+//! Table with sized columns, a header row and a selectable, scrolling body
+
+use super::{Canvas, Selection, ellipsize};
+use crate::attr::Attr;
+use crate::error::Result;
+use crate::input::Key;
+use crate::layout::Rect;
+use crate::slk::Justify;
+use crate::style::Style;
+
+/// A table column and how it is sized
+#[derive(Debug, Clone)]
+pub struct Column {
+    title: String,
+    min: u16,
+    max: u16,
+    weight: u16,
+    justify: Justify,
+}
+
+impl Column {
+    /// Column with a title, growing freely with weight 1
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            min: 0,
+            max: u16::MAX,
+            weight: 1,
+            justify: Justify::Left,
+        }
+    }
+
+    /// Minimum width in cells
+    pub fn min(mut self, min: u16) -> Self {
+        self.min = min;
+        self
+    }
+
+    /// Maximum width in cells
+    pub fn max(mut self, max: u16) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Share of the space left after minimums (0 keeps the minimum width)
+    pub fn weight(mut self, weight: u16) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Alignment of the title and cells
+    pub fn justify(mut self, justify: Justify) -> Self {
+        self.justify = justify;
+        self
+    }
+}
+
+/// Rows of text under a header, with an optional selected row
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    selection: Selection,
+    header_style: Style,
+    selected_style: Style,
+}
+
+impl Table {
+    /// Create an empty table
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+            selection: Selection::default(),
+            header_style: Style::new().attr(Attr::BOLD),
+            selected_style: Style::new().attr(Attr::REVERSE),
+        }
+    }
+
+    /// Style of the header row (bold by default)
+    pub fn header_style(mut self, style: Style) -> Self {
+        self.header_style = style;
+        self
+    }
+
+    /// Style of the selected row (reverse video by default)
+    pub fn selected_style(mut self, style: Style) -> Self {
+        self.selected_style = style;
+        self
+    }
+
+    /// Append a row (missing cells are blank, extra cells ignored)
+    pub fn push_row<S: Into<String>>(&mut self, cells: impl IntoIterator<Item = S>) {
+        self.rows.push(cells.into_iter().map(Into::into).collect());
+    }
+
+    /// Replace all rows, keeping the selection in range
+    pub fn set_rows(&mut self, rows: Vec<Vec<String>>) {
+        self.rows = rows;
+        self.selection
+            .select(self.selection.selected, self.rows.len());
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Check if the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Cells of row `index`
+    pub fn row(&self, index: usize) -> Option<&[String]> {
+        self.rows.get(index).map(Vec::as_slice)
+    }
+
+    /// Index of the selected row
+    pub fn selected(&self) -> Option<usize> {
+        self.selection.selected
+    }
+
+    /// Select a row (clamped to the last row), or none
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selection.select(index, self.rows.len());
+    }
+
+    /// Move the selection with arrows, PageUp/PageDown and Home/End
+    ///
+    /// Returns true if the key was handled. Paging uses the height of the
+    /// last render.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        self.selection.handle_key(key, self.rows.len())
+    }
+
+    /// Column widths for a table `width` cells wide
+    ///
+    /// Columns get their minimum first; the rest is shared by weight up to
+    /// each column's maximum. Columns are one cell apart.
+    pub fn column_widths(&self, width: u16) -> Vec<u16> {
+        let gaps = self.columns.len().saturating_sub(1) as u16;
+        let mut widths: Vec<u16> = self.columns.iter().map(|c| c.min.min(c.max)).collect();
+        let used: u16 = widths.iter().fold(0u16, |sum, w| sum.saturating_add(*w));
+        let mut left = width.saturating_sub(gaps).saturating_sub(used);
+
+        while left > 0 {
+            let open: Vec<usize> = (0..widths.len())
+                .filter(|&i| self.columns[i].weight > 0 && widths[i] < self.columns[i].max)
+                .collect();
+            if open.is_empty() {
+                break;
+            }
+
+            let total: u32 = open.iter().map(|&i| self.columns[i].weight as u32).sum();
+            let mut given = 0;
+            for &i in &open {
+                let share = (left as u32 * self.columns[i].weight as u32 / total) as u16;
+                let grow = share.min(self.columns[i].max - widths[i]);
+                widths[i] += grow;
+                given += grow;
+            }
+            if given == 0 {
+                // Shares rounded down to nothing: hand out single cells
+                for &i in open.iter().take(left as usize) {
+                    widths[i] += 1;
+                    given += 1;
+                }
+            }
+            left -= given;
+        }
+        widths
+    }
+
+    /// Draw the header and as many rows as fit into `area`, scrolling to
+    /// keep the selected row visible
+    pub fn render(&mut self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let widths = self.column_widths(area.width);
+        let titles: Vec<&str> = self.columns.iter().map(|c| c.title.as_str()).collect();
+        let unicode = canvas.unicode();
+        let header = self.format_row(&titles, &widths, area.width, unicode);
+        canvas.put_str(area.y, area.x, &header, self.header_style)?;
+
+        let height = area.height as usize - 1;
+        self.selection.scroll_into_view(height, self.rows.len());
+
+        let offset = self.selection.offset;
+        for line in 0..height {
+            let index = offset + line;
+            let (text, style) = match self.rows.get(index) {
+                Some(row) => {
+                    let cells: Vec<&str> = row.iter().map(String::as_str).collect();
+                    let style = if self.selection.selected == Some(index) {
+                        self.selected_style
+                    } else {
+                        Style::new()
+                    };
+                    (self.format_row(&cells, &widths, area.width, unicode), style)
+                }
+                None => (" ".repeat(area.width as usize), Style::new()),
+            };
+            canvas.put_str(area.y + 1 + line as u16, area.x, &text, style)?;
+        }
+        Ok(())
+    }
+
+    /// One line of cells, each fitted to its column, padded to `width`
+    fn format_row(&self, cells: &[&str], widths: &[u16], width: u16, unicode: bool) -> String {
+        let mut line = String::new();
+        for (i, (column, &w)) in self.columns.iter().zip(widths).enumerate() {
+            if i > 0 {
+                line.push(' ');
+            }
+            let text = ellipsize(cells.get(i).copied().unwrap_or(""), w as usize, unicode);
+            line.push_str(&column.justify.pad(&text, w as usize));
+        }
+        Justify::Left.pad(&line, width as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::Screen;
+    use unicode_width::UnicodeWidthStr;
+
+    fn table() -> Table {
+        Table::new(vec![
+            Column::new("Name").min(4).weight(2),
+            Column::new("Size").min(4).max(6).justify(Justify::Right),
+            Column::new("Kind").weight(1),
+        ])
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let table = table();
+        // 2 gaps, 8 cells of minimums, 20 shared 2:1:1 with Size capped at 6
+        assert_eq!(table.column_widths(30), vec![16, 6, 6]);
+        assert_eq!(table.column_widths(30).iter().sum::<u16>(), 28);
+        // Too narrow: minimums are kept
+        assert_eq!(table.column_widths(5), vec![4, 4, 0]);
+
+        let fixed = Table::new(vec![Column::new("a").min(3).weight(0)]);
+        assert_eq!(fixed.column_widths(40), vec![3]);
+    }
+
+    #[test]
+    fn test_render_truncates_and_scrolls() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut table = table();
+        for i in 0..20 {
+            table.push_row([format!("file-with-long-name-{}", i), "12K".into()]);
+        }
+
+        table.select(Some(5));
+        table.render(&mut scr, Rect::new(0, 0, 4, 30)).unwrap();
+        let lines = scr.capture(Rect::new(0, 0, 4, 30));
+        assert_eq!(lines[0].text(), "Name               Size Kind  ");
+        assert_eq!(lines[0].spans[0].attr, Attr::BOLD);
+        assert_eq!(lines[3].text(), "file-with-long-…    12K       ");
+        assert_eq!(lines[3].spans[0].attr, Attr::REVERSE);
+
+        // Wide characters are measured in cells and never split, so later
+        // columns stay under their headers
+        let widths = table.column_widths(30);
+        let row = table.format_row(&["日本語のファイル名", "1.5M", "日本語"], &widths, 30, true);
+        assert_eq!(row, "日本語のファイ…    1.5M 日本語");
+        assert_eq!(row.width(), 30);
+        let row = table.format_row(&["a", "1K", "日本語です"], &widths, 30, true);
+        assert_eq!(row, "a                    1K 日本… ");
+        let row = table.format_row(&["a", "1K", "日本語です"], &widths, 30, false);
+        assert_eq!(row, "a                    1K 日本~ ");
+
+        // Paging uses the rendered body height
+        assert!(table.handle_key(&Key::PageDown));
+        assert_eq!(table.selected(), Some(8));
+        assert!(!table.handle_key(&Key::Enter));
+
+        table.set_rows(vec![vec!["only".into()]]);
+        assert_eq!(table.selected(), Some(0));
+        assert_eq!(table.row(0).unwrap()[0], "only");
+    }
+}
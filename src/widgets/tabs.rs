@@ -0,0 +1,333 @@
+//! Tab strip over a set of windows, one shown at a time
+
+use super::{Canvas, ellipsize, put_line};
+use crate::attr::Attr;
+use crate::error::{Error, Result};
+use crate::input::Key;
+use crate::kitty::Modifiers;
+use crate::layout::Rect;
+use crate::screen::Screen;
+use crate::style::Style;
+use crate::text::{Line, Span};
+use crate::window::Window;
+use unicode_width::UnicodeWidthStr;
+
+struct Tab {
+    title: String,
+    window: Window,
+}
+
+/// Named tabs sharing one content area below a tab strip
+///
+/// Only the active tab is drawn: switching tabs sets `needs_redraw`, and
+/// output queued into inactive tabs is dropped on refresh, so the
+/// application draws a tab's content only while it is visible.
+///
+/// ```no_run
+/// # use zaz::{Rect, Screen, TabBar};
+/// # let mut scr = Screen::init()?;
+/// let mut tabs = TabBar::new(&scr, scr.area())?;
+/// tabs.add_tab(&scr, "Files")?;
+/// tabs.add_tab(&scr, "Log")?;
+/// loop {
+///     if tabs.needs_redraw() {
+///         let win = tabs.active_window_mut().unwrap();
+///         win.clear()?;
+///         // Draw the active tab's content
+///     }
+///     tabs.refresh()?;
+///     let key = scr.getch()?;
+///     if !tabs.handle_key(&key) {
+///         break;
+///     }
+/// }
+/// # Ok::<(), zaz::Error>(())
+/// ```
+pub struct TabBar {
+    area: Rect,
+    strip: Window,
+    tabs: Vec<Tab>,
+    active: usize,
+    needs_redraw: bool,
+    active_style: Style,
+    inactive_style: Style,
+}
+
+impl TabBar {
+    /// Tab strip on the first row of `area` of `scr`, tab contents below it
+    pub fn new(scr: &Screen, area: Rect) -> Result<Self> {
+        if area.height < 2 || area.width == 0 {
+            return Err(Error::InvalidDimensions {
+                height: area.height,
+                width: area.width,
+            });
+        }
+        Ok(Self {
+            area,
+            strip: screen_window(scr, Rect::new(area.y, area.x, 1, area.width))?,
+            tabs: Vec::new(),
+            active: 0,
+            needs_redraw: true,
+            active_style: Style::new().attr(Attr::REVERSE | Attr::BOLD),
+            inactive_style: Style::new(),
+        })
+    }
+
+    /// Style of the active tab's title (bold reverse video by default)
+    pub fn active_style(mut self, style: Style) -> Self {
+        self.active_style = style;
+        self
+    }
+
+    /// Style of the other titles (plain by default)
+    pub fn inactive_style(mut self, style: Style) -> Self {
+        self.inactive_style = style;
+        self
+    }
+
+    /// Add a tab with its own content window, returning its index
+    pub fn add_tab(&mut self, scr: &Screen, title: impl Into<String>) -> Result<usize> {
+        let window = screen_window(scr, self.content_area())?;
+        self.tabs.push(Tab {
+            title: title.into(),
+            window,
+        });
+        Ok(self.tabs.len() - 1)
+    }
+
+    /// Number of tabs
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    /// Check if there are no tabs
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// Area of the tab contents
+    pub fn content_area(&self) -> Rect {
+        Rect::new(
+            self.area.y + 1,
+            self.area.x,
+            self.area.height - 1,
+            self.area.width,
+        )
+    }
+
+    /// Index of the active tab
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Title of tab `index`
+    pub fn title(&self, index: usize) -> Option<&str> {
+        self.tabs.get(index).map(|tab| tab.title.as_str())
+    }
+
+    /// Window of tab `index`
+    pub fn window_mut(&mut self, index: usize) -> Option<&mut Window> {
+        self.tabs.get_mut(index).map(|tab| &mut tab.window)
+    }
+
+    /// Window of the active tab
+    pub fn active_window_mut(&mut self) -> Option<&mut Window> {
+        self.window_mut(self.active)
+    }
+
+    /// Switch to tab `index` (ignored if out of range)
+    pub fn select(&mut self, index: usize) {
+        if index < self.tabs.len() && index != self.active {
+            self.active = index;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Switch to the next tab, wrapping around
+    pub fn next(&mut self) {
+        if !self.tabs.is_empty() {
+            self.select((self.active + 1) % self.tabs.len());
+        }
+    }
+
+    /// Switch to the previous tab, wrapping around
+    pub fn prev(&mut self) {
+        if !self.tabs.is_empty() {
+            self.select((self.active + self.tabs.len() - 1) % self.tabs.len());
+        }
+    }
+
+    /// Check if the active tab's content must be drawn again (it was just
+    /// switched to, or hasn't been shown yet)
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    /// Switch tabs with Ctrl+PageUp/PageDown or Alt+1..9
+    ///
+    /// Returns true if the key was handled.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        match key.modified() {
+            (Key::PageUp, Modifiers::CTRL) => self.prev(),
+            (Key::PageDown, Modifiers::CTRL) => self.next(),
+            (Key::Char(digit @ '1'..='9'), Modifiers::ALT) => {
+                self.select(digit as usize - '1' as usize)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The tab strip as a line `width` cells wide
+    ///
+    /// Titles are numbered for the Alt+digit shortcuts and shortened with
+    /// an ellipsis when they don't all fit.
+    fn strip_line(&self, width: u16, unicode: bool) -> Line {
+        let titles: Vec<String> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| format!(" {}:{} ", i + 1, tab.title))
+            .collect();
+        let total: usize = titles.iter().map(|t| t.width()).sum();
+        let per_tab = if total > width as usize {
+            (width as usize / titles.len()).max(1)
+        } else {
+            usize::MAX
+        };
+
+        let mut line = Line::new();
+        for (i, title) in titles.into_iter().enumerate() {
+            let title = if title.width() > per_tab {
+                format!(
+                    "{} ",
+                    ellipsize(title.trim_end(), per_tab.saturating_sub(1), unicode)
+                )
+            } else {
+                title
+            };
+            let style = if i == self.active {
+                self.active_style
+            } else {
+                self.inactive_style
+            };
+            line.push(Span::styled(title, style.attr, style.fg, style.bg));
+        }
+        line
+    }
+
+    /// Draw the strip and write it and the active tab to the terminal
+    pub fn refresh(&mut self) -> Result<()> {
+        let line = self.strip_line(self.area.width, self.strip.unicode());
+        put_line(&mut self.strip, 0, 0, &line, self.area.width, None)?;
+        self.strip.refresh()?;
+
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            if i == self.active {
+                tab.window.refresh()?;
+            } else {
+                tab.window.discard_output();
+            }
+        }
+        self.needs_redraw = false;
+        Ok(())
+    }
+
+    /// Draw the strip onto another surface (e.g. a `Screen`) at its area
+    pub fn render_strip(&self, canvas: &mut impl Canvas) -> Result<()> {
+        let line = self.strip_line(self.area.width, canvas.unicode());
+        put_line(
+            canvas,
+            self.area.y,
+            self.area.x,
+            &line,
+            self.area.width,
+            None,
+        )
+    }
+}
+
+/// Window of `scr` over `area`, given in screen rows
+fn screen_window(scr: &Screen, area: Rect) -> Result<Window> {
+    // Windows are placed in terminal rows, which include reserved lines
+    let y = area.y + scr.reserved_lines().0;
+    scr.newwin(area.height, area.width, y, area.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tabs() -> TabBar {
+        let scr = Screen::with_size(24, 80);
+        let mut tabs = TabBar::new(&scr, Rect::new(0, 0, 10, 30)).unwrap();
+        tabs.add_tab(&scr, "Files").unwrap();
+        tabs.add_tab(&scr, "Log").unwrap();
+        tabs.add_tab(&scr, "Settings").unwrap();
+        tabs
+    }
+
+    #[test]
+    fn test_switching() {
+        let mut tabs = tabs();
+        assert_eq!(tabs.content_area(), Rect::new(1, 0, 9, 30));
+        assert_eq!(tabs.window_mut(2).unwrap().rect(), tabs.content_area());
+
+        tabs.needs_redraw = false;
+        assert!(tabs.handle_key(&Key::Alt('3')));
+        assert_eq!(tabs.active(), 2);
+        assert!(tabs.needs_redraw());
+
+        let ctrl_page_down = Key::Modified(Box::new(Key::PageDown), Modifiers::CTRL);
+        assert!(tabs.handle_key(&ctrl_page_down));
+        assert_eq!(tabs.active(), 0);
+        tabs.prev();
+        assert_eq!(tabs.title(tabs.active()), Some("Settings"));
+
+        tabs.select(7);
+        assert_eq!(tabs.active(), 2);
+        assert!(!tabs.handle_key(&Key::Alt('x')));
+        let scr = Screen::with_size(24, 80);
+        assert!(TabBar::new(&scr, Rect::new(0, 0, 1, 30)).is_err());
+    }
+
+    #[test]
+    fn test_windows_below_reserved_lines() {
+        let mut scr = Screen::with_size(24, 80);
+        scr.reserve_lines(2, 0).unwrap();
+        let mut tabs = TabBar::new(&scr, Rect::new(0, 0, 10, 30)).unwrap();
+        tabs.add_tab(&scr, "Files").unwrap();
+
+        // Screen row 0 is terminal row 2
+        assert_eq!(tabs.strip.get_position(), (2, 0));
+        assert_eq!(tabs.window_mut(0).unwrap().get_position(), (3, 0));
+    }
+
+    #[test]
+    fn test_strip() {
+        let mut tabs = tabs();
+        tabs.select(1);
+
+        let mut scr = Screen::with_size(24, 80);
+        tabs.render_strip(&mut scr).unwrap();
+        let line = &scr.capture(Rect::new(0, 0, 1, 30))[0];
+        assert_eq!(line.text(), " 1:Files  2:Log  3:Settings   ");
+        assert_eq!(line.spans[1].text, " 2:Log ");
+        assert!(line.spans[1].attr.contains(Attr::REVERSE));
+
+        // Narrow strips shorten titles
+        let strip = tabs.strip_line(21, true);
+        assert_eq!(strip.text(), " 1:Fi…  2:Log  3:Se… ");
+        let strip = tabs.strip_line(21, false);
+        assert_eq!(strip.text(), " 1:Fi~  2:Log  3:Se~ ");
+
+        // Wide titles are measured in cells
+        let scr = Screen::with_size(24, 80);
+        let mut tabs = TabBar::new(&scr, Rect::new(0, 0, 10, 30)).unwrap();
+        tabs.add_tab(&scr, "ファイル").unwrap();
+        tabs.add_tab(&scr, "ログ").unwrap();
+        let strip = tabs.strip_line(16, true);
+        assert_eq!(strip.text(), " 1:フ…  2:ログ ");
+        assert!(strip.text().width() <= 16);
+    }
+}
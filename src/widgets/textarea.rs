@@ -0,0 +1,886 @@
+//! Multi-line text editor with selection, clipboard and undo
+
+use super::{Canvas, key_modifiers, put_line};
+use crate::attr::Attr;
+use crate::cell::Cell;
+use crate::error::Result;
+use crate::input::Key;
+use crate::kitty::Modifiers;
+use crate::layout::Rect;
+use crate::style::Style;
+use crate::text::Line;
+use unicode_width::UnicodeWidthChar;
+
+/// Undo steps kept
+const UNDO_LIMIT: usize = 100;
+
+/// Spaces a tab is expanded to
+const TAB_WIDTH: usize = 4;
+
+/// Line index and character index in the line
+type Pos = (usize, usize);
+
+/// Text and cursor saved for undo
+#[derive(Debug, Clone)]
+struct State {
+    lines: Vec<Vec<char>>,
+    cursor: Pos,
+}
+
+/// Kind of the previous edit; typing or deleting in a row undoes as one
+/// step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Other,
+    Typing,
+    Deleting,
+}
+
+/// A multi-line text editor
+///
+/// Arrows, Home/End and PageUp/PageDown move, Ctrl/Alt+arrows by word and
+/// Ctrl+Home/End to either end of the text; with Shift held they extend
+/// the selection. Ctrl-A selects everything, Ctrl-C/X/V copy, cut and
+/// paste, and Ctrl-Z/Ctrl-Y (or Ctrl-Shift-Z) undo and redo. Tab is left
+/// to the app, e.g. for moving focus.
+///
+/// Copied text is kept for Ctrl-V and handed to the app by `take_copied`,
+/// to put on the system clipboard with `Screen::copy_to_clipboard`; the
+/// terminal's own paste arrives as `Key::Paste`.
+pub struct TextArea {
+    lines: Vec<Vec<char>>,
+    cursor: Pos,
+    anchor: Option<Pos>,
+    // Column in the row kept while moving up and down through shorter lines
+    goal: Option<usize>,
+    wrap: bool,
+    // First row shown: line, and row of the line when wrapping
+    top: Pos,
+    // First column shown when not wrapping
+    left: usize,
+    undo: Vec<State>,
+    redo: Vec<State>,
+    last_edit: EditKind,
+    clipboard: String,
+    copied: Option<String>,
+    style: Style,
+    selection_style: Style,
+    cursor_style: Style,
+    width: usize,
+    height: usize,
+    cursor_position: Option<(u16, u16)>,
+}
+
+impl TextArea {
+    /// Create an empty editor; long lines wrap
+    pub fn new() -> Self {
+        Self {
+            lines: vec![Vec::new()],
+            cursor: (0, 0),
+            anchor: None,
+            goal: None,
+            wrap: true,
+            top: (0, 0),
+            left: 0,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_edit: EditKind::Other,
+            clipboard: String::new(),
+            copied: None,
+            style: Style::new(),
+            selection_style: Style::new().attr(Attr::REVERSE),
+            cursor_style: Style::new().attr(Attr::REVERSE | Attr::BOLD),
+            width: 0,
+            height: 0,
+            cursor_position: None,
+        }
+    }
+
+    /// Style of the text
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Style of selected text (reverse video by default)
+    pub fn selection_style(mut self, style: Style) -> Self {
+        self.selection_style = style;
+        self
+    }
+
+    /// Style of the cell under the cursor (bold reverse video by default)
+    pub fn cursor_style(mut self, style: Style) -> Self {
+        self.cursor_style = style;
+        self
+    }
+
+    /// The text, lines joined with `\n`
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+            text.extend(line);
+        }
+        text
+    }
+
+    /// Replace the text, moving the cursor to the start and clearing the
+    /// undo history
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = split_lines(text);
+        self.cursor = (0, 0);
+        self.anchor = None;
+        self.top = (0, 0);
+        self.left = 0;
+        self.undo.clear();
+        self.redo.clear();
+        self.last_edit = EditKind::Other;
+    }
+
+    /// Number of lines
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Wrap long lines instead of scrolling horizontally
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.left = 0;
+        self.top.1 = 0;
+    }
+
+    /// Check if long lines wrap
+    pub fn wrap(&self) -> bool {
+        self.wrap
+    }
+
+    /// Cursor line and character
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Move the cursor (clamped to the text), clearing the selection
+    pub fn set_cursor(&mut self, line: usize, col: usize) {
+        let line = line.min(self.lines.len() - 1);
+        self.cursor = (line, col.min(self.lines[line].len()));
+        self.anchor = None;
+    }
+
+    /// Screen position of the cursor at the last render, for placing the
+    /// terminal cursor; `None` if it wasn't drawn
+    pub fn cursor_position(&self) -> Option<(u16, u16)> {
+        self.cursor_position
+    }
+
+    /// Start and end of the selection, if any text is selected
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor.filter(|&anchor| anchor != self.cursor)?;
+        Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    /// The selected text
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.text_range(start, end))
+    }
+
+    /// Select the whole text
+    pub fn select_all(&mut self) {
+        self.anchor = Some((0, 0));
+        let last = self.lines.len() - 1;
+        self.cursor = (last, self.lines[last].len());
+    }
+
+    /// Insert `text` at the cursor, replacing the selection
+    pub fn insert_str(&mut self, text: &str) {
+        self.checkpoint(EditKind::Other, EditKind::Other);
+        self.delete_selection();
+        self.insert_text(text);
+    }
+
+    /// Text copied or cut since the last call
+    pub fn take_copied(&mut self) -> Option<String> {
+        self.copied.take()
+    }
+
+    /// Undo the last edit; returns false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(state) = self.undo.pop() else {
+            return false;
+        };
+        let current = self.restore(state);
+        self.redo.push(current);
+        true
+    }
+
+    /// Redo the last undone edit; returns false if there was nothing to
+    /// redo
+    pub fn redo(&mut self) -> bool {
+        let Some(state) = self.redo.pop() else {
+            return false;
+        };
+        let current = self.restore(state);
+        self.undo.push(current);
+        true
+    }
+
+    /// Edit the text with `key`
+    ///
+    /// Returns true if the key was handled.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let Some((base, modifiers)) = key_modifiers(key) else {
+            return false;
+        };
+        let shift = modifiers.contains(Modifiers::SHIFT);
+        let command = modifiers - Modifiers::SHIFT;
+        let previous = std::mem::replace(&mut self.last_edit, EditKind::Other);
+        let goal = self.goal.take();
+
+        match base {
+            Key::Left
+            | Key::Right
+            | Key::Up
+            | Key::Down
+            | Key::Home
+            | Key::End
+            | Key::PageUp
+            | Key::PageDown => {
+                let by_word = command.intersects(Modifiers::CTRL | Modifiers::ALT);
+                self.navigate(&base, shift, by_word, goal);
+            }
+            Key::Char(ch) if command.is_empty() => {
+                let kind = if ch.is_whitespace() {
+                    EditKind::Other
+                } else {
+                    EditKind::Typing
+                };
+                self.checkpoint(kind, previous);
+                self.delete_selection();
+                self.insert_text(ch.encode_utf8(&mut [0; 4]));
+            }
+            Key::Char(ch) if command == Modifiers::CTRL => match ch {
+                'a' => self.select_all(),
+                'c' => self.copy(),
+                'x' => {
+                    self.copy();
+                    if self.selection().is_some() {
+                        self.checkpoint(EditKind::Other, previous);
+                        self.delete_selection();
+                    }
+                }
+                'v' => {
+                    let text = self.clipboard.clone();
+                    self.insert_str(&text);
+                }
+                'z' if shift => {
+                    self.redo();
+                }
+                'z' => {
+                    self.undo();
+                }
+                'y' => {
+                    self.redo();
+                }
+                _ => return self.unhandled(previous, goal),
+            },
+            Key::Enter if command.is_empty() => self.insert_str("\n"),
+            Key::Paste(text) => self.insert_str(&text),
+            Key::Backspace | Key::Delete => {
+                if self.selection().is_some() {
+                    self.checkpoint(EditKind::Other, previous);
+                    self.delete_selection();
+                    return true;
+                }
+                self.checkpoint(EditKind::Deleting, previous);
+                let (line, col) = self.cursor;
+                let len = self.lines[line].len();
+                let (start, end) = if base == Key::Backspace {
+                    match (line, col) {
+                        (0, 0) => return true,
+                        (line, 0) => ((line - 1, self.lines[line - 1].len()), (line, 0)),
+                        (line, col) => ((line, col - 1), (line, col)),
+                    }
+                } else if col < len {
+                    ((line, col), (line, col + 1))
+                } else if line + 1 < self.lines.len() {
+                    ((line, col), (line + 1, 0))
+                } else {
+                    return true;
+                };
+                self.remove_range(start, end);
+                self.cursor = start;
+                self.anchor = None;
+            }
+            _ => return self.unhandled(previous, goal),
+        }
+        true
+    }
+
+    /// Draw the visible part of the text into `area`, scrolling to keep
+    /// the cursor visible
+    pub fn render(&mut self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        self.cursor_position = None;
+        if area.is_empty() {
+            return Ok(());
+        }
+        self.width = area.width as usize;
+        self.height = area.height as usize;
+        self.scroll_into_view();
+
+        let selection = self.selection();
+        let blank = Cell::with_style(' ', self.style.attr, self.style.fg, self.style.bg);
+        let (mut line, mut row) = self.top;
+        for y in 0..self.height {
+            let screen_y = area.y + y as u16;
+            let mut cells = Vec::new();
+            // Columns filled so far
+            let mut x = 0;
+            if let Some(text) = self.lines.get(line) {
+                let starts = self.row_starts(line);
+                let (start, end) = if self.wrap {
+                    (
+                        starts[row],
+                        starts.get(row + 1).copied().unwrap_or(text.len()),
+                    )
+                } else {
+                    (0, text.len())
+                };
+                let has_cursor = self.cursor.0 == line && self.row_of(self.cursor) == row;
+                let mut column = 0;
+                for (col, &ch) in text.iter().enumerate().take(end).skip(start) {
+                    let w = char_width(ch);
+                    let from = column;
+                    column += w;
+                    if !self.wrap && from < self.left {
+                        // A wide character cut by the left edge shows as
+                        // blanks
+                        let shown = column.saturating_sub(self.left);
+                        cells.extend(std::iter::repeat_n(blank.clone(), shown));
+                        x += shown;
+                        continue;
+                    }
+                    if x + w > self.width {
+                        break;
+                    }
+                    let style = if has_cursor && col == self.cursor.1 {
+                        self.cursor_position = Some((screen_y, area.x + x as u16));
+                        self.cursor_style
+                    } else if selection
+                        .is_some_and(|(from, to)| from <= (line, col) && (line, col) < to)
+                    {
+                        self.selection_style
+                    } else {
+                        self.style
+                    };
+                    cells.push(Cell::with_style(ch, style.attr, style.fg, style.bg));
+                    x += w;
+                }
+
+                // At the end of the line the cursor takes the next column,
+                // or the last character if the row is full
+                if has_cursor && self.cursor.1 == end {
+                    let style = self.cursor_style;
+                    if x < self.width {
+                        cells.push(Cell::with_style(' ', style.attr, style.fg, style.bg));
+                        self.cursor_position = Some((screen_y, area.x + x as u16));
+                        x += 1;
+                    } else if let Some(last) = cells.last_mut() {
+                        let w = char_width(last.ch());
+                        *last = Cell::with_style(last.ch(), style.attr, style.fg, style.bg);
+                        self.cursor_position = Some((screen_y, area.x + (x - w) as u16));
+                    }
+                }
+            }
+            cells.extend(std::iter::repeat_n(blank.clone(), self.width - x));
+
+            let rendered = Line::from_cells(&cells);
+            put_line(canvas, screen_y, area.x, &rendered, area.width, None)?;
+            (line, row) = self.next_row((line, row));
+        }
+        Ok(())
+    }
+
+    /// Keep `goal` and the previous edit for keys that weren't handled
+    fn unhandled(&mut self, previous: EditKind, goal: Option<usize>) -> bool {
+        self.last_edit = previous;
+        self.goal = goal;
+        false
+    }
+
+    fn navigate(&mut self, key: &Key, extend: bool, by_word: bool, goal: Option<usize>) {
+        let (line, col) = self.cursor;
+        let last = self.lines.len() - 1;
+        // Without Shift, Left and Right collapse a selection to its ends
+        if !extend
+            && let Some((start, end)) = self.selection()
+            && matches!(key, Key::Left | Key::Right)
+        {
+            self.cursor = if *key == Key::Left { start } else { end };
+            self.anchor = None;
+            return;
+        }
+
+        let target = match key {
+            Key::Left if by_word => self.word_left(),
+            Key::Right if by_word => self.word_right(),
+            Key::Left if col > 0 => (line, col - 1),
+            Key::Left if line > 0 => (line - 1, self.lines[line - 1].len()),
+            Key::Right if col < self.lines[line].len() => (line, col + 1),
+            Key::Right if line < last => (line + 1, 0),
+            Key::Left | Key::Right => self.cursor,
+            Key::Home if by_word => (0, 0),
+            Key::End if by_word => (last, self.lines[last].len()),
+            Key::Home => (line, 0),
+            Key::End => (line, self.lines[line].len()),
+            _ => {
+                let rows = match key {
+                    Key::Up => -1,
+                    Key::Down => 1,
+                    Key::PageUp => -(self.height.max(1) as isize),
+                    _ => self.height.max(1) as isize,
+                };
+                let goal = goal.unwrap_or_else(|| self.row_column(self.cursor));
+                self.goal = Some(goal);
+                self.vertical(rows, goal)
+            }
+        };
+
+        if extend {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = target;
+    }
+
+    /// Position `rows` rows up (negative) or down from the cursor, at
+    /// column `goal` of the row
+    fn vertical(&self, rows: isize, goal: usize) -> Pos {
+        let mut pos = (self.cursor.0, self.row_of(self.cursor));
+        for _ in 0..rows.unsigned_abs() {
+            let next = if rows < 0 {
+                self.prev_row(pos)
+            } else {
+                self.next_row(pos)
+            };
+            if next.0 >= self.lines.len() {
+                break;
+            }
+            pos = next;
+        }
+        let (line, row) = pos;
+        let starts = self.row_starts(line);
+        // The last position of a wrapped row is the next row's start
+        let end = starts
+            .get(row + 1)
+            .map_or(self.lines[line].len(), |&next| next - 1);
+        let mut col = starts[row];
+        let mut x = 0;
+        while col < end {
+            x += char_width(self.lines[line][col]);
+            if x > goal {
+                break;
+            }
+            col += 1;
+        }
+        (line, col)
+    }
+
+    fn word_left(&self) -> Pos {
+        let (line, mut col) = self.cursor;
+        if col == 0 {
+            return if line > 0 {
+                (line - 1, self.lines[line - 1].len())
+            } else {
+                (0, 0)
+            };
+        }
+        let text = &self.lines[line];
+        while col > 0 && !text[col - 1].is_alphanumeric() {
+            col -= 1;
+        }
+        while col > 0 && text[col - 1].is_alphanumeric() {
+            col -= 1;
+        }
+        (line, col)
+    }
+
+    fn word_right(&self) -> Pos {
+        let (line, mut col) = self.cursor;
+        let text = &self.lines[line];
+        if col == text.len() {
+            return if line + 1 < self.lines.len() {
+                (line + 1, 0)
+            } else {
+                self.cursor
+            };
+        }
+        while col < text.len() && !text[col].is_alphanumeric() {
+            col += 1;
+        }
+        while col < text.len() && text[col].is_alphanumeric() {
+            col += 1;
+        }
+        (line, col)
+    }
+
+    fn copy(&mut self) {
+        if let Some(text) = self.selected_text() {
+            self.clipboard = text.clone();
+            self.copied = Some(text);
+        }
+    }
+
+    /// Save the text for undo, unless the edit continues the previous one
+    fn checkpoint(&mut self, kind: EditKind, previous: EditKind) {
+        if kind == EditKind::Other || kind != previous {
+            self.undo.push(State {
+                lines: self.lines.clone(),
+                cursor: self.cursor,
+            });
+            if self.undo.len() > UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+        }
+        self.last_edit = kind;
+    }
+
+    /// Switch to `state`, returning the current one
+    fn restore(&mut self, state: State) -> State {
+        self.anchor = None;
+        self.last_edit = EditKind::Other;
+        State {
+            lines: std::mem::replace(&mut self.lines, state.lines),
+            cursor: std::mem::replace(&mut self.cursor, state.cursor),
+        }
+    }
+
+    fn delete_selection(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            self.remove_range(start, end);
+            self.cursor = start;
+        }
+        self.anchor = None;
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        let mut new = split_lines(text);
+        let (line, col) = self.cursor;
+        let tail = self.lines[line].split_off(col);
+        let added = new.len() - 1;
+        let end_col = new[added].len() + if added == 0 { col } else { 0 };
+        new[added].extend(tail);
+
+        let first = new.remove(0);
+        self.lines[line].extend(first);
+        self.lines.splice(line + 1..line + 1, new);
+        self.cursor = (line + added, end_col);
+    }
+
+    fn text_range(&self, start: Pos, end: Pos) -> String {
+        if start.0 == end.0 {
+            return self.lines[start.0][start.1..end.1].iter().collect();
+        }
+        let mut text: String = self.lines[start.0][start.1..].iter().collect();
+        for line in &self.lines[start.0 + 1..end.0] {
+            text.push('\n');
+            text.extend(line);
+        }
+        text.push('\n');
+        text.extend(&self.lines[end.0][..end.1]);
+        text
+    }
+
+    fn remove_range(&mut self, start: Pos, end: Pos) {
+        let tail = self.lines[end.0].split_off(end.1);
+        self.lines[start.0].truncate(start.1);
+        self.lines[start.0].extend(tail);
+        self.lines.drain(start.0 + 1..=end.0);
+    }
+
+    /// Index of the first character of each row `line` takes on screen
+    ///
+    /// Rows are filled by display width; without wrapping there is one.
+    fn row_starts(&self, line: usize) -> Vec<usize> {
+        let mut starts = vec![0];
+        if !self.wrap || self.width == 0 {
+            return starts;
+        }
+        let mut used = 0;
+        for (i, &ch) in self.lines[line].iter().enumerate() {
+            let w = char_width(ch);
+            if used + w > self.width && used > 0 {
+                starts.push(i);
+                used = 0;
+            }
+            used += w;
+        }
+        starts
+    }
+
+    /// Rows `line` takes on screen
+    fn rows_of(&self, line: usize) -> usize {
+        self.row_starts(line).len()
+    }
+
+    /// Row of its line that `pos` is drawn on
+    fn row_of(&self, pos: Pos) -> usize {
+        let starts = self.row_starts(pos.0);
+        starts
+            .iter()
+            .rposition(|&start| start <= pos.1)
+            .unwrap_or(0)
+    }
+
+    /// Display column of `pos` in its row
+    fn row_column(&self, (line, col): Pos) -> usize {
+        let start = self.row_starts(line)[self.row_of((line, col))];
+        self.lines[line][start..col]
+            .iter()
+            .map(|&ch| char_width(ch))
+            .sum()
+    }
+
+    /// Row after (line, row); past the last line at the end
+    fn next_row(&self, (line, row): Pos) -> Pos {
+        if line < self.lines.len() && row + 1 < self.rows_of(line) {
+            (line, row + 1)
+        } else {
+            (line + 1, 0)
+        }
+    }
+
+    /// Row before (line, row); the first row stays
+    fn prev_row(&self, (line, row): Pos) -> Pos {
+        match (line, row) {
+            (0, 0) => (0, 0),
+            (line, 0) => (line - 1, self.rows_of(line - 1) - 1),
+            (line, row) => (line, row - 1),
+        }
+    }
+
+    /// Adjust the first row (and column) shown so the cursor is visible
+    fn scroll_into_view(&mut self) {
+        let last = self.lines.len() - 1;
+        if self.top.0 > last {
+            self.top = (last, 0);
+        }
+        self.top.1 = self.top.1.min(self.rows_of(self.top.0) - 1);
+
+        let cursor = (self.cursor.0, self.row_of(self.cursor));
+        if cursor < self.top {
+            self.top = cursor;
+        } else {
+            let mut pos = self.top;
+            let mut rows = 0;
+            while pos != cursor && rows < self.height {
+                pos = self.next_row(pos);
+                rows += 1;
+            }
+            if rows >= self.height {
+                self.top = cursor;
+                for _ in 1..self.height {
+                    self.top = self.prev_row(self.top);
+                }
+            }
+        }
+
+        if !self.wrap {
+            let (line, col) = self.cursor;
+            let x = self.row_column(self.cursor);
+            let w = self.lines[line]
+                .get(col)
+                .map_or(1, |&ch| char_width(ch).max(1));
+            if x < self.left {
+                self.left = x;
+            } else if x + w > self.left + self.width {
+                self.left = (x + w).saturating_sub(self.width);
+            }
+        }
+    }
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Columns `ch` takes on screen
+fn char_width(ch: char) -> usize {
+    ch.width().unwrap_or(0)
+}
+
+/// Lines of `text`, split at `\n`, `\r\n` or `\r`; tabs become spaces and
+/// other control characters are dropped
+fn split_lines(text: &str) -> Vec<Vec<char>> {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    text.split('\n')
+        .map(|line| {
+            let mut chars = Vec::with_capacity(line.len());
+            for ch in line.chars() {
+                match ch {
+                    '\t' => chars.extend([' '; TAB_WIDTH]),
+                    ch if ch.is_control() => {}
+                    ch => chars.push(ch),
+                }
+            }
+            chars
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::cells_text;
+    use crate::kitty::KeyEvent;
+    use crate::screen::Screen;
+    use crate::window::Window;
+
+    fn typed(text: &str) -> TextArea {
+        let mut area = TextArea::new();
+        for ch in text.chars() {
+            let key = if ch == '\n' {
+                Key::Enter
+            } else {
+                Key::Char(ch)
+            };
+            area.handle_key(&key);
+        }
+        area
+    }
+
+    fn shifted(key: Key) -> Key {
+        Key::Modified(Box::new(key), Modifiers::SHIFT)
+    }
+
+    #[test]
+    fn test_editing_and_undo() {
+        let mut area = typed("hello world\nsecond");
+        assert_eq!(area.text(), "hello world\nsecond");
+        assert_eq!(area.cursor(), (1, 6));
+
+        area.handle_key(&Key::Home);
+        area.handle_key(&Key::Backspace);
+        assert_eq!(area.text(), "hello worldsecond");
+        area.handle_key(&Key::Ctrl('z'));
+        assert_eq!(area.text(), "hello world\nsecond");
+
+        // Words typed in a row undo one at a time
+        area.handle_key(&Key::Ctrl('z'));
+        assert_eq!(area.text(), "hello world\n");
+        area.handle_key(&Key::Ctrl('z'));
+        assert_eq!(area.text(), "hello world");
+        area.handle_key(&Key::Ctrl('y'));
+        area.handle_key(&Key::Ctrl('y'));
+        assert_eq!(area.text(), "hello world\nsecond");
+        assert_eq!(area.cursor(), (1, 0));
+
+        area.insert_str("a\r\nb\tc");
+        assert_eq!(area.text(), "hello world\na\nb    csecond");
+        assert!(!area.redo());
+        assert!(!area.handle_key(&Key::Tab));
+    }
+
+    #[test]
+    fn test_selection_and_clipboard() {
+        let mut area = typed("one two\nthree");
+        area.handle_key(&shifted(Key::Up));
+        area.handle_key(&Key::Modified(
+            Box::new(Key::Left),
+            Modifiers::SHIFT | Modifiers::CTRL,
+        ));
+        assert_eq!(area.selected_text().as_deref(), Some("two\nthree"));
+
+        area.handle_key(&Key::Ctrl('x'));
+        assert_eq!(area.text(), "one ");
+        assert_eq!(area.take_copied().as_deref(), Some("two\nthree"));
+        assert_eq!(area.take_copied(), None);
+
+        area.handle_key(&Key::Ctrl('v'));
+        area.handle_key(&Key::Ctrl('v'));
+        assert_eq!(area.text(), "one two\nthreetwo\nthree");
+
+        // Kitty events carry Shift for the selection too
+        let mut event = KeyEvent::with_modifiers('a' as u32, Modifiers::CTRL);
+        area.handle_key(&Key::Enhanced(event.clone()));
+        assert_eq!(area.selection(), Some(((0, 0), (2, 5))));
+        area.handle_key(&Key::Left);
+        assert_eq!((area.cursor(), area.selection()), ((0, 0), None));
+
+        event = KeyEvent::with_modifiers(57351, Modifiers::SHIFT);
+        area.handle_key(&Key::Enhanced(event));
+        area.handle_key(&Key::Paste("1".to_string()));
+        assert_eq!(area.text(), "1ne two\nthreetwo\nthree");
+    }
+
+    #[test]
+    fn test_wrap_and_render() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut area = TextArea::new();
+        area.set_text("abcdefghij\nxy");
+        let rect = Rect::new(0, 0, 2, 4);
+        area.render(&mut scr, rect).unwrap();
+        let text =
+            |scr: &Screen| -> Vec<String> { scr.capture(rect).iter().map(Line::text).collect() };
+        assert_eq!(text(&scr), ["abcd", "efgh"]);
+
+        area.handle_key(&Key::Down);
+        area.handle_key(&Key::Down);
+        assert_eq!(area.cursor(), (0, 8));
+        area.handle_key(&Key::Down);
+        assert_eq!(area.cursor(), (1, 0));
+        area.render(&mut scr, rect).unwrap();
+        assert_eq!(text(&scr), ["ij  ", "xy  "]);
+        assert_eq!(area.cursor_position(), Some((1, 0)));
+
+        // Everything is drawn again after the screen was cleared
+        scr.clear().unwrap();
+        area.render(&mut scr, rect).unwrap();
+        assert_eq!(text(&scr), ["ij  ", "xy  "]);
+
+        area.set_wrap(false);
+        area.set_cursor(0, 9);
+        area.render(&mut scr, rect).unwrap();
+        assert_eq!(text(&scr), ["ghij", "    "]);
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        // Windows keep the second column of wide characters
+        let mut win = Window::new(3, 4, 0, 0).unwrap();
+        let mut area = TextArea::new();
+        area.insert_str("日本語日本語");
+        let rect = Rect::new(0, 0, 3, 4);
+        area.render(&mut win, rect).unwrap();
+        let text = |win: &Window| -> Vec<String> {
+            (0..3)
+                .map(|y| {
+                    let cells: Vec<Cell> =
+                        (0..4).map(|x| win.cell_at(y, x).unwrap().clone()).collect();
+                    cells_text(&cells)
+                })
+                .collect()
+        };
+        assert_eq!(text(&win), ["日本", "語日", "本語"]);
+        // The row is full, so the cursor sits on the last character
+        assert_eq!(area.cursor_position(), Some((2, 2)));
+
+        area.handle_key(&Key::Up);
+        area.handle_key(&Key::Up);
+        assert_eq!(area.cursor(), (0, 1));
+        area.handle_key(&Key::Left);
+        area.render(&mut win, rect).unwrap();
+        assert_eq!(area.cursor_position(), Some((0, 0)));
+
+        area.set_wrap(false);
+        area.set_cursor(0, 5);
+        area.render(&mut win, rect).unwrap();
+        assert_eq!(text(&win)[0], "本語");
+        assert_eq!(area.cursor_position(), Some((0, 2)));
+    }
+}
@@ -0,0 +1,403 @@
+//! Scrollable viewer for styled text with search
+
+use super::{Canvas, put_line, render_scrollbar};
+use crate::attr::Attr;
+use crate::cell::{Cell, graphemes};
+use crate::error::Result;
+use crate::input::Key;
+use crate::layout::{Direction, Rect};
+use crate::style::Style;
+use crate::text::Line;
+
+/// A search match: line index, first cell and length in cells
+type Match = (usize, usize, usize);
+
+/// Read-only view of styled lines, like a pager
+pub struct TextView {
+    lines: Vec<Line>,
+    top: usize,
+    left: usize,
+    wrap: bool,
+    query: String,
+    matches: Vec<Match>,
+    current: Option<usize>,
+    match_style: Style,
+    current_match_style: Style,
+    show_scrollbar: bool,
+    // Size shown by the last render, for paging and keeping matches visible
+    height: usize,
+    width: usize,
+}
+
+impl TextView {
+    /// Create a view of `lines`
+    pub fn new(lines: Vec<Line>) -> Self {
+        Self {
+            lines,
+            top: 0,
+            left: 0,
+            wrap: false,
+            query: String::new(),
+            matches: Vec::new(),
+            current: None,
+            match_style: Style::new().attr(Attr::REVERSE),
+            current_match_style: Style::new().attr(Attr::REVERSE | Attr::BOLD),
+            show_scrollbar: false,
+            height: 0,
+            width: 0,
+        }
+    }
+
+    /// Style of search matches (reverse video by default)
+    pub fn match_style(mut self, style: Style) -> Self {
+        self.match_style = style;
+        self
+    }
+
+    /// Style of the current search match (bold reverse video by default)
+    pub fn current_match_style(mut self, style: Style) -> Self {
+        self.current_match_style = style;
+        self
+    }
+
+    /// Show a scrollbar when the lines don't fit (off by default)
+    ///
+    /// The thumb follows line numbers, so with wrapping it is only
+    /// approximate.
+    pub fn scrollbar(mut self, show: bool) -> Self {
+        self.show_scrollbar = show;
+        self
+    }
+
+    /// The lines shown
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Append a line (e.g. a new log entry), updating search matches
+    pub fn push_line(&mut self, line: Line) {
+        let index = self.lines.len();
+        self.matches.extend(find_matches(&line, &self.query, index));
+        self.lines.push(line);
+    }
+
+    /// Wrap long lines instead of scrolling horizontally
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        if wrap {
+            self.left = 0;
+        }
+    }
+
+    /// Check if long lines wrap
+    pub fn wrap(&self) -> bool {
+        self.wrap
+    }
+
+    /// Index of the first line shown
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// First column shown (always 0 while wrapping)
+    pub fn left(&self) -> usize {
+        self.left
+    }
+
+    /// Scroll so `line` is the first line shown
+    pub fn scroll_to(&mut self, line: usize) {
+        self.top = line.min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Scroll down (positive) or up (negative) by `lines`
+    pub fn scroll_by(&mut self, lines: isize) {
+        self.scroll_to(self.top.saturating_add_signed(lines));
+    }
+
+    /// Scroll right (positive) or left (negative) by `cols`; ignored while
+    /// wrapping
+    pub fn scroll_horizontal(&mut self, cols: isize) {
+        if !self.wrap {
+            let longest = self.lines.iter().map(Line::width).max().unwrap_or(0);
+            self.left = self.left.saturating_add_signed(cols).min(longest);
+        }
+    }
+
+    /// Search for `query`, jumping to the first match at or below the top
+    ///
+    /// Call on every edit of the query for incremental search; an empty
+    /// query clears the matches. Returns the number of matches.
+    pub fn search(&mut self, query: &str) -> usize {
+        self.query = query.to_string();
+        self.matches = self
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| find_matches(line, query, i))
+            .collect();
+
+        self.current = self
+            .matches
+            .iter()
+            .position(|&(line, _, _)| line >= self.top)
+            .or((!self.matches.is_empty()).then_some(0));
+        self.reveal_current();
+        self.matches.len()
+    }
+
+    /// Number of matches for the current query
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Current match (line, first cell, length in cells)
+    pub fn current_match(&self) -> Option<(usize, usize, usize)> {
+        self.matches.get(self.current?).copied()
+    }
+
+    /// Move to the next match, wrapping around at the end
+    pub fn next_match(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = Some(self.current.map_or(0, |i| (i + 1) % self.matches.len()));
+            self.reveal_current();
+        }
+    }
+
+    /// Move to the previous match, wrapping around at the start
+    pub fn prev_match(&mut self) {
+        if !self.matches.is_empty() {
+            let last = self.matches.len() - 1;
+            self.current = Some(
+                self.current
+                    .map_or(last, |i| i.checked_sub(1).unwrap_or(last)),
+            );
+            self.reveal_current();
+        }
+    }
+
+    /// Scroll with arrows, PageUp/PageDown and Home/End; `n`/`N` step
+    /// through search matches
+    ///
+    /// Returns true if the key was handled.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let page = self.height.max(1) as isize;
+        match key {
+            Key::Up => self.scroll_by(-1),
+            Key::Down => self.scroll_by(1),
+            Key::PageUp => self.scroll_by(-page),
+            Key::PageDown => self.scroll_by(page),
+            Key::Home => self.scroll_to(0),
+            Key::End => self.scroll_to(self.lines.len().saturating_sub(page as usize)),
+            Key::Left => self.scroll_horizontal(-1),
+            Key::Right => self.scroll_horizontal(1),
+            Key::Char('n') => self.next_match(),
+            Key::Char('N') => self.prev_match(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draw the visible part into `area`
+    pub fn render(&mut self, canvas: &mut impl Canvas, area: Rect) -> Result<()> {
+        let has_scrollbar =
+            self.show_scrollbar && self.lines.len() > area.height as usize && area.width > 1;
+        let text_width = area.width - u16::from(has_scrollbar);
+        self.height = area.height as usize;
+        self.width = text_width as usize;
+        if area.is_empty() {
+            return Ok(());
+        }
+
+        let width = text_width as usize;
+        let mut rows = Vec::with_capacity(self.height);
+        for (index, line) in self.lines.iter().enumerate().skip(self.top) {
+            let cells = self.highlighted(index, line);
+            if self.wrap {
+                if cells.is_empty() {
+                    rows.push(Line::new());
+                }
+                rows.extend(cells.chunks(width).map(Line::from_cells));
+            } else {
+                let start = self.left.min(cells.len());
+                let end = (start + width).min(cells.len());
+                rows.push(Line::from_cells(&cells[start..end]));
+            }
+            if rows.len() >= self.height {
+                break;
+            }
+        }
+
+        for row in 0..area.height {
+            let line = rows.get(row as usize).cloned().unwrap_or_default();
+            put_line(canvas, area.y + row, area.x, &line, text_width, None)?;
+        }
+
+        if has_scrollbar {
+            render_scrollbar(
+                canvas,
+                area,
+                Direction::Vertical,
+                self.lines.len(),
+                self.height,
+                self.top,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Cells of `line` with search matches styled
+    fn highlighted(&self, index: usize, line: &Line) -> Vec<Cell> {
+        let mut cells = line.cells();
+        let first = self.matches.partition_point(|&(l, _, _)| l < index);
+        for (i, &(l, start, len)) in self.matches.iter().enumerate().skip(first) {
+            if l != index {
+                break;
+            }
+            let style = if self.current == Some(i) {
+                self.current_match_style
+            } else {
+                self.match_style
+            };
+            for cell in cells.iter_mut().skip(start).take(len) {
+                cell.attr = style.attr;
+                cell.fg = style.fg;
+                cell.bg = style.bg;
+            }
+        }
+        cells
+    }
+
+    /// Scroll so the current match is on screen
+    fn reveal_current(&mut self) {
+        let Some((line, start, len)) = self.current_match() else {
+            return;
+        };
+        let height = self.height.max(1);
+        if line < self.top || line >= self.top + height {
+            self.top = line.saturating_sub(height / 2);
+        }
+        if !self.wrap && self.width > 0 {
+            if start < self.left {
+                self.left = start;
+            } else if start + len > self.left + self.width {
+                self.left = (start + len).saturating_sub(self.width);
+            }
+        }
+    }
+}
+
+/// Matches of `query` in `line` (line `index`), in cell positions
+fn find_matches(line: &Line, query: &str, index: usize) -> Vec<Match> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    // The line's text, with the cell each byte is drawn in (as `Line::cells`
+    // splits it)
+    let mut text = String::new();
+    let mut cell_of = Vec::new();
+    let clusters = line.spans.iter().flat_map(|span| graphemes(&span.text));
+    for (cell, (grapheme, _)) in clusters.enumerate() {
+        text.push_str(grapheme);
+        cell_of.resize(text.len(), cell);
+    }
+    text.match_indices(query)
+        .map(|(byte, found)| {
+            let first = cell_of[byte];
+            let last = cell_of[byte + found.len() - 1];
+            (index, first, last - first + 1)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::Screen;
+
+    fn view() -> TextView {
+        let lines = (0..100)
+            .map(|i| Line::from(format!("line {} of the log", i).as_str()))
+            .collect();
+        TextView::new(lines)
+    }
+
+    fn text(scr: &Screen, rect: Rect) -> Vec<String> {
+        scr.capture(rect).iter().map(Line::text).collect()
+    }
+
+    #[test]
+    fn test_scrolling_and_wrap() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut view = view();
+        let area = Rect::new(0, 0, 3, 8);
+
+        view.render(&mut scr, area).unwrap();
+        view.handle_key(&Key::PageDown);
+        view.scroll_horizontal(5);
+        view.render(&mut scr, area).unwrap();
+        assert_eq!(text(&scr, area), vec!["3 of the", "4 of the", "5 of the"]);
+
+        view.set_wrap(true);
+        assert_eq!(view.left(), 0);
+        view.render(&mut scr, area).unwrap();
+        assert_eq!(text(&scr, area), vec!["line 3 o", "f the lo", "g       "]);
+
+        view.handle_key(&Key::End);
+        assert_eq!(view.top(), 97);
+    }
+
+    #[test]
+    fn test_incremental_search() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut view = view();
+        let area = Rect::new(0, 0, 4, 20);
+        view.render(&mut scr, area).unwrap();
+
+        view.search("line 5");
+        assert_eq!(view.match_count(), 11);
+        view.search("line 50");
+        assert_eq!(view.current_match(), Some((50, 0, 7)));
+        assert_eq!(view.top(), 48);
+
+        view.search("log");
+        view.render(&mut scr, area).unwrap();
+        let line = &scr.capture(Rect::new(0, 0, 1, 20))[0];
+        assert_eq!(line.spans[1].text, "log");
+        assert!(line.spans[1].attr.contains(Attr::BOLD));
+
+        view.prev_match();
+        assert_eq!(view.current_match().unwrap().0, 47);
+        view.search("");
+        assert_eq!(view.current_match(), None);
+    }
+
+    #[test]
+    fn test_scrollbar() {
+        let mut scr = Screen::with_size(24, 80);
+        let mut view = view().scrollbar(true);
+        let area = Rect::new(0, 0, 2, 10);
+        view.scroll_to(98);
+        view.render(&mut scr, area).unwrap();
+        assert_eq!(text(&scr, area), vec!["line 98 o░", "line 99 o█"]);
+        assert!(view.handle_key(&Key::End));
+    }
+
+    #[test]
+    fn test_matches_in_cells() {
+        let line = Line::from("e\u{301}e\u{301} error");
+        assert_eq!(find_matches(&line, "error", 4), vec![(4, 3, 5)]);
+        // A match inside a cluster covers the whole cell
+        assert_eq!(find_matches(&line, "\u{301} e", 4), vec![(4, 1, 3)]);
+    }
+
+    #[test]
+    fn test_push_line_extends_matches() {
+        let mut view = TextView::new(Vec::new());
+        view.search("error");
+        view.push_line(Line::from("disk error"));
+        view.push_line(Line::from("ok"));
+        assert_eq!(view.match_count(), 1);
+        view.next_match();
+        assert_eq!(view.current_match(), Some((0, 5, 5)));
+    }
+}
@@ -1,9 +1,40 @@
-use crate::attr::Attr;
+use crate::acs::line_chars;
+use crate::attr::{Attr, AttrPolicy};
+use crate::buffer::Buffer;
+use crate::caps::Capabilities;
+use crate::cell::{Cell, cells_text, control_placeholder, graphemes, sanitize_controls};
 use crate::color::Color;
 use crate::error::{Error, Result};
-use smallvec::SmallVec;
+use crate::layout::Rect;
+use crate::sgr::StyleEncoder;
+use crate::style::Style;
 use std::fmt::Write;
-use std::io;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use unicode_width::UnicodeWidthChar;
+
+/// Cells by terminal row and column, None where nothing is queued
+pub(crate) type VirtualScreen = Vec<Vec<Option<Cell>>>;
+
+/// What `wnoutrefresh` queued for the next `Screen::doupdate`, shared by
+/// a screen and its windows
+#[derive(Debug, Default)]
+pub(crate) struct UpdateQueue {
+    /// Cells of the queued windows
+    pub(crate) cells: VirtualScreen,
+    /// Areas of touched windows, to be repainted
+    pub(crate) touched: Vec<Rect>,
+    /// A window wrote to the terminal itself (`refresh`), so the screen no
+    /// longer knows where the cursor is
+    pub(crate) cursor_moved: bool,
+    /// Terminal the screen draws on, which `refresh` writes to (None for
+    /// stdout)
+    #[cfg(unix)]
+    pub(crate) output_fd: Option<std::os::unix::io::RawFd>,
+}
+
+/// Update queue of one screen
+pub(crate) type SharedQueue = Arc<Mutex<UpdateQueue>>;
 
 /// A window (subregion of the screen)
 pub struct Window {
@@ -17,13 +48,28 @@ pub struct Window {
     current_fg: Color,
     current_bg: Color,
     buffer: String,
+    // Reused by `printw` so formatting doesn't allocate per call
+    format_buffer: String,
     scroll_enabled: bool,
-    // Performance optimization: track last emitted style to avoid redundant codes
-    last_emitted_attr: Attr,
-    last_emitted_fg: Color,
-    last_emitted_bg: Color,
-    // Performance optimization: SmallVec for style sequence (stack-allocated for <64 bytes)
-    style_sequence_buf: SmallVec<[u8; 64]>,
+    // `print` continues on the next line instead of clipping at the edge
+    autowrap: bool,
+    // Printed control characters are shown as `^X` instead of sent raw
+    sanitize_controls: bool,
+    // Fill for cleared cells and default style for written text
+    background: Cell,
+    // Style of the output queued so far, so only changes are emitted
+    style: StyleEncoder,
+    // What the window shows, cell by cell
+    content: Buffer,
+    // What the terminal supports, and what to draw for missing attributes
+    capabilities: Capabilities,
+    attr_policy: AttrPolicy,
+    // Repaint the whole window on the next refresh (`touchwin`)
+    touched: bool,
+    // Where `wnoutrefresh` queues the window: its screen's queue
+    queue: SharedQueue,
+    // Top row kept by the screen, for footers that follow a resize
+    row_anchor: Option<Arc<AtomicU16>>,
 }
 
 impl Window {
@@ -43,14 +89,50 @@ impl Window {
             current_fg: Color::Reset,
             current_bg: Color::Reset,
             buffer: String::with_capacity(estimated_capacity),
+            format_buffer: String::new(),
             scroll_enabled: false,
-            last_emitted_attr: Attr::NORMAL,
-            last_emitted_fg: Color::Reset,
-            last_emitted_bg: Color::Reset,
-            style_sequence_buf: SmallVec::new(), // Stack-allocated for sequences <64 bytes
+            autowrap: false,
+            sanitize_controls: true,
+            background: Cell::blank(),
+            style: StyleEncoder::new(),
+            content: Buffer::new(height as usize, width as usize, Cell::blank()),
+            capabilities: Capabilities::default(),
+            attr_policy: AttrPolicy::default(),
+            touched: false,
+            queue: SharedQueue::default(),
+            row_anchor: None,
         })
     }
 
+    /// Queue the window with `wnoutrefresh` for the screen owning `queue`
+    pub(crate) fn set_queue(&mut self, queue: SharedQueue) {
+        self.queue = queue;
+    }
+
+    /// Take the window's top row from `row`, which the screen moves on resize
+    pub(crate) fn follow_row(&mut self, row: Arc<AtomicU16>) {
+        self.row_anchor = Some(row);
+    }
+
+    /// Terminal row of the window's first line
+    fn top(&self) -> u16 {
+        self.row_anchor
+            .as_ref()
+            .map_or(self.begin_y, |row| row.load(Ordering::Relaxed))
+    }
+
+    /// Whether Unicode line and block characters are drawn
+    pub(crate) fn unicode(&self) -> bool {
+        self.capabilities.unicode
+    }
+
+    /// Write only sequences `capabilities` supports, drawing unsupported
+    /// attributes as `policy` says
+    pub(crate) fn set_capabilities(&mut self, capabilities: Capabilities, policy: AttrPolicy) {
+        self.capabilities = capabilities;
+        self.attr_policy = policy;
+    }
+
     /// Get window dimensions (height, width)
     pub fn get_size(&self) -> (u16, u16) {
         (self.height, self.width)
@@ -58,7 +140,17 @@ impl Window {
 
     /// Get window position (y, x)
     pub fn get_position(&self) -> (u16, u16) {
-        (self.begin_y, self.begin_x)
+        (self.top(), self.begin_x)
+    }
+
+    /// Screen area covered by the window
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.top(), self.begin_x, self.height, self.width)
+    }
+
+    /// Get the cursor position (y, x) relative to the window origin
+    pub fn getyx(&self) -> (u16, u16) {
+        (self.cursor_y, self.cursor_x)
     }
 
     /// Move cursor within window (relative to window origin)
@@ -71,7 +163,7 @@ impl Window {
         let dy = (y as i32 - self.cursor_y as i32).abs();
         let dx = (x as i32 - self.cursor_x as i32).abs();
 
-        let abs_y = self.begin_y + y;
+        let abs_y = self.top() + y;
         let abs_x = self.begin_x + x;
 
         // Threshold: use relative movement if distance < 4 cells
@@ -100,44 +192,175 @@ impl Window {
     }
 
     /// Print text at current cursor position
+    ///
+    /// Text past the right edge is dropped, or continues on the next line
+    /// with `set_autowrap(true)`.
     pub fn print(&mut self, text: &str) -> Result<()> {
-        // Truncate text if it exceeds window width
-        let remaining = (self.width - self.cursor_x) as usize;
-        let text_to_print = if text.len() > remaining {
-            &text[..remaining]
+        if self.autowrap {
+            self.print_wrapped(text)
         } else {
-            text
-        };
+            self.print_clipped(text)
+        }
+    }
 
-        // Performance optimization: use ECH (Erase Character) for long blank runs
-        if text_to_print.len() >= 8 && text_to_print.chars().all(|c| c == ' ') {
-            // Use ECH sequence for efficiency
-            write!(self.buffer, "\x1b[{}X", text_to_print.len())?;
-            self.cursor_x += text_to_print.len() as u16;
-            return Ok(());
+    /// Print text at the cursor, dropping whatever doesn't fit on the line
+    pub fn print_clipped(&mut self, text: &str) -> Result<()> {
+        self.put_text(&self.sanitized(text))?;
+        Ok(())
+    }
+
+    /// Print text at the cursor, or fail with `Error::InvalidCoordinates`
+    /// (writing nothing) if it doesn't fit on the line
+    pub fn print_checked(&mut self, text: &str) -> Result<()> {
+        let (y, x) = (self.cursor_y, self.cursor_x);
+        if x >= self.width {
+            return Err(Error::InvalidCoordinates { y, x });
         }
+        let text = self.sanitized(text);
+        let (_, columns) = fit_width(&text, usize::MAX);
+        if x as usize + columns > self.width as usize {
+            return Err(Error::InvalidCoordinates { y, x: self.width });
+        }
+        self.put_text(&text)?;
+        Ok(())
+    }
 
-        self.apply_style()?;
-        write!(self.buffer, "{}", text_to_print)?;
-        self.cursor_x += text_to_print.len() as u16;
+    /// Print text at the cursor, continuing at the start of the next line
+    /// at the right edge
+    ///
+    /// Past the bottom line the window scrolls if `scrollok` is on; the
+    /// rest of the text is dropped otherwise.
+    fn print_wrapped(&mut self, text: &str) -> Result<()> {
+        let sanitized = self.sanitized(text);
+        let mut text = sanitized.as_ref();
+        while !text.is_empty() {
+            if self.cursor_x >= self.width && !self.next_line()? {
+                break;
+            }
+            let written = self.put_text(text)?;
+            if written == 0 {
+                // A wide character doesn't fit in the rest of the line
+                if self.cursor_x == 0 || !self.next_line()? {
+                    break;
+                }
+            }
+            text = &text[written..];
+        }
         Ok(())
     }
 
+    /// Move the cursor to the start of the next line, scrolling at the
+    /// bottom if allowed; false if there is no next line
+    fn next_line(&mut self) -> Result<bool> {
+        if self.cursor_y + 1 < self.height {
+            self.move_cursor(self.cursor_y + 1, 0)?;
+            return Ok(true);
+        }
+        if !self.scroll_enabled {
+            return Ok(false);
+        }
+        self.scroll(1)?;
+        // Scrolling left the terminal's cursor elsewhere
+        self.cursor_y = u16::MAX;
+        self.move_cursor(self.height - 1, 0)?;
+        Ok(true)
+    }
+
+    /// Write as much of `text` as fits on the cursor's line, returning the
+    /// bytes written
+    fn put_text(&mut self, text: &str) -> Result<usize> {
+        // Truncate text to the columns left in the window
+        let remaining = self.width.saturating_sub(self.cursor_x) as usize;
+        let (text_to_print, columns) = fit_width(text, remaining);
+        self.store_text(self.cursor_y, self.cursor_x, text_to_print);
+
+        self.apply_style()?;
+
+        // Performance optimization: use ECH (Erase Character) for long blank runs
+        if self.capabilities.ech && columns >= 8 && text_to_print.chars().all(|c| c == ' ') {
+            // ECH leaves the cursor in place; step over the erased cells
+            write!(self.buffer, "\x1b[{}X\x1b[{}C", columns, columns)?;
+            self.cursor_x += columns as u16;
+            return Ok(text_to_print.len());
+        }
+
+        self.buffer.push_str(text_to_print);
+        self.cursor_x += columns as u16;
+        Ok(text_to_print.len())
+    }
+
+    /// Set whether `print` wraps at the right edge instead of clipping
+    pub fn set_autowrap(&mut self, enabled: bool) {
+        self.autowrap = enabled;
+    }
+
+    /// Check if `print` wraps at the right edge
+    pub fn autowrap(&self) -> bool {
+        self.autowrap
+    }
+
+    /// Set whether printed control characters (ESC, newline, ...) are shown
+    /// as `^X` placeholders (the default) or written unchanged
+    ///
+    /// Raw control characters can inject escape sequences and desync the
+    /// renderer; only turn this off for trusted content.
+    pub fn set_sanitize_controls(&mut self, enabled: bool) {
+        self.sanitize_controls = enabled;
+    }
+
+    /// `text` as printed: with control characters replaced unless that is
+    /// turned off
+    fn sanitized<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.sanitize_controls {
+            sanitize_controls(text)
+        } else {
+            std::borrow::Cow::Borrowed(text)
+        }
+    }
+
     /// Move cursor and print
     pub fn mvprint(&mut self, y: u16, x: u16, text: &str) -> Result<()> {
         self.move_cursor(y, x)?;
         self.print(text)
     }
 
+    /// Print formatted text at the cursor; see `printw!`
+    pub fn printw(&mut self, args: std::fmt::Arguments) -> Result<()> {
+        if let Some(text) = args.as_str() {
+            return self.print(text);
+        }
+        let mut text = std::mem::take(&mut self.format_buffer);
+        text.clear();
+        let result = text
+            .write_fmt(args)
+            .map_err(Error::from)
+            .and_then(|()| self.print(&text));
+        self.format_buffer = text;
+        result
+    }
+
+    /// Move the cursor and print formatted text; see `mvprintw!`
+    pub fn mvprintw(&mut self, y: u16, x: u16, args: std::fmt::Arguments) -> Result<()> {
+        self.move_cursor(y, x)?;
+        self.printw(args)
+    }
+
     /// Add a single character
     pub fn addch(&mut self, ch: char) -> Result<()> {
-        if self.cursor_x >= self.width {
+        if self.sanitize_controls
+            && let Some(placeholder) = control_placeholder(ch)
+        {
+            return self.print_clipped(&String::from_iter(placeholder));
+        }
+        let width = ch.width().unwrap_or(0) as u16;
+        if self.cursor_x >= self.width || self.cursor_x + width > self.width {
             return Ok(());
         }
 
+        self.store_text(self.cursor_y, self.cursor_x, ch.encode_utf8(&mut [0; 4]));
         self.apply_style()?;
-        write!(self.buffer, "{}", ch)?;
-        self.cursor_x += 1;
+        self.buffer.push(ch);
+        self.cursor_x += width;
         Ok(())
     }
 
@@ -147,6 +370,100 @@ impl Window {
         self.addch(ch)
     }
 
+    /// Draw `n` copies of `ch` rightwards from (y, x)
+    ///
+    /// Clipped to the window; the cursor doesn't move.
+    pub fn hline(&mut self, y: u16, x: u16, ch: char, n: u16) -> Result<()> {
+        self.fill_rect(y, x, 1, n, ch)
+    }
+
+    /// Restyle `n` cells from (y, x) without changing their text (like
+    /// curses `chgat`)
+    ///
+    /// Stops at the end of the row (`u16::MAX` for the rest of it); the
+    /// cursor doesn't move. A wide character partly in range is restyled
+    /// whole.
+    pub fn chgat(
+        &mut self,
+        y: u16,
+        x: u16,
+        n: u16,
+        attr: Attr,
+        fg: Color,
+        bg: Color,
+    ) -> Result<()> {
+        if y >= self.height || x >= self.width {
+            return Err(Error::InvalidCoordinates { y, x });
+        }
+        let mut end = x.saturating_add(n).min(self.width) as usize;
+        if end == x as usize {
+            return Ok(());
+        }
+
+        let (attr, fg, bg) = self.merged_style(attr, fg, bg);
+        let row = &mut self.content[y as usize];
+        let mut start = x as usize;
+        while start > 0 && row[start].is_continuation() {
+            start -= 1;
+        }
+        while end < row.len() && row[end].is_continuation() {
+            end += 1;
+        }
+        for cell in &mut row[start..end] {
+            (cell.attr, cell.fg, cell.bg) = (attr, fg, bg);
+        }
+
+        let (cursor_y, cursor_x) = (self.cursor_y, self.cursor_x);
+        self.move_cursor(y, start as u16)?;
+        let attr = self.attr_policy.apply(attr, self.capabilities.attrs);
+        for cell in &self.content[y as usize][start..end] {
+            if !cell.is_continuation() {
+                self.style.encode(attr, fg, bg, &mut self.buffer);
+                cell.push_grapheme(&mut self.buffer);
+            }
+        }
+        // The terminal's cursor moved past the restyled cells
+        self.cursor_y = u16::MAX;
+        self.move_cursor(cursor_y, cursor_x.min(self.width - 1))?;
+        self.cursor_x = cursor_x;
+        Ok(())
+    }
+
+    /// Draw `n` copies of `ch` downwards from (y, x)
+    ///
+    /// Clipped to the window; the cursor doesn't move.
+    pub fn vline(&mut self, y: u16, x: u16, ch: char, n: u16) -> Result<()> {
+        self.fill_rect(y, x, n, 1, ch)
+    }
+
+    /// Fill a `height` x `width` rectangle at (y, x) with `ch`
+    ///
+    /// Clipped to the window; the cursor doesn't move. A wide `ch` fills
+    /// as many whole copies as fit, leaving an odd column blank; a
+    /// zero-width `ch` draws nothing.
+    pub fn fill_rect(&mut self, y: u16, x: u16, height: u16, width: u16, ch: char) -> Result<()> {
+        let area =
+            Rect::new(y, x, height, width).intersection(&Rect::new(0, 0, self.height, self.width));
+        let ch_width = ch.width().unwrap_or(0);
+        if area.is_empty() || ch_width == 0 {
+            return Ok(());
+        }
+
+        let (cursor_y, cursor_x) = (self.cursor_y, self.cursor_x);
+        let columns = area.width as usize;
+        let mut run: String = std::iter::repeat_n(ch, columns / ch_width).collect();
+        run.extend(std::iter::repeat_n(' ', columns % ch_width));
+
+        for row in area.y..area.bottom() {
+            self.store_text(row, area.x, &run);
+            self.move_cursor(row, area.x)?;
+            self.apply_style()?;
+            self.buffer.push_str(&run);
+        }
+
+        self.move_cursor(cursor_y, cursor_x)
+    }
+
     /// Turn on attributes
     pub fn attron(&mut self, attr: Attr) -> Result<()> {
         self.current_attr = self.current_attr | attr;
@@ -159,6 +476,12 @@ impl Window {
         Ok(())
     }
 
+    /// Set attributes
+    pub fn attrset(&mut self, attr: Attr) -> Result<()> {
+        self.current_attr = attr;
+        Ok(())
+    }
+
     /// Set foreground color
     pub fn set_fg(&mut self, color: Color) -> Result<()> {
         self.current_fg = color;
@@ -173,22 +496,152 @@ impl Window {
 
     /// Clear the window
     pub fn clear(&mut self) -> Result<()> {
+        self.content.fill(&self.background);
         // Performance optimization: use ED (Erase in Display) instead of line-by-line clear
         self.move_cursor(0, 0)?;
+        self.apply_background_style()?;
 
         // Fill the entire window with blanks using optimized sequences
+        let fill: String = std::iter::repeat_n(self.background.ch, self.width as usize).collect();
         for y in 0..self.height {
             if y > 0 {
                 self.move_cursor(y, 0)?;
             }
-            // Use EL (Erase in Line) to clear to end of line
-            write!(self.buffer, "\x1b[K")?;
+            if self.background.ch == ' ' {
+                // Use EL (Erase in Line) to clear to end of line
+                write!(self.buffer, "\x1b[K")?;
+            } else {
+                self.buffer.push_str(&fill);
+            }
         }
 
         self.move_cursor(0, 0)?;
         Ok(())
     }
 
+    /// Set the background: the fill used by `clear` and a default style
+    /// for written text (like curses `wbkgd`)
+    ///
+    /// Written text gets the background attributes added, and its colors
+    /// wherever the current color is `Color::Reset`. Cells showing the old
+    /// background are switched to the new one and drawn on the next
+    /// refresh.
+    pub fn bkgd(&mut self, ch: char, attr: Attr, fg: Color, bg: Color) -> Result<()> {
+        let old = std::mem::replace(&mut self.background, Cell::with_style(ch, attr, fg, bg));
+
+        for cell in self.content.rows_mut().flatten() {
+            if *cell == old {
+                *cell = self.background.clone();
+                self.touched = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the background cell set by `bkgd`
+    pub fn getbkgd(&self) -> &Cell {
+        &self.background
+    }
+
+    /// The cell shown at (y, x)
+    ///
+    /// The second column of a wide character holds `Cell::CONTINUATION`.
+    pub fn cell_at(&self, y: u16, x: u16) -> Result<&Cell> {
+        if y >= self.height || x >= self.width {
+            return Err(Error::InvalidCoordinates { y, x });
+        }
+        Ok(&self.content[y as usize][x as usize])
+    }
+
+    /// Text shown in `len` columns from (y, x), like curses `winnstr`
+    ///
+    /// Stops at the end of the row. Attributes and colors are left out.
+    pub fn read_str(&self, y: u16, x: u16, len: u16) -> Result<String> {
+        if y >= self.height || x >= self.width {
+            return Err(Error::InvalidCoordinates { y, x });
+        }
+        let end = x.saturating_add(len).min(self.width);
+        Ok(cells_text(
+            &self.content[y as usize][x as usize..end as usize],
+        ))
+    }
+
+    /// Record text written at (y, x) in the current style
+    fn store_text(&mut self, y: u16, x: u16, text: &str) {
+        let (attr, fg, bg) = self.effective_style();
+        let row = &mut self.content[y as usize];
+        let mut x = x as usize;
+        // The cell holding the last grapheme cluster written
+        let mut last = (x > 0).then(|| {
+            (0..x)
+                .rev()
+                .find(|&i| !row[i].is_continuation())
+                .unwrap_or(0)
+        });
+        for ch in text.chars() {
+            if let Some(i) = last
+                && row[i].continued_by(ch)
+            {
+                row[i].push_combining(ch);
+                continue;
+            }
+            let width = ch.width().unwrap_or(0);
+            if width == 0 {
+                continue;
+            }
+            if x + width > row.len() {
+                break;
+            }
+            row[x] = Cell::with_style(ch, attr, fg, bg);
+            last = Some(x);
+            for cell in &mut row[x + 1..x + width] {
+                *cell = Cell::with_style(Cell::CONTINUATION, attr, fg, bg);
+            }
+            x += width;
+        }
+    }
+
+    /// Emit the background's own style (for erasing)
+    fn apply_background_style(&mut self) -> Result<()> {
+        let (attr, fg, bg) = (self.current_attr, self.current_fg, self.current_bg);
+        self.current_attr = Attr::NORMAL;
+        self.current_fg = Color::Reset;
+        self.current_bg = Color::Reset;
+        let result = self.apply_style();
+        (self.current_attr, self.current_fg, self.current_bg) = (attr, fg, bg);
+        result
+    }
+
+    /// Attributes and colors currently used for drawing
+    pub(crate) fn current_style(&self) -> Style {
+        Style {
+            attr: self.current_attr,
+            fg: self.current_fg,
+            bg: self.current_bg,
+        }
+    }
+
+    /// Current style merged with the background
+    fn effective_style(&self) -> (Attr, Color, Color) {
+        self.merged_style(self.current_attr, self.current_fg, self.current_bg)
+    }
+
+    /// A style merged with the background, as it is stored in cells
+    fn merged_style(&self, attr: Attr, fg: Color, bg: Color) -> (Attr, Color, Color) {
+        let pick = |color, fallback| {
+            if color == Color::Reset {
+                fallback
+            } else {
+                color
+            }
+        };
+        (
+            attr | self.background.attr,
+            pick(fg, self.background.fg),
+            pick(bg, self.background.bg),
+        )
+    }
+
     /// Draw a border around the window
     pub fn border(
         &mut self,
@@ -201,6 +654,9 @@ impl Window {
         bl: char,
         br: char,
     ) -> Result<()> {
+        let [ls, rs, ts, bs, tl, tr, bl, br] =
+            line_chars(self.capabilities.unicode, [ls, rs, ts, bs, tl, tr, bl, br]);
+
         // Top border
         self.mvaddch(0, 0, tl)?;
         for _ in 1..self.width - 1 {
@@ -239,23 +695,113 @@ impl Window {
         )
     }
 
-    /// Refresh the window (flush buffer to stdout)
+    /// Mark the whole window as changed without modifying it, so the next
+    /// refresh redraws all of it (like curses `touchwin`)
+    ///
+    /// For areas damaged by images or other writers: `refresh` writes every
+    /// cell again, and `wnoutrefresh` makes `Screen::doupdate` repaint the
+    /// window's area.
+    pub fn touchwin(&mut self) {
+        self.touched = true;
+    }
+
+    /// Undo `touchwin` before the next refresh
+    pub fn untouchwin(&mut self) {
+        self.touched = false;
+    }
+
+    /// Check if the window will be redrawn in full on the next refresh
+    pub fn is_wintouched(&self) -> bool {
+        self.touched
+    }
+
+    /// Queue every cell for output, replacing what was queued
+    fn redraw_content(&mut self) -> Result<()> {
+        let (cursor_y, cursor_x) = (self.cursor_y, self.cursor_x);
+        self.discard_output();
+        for y in 0..self.height {
+            // Where the terminal's cursor is isn't tracked, so move absolutely
+            self.cursor_y = u16::MAX;
+            self.move_cursor(y, 0)?;
+            for cell in &self.content[y as usize] {
+                if !cell.is_continuation() {
+                    let attr = self.attr_policy.apply(cell.attr, self.capabilities.attrs);
+                    self.style.encode(attr, cell.fg, cell.bg, &mut self.buffer);
+                    cell.push_grapheme(&mut self.buffer);
+                }
+            }
+        }
+        self.cursor_y = u16::MAX;
+        self.move_cursor(cursor_y, cursor_x.min(self.width - 1))?;
+        self.cursor_x = cursor_x;
+        Ok(())
+    }
+
+    /// Refresh the window (flush buffer to the terminal of the screen that
+    /// created it, or stdout)
     pub fn refresh(&mut self) -> Result<()> {
-        use std::io::Write as IoWrite;
-        io::stdout().write_all(self.buffer.as_bytes())?;
-        io::stdout().flush()?;
+        if std::mem::take(&mut self.touched) {
+            self.redraw_content()?;
+        }
+        self.style.finish(&mut self.buffer);
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.cursor_moved = true;
+        let bytes = self.buffer.as_bytes();
+        #[cfg(unix)]
+        match queue.output_fd {
+            Some(fd) => crate::platform_io::write_all_fd(fd, bytes)?,
+            None => crate::platform_io::write_all_stdout(bytes)?,
+        }
+        #[cfg(not(unix))]
+        crate::platform_io::write_all_stdout(bytes)?;
+        drop(queue);
         self.buffer.clear();
         Ok(())
     }
 
-    /// Update internal buffer without refreshing screen
+    /// Queue the window's cells for the next `Screen::doupdate` of the
+    /// screen that created it
+    ///
+    /// Cells are merged into a virtual screen, so windows queued later
+    /// cover earlier ones and `doupdate` writes only what changed. Output
+    /// queued for `refresh` is dropped.
     pub fn wnoutrefresh(&mut self) -> Result<()> {
-        use crate::backend::Backend;
-        Backend::add_to_update_buffer(&self.buffer)?;
-        self.buffer.clear();
+        self.discard_output();
+        let mut queue = self.queue.lock().unwrap();
+        if std::mem::take(&mut self.touched) {
+            queue.touched.push(self.rect());
+        }
+        self.queue_cells(&mut queue.cells);
         Ok(())
     }
 
+    /// Copy the window's cells into `screen` at its position
+    pub(crate) fn queue_cells(&self, screen: &mut VirtualScreen) {
+        let (top, left) = (self.top() as usize, self.begin_x as usize);
+        let bottom = top + self.height as usize;
+        if screen.len() < bottom {
+            screen.resize(bottom, Vec::new());
+        }
+        for (row, cells) in screen[top..bottom].iter_mut().zip(self.content.rows()) {
+            if row.len() < left + cells.len() {
+                row.resize(left + cells.len(), None);
+            }
+            for (slot, cell) in row[left..].iter_mut().zip(cells) {
+                *slot = Some(cell.clone());
+            }
+        }
+    }
+
+    /// Drop output queued since the last refresh
+    pub(crate) fn discard_output(&mut self) {
+        self.buffer.clear();
+        self.style.assume_default();
+    }
+
     /// Enable or disable scrolling
     pub fn scrollok(&mut self, enabled: bool) -> Result<()> {
         self.scroll_enabled = enabled;
@@ -267,6 +813,12 @@ impl Window {
         if !self.scroll_enabled {
             return Ok(());
         }
+        self.content.shift_rows(lines as isize, &self.background);
+
+        // Without scroll regions the shifted content is redrawn
+        if !self.capabilities.scroll_region {
+            return self.redraw_content();
+        }
 
         if lines > 0 {
             // Scroll up
@@ -274,10 +826,10 @@ impl Window {
                 write!(
                     self.buffer,
                     "\x1b[{};{}r",
-                    self.begin_y + 1,
-                    self.begin_y + self.height
+                    self.top() + 1,
+                    self.top() + self.height
                 )?;
-                write!(self.buffer, "\x1b[{}H\n", self.begin_y + self.height)?;
+                write!(self.buffer, "\x1b[{}H\n", self.top() + self.height)?;
                 write!(self.buffer, "\x1b[r")?;
             }
         } else if lines < 0 {
@@ -286,10 +838,10 @@ impl Window {
                 write!(
                     self.buffer,
                     "\x1b[{};{}r",
-                    self.begin_y + 1,
-                    self.begin_y + self.height
+                    self.top() + 1,
+                    self.top() + self.height
                 )?;
-                write!(self.buffer, "\x1b[{}H\x1bM", self.begin_y + 1)?;
+                write!(self.buffer, "\x1b[{}H\x1bM", self.top() + 1)?;
                 write!(self.buffer, "\x1b[r")?;
             }
         }
@@ -298,81 +850,150 @@ impl Window {
     }
 
     fn apply_style(&mut self) -> Result<()> {
-        // Performance optimization: only emit ANSI codes if style changed since last emission
-        let style_changed = self.current_attr != self.last_emitted_attr
-            || self.current_fg != self.last_emitted_fg
-            || self.current_bg != self.last_emitted_bg;
+        let (attr, fg, bg) = self.effective_style();
+        let attr = self.attr_policy.apply(attr, self.capabilities.attrs);
+        self.style.encode(attr, fg, bg, &mut self.buffer);
+        Ok(())
+    }
+}
 
-        if !style_changed {
-            return Ok(());
+/// The longest prefix of `text` that fits in `columns` terminal columns,
+/// and the columns it takes
+///
+/// A wide character that would straddle the limit is left out whole.
+fn fit_width(text: &str, columns: usize) -> (&str, usize) {
+    let mut used = 0;
+    let mut end = 0;
+    for (grapheme, width) in graphemes(text) {
+        if used + width > columns {
+            return (&text[..end], used);
         }
+        used += width;
+        end += grapheme.len();
+    }
+    (text, used)
+}
 
-        // Performance optimization: use SmallVec (stack-allocated)
-        self.style_sequence_buf.clear();
-        let mut needs_separator = false;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // If any attribute changed, we need to reset and re-apply all
-        // (ANSI doesn't support selective attribute removal)
-        if self.current_attr != self.last_emitted_attr {
-            // Reset all attributes first
-            if self.last_emitted_attr != Attr::NORMAL {
-                self.style_sequence_buf.push(b'0');
-                needs_separator = true;
-            }
+    #[test]
+    fn test_touchwin() {
+        let mut win = Window::new(2, 3, 4, 5).unwrap();
+        win.mvprint(1, 0, "ab").unwrap();
+        win.discard_output();
+        win.touchwin();
+        win.untouchwin();
+        assert!(!win.is_wintouched());
+
+        win.touchwin();
+        win.redraw_content().unwrap();
+        assert_eq!(win.buffer, "\x1b[5;6H   \x1b[6;6Hab \x1b[6;8H");
+        assert_eq!(win.getyx(), (1, 2));
+    }
 
-            // Add current attribute codes
-            if !self.current_attr.is_empty() {
-                for code in self.current_attr.to_ansi_codes() {
-                    if needs_separator {
-                        self.style_sequence_buf.push(b';');
-                    }
-                    self.style_sequence_buf.extend_from_slice(code.as_bytes());
-                    needs_separator = true;
-                }
-            }
-        }
+    #[test]
+    fn test_attr_support() {
+        let mut win = Window::new(1, 4, 0, 0).unwrap();
+        win.set_capabilities(
+            Capabilities::default().attrs(Attr::BOLD | Attr::UNDERLINE),
+            AttrPolicy::default(),
+        );
+        win.attron(Attr::ITALIC).unwrap();
+        win.print("a").unwrap();
+        assert!(win.buffer.contains("\x1b[4m"));
+        assert!(!win.buffer.contains("\x1b[3m"));
+
+        // Full redraws follow the policy too; the cell keeps the attribute
+        win.touchwin();
+        win.redraw_content().unwrap();
+        assert!(win.buffer.contains("\x1b[4ma"));
+        assert!(!win.buffer.contains("\x1b[3m"));
+        assert!(win.cell_at(0, 0).unwrap().attr.contains(Attr::ITALIC));
+    }
 
-        // Add color codes if changed (using temporary buffer for String conversion)
-        let mut color_buf = String::with_capacity(20);
-        if self.current_fg != self.last_emitted_fg {
-            if needs_separator {
-                self.style_sequence_buf.push(b';');
-            }
-            color_buf.clear();
-            self.current_fg.write_ansi_fg(&mut color_buf);
-            self.style_sequence_buf
-                .extend_from_slice(color_buf.as_bytes());
-            needs_separator = true;
-        }
-        if self.current_bg != self.last_emitted_bg {
-            if needs_separator {
-                self.style_sequence_buf.push(b';');
-            }
-            color_buf.clear();
-            self.current_bg.write_ansi_bg(&mut color_buf);
-            self.style_sequence_buf
-                .extend_from_slice(color_buf.as_bytes());
-        }
+    #[test]
+    fn test_chgat() {
+        let mut win = Window::new(2, 8, 3, 4).unwrap();
+        win.mvprint(0, 0, "a\u{4E2D}bcd").unwrap();
+        win.move_cursor(1, 0).unwrap();
+        win.discard_output();
+
+        // Starting on the second column of the wide character takes all of it
+        win.chgat(0, 2, 2, Attr::BOLD, Color::Red, Color::Reset)
+            .unwrap();
+        assert_eq!(win.cell_at(0, 1).unwrap().attr, Attr::BOLD);
+        assert_eq!(win.cell_at(0, 3).unwrap().fg, Color::Red);
+        assert_eq!(win.cell_at(0, 4).unwrap().attr, Attr::NORMAL);
+        assert_eq!(win.read_str(0, 0, 5).unwrap(), "a\u{4E2D}bc");
+        assert!(win.buffer.starts_with("\x1b[4;6H"));
+        assert!(win.buffer.contains("\u{4E2D}b"));
+        assert!(win.buffer.ends_with("\x1b[5;5H"));
+        assert_eq!(win.getyx(), (1, 0));
+        assert!(
+            win.chgat(2, 0, 1, Attr::BOLD, Color::Reset, Color::Reset)
+                .is_err()
+        );
+    }
 
-        if !self.style_sequence_buf.is_empty() {
-            self.buffer.push_str("\x1b[");
-            self.buffer
-                .push_str(std::str::from_utf8(&self.style_sequence_buf).unwrap());
-            self.buffer.push('m');
-        }
+    #[test]
+    fn test_sanitize_controls() {
+        let mut win = Window::new(1, 10, 0, 0).unwrap();
+        win.print("x\x1b]0;owned\x07").unwrap();
+        assert_eq!(win.read_str(0, 0, 10).unwrap(), "x^[]0;owne");
+        assert!(!win.buffer.contains("\x1b]"));
+    }
 
-        // Update last emitted state
-        self.last_emitted_attr = self.current_attr;
-        self.last_emitted_fg = self.current_fg;
-        self.last_emitted_bg = self.current_bg;
+    #[test]
+    fn test_print_modes() {
+        let mut win = Window::new(2, 5, 0, 0).unwrap();
+        win.print("abcdefg").unwrap();
+        assert_eq!(win.read_str(0, 0, 5).unwrap(), "abcde");
+
+        win.move_cursor(1, 1).unwrap();
+        assert!(matches!(
+            win.print_checked("\u{4E2D}\u{6587}x"),
+            Err(Error::InvalidCoordinates { y: 1, x: 5 })
+        ));
+        win.print_checked("\u{4E2D}\u{6587}").unwrap();
+        assert!(win.print_checked("x").is_err());
+
+        // A wide character that doesn't fit moves to the next line whole
+        win.set_autowrap(true);
+        win.mvprint(0, 3, "xy\u{4E2D}z").unwrap();
+        assert_eq!(win.read_str(0, 0, 5).unwrap(), "abcxy");
+        assert_eq!(win.read_str(1, 0, 5).unwrap(), "\u{4E2D}z\u{6587}");
+
+        // With scrolling, text carries on past the bottom
+        win.scrollok(true).unwrap();
+        win.mvprint(1, 0, "1234567").unwrap();
+        assert_eq!(win.read_str(0, 0, 5).unwrap(), "12345");
+        assert_eq!(win.read_str(1, 0, 5).unwrap(), "67   ");
+        assert_eq!(win.getyx(), (1, 2));
+    }
 
-        Ok(())
+    #[test]
+    fn test_printw() {
+        let mut win = Window::new(2, 10, 0, 0).unwrap();
+        crate::mvprintw!(win, 1, 0, "{}/{}", 3, 4).unwrap();
+        crate::printw!(win, " ok").unwrap();
+        assert_eq!(win.read_str(1, 0, 6).unwrap(), "3/4 ok");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_window_graphemes() {
+        let mut win = Window::new(1, 6, 0, 0).unwrap();
+        win.print("e\u{301}\u{1F44D}\u{1F3FD}xyz").unwrap();
+        assert_eq!(win.cell_at(0, 0).unwrap().grapheme(), "e\u{301}");
+        assert_eq!(win.cell_at(0, 1).unwrap().grapheme(), "\u{1F44D}\u{1F3FD}");
+        assert!(win.cell_at(0, 2).unwrap().is_continuation());
+        assert_eq!(
+            win.read_str(0, 0, 6).unwrap(),
+            "e\u{301}\u{1F44D}\u{1F3FD}xyz"
+        );
+        assert_eq!(win.cursor_x, 6);
+    }
 
     #[test]
     fn test_window_creation() {
@@ -412,6 +1033,42 @@ mod tests {
         assert_eq!(win.cursor_x, 20);
     }
 
+    #[test]
+    fn test_window_print_wide_chars() {
+        let mut win = Window::new(10, 20, 0, 0).unwrap();
+        win.move_cursor(0, 15).unwrap();
+        // Each CJK character takes two columns; the third would straddle
+        // the edge and is left out
+        win.print("日本語").unwrap();
+        assert_eq!(win.cursor_x, 19);
+        assert!(win.buffer.ends_with("日本"));
+
+        win.move_cursor(1, 14).unwrap();
+        win.print("é🎉x🎉🎉").unwrap();
+        assert_eq!(win.cursor_x, 20);
+        assert!(win.buffer.ends_with("é🎉x🎉"));
+
+        // Combining marks take no columns
+        win.move_cursor(2, 0).unwrap();
+        win.print("e\u{301}").unwrap();
+        assert_eq!(win.cursor_x, 1);
+    }
+
+    #[test]
+    fn test_window_addch_wide_char() {
+        let mut win = Window::new(10, 20, 0, 0).unwrap();
+        win.move_cursor(0, 18).unwrap();
+        win.addch('字').unwrap();
+        assert_eq!(win.cursor_x, 20);
+
+        // No room for a second wide character
+        win.move_cursor(0, 19).unwrap();
+        win.buffer.clear();
+        win.addch('字').unwrap();
+        assert_eq!(win.cursor_x, 19);
+        assert!(!win.buffer.contains('字'));
+    }
+
     #[test]
     fn test_window_attributes() {
         let mut win = Window::new(10, 20, 0, 0).unwrap();
@@ -442,6 +1099,31 @@ mod tests {
         assert_eq!(win.cursor_y, 0);
     }
 
+    #[test]
+    fn test_window_read_back() {
+        let mut win = Window::new(3, 10, 2, 2).unwrap();
+        win.attron(Attr::BOLD).unwrap();
+        win.mvprint(0, 0, "ab字c").unwrap();
+        win.hline(1, 6, '-', 10).unwrap();
+
+        assert_eq!(win.cell_at(0, 2).unwrap().ch(), '字');
+        assert!(win.cell_at(0, 3).unwrap().is_continuation());
+        assert_eq!(win.cell_at(0, 4).unwrap().attr(), Attr::BOLD);
+        assert_eq!(win.read_str(0, 0, 5).unwrap(), "ab字c");
+        assert_eq!(win.read_str(1, 4, 10).unwrap(), "  ----");
+        assert!(win.cell_at(3, 0).is_err());
+
+        win.scrollok(true).unwrap();
+        win.scroll(1).unwrap();
+        assert_eq!(win.read_str(0, 6, 4).unwrap(), "----");
+        assert_eq!(win.read_str(2, 0, 10).unwrap(), " ".repeat(10));
+
+        win.bkgd('.', Attr::NORMAL, Color::Reset, Color::Reset)
+            .unwrap();
+        win.clear().unwrap();
+        assert_eq!(win.read_str(0, 0, 3).unwrap(), "...");
+    }
+
     #[test]
     fn test_window_border_buffer() {
         let mut win = Window::new(5, 10, 0, 0).unwrap();
@@ -511,6 +1193,22 @@ mod tests {
         assert!(win.buffer.contains("\x1b[")); // Contains escape sequence
     }
 
+    #[test]
+    fn test_scroll_without_scroll_region() {
+        let mut win = Window::new(2, 3, 5, 5).unwrap();
+        win.set_capabilities(
+            Capabilities::default().scroll_region(false),
+            AttrPolicy::default(),
+        );
+        win.scrollok(true).unwrap();
+        win.mvprint(1, 0, "ab").unwrap();
+
+        // The shifted lines are rewritten instead of scrolled
+        win.scroll(1).unwrap();
+        assert!(!win.buffer.contains('r'));
+        assert_eq!(win.buffer, "\x1b[6;6Hab \x1b[7;6H   \x1b[7;8H");
+    }
+
     #[test]
     fn test_scroll_zero() {
         let mut win = Window::new(10, 20, 0, 0).unwrap();
@@ -751,6 +1449,30 @@ mod tests {
         assert_eq!(win.cursor_x, 8);
     }
 
+    #[test]
+    fn test_window_rle_without_ech() {
+        let mut win = Window::new(10, 20, 0, 0).unwrap();
+        win.set_capabilities(Capabilities::from_term("vt100"), AttrPolicy::default());
+
+        // vt100 has no ECH: the blanks are written out
+        win.print("          ").unwrap();
+        assert_eq!(win.buffer, "          ");
+        assert_eq!(win.cursor_x, 10);
+    }
+
+    #[test]
+    fn test_window_rle_erases_with_background() {
+        let mut win = Window::new(10, 20, 0, 0).unwrap();
+        win.set_bg(Color::Blue).unwrap();
+        win.discard_output();
+
+        // The background is set before the erase, which then steps past the cells
+        win.print("        ").unwrap();
+        assert!(win.buffer.ends_with("\x1b[8X\x1b[8C"));
+        assert!(win.buffer.find("\x1b[44m").unwrap() < win.buffer.find("\x1b[8X").unwrap());
+        assert_eq!(win.cursor_x, 8);
+    }
+
     #[test]
     fn test_window_rle_with_truncation() {
         let mut win = Window::new(10, 20, 0, 0).unwrap();
@@ -773,4 +1495,97 @@ mod tests {
         assert_eq!(win.buffer, "Hello");
         assert_eq!(win.cursor_x, 5);
     }
+
+    #[test]
+    fn test_window_bkgd() {
+        let mut win = Window::new(2, 4, 0, 0).unwrap();
+        win.bkgd('.', Attr::NORMAL, Color::Reset, Color::Blue)
+            .unwrap();
+
+        // Non-blank fill is written out in the background colors
+        win.clear().unwrap();
+        assert!(win.buffer.contains("\x1b[44m"));
+        assert_eq!(win.buffer.matches("....").count(), 2);
+
+        // Text merges the background color
+        win.buffer.clear();
+        win.set_fg(Color::Red).unwrap();
+        win.print("hi").unwrap();
+        assert_eq!(win.buffer, "\x1b[31mhi");
+    }
+
+    #[test]
+    fn test_window_bkgd_switches_old_background() {
+        let mut win = Window::new(2, 6, 0, 0).unwrap();
+        win.mvprint(0, 0, "text").unwrap();
+        win.discard_output();
+        win.bkgd('.', Attr::DIM, Color::Reset, Color::Blue).unwrap();
+
+        // Old blanks take the new background, drawn cells are kept
+        assert_eq!(win.cell_at(0, 0).unwrap().ch(), 't');
+        assert_eq!(win.cell_at(0, 4).unwrap(), win.getbkgd());
+        assert_eq!(win.cell_at(1, 5).unwrap().bg, Color::Blue);
+        assert_eq!(win.read_str(1, 0, 6).unwrap(), "......");
+
+        // and are drawn on the next refresh
+        assert!(win.is_wintouched());
+        win.redraw_content().unwrap();
+        assert!(win.buffer.contains("......"));
+    }
+
+    #[test]
+    fn test_window_lines() {
+        let mut win = Window::new(5, 10, 2, 2).unwrap();
+
+        win.hline(0, 8, '-', 5).unwrap();
+        assert_eq!(win.buffer, "\x1b[3;11H--\x1b[3;3H");
+        assert_eq!(win.getyx(), (0, 0));
+
+        win.buffer.clear();
+        win.vline(3, 0, '|', 4).unwrap();
+        assert_eq!(win.buffer.matches('|').count(), 2);
+
+        // Entirely outside, as on a screen, nothing is drawn
+        win.buffer.clear();
+        win.fill_rect(5, 0, 1, 1, '#').unwrap();
+        win.fill_rect(0, 10, 1, 1, '#').unwrap();
+        win.fill_rect(0, 0, 1, 4, '\u{301}').unwrap();
+        assert!(win.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_window_lines_wide_chars() {
+        let mut win = Window::new(2, 4, 0, 0).unwrap();
+
+        // Only whole characters are written, matching the stored cells
+        win.fill_rect(0, 0, 1, 4, '日').unwrap();
+        assert_eq!(win.read_str(0, 0, 4).unwrap(), "日日");
+        assert_eq!(win.buffer.matches('日').count(), 2);
+
+        win.discard_output();
+        win.hline(1, 0, '日', 3).unwrap();
+        assert_eq!(win.read_str(1, 0, 4).unwrap(), "日  ");
+        assert!(win.buffer.contains("日 "));
+    }
+
+    #[test]
+    fn test_queue_cells_overlap() {
+        let mut below = Window::new(2, 3, 0, 0).unwrap();
+        below.fill_rect(0, 0, 2, 3, 'a').unwrap();
+        let mut above = Window::new(1, 2, 1, 2).unwrap();
+        above.print("bc").unwrap();
+
+        let mut screen = VirtualScreen::new();
+        below.queue_cells(&mut screen);
+        above.queue_cells(&mut screen);
+        let text: Vec<String> = screen
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|c| c.as_ref().map_or('.', Cell::ch))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(text, vec!["aaa", "aabc"]);
+    }
 }